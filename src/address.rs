@@ -0,0 +1,200 @@
+//! Street addresses for places without a name, so a row says "Torstraße 1,
+//! Mitte" rather than just an OSM id. Looked up with Nominatim's `/reverse`
+//! when the user turns it on, only for rows being shown. Its policy allows a
+//! request a second and has no batch endpoint, so lookups queue up and go one
+//! at a time through [`policy`], the queue keeps only the latest page's worth,
+//! and answers are kept on the device so each place is asked about once.
+
+use crate::bus::{use_bus, Bus, Event, Provider};
+use crate::geocode::{self, SearchOptions};
+use crate::policy::{self, Service};
+use crate::storage;
+use leptos::{error::Result, *};
+use serde_derive::Deserialize;
+use std::collections::{HashMap, VecDeque};
+
+const NOMINATIM_REVERSE_URL: &str = "https://nominatim.openstreetmap.org/reverse";
+pub const STORAGE_KEY: &str = "addresses";
+/// Addresses kept on the device; the oldest go first.
+const MAX_CACHED: usize = 500;
+/// Lookups waiting at once; older ones are for rows no longer shown.
+const MAX_QUEUED: usize = 50;
+
+/// One result of `/reverse?format=jsonv2`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReversePlace {
+    /// What's at the spot, when it has a name (a café, a station...).
+    pub name: String,
+    pub display_name: String,
+    pub address: HashMap<String, String>,
+}
+
+/// Places closer than about 10 m share an address.
+pub fn cache_key((lat, lon): (f64, f64)) -> String {
+    format!("{lat:.4},{lon:.4}")
+}
+
+/// `"Café Fern, Torstraße 1"`: what's there and the street, else the street
+/// and the neighbourhood, else the start of the full address.
+pub fn describe(place: &ReversePlace) -> Option<String> {
+    let first = |keys: &[&str]| keys.iter().find_map(|k| place.address.get(*k).filter(|v| !v.is_empty()).cloned());
+    let street = first(&["road", "pedestrian", "footway", "path", "square"]).map(|road| match place.address.get("house_number") {
+        Some(number) => format!("{road} {number}"),
+        None => road,
+    });
+    let area = first(&["neighbourhood", "suburb", "quarter", "village", "town", "city"]);
+    let name = Some(place.name.trim().to_string()).filter(|n| !n.is_empty());
+    let parts: Vec<String> = [name, street, area].into_iter().flatten().take(2).collect();
+    if !parts.is_empty() {
+        return Some(parts.join(", "));
+    }
+    let fallback: Vec<&str> = place.display_name.split(',').map(str::trim).filter(|p| !p.is_empty()).take(2).collect();
+    (!fallback.is_empty()).then(|| fallback.join(", "))
+}
+
+/// Adds `key` as the newest entry, dropping the oldest past [`MAX_CACHED`].
+/// Places Nominatim has no address for are kept too, as an empty one, so
+/// they aren't asked about again.
+pub fn remember(cached: &mut Vec<(String, String)>, key: String, address: String) {
+    cached.retain(|(k, _)| *k != key);
+    cached.push((key, address));
+    if cached.len() > MAX_CACHED {
+        cached.drain(..cached.len() - MAX_CACHED);
+    }
+}
+
+/// Queue `key` unless it's there already, dropping the oldest past [`MAX_QUEUED`].
+pub fn enqueue(queue: &mut VecDeque<(String, (f64, f64))>, key: String, location: (f64, f64)) {
+    if queue.iter().any(|(k, _)| *k == key) {
+        return;
+    }
+    queue.push_back((key, location));
+    if queue.len() > MAX_QUEUED {
+        queue.pop_front();
+    }
+}
+
+async fn reverse(location: (f64, f64), options: &SearchOptions) -> Result<Option<String>> {
+    let (lat, lon) = location;
+    let language = options.language.map(|l| format!("&accept-language={}", l.code())).unwrap_or_default();
+    let url = format!("{NOMINATIM_REVERSE_URL}?format=jsonv2&zoom=18&lat={lat}&lon={lon}{language}");
+    let place: ReversePlace = policy::get(Service::Nominatim, &url).await.send().await?.json().await?;
+    Ok(describe(&place))
+}
+
+/// Addresses known so far, and the lookups still to do.
+#[derive(Clone, Copy)]
+pub struct Addresses {
+    known: RwSignal<HashMap<String, String>>,
+    queue: StoredValue<VecDeque<(String, (f64, f64))>>,
+    running: StoredValue<bool>,
+    options: Signal<SearchOptions>,
+    bus: Bus,
+}
+
+impl Addresses {
+    /// Reactive: the address at `location`, once it's been looked up and if it has one.
+    pub fn get(&self, location: (f64, f64)) -> Option<String> {
+        self.known.with(|known| known.get(&cache_key(location)).filter(|a| !a.is_empty()).cloned())
+    }
+
+    /// Look up `location` unless it's known or on its way.
+    pub fn request(&self, location: (f64, f64)) {
+        let key = cache_key(location);
+        if self.known.with_untracked(|known| known.contains_key(&key)) {
+            return;
+        }
+        self.queue.update_value(|queue| enqueue(queue, key, location));
+        if !self.running.get_value() {
+            self.running.set_value(true);
+            let addresses = *self;
+            spawn_local(async move { addresses.drain().await });
+        }
+    }
+
+    async fn drain(self) {
+        while let Some((key, location)) = self.queue.try_update_value(VecDeque::pop_front).flatten() {
+            if self.known.with_untracked(|known| known.contains_key(&key)) {
+                continue;
+            }
+            let res = reverse(location, &self.options.get_untracked()).await;
+            self.bus.broadcast(Event::ProviderStatus { provider: Provider::Geocoder, ok: res.is_ok() });
+            match res {
+                Ok(address) => {
+                    let address = address.unwrap_or_default();
+                    let mut cached = storage::load::<Vec<(String, String)>>(STORAGE_KEY).unwrap_or_default();
+                    remember(&mut cached, key.clone(), address.clone());
+                    storage::save(STORAGE_KEY, &cached);
+                    self.known.update(|known| {
+                        known.insert(key, address);
+                    });
+                }
+                Err(e) => log::warn!("Reverse geocoding failed: {e}"),
+            }
+        }
+        self.running.set_value(false);
+    }
+}
+
+pub fn provide_addresses(cx: Scope) -> Addresses {
+    let cached = storage::load::<Vec<(String, String)>>(STORAGE_KEY).unwrap_or_default();
+    let addresses = Addresses {
+        known: create_rw_signal(cx, cached.into_iter().collect()),
+        queue: store_value(cx, VecDeque::new()),
+        running: store_value(cx, false),
+        options: geocode::use_search_options(cx),
+        bus: use_bus(cx),
+    };
+    provide_context(cx, addresses);
+    addresses
+}
+
+/// Without a provider nothing is looked up twice in the same view, but nothing is kept either.
+pub fn use_addresses(cx: Scope) -> Addresses {
+    use_context(cx).unwrap_or_else(|| Addresses {
+        known: create_rw_signal(cx, HashMap::new()),
+        queue: store_value(cx, VecDeque::new()),
+        running: store_value(cx, false),
+        options: Signal::derive(cx, SearchOptions::default),
+        bus: use_bus(cx),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(json: &str) -> ReversePlace {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn describes_what_and_where() {
+        let cafe = place(r#"{"name":"Café Fern","display_name":"Café Fern, 1, Torstraße, Mitte, Berlin","address":{"road":"Torstraße","house_number":"1","suburb":"Mitte"}}"#);
+        assert_eq!(describe(&cafe).as_deref(), Some("Café Fern, Torstraße 1"));
+        let path = place(r#"{"name":"","display_name":"Uferweg, Kreuzberg, Berlin","address":{"footway":"Uferweg","suburb":"Kreuzberg"}}"#);
+        assert_eq!(describe(&path).as_deref(), Some("Uferweg, Kreuzberg"));
+        let bare = place(r#"{"display_name":"Tiergarten, Berlin, Deutschland"}"#);
+        assert_eq!(describe(&bare).as_deref(), Some("Tiergarten, Berlin"));
+        assert_eq!(describe(&place("{}")), None);
+    }
+
+    #[test]
+    fn keeps_the_newest_and_queues_each_place_once() {
+        assert_eq!(cache_key((52.520_04, 13.404_96)), "52.5200,13.4050");
+        let mut cached: Vec<(String, String)> = (0..MAX_CACHED).map(|i| (i.to_string(), String::new())).collect();
+        remember(&mut cached, "new".to_string(), "Torstraße 1".to_string());
+        assert_eq!((cached.len(), cached[0].0.as_str()), (MAX_CACHED, "1"));
+        assert_eq!(cached.last().unwrap().0, "new");
+
+        let mut queue = VecDeque::new();
+        enqueue(&mut queue, "a".to_string(), (0.0, 0.0));
+        enqueue(&mut queue, "a".to_string(), (0.0, 0.0));
+        assert_eq!(queue.len(), 1);
+        for i in 0..MAX_QUEUED {
+            enqueue(&mut queue, i.to_string(), (0.0, 0.0));
+        }
+        assert_eq!((queue.len(), queue[0].0.as_str()), (MAX_QUEUED, "0"));
+    }
+}
@@ -0,0 +1,243 @@
+//! The user's own marks on elements: a favorite star, a private note, a
+//! 1–5 rating and whether they've been there. Kept in local storage, keyed by `type/id`, and updated
+//! optimistically (see [`optimistic`](crate::optimistic)). Favorites keep a
+//! copy of their element, so they can be listed wherever the user is searching
+//! (see [`crate::favorites`]).
+
+use crate::storage::{self, StorageError};
+use crate::toast::{self, Toasts};
+use crate::{kiosk, optimistic, quality, tabs, Element};
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+const STORAGE_KEY: &str = "annotations";
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Annotation {
+    pub favorite: bool,
+    pub note: String,
+    pub rating: Option<u8>,
+    /// When the user said they'd been here, in milliseconds since the epoch.
+    pub visited: Option<f64>,
+    /// The element as it was when starred, while it's a favorite.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved: Option<Element>,
+}
+
+impl Annotation {
+    fn is_empty(&self) -> bool {
+        *self == Annotation::default()
+    }
+}
+
+pub type Annotations = HashMap<String, Annotation>;
+
+pub fn element_key(element: &Element) -> String {
+    format!("{}/{}", quality::osm_type(element), element.id)
+}
+
+/// Edit one element's annotation, dropping it once it's back to the default.
+pub fn edit(annotations: &mut Annotations, key: &str, f: impl FnOnce(&mut Annotation)) {
+    let annotation = annotations.entry(key.to_string()).or_default();
+    f(annotation);
+    if !annotation.favorite {
+        annotation.saved = None;
+    }
+    if let Some(rating) = &mut annotation.rating {
+        *rating = (*rating).clamp(1, MAX_RATING);
+    }
+    if annotation.is_empty() {
+        annotations.remove(key);
+    }
+}
+
+/// Star or unstar, keeping a copy of the element while starred.
+pub fn toggle_favorite(annotation: &mut Annotation, element: &Element) {
+    annotation.favorite = !annotation.favorite;
+    annotation.saved = annotation.favorite.then(|| element.clone());
+}
+
+/// Favorites that have a copy of their element, by name, unnamed ones last.
+pub fn favorites(annotations: &Annotations) -> Vec<(&str, &Element)> {
+    let mut favorites: Vec<_> = annotations
+        .iter()
+        .filter(|(_, a)| a.favorite)
+        .filter_map(|(key, a)| Some((key.as_str(), a.saved.as_ref()?)))
+        .collect();
+    favorites.sort_by_key(|(key, e)| (e.tags.get("name").is_none(), e.tags.get("name"), *key));
+    favorites
+}
+
+/// Keys of the elements the user has been to.
+pub fn visited_keys(annotations: &Annotations) -> HashSet<String> {
+    annotations.iter().filter(|(_, a)| a.visited.is_some()).map(|(key, _)| key.clone()).collect()
+}
+
+/// Copy `elements` into favorites starred before copies were kept, or
+/// refresh stale copies; whether anything changed.
+pub fn remember_favorites<'a>(annotations: &mut Annotations, elements: impl IntoIterator<Item = &'a Element>) -> bool {
+    let mut changed = false;
+    for element in elements {
+        if let Some(annotation) = annotations.get_mut(&element_key(element)).filter(|a| a.favorite) {
+            if annotation.saved.as_ref() != Some(element) {
+                annotation.saved = Some(element.clone());
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+async fn persist(annotations: Annotations) -> Result<(), StorageError> {
+    storage::try_save(STORAGE_KEY, &annotations)
+}
+
+/// [`edit`] now and persist in the background, rolling back if that fails.
+pub fn change(annotations: RwSignal<Annotations>, toasts: Toasts, key: &str, f: impl FnOnce(&mut Annotation)) {
+    optimistic::update(annotations, toasts, |a| edit(a, key, f), persist);
+}
+
+pub fn provide_annotations(cx: Scope) -> RwSignal<Annotations> {
+    let annotations = create_rw_signal(cx, storage::load::<Annotations>(STORAGE_KEY).unwrap_or_default());
+    tabs::use_tabs(cx).on_stored_elsewhere(cx, |key| key == STORAGE_KEY, move |_| {
+        annotations.set(storage::load(STORAGE_KEY).unwrap_or_default());
+    });
+    provide_context(cx, annotations);
+    annotations
+}
+
+pub fn use_annotations(cx: Scope) -> RwSignal<Annotations> {
+    use_context(cx).unwrap_or_else(|| create_rw_signal(cx, Annotations::new()))
+}
+
+/// Keep favorites' copies up to date with results as they come in.
+pub fn track_favorites(cx: Scope, elements: impl Fn() -> Vec<Rc<Element>> + 'static) {
+    let annotations = use_annotations(cx);
+    create_effect(cx, move |_| {
+        let elements = elements();
+        let changed = annotations.try_update_untracked(|a| remember_favorites(a, elements.iter().map(|e| e.as_ref())));
+        if changed == Some(true) {
+            annotations.update(|_| {});
+            annotations.with_untracked(|a| storage::save(STORAGE_KEY, a));
+        }
+    });
+}
+
+/// Star, rating and note inputs for one element. Nothing in kiosk mode.
+#[component]
+pub fn AnnotationControls(cx: Scope, element: Rc<Element>) -> impl IntoView {
+    if kiosk::enabled() {
+        return None;
+    }
+    let annotations = use_annotations(cx);
+    let toasts = toast::use_toasts(cx);
+    let key = store_value(cx, element_key(&element));
+    let current = move || annotations.with(|a| key.with_value(|k| a.get(k).cloned().unwrap_or_default()));
+    let change = move |f: Box<dyn FnOnce(&mut Annotation)>| key.with_value(|k| change(annotations, toasts, k, f));
+
+    Some(view! { cx,
+        <div class="annotations">
+            <button
+                class="favorite"
+                aria-pressed=move || current().favorite.to_string()
+                on:click={
+                    let element = element.clone();
+                    move |_| {
+                        let element = element.clone();
+                        change(Box::new(move |a| toggle_favorite(a, &element)));
+                    }
+                }
+            >
+                {move || if current().favorite { "★ Favorite" } else { "☆ Favorite" }}
+            </button>
+            " "
+            <button
+                class="visited"
+                aria-pressed=move || current().visited.is_some().to_string()
+                on:click=move |_| {
+                    let visited = current().visited.is_none().then(js_sys::Date::now);
+                    change(Box::new(move |a| a.visited = visited));
+                }
+            >
+                {move || if current().visited.is_some() { "✓ Been here" } else { "I've been here" }}
+            </button>
+            <label>
+                " Rating "
+                <select on:change=move |ev| {
+                    let rating = event_target_value(&ev).parse().ok();
+                    change(Box::new(move |a| a.rating = rating));
+                }>
+                    <option value="" selected=move || current().rating.is_none()>"–"</option>
+                    {(1..=MAX_RATING).map(|r| view! { cx,
+                        <option value=r.to_string() selected=move || current().rating == Some(r)>{"★".repeat(r.into())}</option>
+                    }).collect_view(cx)}
+                </select>
+            </label>
+            <label>
+                " Note "
+                <input
+                    prop:value=move || current().note
+                    on:change=move |ev| {
+                        let note = event_target_value(&ev).trim().to_string();
+                        change(Box::new(move |a| a.note = note));
+                    }
+                />
+            </label>
+        </div>
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OsmType;
+
+    #[test]
+    fn empty_annotations_are_dropped() {
+        let mut annotations = Annotations::new();
+        edit(&mut annotations, "node/1", |a| a.favorite = true);
+        assert!(annotations["node/1"].favorite);
+        edit(&mut annotations, "node/1", |a| a.favorite = false);
+        assert!(annotations.is_empty());
+        edit(&mut annotations, "way/2", |a| a.visited = Some(0.0));
+        assert_eq!(visited_keys(&annotations), HashSet::from(["way/2".to_string()]));
+        edit(&mut annotations, "way/2", |a| a.visited = None);
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn favorites_keep_a_copy_of_their_element() {
+        let mut annotations = Annotations::new();
+        let mut element = Element { id: 1, ..Default::default() };
+        edit(&mut annotations, "node/1", |a| toggle_favorite(a, &element));
+        edit(&mut annotations, "node/2", |a| a.favorite = true);
+        assert_eq!(favorites(&annotations), [("node/1", &element)]);
+        element.tags.insert("name".to_string(), "Renamed".to_string());
+        let second = Element { id: 2, ..Default::default() };
+        assert!(remember_favorites(&mut annotations, [&element, &second]));
+        assert!(!remember_favorites(&mut annotations, [&element, &second]));
+        assert_eq!(favorites(&annotations), [("node/1", &element), ("node/2", &second)]);
+        edit(&mut annotations, "node/1", |a| toggle_favorite(a, &element));
+        assert!(!annotations.contains_key("node/1"));
+    }
+
+    #[test]
+    fn ratings_are_clamped() {
+        let mut annotations = Annotations::new();
+        edit(&mut annotations, "node/1", |a| a.rating = Some(9));
+        assert_eq!(annotations["node/1"].rating, Some(MAX_RATING));
+        edit(&mut annotations, "node/1", |a| a.rating = Some(0));
+        assert_eq!(annotations["node/1"].rating, Some(1));
+    }
+
+    #[test]
+    fn keys_include_the_type() {
+        let element = Element { id: 5, type_field: OsmType::Way, ..Default::default() };
+        assert_eq!(element_key(&element), "way/5");
+        assert_eq!(element_key(&Element { id: 5, ..Default::default() }), "node/5");
+    }
+}
@@ -0,0 +1,4 @@
+//! Clients for the web services a search goes through.
+
+pub mod osrm;
+pub mod overpass;
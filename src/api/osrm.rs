@@ -0,0 +1,76 @@
+//! Walking routes and distance tables from the public OSRM servers.
+
+use crate::models::{RouteRoot, TableRoot};
+use crate::policy::{self, Service};
+use crate::tasks::TaskRunner;
+use leptos::error::Result;
+
+pub async fn fetch_walking_data(origin: (f64, f64), destinations: Vec<(f64, f64)>) -> Result<RouteRoot> {
+    let route_url = generate_route_url(origin, destinations);
+    let response = policy::get(Service::Fossgis, &route_url).await.send().await?;
+    let json = response.json().await?;
+
+    Ok(json)
+}
+pub async fn fetch_table_data(origin: (f64, f64), destinations: Vec<(f64, f64)>) -> Result<TableRoot> {
+    let route_url = generate_table_url(origin, destinations);
+    let response = policy::get(Service::Osrm, &route_url).await.send().await?;
+    let json = response.json().await?;
+
+    Ok(json)
+}
+
+/// The public OSRM server rejects tables with more than 100 coordinates, origin included.
+const OSRM_TABLE_BATCH: usize = 99;
+
+/// Like `fetch_table_data`, but splits the destinations into batches the OSRM server accepts.
+pub async fn fetch_table_data_batched(origin: (f64, f64), destinations: Vec<(f64, f64)>, runner: &TaskRunner) -> Result<TableRoot> {
+    let batches = destinations
+        .chunks(OSRM_TABLE_BATCH)
+        .map(|batch| fetch_table_data(origin, batch.to_vec()));
+    let mut table = TableRoot::default();
+    for batch in runner.for_service(Service::Osrm).run(batches, |_| {}).await? {
+        table.append_batch(batch?);
+    }
+
+    Ok(table)
+}
+
+fn generate_route_url(origin: (f64, f64), destinations: Vec<(f64, f64)>) -> String {
+    let (lat, lon) = origin;
+    let mut route_url = format!("https://routing.openstreetmap.de/routed-foot/route/v1/driving/{},{}", lon, lat);
+
+    for (lat_dest, lon_dest) in destinations {
+        route_url.push_str(&format!(";{},{}", lon_dest, lat_dest));
+    }
+
+    route_url
+}
+
+fn generate_table_url(origin: (f64, f64), destinations: Vec<(f64, f64)>) -> String {
+    let (lat, lon) = origin;
+    // let mut https://router.project-osrm.org/table/v1/driving/13.388860,52.517037;13.397634,52.529407;13.428555,52.523219?annotations=distance,duration&sources=0
+    let mut route_url = format!("https://router.project-osrm.org/table/v1/driving/{},{}", lon, lat);
+
+    for (lat_dest, lon_dest) in destinations {
+        route_url.push_str(&format!(";{},{}", lon_dest, lat_dest));
+    }
+    route_url.push_str("?annotations=distance,duration&sources=0");
+
+    route_url
+}
+
+pub fn extract_distances(json: &RouteRoot) -> Result<Vec<f64>> {
+    let distances: Vec<f64> = json.routes[0].legs
+        .iter()
+        .map(|leg| leg.distance)
+        .collect();
+
+    Ok(distances)
+}
+
+pub async fn walking_time_distance(origin: (f64, f64), destinations: Vec<(f64, f64)>) -> Result<Vec<f64>> {
+    let json = fetch_walking_data(origin, destinations).await?;
+    let distances = extract_distances(&json)?;
+    Ok(distances)
+}
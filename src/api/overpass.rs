@@ -0,0 +1,75 @@
+//! Searches against the Overpass mirrors (see [`mirrors`]), recorded for the
+//! debug panel when it's on.
+
+use crate::geo::BBox;
+use crate::models::OverpassResponse;
+use crate::overpass::{self, Area};
+use crate::packs::AmenityPack;
+use crate::policy::Service;
+use crate::tasks::{Progress, TaskRunner};
+use crate::{cost, debug, mirrors, net, perf, SearchArea};
+use leptos::error::Result;
+
+pub async fn fetch_overpass(query: &str) -> Result<OverpassResponse> {
+    perf::mark(perf::Mark::FetchStart);
+    let endpoints = mirrors::endpoints();
+    let urls: Vec<String> = endpoints.iter().map(|e| format!("{e}?data={query}")).collect();
+    let (answered, response) = net::get_any(Service::Overpass, &urls, &net::RetryPolicy::OVERPASS).await?;
+    mirrors::remember(endpoints[answered]);
+    perf::mark(perf::Mark::FirstByte);
+    let body = response.text().await?;
+    // Before parsing, so responses that don't parse can be looked at too.
+    debug::record(|| debug::Exchange {
+        endpoint: endpoints[answered],
+        query: query.to_string(),
+        body: body.clone(),
+        at_ms: js_sys::Date::now(),
+    });
+    let res = serde_json::from_str::<OverpassResponse>(&body)?;
+    perf::mark(perf::Mark::ParseDone);
+    Ok(res)
+}
+
+/// How many elements an `out count` query matched.
+pub async fn fetch_count(query: &str) -> Result<Option<u64>> {
+    let endpoints = mirrors::endpoints();
+    let urls: Vec<String> = endpoints.iter().map(|e| format!("{e}?data={query}")).collect();
+    let (answered, response) = net::get_any(Service::Overpass, &urls, &net::RetryPolicy::OVERPASS).await?;
+    mirrors::remember(endpoints[answered]);
+    Ok(response.json::<overpass::CountResponse>().await?.total())
+}
+
+/// The radius to search within, cut down when counting first says there are
+/// too many results. Only counted when the estimate already looks heavy.
+pub async fn narrow_radius(origin: (f64, f64), radius: i64, pack: AmenityPack) -> (i64, Option<cost::Narrowed>) {
+    if SearchArea::Around(radius).cost(pack).level == cost::CostLevel::Cheap {
+        return (radius, None);
+    }
+    match fetch_count(&pack.count_query(origin, &Area::around(radius, origin))).await {
+        Ok(count) => {
+            let narrowed = count.and_then(|count| cost::Narrowed::from_count(radius, count));
+            (narrowed.map_or(radius, |n| n.to_m), narrowed)
+        }
+        Err(e) => {
+            log::warn!("couldn't count results first, searching anyway: {e}");
+            (radius, None)
+        }
+    }
+}
+
+/// Query `bbox` as a grid of `tiles` sub-queries, a few at a time, reporting finished tiles to `progress`.
+pub async fn fetch_bbox_tiled(bbox: BBox, pack: AmenityPack, tiles: usize, runner: &TaskRunner, progress: impl FnMut(Progress)) -> Result<OverpassResponse> {
+    let queries = bbox.tiles(tiles).into_iter().map(|tile| async move {
+        fetch_overpass(&pack.query(tile.center(), &Area::BBox(tile))).await
+    });
+
+    let mut merged = OverpassResponse::default();
+    for (i, res) in runner.for_service(Service::Overpass).run(queries, progress).await?.into_iter().enumerate() {
+        if i == 0 {
+            merged = res?;
+        } else {
+            merged.merge(res?);
+        }
+    }
+    Ok(merged)
+}
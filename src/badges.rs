@@ -0,0 +1,195 @@
+//! Raw OSM tag values turned into short labels with an icon, for the results
+//! table. Only tags that answer "can I use it, and what's there" are shown;
+//! the full list is in the row details.
+
+use crate::family;
+use crate::opening_hours::OpenStatus;
+use crate::tags::Tags;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Good,
+    Neutral,
+    /// Something that may rule the place out.
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    pub icon: &'static str,
+    pub label: String,
+    pub tone: Tone,
+}
+
+impl Badge {
+    fn new(icon: &'static str, label: impl Into<String>, tone: Tone) -> Badge {
+        Badge { icon, label: label.into(), tone }
+    }
+
+    pub fn class(&self) -> &'static str {
+        match self.tone {
+            Tone::Good => "badge good",
+            Tone::Neutral => "badge",
+            Tone::Warning => "badge warning",
+        }
+    }
+}
+
+fn fee(tags: &Tags) -> Option<Badge> {
+    match tags.get("fee")? {
+        "no" => Some(Badge::new("🆓", "Free", Tone::Good)),
+        "yes" => Some(match tags.get("charge") {
+            Some(charge) => Badge::new("💰", format!("Fee: {charge}"), Tone::Neutral),
+            None => Badge::new("💰", "Fee", Tone::Neutral),
+        }),
+        _ => None,
+    }
+}
+
+fn wheelchair(tags: &Tags) -> Option<Badge> {
+    match tags.get("wheelchair")? {
+        "yes" | "designated" => Some(Badge::new("♿", "Accessible", Tone::Good)),
+        "limited" => Some(Badge::new("♿", "Partly accessible", Tone::Neutral)),
+        "no" => Some(Badge::new("♿", "Not accessible", Tone::Warning)),
+        _ => None,
+    }
+}
+
+fn changing_table(tags: &Tags) -> Option<Badge> {
+    family::has_changing_table(tags).then(|| Badge::new("🚼", "Changing table", Tone::Good))
+}
+
+fn genders(tags: &Tags) -> Vec<Badge> {
+    if tags.get("unisex") == Some("yes") {
+        return vec![Badge::new("⚧", "Unisex", Tone::Neutral)];
+    }
+    let mut badges = Vec::new();
+    if tags.get("female") == Some("yes") {
+        badges.push(Badge::new("🚺", "Women", Tone::Neutral));
+    }
+    if tags.get("male") == Some("yes") {
+        badges.push(Badge::new("🚹", "Men", Tone::Neutral));
+    }
+    badges
+}
+
+fn access(tags: &Tags) -> Option<Badge> {
+    match tags.get("access")? {
+        "customers" => Some(Badge::new("🛒", "Customers only", Tone::Warning)),
+        "private" => Some(Badge::new("🔒", "Private", Tone::Warning)),
+        "no" => Some(Badge::new("⛔", "No access", Tone::Warning)),
+        "permissive" | "yes" | "public" => None,
+        "key" => Some(Badge::new("🔑", "Ask for the key", Tone::Warning)),
+        _ => None,
+    }
+}
+
+fn drinking_water(tags: &Tags) -> Vec<Badge> {
+    let mut badges = Vec::new();
+    if tags.get("drinking_water") == Some("no") {
+        badges.push(Badge::new("🚱", "Not drinkable", Tone::Warning));
+    }
+    if tags.get("bottle") == Some("yes") {
+        badges.push(Badge::new("🍶", "Bottle refill", Tone::Good));
+    }
+    if tags.get("seasonal").is_some_and(|s| s != "no") {
+        badges.push(Badge::new("🗓", "Seasonal", Tone::Warning));
+    }
+    badges
+}
+
+fn bench(tags: &Tags) -> Vec<Badge> {
+    let mut badges = Vec::new();
+    match tags.get("backrest") {
+        Some("yes") => badges.push(Badge::new("🪑", "Backrest", Tone::Good)),
+        Some("no") => badges.push(Badge::new("🪑", "No backrest", Tone::Neutral)),
+        _ => {}
+    }
+    if let Some(seats) = tags.get("seats").and_then(|s| s.parse::<u32>().ok()) {
+        badges.push(Badge::new("👥", format!("{seats} seats"), Tone::Neutral));
+    }
+    if tags.get("covered") == Some("yes") {
+        badges.push(Badge::new("☂", "Covered", Tone::Good));
+    }
+    badges
+}
+
+fn atm(tags: &Tags) -> Vec<Badge> {
+    let bank = tags.get("brand").or(tags.get("operator"));
+    let mut badges: Vec<Badge> = bank.map(|bank| Badge::new("🏦", bank, Tone::Neutral)).into_iter().collect();
+    if tags.get("cash_in") == Some("yes") {
+        badges.push(Badge::new("📥", "Deposits", Tone::Good));
+    }
+    badges
+}
+
+fn pharmacy(tags: &Tags) -> Option<Badge> {
+    match tags.get("dispensing")? {
+        "yes" => Some(Badge::new("℞", "Prescriptions", Tone::Good)),
+        "no" => Some(Badge::new("℞", "No prescriptions", Tone::Neutral)),
+        _ => None,
+    }
+}
+
+/// What matters about the non-toilet amenities of [`AmenityPack`](crate::packs::AmenityPack)s.
+fn amenity(tags: &Tags) -> Vec<Badge> {
+    match tags.get("amenity") {
+        Some("drinking_water") => drinking_water(tags),
+        Some("bench") => bench(tags),
+        Some("atm") => atm(tags),
+        Some("pharmacy") => pharmacy(tags).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether it's open right now; unknown when there are no hours or they don't parse.
+pub fn open_status(status: OpenStatus) -> Badge {
+    match status {
+        OpenStatus::Open => Badge::new("🟢", "Open now", Tone::Good),
+        OpenStatus::Closed => Badge::new("🔴", "Closed now", Tone::Warning),
+        OpenStatus::Unknown => Badge::new("⚪", "Hours unknown", Tone::Neutral),
+    }
+}
+
+/// Badges for fee, wheelchair access, changing table, genders and access, in
+/// that order, then any specific to what kind of amenity it is.
+pub fn badges(tags: &Tags) -> Vec<Badge> {
+    let mut badges: Vec<Badge> = [fee(tags), wheelchair(tags), changing_table(tags)].into_iter().flatten().collect();
+    badges.extend(genders(tags));
+    badges.extend(access(tags));
+    badges.extend(amenity(tags));
+    badges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::tests::tags;
+
+    fn labels(pairs: &[(&str, &str)]) -> Vec<String> {
+        badges(&tags(pairs)).into_iter().map(|b| b.label).collect()
+    }
+
+    #[test]
+    fn interprets_useful_tags() {
+        assert_eq!(
+            labels(&[("fee", "yes"), ("charge", "0.50 EUR"), ("wheelchair", "limited"), ("changing_table", "yes"), ("access", "customers")]),
+            ["Fee: 0.50 EUR", "Partly accessible", "Changing table", "Customers only"]
+        );
+        assert_eq!(labels(&[("unisex", "yes"), ("male", "yes")]), ["Unisex"]);
+        assert_eq!(labels(&[("male", "yes"), ("female", "yes"), ("access", "yes")]), ["Women", "Men"]);
+        assert!(labels(&[("fee", "unknown"), ("wheelchair", "maybe")]).is_empty());
+        assert_eq!(badges(&tags(&[("wheelchair", "no")]))[0].class(), "badge warning");
+        assert_eq!(open_status(OpenStatus::Closed).label, "Closed now");
+    }
+
+    #[test]
+    fn describes_other_amenities() {
+        assert_eq!(labels(&[("amenity", "bench"), ("backrest", "no"), ("seats", "3"), ("covered", "yes")]), ["No backrest", "3 seats", "Covered"]);
+        assert_eq!(labels(&[("amenity", "drinking_water"), ("bottle", "yes"), ("seasonal", "summer")]), ["Bottle refill", "Seasonal"]);
+        assert_eq!(labels(&[("amenity", "atm"), ("fee", "no"), ("operator", "Sparkasse"), ("cash_in", "yes")]), ["Free", "Sparkasse", "Deposits"]);
+        assert_eq!(labels(&[("amenity", "pharmacy"), ("dispensing", "yes")]), ["Prescriptions"]);
+        // Only for the amenity they describe.
+        assert!(labels(&[("amenity", "toilets"), ("operator", "City"), ("backrest", "yes")]).is_empty());
+    }
+}
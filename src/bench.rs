@@ -0,0 +1,100 @@
+//! Rough micro-benchmarks for the hot path of a refetch: building the query,
+//! parsing the response and deriving rows. They reach into modules the library
+//! doesn't export, so they're ignored tests here rather than `cargo bench` targets:
+//!
+//! ```text
+//! cargo test --release bench -- --ignored --nocapture
+//! ```
+
+use crate::packs::AmenityPack;
+use crate::overpass::Area;
+use crate::query_template::QueryTemplate;
+use crate::ranking::Ranking;
+use crate::sorting::SortState;
+use crate::tags::Tags;
+use crate::{results, vocabulary, OverpassResponse, TableRoot};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 2_000;
+const ELEMENTS: usize = 1_000;
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let per_iter = start.elapsed() / iterations;
+    println!("{name}: {per_iter:?}/iter");
+}
+
+fn overpass_json(elements: usize) -> String {
+    let elements: Vec<String> = (0..elements)
+        .map(|i| {
+            format!(
+                r#"{{"type":"node","id":{i},"lat":52.5,"lon":13.4,"timestamp":"2020-01-01T00:00:00Z","version":3,"tags":{{"amenity":"toilets","fee":"no","wheelchair":"yes"}}}}"#
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"version":0.6,"generator":"bench","osm3s":{{"timestamp_osm_base":"","copyright":""}},"elements":[{}]}}"#,
+        elements.join(",")
+    )
+}
+
+#[test]
+#[ignore]
+fn bench_query_builder() {
+    let origin = (52.52, 13.40);
+    let area = &Area::around(1000, (52.52, 13.4));
+    bench("query, interned", ITERATIONS * 10, || {
+        black_box(AmenityPack::Toilets.query(black_box(origin), area));
+    });
+    bench("query, compiled every time", ITERATIONS * 10, || {
+        let filters = AmenityPack::Toilets.filters_in(vocabulary::regions_at(black_box(origin)));
+        black_box(QueryTemplate::compile(&filters).render(area));
+    });
+}
+
+#[test]
+#[ignore]
+fn bench_parse_and_rows() {
+    let json = overpass_json(ELEMENTS);
+    bench("parse overpass response", ITERATIONS / 10, || {
+        black_box(serde_json::from_str::<OverpassResponse>(black_box(&json)).unwrap());
+    });
+    let response: OverpassResponse = serde_json::from_str(&json).unwrap();
+    let row: Vec<f64> = (0..=ELEMENTS).map(|i| i as f64).collect();
+    let table = TableRoot {
+        distances: vec![row.clone()],
+        durations: vec![row],
+        ..Default::default()
+    };
+    let bathrooms = (response, table, (52.5, 13.4));
+    bench("parse + score + sort rows", ITERATIONS / 10, || {
+        let rows = results::parse(black_box(&bathrooms));
+        black_box(results::sort(results::score(&rows, 0.0, Ranking::default().strategy()), SortState::default(), None, &Default::default()));
+    });
+}
+
+#[test]
+#[ignore]
+fn bench_tag_representation() {
+    let json = r#"{"amenity":"toilets","fee":"no","wheelchair":"yes","opening_hours":"24/7","name":"Public toilet","operator":"City","unisex":"yes","changing_table":"no"}"#;
+    bench("tags into HashMap", ITERATIONS * 10, || {
+        black_box(serde_json::from_str::<HashMap<String, String>>(black_box(json)).unwrap());
+    });
+    bench("tags into Tags", ITERATIONS * 10, || {
+        black_box(serde_json::from_str::<Tags>(black_box(json)).unwrap());
+    });
+    let map: HashMap<String, String> = serde_json::from_str(json).unwrap();
+    let tags: Tags = serde_json::from_str(json).unwrap();
+    bench("HashMap lookups", ITERATIONS * 10, || {
+        black_box((map.get("fee"), map.get("wheelchair"), map.get("name"), map.get("missing")));
+    });
+    bench("Tags lookups", ITERATIONS * 10, || {
+        black_box((tags.get("fee"), tags.get("wheelchair"), tags.get("name"), tags.get("missing")));
+    });
+}
@@ -0,0 +1,131 @@
+//! A typed event bus for subsystems that shouldn't know about each other: the
+//! map and the table tell each other about selections, the notifier hands
+//! messages to the toasts, and service trouble is reported to whoever shows it.
+//!
+//! Events are delivered synchronously to every subscriber in this tab.
+//! [`Bus::broadcast`] also sends them to the app's other tabs over a
+//! `BroadcastChannel`, which is why events are plain serde data.
+
+use crate::OsmType;
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{BroadcastChannel, MessageEvent};
+
+const CHANNEL: &str = "free2pee";
+
+/// Third-party services whose trouble is worth telling the user about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    /// Overpass and OSRM, behind the main search.
+    Search,
+    /// Nominatim, behind the address search.
+    Geocoder,
+}
+
+impl Provider {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Provider::Search => "The OpenStreetMap search",
+            Provider::Geocoder => "Address search",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A message for the user; see [`crate::toast`].
+    Toast { message: String },
+    /// A result was selected, in the table or on the map; `None` clears the selection.
+    Selected { key: Option<(OsmType, i64)> },
+    /// The last request to `provider` failed or worked.
+    ProviderStatus { provider: Provider, ok: bool },
+    /// Another tab saved the value at this storage key; see [`crate::tabs`].
+    Stored { key: String },
+}
+
+#[derive(Clone, Copy)]
+pub struct Bus {
+    last: RwSignal<Option<Event>>,
+    channel: StoredValue<Option<BroadcastChannel>>,
+}
+
+impl Bus {
+    pub fn publish(&self, event: Event) {
+        self.last.set(Some(event));
+    }
+
+    /// Publish here and in the app's other open tabs.
+    pub fn broadcast(&self, event: Event) {
+        self.channel.with_value(|channel| {
+            let json = channel.as_ref().and_then(|_| serde_json::to_string(&event).ok());
+            if let (Some(channel), Some(json)) = (channel, json) {
+                if let Err(e) = channel.post_message(&json.into()) {
+                    log::warn!("couldn't broadcast {event:?}: {e:?}");
+                }
+            }
+        });
+        self.publish(event);
+    }
+
+    /// Call `f` with every event published from now on, for as long as `cx` lives.
+    pub fn subscribe(&self, cx: Scope, f: impl Fn(&Event) + 'static) {
+        let last = self.last;
+        create_effect(cx, move |first: Option<()>| {
+            // Cloned rather than borrowed, so `f` can publish in turn.
+            let event = last.get();
+            if let (Some(()), Some(event)) = (first, event) {
+                f(&event);
+            }
+        });
+    }
+}
+
+fn open_channel(last: RwSignal<Option<Event>>) -> Option<BroadcastChannel> {
+    let channel = BroadcastChannel::new(CHANNEL).ok()?;
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |ev: MessageEvent| {
+        match ev.data().as_string().map(|json| serde_json::from_str::<Event>(&json)) {
+            Some(Ok(event)) => last.set(Some(event)),
+            _ => log::warn!("ignoring malformed broadcast"),
+        }
+    });
+    channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+    Some(channel)
+}
+
+pub fn provide_bus(cx: Scope) -> Bus {
+    let last = create_rw_signal(cx, None);
+    let bus = Bus { last, channel: store_value(cx, open_channel(last)) };
+    on_cleanup(cx, move || {
+        bus.channel.with_value(|c| c.as_ref().map(BroadcastChannel::close));
+    });
+    provide_context(cx, bus);
+    bus
+}
+
+/// Without a provider, events only reach subscribers of this fallback bus.
+pub fn use_bus(cx: Scope) -> Bus {
+    use_context(cx).unwrap_or_else(|| Bus {
+        last: create_rw_signal(cx, None),
+        channel: store_value(cx, None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_tagged_json() {
+        let event = Event::Selected { key: Some((OsmType::Way, 42)) };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"type":"selected","key":["way",42]}"#);
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+        let status: Event = serde_json::from_str(r#"{"type":"provider_status","provider":"geocoder","ok":false}"#).unwrap();
+        assert_eq!(status, Event::ProviderStatus { provider: Provider::Geocoder, ok: false });
+    }
+}
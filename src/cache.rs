@@ -0,0 +1,183 @@
+//! Timestamped results cache, keyed by amenity pack, search center and radius.
+//! Stale-while-revalidate: results younger than the user's threshold are used
+//! as they are, older ones are shown right away while fresh ones are fetched
+//! in the background, and ones past [`MAX_STALE_MIN`] aren't shown at all.
+
+use crate::{packs::AmenityPack, storage, Bathrooms};
+use serde_derive::{Deserialize, Serialize};
+
+/// Cached results younger than this are used without refetching, unless the user picked otherwise.
+pub const DEFAULT_MAX_AGE_MIN: u32 = 15;
+
+/// Past this, results are too old to show even while refreshing them.
+pub const MAX_STALE_MIN: u32 = 24 * 60;
+
+/// Entries kept; storing more evicts the oldest.
+const MAX_ENTRIES: usize = 20;
+
+const PREFIX: &str = "cache.";
+
+/// Each entry's key and when it was fetched, so evicting doesn't have to read them all.
+pub const INDEX_KEY: &str = "cache.index";
+const MS_PER_MIN: f64 = 60.0 * 1000.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedBathrooms {
+    pub fetched_at: f64,
+    pub bathrooms: Bathrooms,
+}
+
+/// What became of a cached entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freshness {
+    /// Used as is.
+    Fresh { age_ms: f64 },
+    /// Too old, so shown while fetched again.
+    Stale { age_ms: f64 },
+}
+
+impl Freshness {
+    pub fn judge(fetched_at: f64, now: f64, max_age_min: u32) -> Freshness {
+        let age_ms = (now - fetched_at).max(0.0);
+        if age_ms < max_age_min as f64 * MS_PER_MIN {
+            Freshness::Fresh { age_ms }
+        } else {
+            Freshness::Stale { age_ms }
+        }
+    }
+
+    pub fn describe(&self, max_age_min: u32) -> String {
+        match *self {
+            Freshness::Fresh { age_ms } if age_ms < MS_PER_MIN => "Showing saved results from just now.".to_string(),
+            Freshness::Fresh { age_ms } => format!("Showing saved results from {:.0} min ago.", (age_ms / MS_PER_MIN).floor()),
+            Freshness::Stale { age_ms } => format!(
+                "Saved results are {:.0} min old (over {max_age_min} min); fetching new ones.",
+                (age_ms / MS_PER_MIN).floor()
+            ),
+        }
+    }
+}
+
+/// ~100 m of rounding so small GPS jitter still hits the same entry.
+pub fn cache_key((lat, lon): (f64, f64), radius: i64, pack: AmenityPack) -> String {
+    format!("{PREFIX}{}.{lat:.3},{lon:.3},{radius}", pack.key())
+}
+
+/// The entry and how fresh it is; `None` when there's none worth showing.
+pub fn lookup(key: &str, max_age_min: u32) -> Option<(Freshness, CachedBathrooms)> {
+    let cached: CachedBathrooms = storage::load(key)?;
+    let now = js_sys::Date::now();
+    let too_old = now - cached.fetched_at >= MAX_STALE_MIN.max(max_age_min) as f64 * MS_PER_MIN;
+    (!too_old).then(|| (Freshness::judge(cached.fetched_at, now, max_age_min), cached))
+}
+
+pub fn load_fresh(key: &str, max_age_min: u32) -> Option<Bathrooms> {
+    match lookup(key, max_age_min)? {
+        (Freshness::Fresh { .. }, cached) => Some(cached.bathrooms),
+        (Freshness::Stale { .. }, _) => None,
+    }
+}
+
+/// Whatever is stored under `key`, however old; for when there's no network.
+pub fn load_any(key: &str) -> Option<CachedBathrooms> {
+    storage::load(key)
+}
+
+/// Key of the most recently fetched of `entries`.
+pub fn newest_key(entries: Vec<(String, f64)>) -> Option<String> {
+    entries.into_iter().max_by(|(_, a), (_, b)| a.total_cmp(b)).map(|(key, _)| key)
+}
+
+/// The last results fetched for `pack` anywhere, however old.
+pub fn newest(pack: AmenityPack) -> Option<CachedBathrooms> {
+    let prefix = format!("{PREFIX}{}.", pack.key());
+    let entries = index().into_iter().filter(|(key, _)| key.starts_with(&prefix)).collect();
+    load_any(&newest_key(entries)?)
+}
+
+/// Keys of all but the `keep` newest entries.
+pub fn evictions(mut entries: Vec<(String, f64)>, keep: usize) -> Vec<String> {
+    entries.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    entries.into_iter().skip(keep).map(|(key, _)| key).collect()
+}
+
+/// `index` with `key` fetched at `fetched_at`, in place of any earlier entry for it.
+pub fn indexed(mut index: Vec<(String, f64)>, key: &str, fetched_at: f64) -> Vec<(String, f64)> {
+    index.retain(|(k, _)| k != key);
+    index.push((key.to_string(), fetched_at));
+    index
+}
+
+/// Every entry's key and when it was fetched.
+fn index() -> Vec<(String, f64)> {
+    storage::load(INDEX_KEY).unwrap_or_else(scan)
+}
+
+/// [`index`] the slow way, by reading every entry; for caches from before there was one.
+fn scan() -> Vec<(String, f64)> {
+    storage::entries()
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(PREFIX) && key != INDEX_KEY)
+        .filter_map(|(key, _)| {
+            let fetched_at = storage::load::<CachedBathrooms>(&key)?.fetched_at;
+            Some((key, fetched_at))
+        })
+        .collect()
+}
+
+pub fn store(key: &str, bathrooms: &Bathrooms) {
+    let fetched_at = js_sys::Date::now();
+    storage::save(
+        key,
+        &CachedBathrooms {
+            fetched_at,
+            bathrooms: bathrooms.clone(),
+        },
+    );
+    let mut index = indexed(index(), key, fetched_at);
+    let evicted = evictions(index.clone(), MAX_ENTRIES);
+    for key in &evicted {
+        storage::remove(key);
+    }
+    index.retain(|(key, _)| !evicted.contains(key));
+    storage::save(INDEX_KEY, &index);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_go_stale_at_the_threshold() {
+        let minute = MS_PER_MIN;
+        assert_eq!(Freshness::judge(0.0, 9.0 * minute, 10), Freshness::Fresh { age_ms: 9.0 * minute });
+        assert_eq!(Freshness::judge(0.0, 10.0 * minute, 10), Freshness::Stale { age_ms: 10.0 * minute });
+        assert_eq!(Freshness::Fresh { age_ms: 4.5 * minute }.describe(10), "Showing saved results from 4 min ago.");
+        assert_eq!(
+            Freshness::Stale { age_ms: 32.0 * minute }.describe(10),
+            "Saved results are 32 min old (over 10 min); fetching new ones."
+        );
+    }
+
+    #[test]
+    fn evicts_the_oldest_entries() {
+        let entries = vec![("a".to_string(), 3.0), ("b".to_string(), 1.0), ("c".to_string(), 2.0)];
+        assert_eq!(evictions(entries.clone(), 2), ["b"]);
+        assert!(evictions(entries.clone(), 5).is_empty());
+        assert_eq!(newest_key(entries).as_deref(), Some("a"));
+        assert_eq!(newest_key(Vec::new()), None);
+    }
+
+    #[test]
+    fn reindexes_stored_entries() {
+        let index = vec![("a".to_string(), 1.0), ("b".to_string(), 2.0)];
+        assert_eq!(indexed(index.clone(), "a", 3.0), [("b".to_string(), 2.0), ("a".to_string(), 3.0)]);
+        assert_eq!(indexed(index, "c", 3.0).len(), 3);
+    }
+
+    #[test]
+    fn keys_by_pack_and_rounded_location() {
+        assert_eq!(cache_key((52.520_04, 13.404_96), 1000, AmenityPack::Toilets), "cache.toilets.52.520,13.405,1000");
+        assert_eq!(cache_key((52.52, 13.405), 500, AmenityPack::DrinkingWater), "cache.drinking_water.52.520,13.405,500");
+    }
+}
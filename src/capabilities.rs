@@ -0,0 +1,60 @@
+//! Optional integrations, each behind a provider trait so the rest of the app
+//! only asks "is there something that can do X?".
+//!
+//! Providers that need third-party accounts are compiled in with a cargo feature
+//! and are absent from default builds.
+
+use async_trait::async_trait;
+use leptos::{error::Result, *};
+use std::rc::Rc;
+
+/// Turns coordinates into a short word address (e.g. what3words) that's easy to read out loud.
+#[async_trait(?Send)]
+pub trait WordAddressProvider {
+    fn name(&self) -> &'static str;
+    async fn word_address(&self, lat: f64, lon: f64) -> Result<String>;
+}
+
+/// Registry of the providers available in this build and configuration.
+#[derive(Clone, Default)]
+pub struct Capabilities {
+    pub word_address: Option<Rc<dyn WordAddressProvider>>,
+}
+
+impl Capabilities {
+    pub fn detect() -> Capabilities {
+        Capabilities {
+            #[cfg(feature = "what3words")]
+            word_address: crate::what3words::What3Words::from_config()
+                .map(|p| Rc::new(p) as Rc<dyn WordAddressProvider>),
+            #[cfg(not(feature = "what3words"))]
+            word_address: None,
+        }
+    }
+}
+
+pub fn provide_capabilities(cx: Scope) {
+    provide_context(cx, Capabilities::detect());
+}
+
+pub fn use_capabilities(cx: Scope) -> Capabilities {
+    use_context::<Capabilities>(cx).unwrap_or_default()
+}
+
+/// The word address of a point, or nothing at all when no provider is configured.
+#[component]
+pub fn WordAddress(cx: Scope, lat: f64, lon: f64) -> impl IntoView {
+    let provider = use_capabilities(cx).word_address;
+    provider.map(|provider| {
+        let name = provider.name();
+        let address = create_local_resource(cx, || (), move |_| {
+            let provider = provider.clone();
+            async move { provider.word_address(lat, lon).await }
+        });
+        view! { cx,
+            <span class="word-address" title=name>
+                {move || address.read(cx).map(|a| a.unwrap_or_else(|e| e.to_string()))}
+            </span>
+        }
+    })
+}
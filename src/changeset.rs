@@ -0,0 +1,105 @@
+//! Changeset comments and hashtags for edits made through the app, as the user
+//! configured them, checked against what the OSM API will take.
+
+use thiserror::Error;
+
+pub const DEFAULT_COMMENT_TEMPLATE: &str = "Add {key}={value} to toilets";
+pub const DEFAULT_HASHTAGS: &str = "#free2pee";
+
+/// OSM rejects tag values longer than this many characters.
+pub const MAX_TAG_LENGTH: usize = 255;
+
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum ChangesetError {
+    #[error("The changeset comment can't be empty.")]
+    EmptyComment,
+    #[error("The changeset comment is {0} characters; the limit is {MAX_TAG_LENGTH}.")]
+    TooLong(usize),
+    #[error("The changeset comment can't contain control characters.")]
+    ForbiddenChar,
+    #[error("\"{0}\" isn't a hashtag; use # followed by letters, digits, - or _.")]
+    BadHashtag(String),
+}
+
+/// Fill in `{key}` and `{value}`.
+pub fn render_comment(template: &str, key: &str, value: &str) -> String {
+    template.replace("{key}", key).replace("{value}", value).trim().to_string()
+}
+
+/// Hashtags separated by spaces, commas or semicolons.
+pub fn parse_hashtags(input: &str) -> Result<Vec<String>, ChangesetError> {
+    input
+        .split([' ', ',', ';'])
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| {
+            let name = tag.strip_prefix('#').unwrap_or_default();
+            let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            valid.then(|| tag.to_string()).ok_or_else(|| ChangesetError::BadHashtag(tag.to_string()))
+        })
+        .collect()
+}
+
+/// The comment as uploaded: with any hashtags it doesn't mention yet appended, like iD does.
+pub fn full_comment(comment: &str, hashtags: &[String]) -> String {
+    let missing: Vec<&str> = hashtags
+        .iter()
+        .filter(|tag| !comment.contains(tag.as_str()))
+        .map(String::as_str)
+        .collect();
+    if missing.is_empty() {
+        comment.to_string()
+    } else {
+        format!("{comment} {}", missing.join(" "))
+    }
+}
+
+pub fn validate(comment: &str, hashtags: &[String]) -> Result<(), ChangesetError> {
+    if comment.trim().is_empty() {
+        return Err(ChangesetError::EmptyComment);
+    }
+    if comment.chars().any(char::is_control) {
+        return Err(ChangesetError::ForbiddenChar);
+    }
+    let length = full_comment(comment, hashtags).chars().count();
+    if length > MAX_TAG_LENGTH {
+        return Err(ChangesetError::TooLong(length));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_placeholders() {
+        assert_eq!(render_comment(DEFAULT_COMMENT_TEMPLATE, "fee", "no"), "Add fee=no to toilets");
+        assert_eq!(render_comment("Survey ", "fee", "no"), "Survey");
+    }
+
+    #[test]
+    fn parses_hashtags() {
+        assert_eq!(parse_hashtags("#free2pee, #toilets;#a_b-1").unwrap(), vec!["#free2pee", "#toilets", "#a_b-1"]);
+        assert_eq!(parse_hashtags("  ").unwrap(), Vec::<String>::new());
+        assert_eq!(parse_hashtags("free2pee"), Err(ChangesetError::BadHashtag("free2pee".to_string())));
+        assert_eq!(parse_hashtags("#"), Err(ChangesetError::BadHashtag("#".to_string())));
+        assert_eq!(parse_hashtags("#a&b"), Err(ChangesetError::BadHashtag("#a&b".to_string())));
+    }
+
+    #[test]
+    fn appends_unmentioned_hashtags() {
+        let tags = vec!["#free2pee".to_string(), "#toilets".to_string()];
+        assert_eq!(full_comment("Add fee #toilets", &tags), "Add fee #toilets #free2pee");
+    }
+
+    #[test]
+    fn validates_comment() {
+        let tags = vec!["#free2pee".to_string()];
+        assert_eq!(validate("Add fee", &tags), Ok(()));
+        assert_eq!(validate("  ", &tags), Err(ChangesetError::EmptyComment));
+        assert_eq!(validate("Add\nfee", &tags), Err(ChangesetError::ForbiddenChar));
+        let long = "x".repeat(250);
+        assert_eq!(validate(&long, &tags), Err(ChangesetError::TooLong(260)));
+        assert_eq!(validate(&long, &[]), Ok(()));
+    }
+}
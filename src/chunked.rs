@@ -0,0 +1,107 @@
+//! Rendering long lists a frame at a time. Inserting a few thousand rows in
+//! one go blocks the main thread for long enough to drop frames (and taps),
+//! so past [`CHUNK_THRESHOLD`] items only a growing prefix is rendered, sized
+//! to what the last frame managed within [`FRAME_BUDGET_MS`].
+
+use crate::tasks::Progress;
+use leptos::*;
+
+/// Lists up to this long are rendered in one go.
+pub const CHUNK_THRESHOLD: usize = 200;
+
+/// Leaves room for layout and paint in a 60 Hz frame.
+pub const FRAME_BUDGET_MS: f64 = 10.0;
+
+const FIRST_CHUNK: usize = 50;
+const MIN_CHUNK: usize = 10;
+const MAX_CHUNK: usize = 400;
+
+/// The next chunk size, given how long the frame that rendered `chunk` items took.
+pub fn next_chunk(chunk: usize, frame_ms: f64) -> usize {
+    if frame_ms > FRAME_BUDGET_MS * 1.5 {
+        (chunk / 2).max(MIN_CHUNK)
+    } else if frame_ms < FRAME_BUDGET_MS / 2.0 {
+        (chunk * 2).min(MAX_CHUNK)
+    } else {
+        chunk
+    }
+}
+
+/// How many of `len` items to show when the list changes, if `shown` were
+/// visible before. Keeps what's already rendered, so reorders and small
+/// changes don't start over, and only holds back new items past the threshold.
+pub fn initially_shown(len: usize, shown: usize) -> usize {
+    if len <= CHUNK_THRESHOLD {
+        len
+    } else {
+        shown.clamp(FIRST_CHUNK, len)
+    }
+}
+
+/// A view of `items` that catches up with it over as many animation frames as
+/// it takes, with `progress` while it's behind. Render `visible` instead of
+/// `items`.
+#[derive(Clone, Copy)]
+pub struct Chunked<T: 'static> {
+    pub visible: Memo<Vec<T>>,
+    pub progress: Signal<Option<Progress>>,
+}
+
+pub fn render_in_chunks<T: Clone + PartialEq + 'static>(cx: Scope, items: Memo<Vec<T>>) -> Chunked<T> {
+    let (shown, set_shown) = create_signal(cx, 0);
+    let chunk = store_value(cx, FIRST_CHUNK);
+    let len = create_memo(cx, move |_| items.with(Vec::len));
+    create_effect(cx, move |_| {
+        let len = len.get();
+        set_shown.set(initially_shown(len, shown.get_untracked()));
+    });
+    create_effect(cx, move |_| {
+        let (shown, len) = (shown.get(), len.get());
+        if shown >= len {
+            return;
+        }
+        let started = now_ms();
+        request_animation_frame(move || {
+            // Roughly the cost of the previous chunk, layout and paint included.
+            let size = next_chunk(chunk.get_value(), now_ms() - started);
+            chunk.set_value(size);
+            set_shown.update(|s| *s = (*s + size).min(len));
+        });
+    });
+    let visible = create_memo(cx, move |_| items.with(|items| items[..shown.get().min(items.len())].to_vec()));
+    let progress = Signal::derive(cx, move || {
+        let (done, total) = (shown.get().min(len.get()), len.get());
+        (done < total).then_some(Progress { done, total })
+    });
+    Chunked { visible, progress }
+}
+
+fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|w| w.performance())
+        .map_or_else(js_sys::Date::now, |p| p.now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adapts_the_chunk_to_the_frame_budget() {
+        assert_eq!(next_chunk(100, 30.0), 50);
+        assert_eq!(next_chunk(100, 10.0), 100);
+        assert_eq!(next_chunk(100, 2.0), 200);
+        assert_eq!(next_chunk(MIN_CHUNK, 100.0), MIN_CHUNK);
+        assert_eq!(next_chunk(MAX_CHUNK, 0.0), MAX_CHUNK);
+    }
+
+    #[test]
+    fn only_holds_back_long_lists() {
+        assert_eq!(initially_shown(150, 0), 150);
+        assert_eq!(initially_shown(1000, 0), FIRST_CHUNK);
+        // A reorder or refetch keeps what's on screen.
+        assert_eq!(initially_shown(1000, 1000), 1000);
+        assert_eq!(initially_shown(1000, 600), 600);
+        assert_eq!(initially_shown(500, 1000), 500);
+    }
+}
@@ -0,0 +1,103 @@
+//! Locale-aware string ordering for sorting by name. In the browser this is
+//! `Intl.Collator`, so umlauts, accents and CJK sort the way readers expect;
+//! elsewhere (tests) a diacritic-folding comparison stands in.
+
+use crate::i18n::Locale;
+use std::cmp::Ordering;
+
+pub struct Collator {
+    #[cfg(target_arch = "wasm32")]
+    compare: js_sys::Function,
+    #[cfg(not(target_arch = "wasm32"))]
+    _locale: Locale,
+}
+
+impl Collator {
+    /// Numeric collation, so "Toilet 2" sorts before "Toilet 10".
+    #[cfg(target_arch = "wasm32")]
+    pub fn new(locale: Locale) -> Collator {
+        use wasm_bindgen::JsValue;
+        let locales = js_sys::Array::of1(&JsValue::from_str(locale.code()));
+        let options = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&options, &"numeric".into(), &JsValue::TRUE);
+        let collator = js_sys::Intl::Collator::new(&locales, &options);
+        Collator { compare: collator.compare() }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(locale: Locale) -> Collator {
+        Collator { _locale: locale }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let result = self
+            .compare
+            .call2(&wasm_bindgen::JsValue::NULL, &a.into(), &b.into())
+            .ok()
+            .and_then(|v| v.as_f64());
+        match result {
+            Some(n) => n.partial_cmp(&0.0).unwrap_or(Ordering::Equal),
+            None => fallback_compare(a, b),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        fallback_compare(a, b)
+    }
+}
+
+/// Base letter of common Latin letters with diacritics.
+fn fold(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => 'a',
+        'ç' | 'č' | 'ć' => 'c',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ě' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' => 'i',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'š' | 'ś' => 's',
+        'ž' | 'ź' | 'ż' => 'z',
+        c => c,
+    }
+}
+
+fn sort_key(s: &str) -> String {
+    s.chars()
+        .flat_map(char::to_lowercase)
+        .map(fold)
+        .collect::<String>()
+        .replace('ß', "ss")
+}
+
+/// Case- and accent-insensitive first, then by the original text so the order is total.
+pub fn fallback_compare(a: &str, b: &str) -> Ordering {
+    sort_key(a).cmp(&sort_key(b)).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&'static str]) -> Vec<&'static str> {
+        let collator = Collator::new(Locale::De);
+        let mut names = names.to_vec();
+        names.sort_by(|a, b| collator.compare(a, b));
+        names
+    }
+
+    #[test]
+    fn umlauts_sort_with_their_base_letter() {
+        assert_eq!(sorted(&["Zoo", "Österreich", "Oase", "Apfel"]), ["Apfel", "Oase", "Österreich", "Zoo"]);
+        assert_eq!(sorted(&["Straße", "Strasse", "Strand"]), ["Strand", "Strasse", "Straße"]);
+    }
+
+    #[test]
+    fn ignores_case_and_accents() {
+        assert_eq!(sorted(&["école", "Eglise", "zebra"]), ["école", "Eglise", "zebra"]);
+        assert_eq!(fallback_compare("Café", "cafe"), Ordering::Less);
+    }
+}
@@ -0,0 +1,142 @@
+//! A place pinned as the destination, and a small compass in the header that
+//! points to it from every page, so users can keep walking while they look at
+//! something else. The arrow turns with the phone where the browser reports
+//! a compass heading (`deviceorientation`); elsewhere it points relative to
+//! north, with the compass point spelled out.
+
+use crate::geo::{bearing_deg, compass_point, haversine_m};
+use crate::measure::format_distance;
+use crate::{follow, idle, storage, tabs};
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+
+pub const STORAGE_KEY: &str = "pinned_destination";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pin {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+pub fn provide_pin(cx: Scope) -> RwSignal<Option<Pin>> {
+    let pin = create_rw_signal(cx, storage::load::<Pin>(STORAGE_KEY));
+    tabs::use_tabs(cx).on_stored_elsewhere(cx, |key| key == STORAGE_KEY, move |_| pin.set(storage::load(STORAGE_KEY)));
+    provide_context(cx, pin);
+    pin
+}
+
+pub fn use_pin(cx: Scope) -> RwSignal<Option<Pin>> {
+    use_context(cx).unwrap_or_else(|| create_rw_signal(cx, None))
+}
+
+/// Pins `place`, or unpins with `None`, and remembers it.
+pub fn set_pin(pin: RwSignal<Option<Pin>>, place: Option<Pin>) {
+    match &place {
+        Some(place) => storage::save(STORAGE_KEY, place),
+        None => storage::remove(STORAGE_KEY),
+    }
+    pin.set(place);
+}
+
+/// Degrees clockwise from north the phone faces. Safari reports it directly;
+/// other browsers only when `alpha` is relative to the earth, not to however
+/// the phone was held when the page loaded.
+pub fn heading(webkit_compass: Option<f64>, alpha: Option<f64>, absolute: bool) -> Option<f64> {
+    match (webkit_compass, alpha) {
+        (Some(heading), _) => Some(heading.rem_euclid(360.0)),
+        (None, Some(alpha)) if absolute => Some((360.0 - alpha).rem_euclid(360.0)),
+        _ => None,
+    }
+}
+
+/// How far to turn the arrow for `bearing` when facing `heading`.
+pub fn arrow_rotation(bearing: f64, heading: Option<f64>) -> f64 {
+    (bearing - heading.unwrap_or(0.0)).rem_euclid(360.0)
+}
+
+fn use_heading(cx: Scope) -> ReadSignal<Option<f64>> {
+    let (heading_deg, set_heading) = create_signal(cx, None::<f64>);
+    window_event_listener(ev::deviceorientation, move |ev| {
+        let webkit = js_sys::Reflect::get(&ev, &"webkitCompassHeading".into()).ok().and_then(|h| h.as_f64());
+        set_heading.set(heading(webkit, ev.alpha(), ev.absolute()));
+    });
+    heading_deg
+}
+
+/// Safari only sends orientation after asking, from a tap; `None` where there's nothing to ask.
+fn permission_request() -> Option<js_sys::Function> {
+    let event = js_sys::Reflect::get(&js_sys::global(), &"DeviceOrientationEvent".into()).ok()?;
+    js_sys::Reflect::get(&event, &"requestPermission".into()).ok()?.dyn_into().ok()
+}
+
+#[component]
+pub fn MiniCompass(cx: Scope) -> impl IntoView {
+    let pin = use_pin(cx);
+    let heading = use_heading(cx);
+    let activity = idle::use_activity(cx);
+    let tracking = Signal::derive(cx, move || pin.with(Option::is_some) && !activity.is_idle());
+    let position = follow::use_live_position(cx, tracking);
+    let (asked, set_asked) = create_signal(cx, false);
+    let ask = move |_| {
+        set_asked.set(true);
+        if let Some(request) = permission_request() {
+            if let Ok(promise) = request.call0(&JsValue::UNDEFINED) {
+                spawn_local(async move {
+                    _ = wasm_bindgen_futures::JsFuture::from(js_sys::Promise::from(promise)).await;
+                });
+            }
+        }
+    };
+    let reading = move || {
+        let target = pin.with(|p| p.as_ref().map(|p| (p.lat, p.lon)))?;
+        let from = position.get()?;
+        Some((haversine_m(from, target), bearing_deg(from, target)))
+    };
+    view! { cx,
+        <Show when=move || pin.with(Option::is_some) fallback=|_| ()>
+            <div class="mini-compass" role="status">
+                {move || match reading() {
+                    Some((distance, bearing)) => view! { cx,
+                        <span
+                            class="arrow"
+                            aria-hidden="true"
+                            style=move || format!("transform: rotate({:.0}deg)", arrow_rotation(bearing, heading.get()))
+                        >"↑"</span>
+                        " "{format_distance(distance)}
+                        {move || heading.get().is_none().then(|| format!(" {}", compass_point(bearing)))}
+                    }.into_view(cx),
+                    None => "Finding you...".into_view(cx),
+                }}
+                " to "{move || pin.with(|p| p.as_ref().map(|p| p.name.clone()))}
+                <Show when=move || heading.get().is_none() && !asked.get() && permission_request().is_some() fallback=|_| ()>
+                    " "<button on:click=ask>"Use compass"</button>
+                </Show>
+                " "<button aria-label="Unpin" on:click=move |_| set_pin(pin, None)>"✕"</button>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heading_needs_an_earth_frame() {
+        assert_eq!(heading(Some(370.0), Some(10.0), false), Some(10.0));
+        assert_eq!(heading(None, Some(90.0), true), Some(270.0));
+        assert_eq!(heading(None, Some(0.0), true), Some(0.0));
+        assert_eq!(heading(None, Some(90.0), false), None);
+        assert_eq!(heading(None, None, true), None);
+    }
+
+    #[test]
+    fn arrow_turns_against_the_heading() {
+        assert_eq!(arrow_rotation(90.0, None), 90.0);
+        assert_eq!(arrow_rotation(90.0, Some(90.0)), 0.0);
+        assert_eq!(arrow_rotation(10.0, Some(350.0)), 20.0);
+        assert_eq!(arrow_rotation(350.0, Some(10.0)), 340.0);
+    }
+}
@@ -0,0 +1,5 @@
+//! Pieces of the finder page that stand on their own.
+
+pub mod error_view;
+pub mod loading;
+pub mod results_table;
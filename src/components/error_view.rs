@@ -0,0 +1,42 @@
+//! Why a search failed, with a way forward: typing a location when there's
+//! none to search around, retrying when the servers couldn't be reached.
+
+use crate::manual_location::ManualLocation;
+use crate::{net, BathroomError};
+use leptos::*;
+
+#[component]
+pub fn ErrorView<F>(cx: Scope, errors: RwSignal<Errors>, on_retry: F) -> impl IntoView
+where
+    F: Fn() + Copy + 'static,
+{
+    let error_list = move || {
+        errors.with(|errors| {
+            errors
+                .iter()
+                .map(|(_, e)| view! { cx, <li>{e.to_string()}</li> })
+                .collect_view(cx)
+        })
+    };
+
+    // Without a location there's nothing to search around, so offer to type one.
+    let location_failed = move || {
+        errors.with(|errors| errors.iter().any(|(_, e)| e.downcast_ref::<BathroomError>().is_some()))
+    };
+    let network_failed = move || {
+        errors.with(|errors| errors.iter().any(|(_, e)| e.downcast_ref::<net::NetError>().is_some()))
+    };
+
+    view! { cx,
+        <div class="error">
+            <h2>"Error"</h2>
+            <ul>{error_list}</ul>
+            <Show when=location_failed fallback=|_| ()>
+                <ManualLocation/>
+            </Show>
+            <Show when=network_failed fallback=|_| ()>
+                <button on:click=move |_| on_retry()>"Retry"</button>
+            </Show>
+        </div>
+    }
+}
@@ -0,0 +1,28 @@
+//! What shows while a search is running.
+
+use crate::tasks::Progress;
+use leptos::*;
+
+/// Tiled searches count their tiles; single queries have nothing to count.
+pub fn loading_text(progress: Progress) -> String {
+    match progress {
+        Progress { done, total } if total > 1 => format!("Loading tiles {done}/{total}..."),
+        _ => "Loading (Suspense Fallback)...".to_string(),
+    }
+}
+
+#[component]
+pub fn Loading(cx: Scope, progress: ReadSignal<Progress>) -> impl IntoView {
+    view! { cx, <div>{move || loading_text(progress.get())}</div> }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_tiles() {
+        assert_eq!(loading_text(Progress { done: 2, total: 6 }), "Loading tiles 2/6...");
+        assert_eq!(loading_text(Progress { done: 0, total: 1 }), "Loading (Suspense Fallback)...");
+    }
+}
@@ -0,0 +1,200 @@
+//! The results table: a keyed row per place with its badges, links and
+//! distances, that expands in place to show the details. Places with toilets
+//! that aren't toilets themselves get their own section below.
+
+use crate::address::use_addresses;
+use crate::annotations::{self, use_annotations};
+use crate::bus::{self, use_bus};
+use crate::filters::use_filter_state;
+use crate::fuzzy::Highlighted;
+use crate::packs::AmenityPack;
+use crate::prefs::use_preferences;
+use crate::results::{RowDetails, ScoredRow};
+use crate::sorting::{SortKey, SortState};
+use crate::{badges, follow, geo, links, opening_hours, osm_links, packs, scoring, Element};
+use leptos::*;
+use std::collections::HashMap;
+
+#[component]
+pub fn ResultsTable(
+    cx: Scope,
+    primary: Memo<Vec<ScoredRow>>,
+    /// `None` when the theme leaves them out.
+    secondary: Option<Memo<Vec<ScoredRow>>>,
+    hidden: Signal<bool>,
+    pack: Memo<AmenityPack>,
+    sort_order: ReadSignal<SortState>,
+    set_sort_order: WriteSignal<SortState>,
+    selected: ReadSignal<Option<Element>>,
+    live_position: ReadSignal<Option<(f64, f64)>>,
+    origin: Memo<Option<(f64, f64)>>,
+    /// Ticks every so often, so "Open now" keeps up.
+    clock: ReadSignal<f64>,
+) -> impl IntoView {
+    let saved_annotations = use_annotations(cx);
+    let bus = use_bus(cx);
+    let prefs = use_preferences(cx);
+    let travel_mode = create_memo(cx, move |_| prefs.with(|p| p.travel_mode));
+    let filter_text = use_filter_state(cx).text;
+    let addresses = use_addresses(cx);
+    let street_addresses = create_memo(cx, move |_| prefs.with(|p| p.street_addresses));
+    // Rows are keyed, so a kept row reads its place's current data from here
+    // rather than what it was created with: a refetch moves the distances.
+    let current_rows = create_memo(cx, move |_| {
+        let mut rows: HashMap<_, _> = primary.with(|rows| rows.iter().map(|r| (r.row.key(), r.clone())).collect());
+        if let Some(secondary) = secondary {
+            secondary.with(|s| rows.extend(s.iter().map(|r| (r.row.key(), r.clone()))));
+        }
+        rows
+    });
+    let row = move |cx: Scope, scored: ScoredRow| {
+        let key = scored.row.key();
+        let current = create_memo(cx, move |_| current_rows.with(|rows| rows.get(&key).cloned()).unwrap_or_else(|| scored.clone()));
+        let place = move || current.with(|r| r.row.element.clone());
+        let element = &place();
+        let (id, location) = (element.id, (element.lat, element.lon));
+        // Row-local, so with keyed rows it survives refetches.
+        let (expanded, set_expanded) = create_signal(cx, false);
+        let annotation_key = annotations::element_key(element);
+        let favorite = {
+            let annotation_key = annotation_key.clone();
+            move || saved_annotations.with(|a| a.get(&annotation_key).is_some_and(|a| a.favorite))
+        };
+        let visited = move || saved_annotations.with(|a| a.get(&annotation_key).is_some_and(|a| a.visited.is_some()));
+        let low_confidence = move || current.with(|r| r.confidence == scoring::Confidence::Low);
+        // The only part of a row that changes as the user walks.
+        let live_distance = create_memo(cx, move |_| live_position.get().map(|at| current.with(|r| follow::distance_from(at, r))));
+        let bearing = move || live_position.get().or(origin.get()).map(|from| geo::compass_point(geo::bearing_deg(from, location)));
+        let select = move |_| bus.publish(bus::Event::Selected { key: Some(key) });
+        view! { cx,
+            <tr
+                class:selected=move || selected.with(|s| s.as_ref().map(|e| e.id) == Some(id))
+                class:low-confidence=low_confidence
+                title=move || low_confidence().then_some("Few details and not edited in years; this may be out of date")
+                on:click=select
+            >
+            // <td>
+            // {format!("{},{}",element.lat, element.lon)}
+            // </td>
+            <td>
+                <button
+                    class="expander"
+                    aria-expanded=move || expanded.get().to_string()
+                    on:click=move |ev| {
+                        ev.stop_propagation();
+                        set_expanded.update(|e| *e = !*e);
+                    }
+                >
+                    {move || if expanded.get() { "▾" } else { "▸" }}
+                </button>
+                {move || packs::icon(&place().tags)}{move || low_confidence().then_some("?")}{move || favorite().then_some("★")}{move || visited().then_some("✓")}
+            </td>
+            <td>{move || match place().tags.get("name") {
+                Some(name) => view! { cx, <Highlighted text=name.to_string() query=filter_text.into()/> }.into_view(cx),
+                // Unnamed places are told apart by where they are.
+                None => {
+                    (move || {
+                        street_addresses.get().then(|| {
+                            addresses.request(location);
+                            addresses.get(location).map(|address| view! { cx, <span class="address">{address}</span> })
+                        })
+                    })
+                    .into_view(cx)
+                }
+            }}</td>
+            <td class="badges">
+                {move || {
+                    let element = place();
+                    let now = opening_hours::WeekTime::from_js_millis(clock.get());
+                    std::iter::once(badges::open_status(opening_hours::status_for_tag(element.tags.get("opening_hours"), now)))
+                        .chain(badges::badges(&element.tags))
+                        .map(|b| view! { cx,
+                            <span class=b.class() title=b.label.clone()>{b.icon}" "{b.label}</span>
+                        })
+                        .collect_view(cx)
+                }}
+            </td>
+            <td>
+            <a href=osm_links::view_url(element) target="_blank">OSM:{element.id}</a>
+            " "
+            <a class="report" href=osm_links::note_url(element) target="_blank" title="Report a problem with this place" on:click=|ev| ev.stop_propagation()>"⚑"</a>
+            </td>
+            <td>
+            // using origin looks more accurate on desktop, but i think current location origin is better for mobile
+                // <a href={format!("https://www.google.com/maps/dir/?api=1&origin={lat},{lon}&destination={},{}", element.lat, element.lon)} target="_blank">"Google Maps"</a>
+                <a href=move || links::google_directions_by(travel_mode.get(), location) target="_blank">"Google Maps"</a>
+            </td>
+            // <td>
+            // <a href={format!("https://www.openstreetmap.org/edit?node={}", element.id)} target="_blank">Edit OSM</a>
+            // </td>
+            <td class:live=move || live_distance.with(Option::is_some)>
+                {move || match live_distance.get() {
+                    Some(d) => format!("{d:.0}"),
+                    None => format!("{:?}", current.with(|r| r.row.distance_m)),
+                }}
+            </td>
+            <td>{bearing}</td>
+            <td>
+                {move || format!("{:?}", current.with(|r| r.row.duration_s))}
+            </td>
+            </tr>
+            {move || expanded.get().then(|| view! { cx,
+                <tr class="details"><RowDetails element=place() columns=8/></tr>
+            })}
+        }
+    };
+    view! { cx,
+        <table prop:hidden=move || hidden.get()>
+        <thead>
+        <tr>
+        // <th>"Node lat,lon"</th>
+        <SortableHeader key=SortKey::Visited label="✓" sort_order set_sort_order/>
+        <SortableHeader key=SortKey::Name label="Name" sort_order set_sort_order/>
+        <th>
+            {move || pack.get().details_heading()}
+            " "<button class="sort" on:click=move |_| set_sort_order.update(|s| *s = s.toggle(SortKey::Fee))>
+                "Fee"{move || sort_order.with(|s| s.indicator(SortKey::Fee))}
+            </button>
+            " "<button class="sort" on:click=move |_| set_sort_order.update(|s| *s = s.toggle(SortKey::Access))>
+                "Access"{move || sort_order.with(|s| s.indicator(SortKey::Access))}
+            </button>
+        </th>
+        <SortableHeader key=SortKey::Id label="OSM Node" sort_order set_sort_order/>
+        <th>"Directions"</th>
+        <SortableHeader key=SortKey::Distance label="Distance [m]" sort_order set_sort_order/>
+        <th>"Bearing"</th>
+        <th>"Duration [s]"</th>
+        </tr>
+        </thead>
+        <tbody>
+            <For each=move || primary.get() key=|r| r.row.key() view=row/>
+        </tbody>
+        {secondary.map(|secondary| view! { cx,
+            <tbody class="secondary" prop:hidden=move || secondary.with(Vec::is_empty)>
+                <tr><th colspan="8">"Other places with toilets (stations, parks, shops...)"</th></tr>
+                <For each=move || secondary.get() key=|r| r.row.key() view=row/>
+            </tbody>
+        })}
+        </table>
+    }
+}
+
+/// A column header that sorts by `key` when clicked, with an arrow when it does.
+#[component]
+fn SortableHeader(
+    cx: Scope,
+    key: SortKey,
+    label: &'static str,
+    sort_order: ReadSignal<SortState>,
+    set_sort_order: WriteSignal<SortState>,
+) -> impl IntoView {
+    view! { cx,
+        <th
+            class="sortable"
+            aria-sort=move || sort_order.with(|s| s.aria(key))
+            on:click=move |_| set_sort_order.update(|s| *s = s.toggle(key))
+        >
+            {label}{move || sort_order.with(|s| s.indicator(key))}
+        </th>
+    }
+}
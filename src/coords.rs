@@ -0,0 +1,221 @@
+//! Coordinate formatting: decimal degrees, DMS, UTM and MGRS grid references.
+//!
+//! UTM uses the usual series expansion of the transverse Mercator projection on
+//! WGS84 (accurate to well under a meter inside a zone). The polar regions,
+//! which use UPS instead, aren't supported and fall back to decimal degrees.
+
+use serde_derive::{Deserialize, Serialize};
+
+const A: f64 = 6_378_137.0;
+const F: f64 = 1.0 / 298.257_223_563;
+const K0: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500_000.0;
+const FALSE_NORTHING_SOUTH: f64 = 10_000_000.0;
+
+const BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWX";
+const MGRS_COLUMNS: [&[u8]; 3] = [b"ABCDEFGH", b"JKLMNPQR", b"STUVWXYZ"];
+const MGRS_ROWS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CoordFormat {
+    #[default]
+    Decimal,
+    Dms,
+    Utm,
+    Mgrs,
+}
+
+impl CoordFormat {
+    pub const ALL: [CoordFormat; 4] = [CoordFormat::Decimal, CoordFormat::Dms, CoordFormat::Utm, CoordFormat::Mgrs];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CoordFormat::Decimal => "Decimal degrees",
+            CoordFormat::Dms => "Degrees, minutes, seconds",
+            CoordFormat::Utm => "UTM",
+            CoordFormat::Mgrs => "MGRS",
+        }
+    }
+
+    pub fn from_label(label: &str) -> CoordFormat {
+        CoordFormat::ALL
+            .into_iter()
+            .find(|f| f.label() == label)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utm {
+    pub zone: u8,
+    pub band: char,
+    pub easting: f64,
+    pub northing: f64,
+}
+
+fn utm_zone(lat: f64, lon: f64) -> u8 {
+    let zone = (((lon + 180.0) / 6.0).floor() as i32).clamp(0, 59) + 1;
+    // Southwest Norway and Svalbard have irregular zones.
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+        return 32;
+    }
+    if (72.0..=84.0).contains(&lat) && lon >= 0.0 {
+        match lon {
+            l if l < 9.0 => return 31,
+            l if l < 21.0 => return 33,
+            l if l < 33.0 => return 35,
+            l if l < 42.0 => return 37,
+            _ => {}
+        }
+    }
+    zone as u8
+}
+
+fn latitude_band(lat: f64) -> char {
+    // Band X is 12° tall, covering 72°N to 84°N.
+    let i = (((lat + 80.0) / 8.0).floor() as usize).min(BANDS.len() - 1);
+    BANDS[i] as char
+}
+
+/// UTM coordinates of a WGS84 point, or `None` outside 80°S..84°N.
+pub fn to_utm(lat: f64, lon: f64) -> Option<Utm> {
+    if !(-80.0..=84.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+    let zone = utm_zone(lat, lon);
+    let lon0 = ((zone as f64 - 1.0) * 6.0 - 180.0 + 3.0).to_radians();
+
+    let e2 = F * (2.0 - F);
+    let e4 = e2 * e2;
+    let e6 = e4 * e2;
+    let ep2 = e2 / (1.0 - e2);
+
+    let phi = lat.to_radians();
+    let (sin, cos, tan) = (phi.sin(), phi.cos(), phi.tan());
+    let n = A / (1.0 - e2 * sin * sin).sqrt();
+    let t = tan * tan;
+    let c = ep2 * cos * cos;
+    let a = cos * (lon.to_radians() - lon0);
+
+    let m = A
+        * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * phi).sin());
+
+    let easting = K0
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0)
+        + FALSE_EASTING;
+    let mut northing = K0
+        * (m + n
+            * tan
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+    if lat < 0.0 {
+        northing += FALSE_NORTHING_SOUTH;
+    }
+
+    Some(Utm {
+        zone,
+        band: latitude_band(lat),
+        easting,
+        northing,
+    })
+}
+
+/// MGRS reference at 1 m precision, e.g. `31N AA 66021 00000`.
+pub fn to_mgrs(lat: f64, lon: f64) -> Option<String> {
+    let utm = to_utm(lat, lon)?;
+    let (e, n) = (utm.easting.floor() as u64, utm.northing.floor() as u64);
+
+    let set = (utm.zone as usize - 1) % 3;
+    let column = MGRS_COLUMNS[set][(e / 100_000) as usize - 1] as char;
+    // Even zones shift the row letters by five.
+    let row_offset = if utm.zone % 2 == 0 { 5 } else { 0 };
+    let row = MGRS_ROWS[((n / 100_000) as usize + row_offset) % MGRS_ROWS.len()] as char;
+
+    Some(format!(
+        "{}{} {column}{row} {:05} {:05}",
+        utm.zone,
+        utm.band,
+        e % 100_000,
+        n % 100_000
+    ))
+}
+
+fn dms(value: f64, positive: char, negative: char) -> String {
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    let total_seconds = (value.abs() * 3600.0 * 10.0).round() / 10.0;
+    let degrees = (total_seconds / 3600.0).floor();
+    let minutes = ((total_seconds - degrees * 3600.0) / 60.0).floor();
+    let seconds = total_seconds - degrees * 3600.0 - minutes * 60.0;
+    format!("{degrees}°{minutes:02}'{seconds:04.1}\"{hemisphere}")
+}
+
+/// `(lat, lon)` in the given format. Grid formats fall back to decimal near the poles.
+pub fn format(lat: f64, lon: f64, format: CoordFormat) -> String {
+    let decimal = || format!("{lat:.5}, {lon:.5}");
+    match format {
+        CoordFormat::Decimal => decimal(),
+        CoordFormat::Dms => format!("{} {}", dms(lat, 'N', 'S'), dms(lon, 'E', 'W')),
+        CoordFormat::Utm => to_utm(lat, lon)
+            .map(|u| format!("{}{} {:.0} {:.0}", u.zone, u.band, u.easting.floor(), u.northing.floor()))
+            .unwrap_or_else(decimal),
+        CoordFormat::Mgrs => to_mgrs(lat, lon).unwrap_or_else(decimal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_utm(lat: f64, lon: f64, zone: u8, band: char, easting: f64, northing: f64) {
+        let utm = to_utm(lat, lon).unwrap();
+        assert_eq!((utm.zone, utm.band), (zone, band), "{lat},{lon}");
+        assert!((utm.easting - easting).abs() < 1.0, "{lat},{lon}: {}", utm.easting);
+        assert!((utm.northing - northing).abs() < 1.0, "{lat},{lon}: {}", utm.northing);
+    }
+
+    #[test]
+    fn utm_reference_points() {
+        assert_utm(0.0, 0.0, 31, 'N', 166_021.44, 0.0);
+        // CN Tower, Toronto.
+        assert_utm(43.642567, -79.387139, 17, 'T', 630_084.3, 4_833_438.6);
+    }
+
+    #[test]
+    fn utm_southern_hemisphere_mirrors_north() {
+        let north = to_utm(33.8688, 151.2093).unwrap();
+        let south = to_utm(-33.8688, 151.2093).unwrap();
+        assert_eq!(south.zone, 56);
+        assert_eq!(south.band, 'H');
+        assert!((south.easting - north.easting).abs() < 1e-6);
+        assert!((south.northing - (FALSE_NORTHING_SOUTH - north.northing)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn utm_zone_exceptions() {
+        assert_eq!(to_utm(60.0, 5.0).unwrap().zone, 32);
+        assert_eq!(to_utm(78.0, 15.0).unwrap().zone, 33);
+        assert_eq!(to_utm(78.0, 8.0).unwrap().zone, 31);
+        assert_eq!(to_utm(83.0, 10.0).unwrap().band, 'X');
+        assert!(to_utm(85.0, 0.0).is_none());
+        assert!(to_utm(-81.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn mgrs_reference_points() {
+        assert_eq!(to_mgrs(0.0, 0.0).unwrap(), "31N AA 66021 00000");
+        assert_eq!(to_mgrs(43.642567, -79.387139).unwrap(), "17T PJ 30084 33438");
+    }
+
+    #[test]
+    fn dms_and_fallbacks() {
+        assert_eq!(format(52.52, -13.405, CoordFormat::Dms), "52°31'12.0\"N 13°24'18.0\"W");
+        assert_eq!(format(-0.5, 0.25, CoordFormat::Decimal), "-0.50000, 0.25000");
+        assert_eq!(format(89.0, 0.0, CoordFormat::Mgrs), "89.00000, 0.00000");
+    }
+}
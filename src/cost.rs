@@ -0,0 +1,180 @@
+//! Rough cost estimate for Overpass queries.
+//!
+//! Overpass gets slow (and eventually times out) on big areas, so before firing a
+//! query we guess how many elements it will return from the searched area and a
+//! per-amenity density table, and warn / suggest splitting when it looks too big.
+
+use std::f64::consts::PI;
+
+/// Expected elements per km² in a fairly dense city, per `amenity` value.
+/// Deliberately on the high side; it's only used to decide when to warn.
+const DENSITY_PER_KM2: &[(&str, f64)] = &[
+    ("toilets", 4.0),
+    ("drinking_water", 6.0),
+    ("bench", 60.0),
+    ("atm", 8.0),
+    ("pharmacy", 3.0),
+    ("shelter", 1.0),
+    ("camp_site", 0.2),
+    ("fuel", 1.0),
+    ("rest_area", 0.05),
+    ("services", 0.05),
+];
+
+/// Density used for amenities missing from the table.
+const DEFAULT_DENSITY_PER_KM2: f64 = 5.0;
+
+/// Above this many expected elements the user gets a warning.
+pub const WARN_ELEMENTS: f64 = 500.0;
+/// Above this many expected elements (or `SPLIT_AREA_KM2`) we suggest splitting.
+pub const SPLIT_ELEMENTS: f64 = 2000.0;
+/// Areas bigger than this tend to hit the Overpass timeout regardless of density.
+pub const SPLIT_AREA_KM2: f64 = 400.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostLevel {
+    Cheap,
+    Heavy,
+    TooLarge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryCost {
+    pub area_km2: f64,
+    pub estimated_elements: f64,
+    pub level: CostLevel,
+}
+
+impl QueryCost {
+    /// Number of roughly equal tiles the query should be split into to bring
+    /// each tile back under the warning threshold. 1 means "don't split".
+    pub fn suggested_tiles(&self) -> usize {
+        let by_elements = (self.estimated_elements / WARN_ELEMENTS).ceil();
+        let by_area = (self.area_km2 / SPLIT_AREA_KM2).ceil();
+        by_elements.max(by_area).max(1.0) as usize
+    }
+
+    /// Human readable warning, or `None` when the query is cheap.
+    pub fn warning(&self) -> Option<String> {
+        match self.level {
+            CostLevel::Cheap => None,
+            CostLevel::Heavy => Some(format!(
+                "This search covers {:.0} km² and may return ~{:.0} results; it could be slow.",
+                self.area_km2, self.estimated_elements
+            )),
+            CostLevel::TooLarge => Some(format!(
+                "This search covers {:.0} km² (~{:.0} results) and will likely time out. Try a smaller area or split it into {} parts.",
+                self.area_km2,
+                self.estimated_elements,
+                self.suggested_tiles()
+            )),
+        }
+    }
+}
+
+/// A radius search cut down after a count said it was too big to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Narrowed {
+    pub count: u64,
+    pub from_m: i64,
+    pub to_m: i64,
+}
+
+impl Narrowed {
+    /// `None` when `count` results within `radius_m` are few enough to fetch.
+    pub fn from_count(radius_m: i64, count: u64) -> Option<Narrowed> {
+        if count as f64 <= SPLIT_ELEMENTS {
+            return None;
+        }
+        // Results grow with the area, so with the square of the radius; whole 100 m.
+        let scaled = radius_m as f64 * (SPLIT_ELEMENTS / count as f64).sqrt();
+        let to_m = ((scaled / 100.0).floor() as i64 * 100).max(100);
+        Some(Narrowed { count, from_m: radius_m, to_m })
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "There are {} results within {} m, too many to fetch at once; showing those within {} m.",
+            self.count, self.from_m, self.to_m
+        )
+    }
+}
+
+pub fn density_per_km2(amenity: &str) -> f64 {
+    DENSITY_PER_KM2
+        .iter()
+        .find(|(name, _)| *name == amenity)
+        .map(|(_, d)| *d)
+        .unwrap_or(DEFAULT_DENSITY_PER_KM2)
+}
+
+pub fn circle_area_km2(radius_m: f64) -> f64 {
+    let r_km = radius_m / 1000.0;
+    PI * r_km * r_km
+}
+
+/// Estimate the cost of querying `amenities` (a union of them) over `area_km2`,
+/// where they're `factor` times as dense as in a city.
+pub fn estimate_query_cost(area_km2: f64, amenities: &[&str], factor: f64) -> QueryCost {
+    let density: f64 = amenities.iter().map(|a| density_per_km2(a)).sum::<f64>() * factor;
+    let estimated_elements = area_km2 * density;
+
+    let level = if estimated_elements > SPLIT_ELEMENTS || area_km2 > SPLIT_AREA_KM2 {
+        CostLevel::TooLarge
+    } else if estimated_elements > WARN_ELEMENTS {
+        CostLevel::Heavy
+    } else {
+        CostLevel::Cheap
+    };
+
+    QueryCost {
+        area_km2,
+        estimated_elements,
+        level,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_radius_is_cheap() {
+        let cost = estimate_query_cost(circle_area_km2(1000.0), &["toilets"], 1.0);
+        assert_eq!(cost.level, CostLevel::Cheap);
+        assert_eq!(cost.suggested_tiles(), 1);
+        assert!(cost.warning().is_none());
+    }
+
+    #[test]
+    fn dense_amenity_unions_get_expensive() {
+        let area = circle_area_km2(3000.0);
+        assert_eq!(estimate_query_cost(area, &["toilets"], 1.0).level, CostLevel::Cheap);
+        assert_eq!(
+            estimate_query_cost(area, &["toilets", "bench"], 1.0).level,
+            CostLevel::Heavy
+        );
+    }
+
+    #[test]
+    fn huge_areas_are_split_even_when_sparse() {
+        let cost = estimate_query_cost(1000.0, &["camp_site"], 1.0);
+        assert_eq!(cost.level, CostLevel::TooLarge);
+        assert_eq!(cost.suggested_tiles(), 3);
+        assert!(cost.warning().unwrap().contains("3 parts"));
+    }
+
+    #[test]
+    fn too_many_counted_results_narrow_the_radius() {
+        assert_eq!(Narrowed::from_count(5000, 1500), None);
+        let narrowed = Narrowed::from_count(5000, 8000).unwrap();
+        assert_eq!(narrowed.to_m, 2500);
+        assert!(narrowed.describe().starts_with("There are 8000 results within 5000 m"));
+        assert_eq!(Narrowed::from_count(300, 1_000_000).unwrap().to_m, 100);
+    }
+
+    #[test]
+    fn unknown_amenity_uses_default_density() {
+        assert_eq!(density_per_km2("nonexistent"), DEFAULT_DENSITY_PER_KM2);
+    }
+}
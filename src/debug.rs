@@ -0,0 +1,142 @@
+//! A debug panel (`?debug=1`) with internals that users don't need to see,
+//! and the last Overpass query and raw response to attach to bug reports.
+
+use crate::intern;
+use crate::perf::{self, FIRST_RESULT_BUDGET_MS};
+use crate::{export, query_template};
+use leptos::*;
+use std::cell::RefCell;
+use std::time::Duration;
+use wasm_bindgen::JsCast;
+
+pub fn enabled() -> bool {
+    matches!(crate::search_params().get("debug").as_deref(), Some("1" | "true"))
+}
+
+/// One Overpass request and what came back, before any parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exchange {
+    pub endpoint: &'static str,
+    pub query: String,
+    pub body: String,
+    pub at_ms: f64,
+}
+
+impl Exchange {
+    /// `overpass-1760000000.json`, by when it was fetched.
+    pub fn filename(&self, ext: &str) -> String {
+        format!("overpass-{}.{ext}", (self.at_ms / 1000.0) as u64)
+    }
+
+    /// The query as Overpass QL, with where it was sent in a comment.
+    pub fn query_file(&self) -> String {
+        format!("/* {} */\n{}\n", self.endpoint, self.query)
+    }
+}
+
+thread_local! {
+    static LAST_EXCHANGE: RefCell<Option<Exchange>> = const { RefCell::new(None) };
+}
+
+/// Keep `exchange` for the panel; responses can be large, so only while it's enabled.
+pub fn record(exchange: impl FnOnce() -> Exchange) {
+    if enabled() {
+        LAST_EXCHANGE.with(|last| *last.borrow_mut() = Some(exchange()));
+    }
+}
+
+fn download_last(file: impl Fn(&Exchange) -> (String, &'static str, String)) {
+    let Some((filename, mime, contents)) = LAST_EXCHANGE.with(|last| last.borrow().as_ref().map(&file)) else {
+        return;
+    };
+    if let Err(e) = export::download(&filename, mime, &contents) {
+        log::error!("couldn't download {filename}: {e:?}");
+    }
+}
+
+/// Size of the WASM linear memory, which only ever grows.
+fn heap_bytes() -> Option<u32> {
+    let memory = wasm_bindgen::memory().dyn_into::<js_sys::WebAssembly::Memory>().ok()?;
+    Some(memory.buffer().dyn_into::<js_sys::ArrayBuffer>().ok()?.byte_length())
+}
+
+fn kib(bytes: usize) -> String {
+    format!("{:.1} KiB", bytes as f64 / 1024.0)
+}
+
+#[component]
+pub fn DebugPanel(cx: Scope) -> impl IntoView {
+    // Marks are recorded outside the reactive system, so poll for new ones.
+    let (timings, set_timings) = create_signal(cx, perf::timings());
+    let (strings, set_strings) = create_signal(cx, intern::stats());
+    let (heap, set_heap) = create_signal(cx, heap_bytes());
+    let last_exchange_at = move || LAST_EXCHANGE.with(|last| last.borrow().as_ref().map(|e| e.at_ms));
+    let (exchange_at, set_exchange_at) = create_signal(cx, last_exchange_at());
+    let poll = move || {
+        set_timings.set(perf::timings());
+        set_strings.set(intern::stats());
+        set_heap.set(heap_bytes());
+        set_exchange_at.set(last_exchange_at());
+    };
+    if let Ok(handle) = set_interval_with_handle(poll, Duration::from_secs(1)) {
+        on_cleanup(cx, move || handle.clear());
+    }
+    let rows = move || {
+        perf::breakdown(&timings.get())
+            .into_iter()
+            .map(|(t, delta)| view! { cx,
+                <tr>
+                    <td>{t.mark.label()}</td>
+                    <td>{format!("{:.0}", t.at_ms)}</td>
+                    <td>{format!("+{delta:.0}")}</td>
+                </tr>
+            })
+            .collect_view(cx)
+    };
+    let budget = move || match perf::over_budget(&timings.get()) {
+        Some(over) => view! { cx,
+            <p class="warning">{format!("{over:.0} ms over the {FIRST_RESULT_BUDGET_MS} ms budget")}</p>
+        },
+        None => view! { cx, <p>{format!("Budget: first row within {FIRST_RESULT_BUDGET_MS} ms")}</p> },
+    };
+
+    view! { cx,
+        <details class="debug" open>
+            <summary>"Debug"</summary>
+            <table>
+                <thead><tr><th>"Mark"</th><th>"ms"</th><th>"Δ ms"</th></tr></thead>
+                <tbody>{rows}</tbody>
+            </table>
+            {budget}
+            <p>{format!("{} query templates interned", query_template::interned_count())}</p>
+            <p>{move || {
+                let s = strings.get();
+                format!("{} tag strings interned ({}), {} reused", s.strings, kib(s.bytes), kib(s.reused_bytes))
+            }}</p>
+            <p>{move || heap.get().map(|b| format!("WASM heap: {}", kib(b as usize)))}</p>
+            <p prop:hidden=move || exchange_at.get().is_none()>
+                "Last Overpass request: "
+                <button on:click=move |_| download_last(|e| (e.filename("overpassql"), "text/plain", e.query_file()))>"Query"</button>
+                " "
+                <button on:click=move |_| download_last(|e| (e.filename("json"), "application/json", e.body.clone()))>"Raw response"</button>
+            </p>
+        </details>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_files_after_the_exchange() {
+        let exchange = Exchange {
+            endpoint: "https://overpass-api.de/api/interpreter",
+            query: "[out:json];node(1);out;".to_string(),
+            body: "{}".to_string(),
+            at_ms: 1_760_000_000_123.0,
+        };
+        assert_eq!(exchange.filename("json"), "overpass-1760000000.json");
+        assert_eq!(exchange.query_file(), "/* https://overpass-api.de/api/interpreter */\n[out:json];node(1);out;\n");
+    }
+}
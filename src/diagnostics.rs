@@ -0,0 +1,289 @@
+//! A self-test page (`#/diagnostics`) for "it doesn't work on my phone": each
+//! subsystem is actually exercised, not just feature-detected, and failures
+//! come with what the user can do about them. The search and geocoder checks
+//! report on the [bus](crate::bus) like real requests do, so the service
+//! status notice agrees with what's shown here.
+
+use crate::bus::{use_bus, Bus, Event, Provider};
+use crate::capabilities::{use_capabilities, Capabilities};
+use crate::net::{self, RetryPolicy};
+use crate::overpass::{Area, Output, QueryBuilder, Select};
+use crate::policy::Service;
+use crate::storage::{self, StorageError};
+use crate::{current_position, geocode, idb, mirrors, BathroomError, OsmType};
+use futures::future::{select, Either};
+use leptos::*;
+use std::time::Duration;
+
+const PROBE_KEY: &str = "diagnostics_probe";
+/// Long enough to answer a permission prompt.
+const GEOLOCATION_WAIT: Duration = Duration::from_secs(20);
+/// One quick try per mirror; the app itself is more patient.
+const PROBE_POLICY: RetryPolicy = RetryPolicy {
+    timeout: Duration::from_secs(10),
+    attempts: 1,
+    base_delay: Duration::ZERO,
+    max_delay: Duration::ZERO,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Storage,
+    OfflineStorage,
+    Geolocation,
+    Overpass,
+    Geocoder,
+    Notifications,
+    WordAddress,
+}
+
+impl Check {
+    pub const ALL: [Check; 7] = [
+        Check::Storage,
+        Check::OfflineStorage,
+        Check::Geolocation,
+        Check::Overpass,
+        Check::Geocoder,
+        Check::Notifications,
+        Check::WordAddress,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Check::Storage => "Settings storage",
+            Check::OfflineStorage => "Offline storage",
+            Check::Geolocation => "Location",
+            Check::Overpass => "OpenStreetMap search (Overpass)",
+            Check::Geocoder => "Address search (Nominatim)",
+            Check::Notifications => "Notifications",
+            Check::WordAddress => "Word addresses",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    /// Works, or isn't needed, but worth knowing.
+    Warn,
+    Fail,
+}
+
+impl Outcome {
+    pub fn class(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "fail",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub outcome: Outcome,
+    pub detail: String,
+    /// What the user can do about it.
+    pub hint: Option<&'static str>,
+}
+
+impl Finding {
+    fn pass(detail: impl Into<String>) -> Finding {
+        Finding { outcome: Outcome::Pass, detail: detail.into(), hint: None }
+    }
+
+    fn warn(detail: impl Into<String>, hint: &'static str) -> Finding {
+        Finding { outcome: Outcome::Warn, detail: detail.into(), hint: Some(hint) }
+    }
+
+    fn fail(detail: impl Into<String>, hint: &'static str) -> Finding {
+        Finding { outcome: Outcome::Fail, detail: detail.into(), hint: Some(hint) }
+    }
+}
+
+pub fn storage_finding(res: Result<bool, StorageError>) -> Finding {
+    match res {
+        Ok(true) => Finding::pass("Saved and read back a test value."),
+        Ok(false) => Finding::fail("A test value didn't read back.", "Another extension or setting may be clearing site data."),
+        Err(StorageError::Unavailable) => Finding::fail(
+            "Storage is turned off.",
+            "Private browsing or blocking cookies for this site turns it off; settings and saved places won't be kept.",
+        ),
+        Err(e) => Finding::fail(e.to_string(), "Delete cached results under Settings to free some space."),
+    }
+}
+
+pub fn geolocation_finding(res: Result<(f64, f64), BathroomError>) -> Finding {
+    match res {
+        Ok(_) => Finding::pass("Got your position."),
+        Err(e @ BathroomError::LocationDenied) => {
+            Finding::fail(e.to_string(), "Allow location for this site in the browser's site settings, then reload.")
+        }
+        Err(e @ BathroomError::LocationTimeout) => {
+            Finding::fail(e.to_string(), "Go near a window or outside, or turn on Wi-Fi to help find your position.")
+        }
+        Err(e @ BathroomError::LocationUnavailable) => {
+            Finding::fail(e.to_string(), "Turn on location services on your device; you can also type an address instead.")
+        }
+    }
+}
+
+/// From `Notification.permission`.
+pub fn notification_finding(permission: Option<&str>) -> Finding {
+    let hint = "Free2Pee only shows messages inside the page, so this isn't needed.";
+    match permission {
+        Some("granted") => Finding::pass("Allowed."),
+        Some("denied") => Finding::warn("Blocked for this site.", hint),
+        Some(_) => Finding::warn("Not asked yet.", hint),
+        None => Finding::warn("Not supported by this browser.", hint),
+    }
+}
+
+fn check_storage() -> Finding {
+    let value = js_sys::Date::now();
+    let res = storage::try_save(PROBE_KEY, &value).map(|()| storage::load::<f64>(PROBE_KEY) == Some(value));
+    storage::remove(PROBE_KEY);
+    storage_finding(res)
+}
+
+async fn check_offline_storage() -> Finding {
+    match idb::usage().await {
+        Ok(stores) => Finding::pass(format!("Opened the database ({} stores).", stores.len())),
+        Err(e) => Finding::fail(e.to_string(), "Edits made offline can't be kept; leave private browsing to enable it."),
+    }
+}
+
+async fn check_geolocation() -> Finding {
+    let position = Box::pin(current_position());
+    let res = match select(position, Box::pin(net::sleep(GEOLOCATION_WAIT))).await {
+        Either::Left((res, _)) => res.map_err(|e| e.downcast_ref::<BathroomError>().cloned().unwrap_or(BathroomError::LocationUnavailable)),
+        Either::Right(_) => Err(BathroomError::LocationTimeout),
+    };
+    geolocation_finding(res)
+}
+
+async fn check_overpass(bus: Bus) -> Finding {
+    let query = QueryBuilder::new()
+        .timeout(5)
+        .select(Select::new(Some(OsmType::Node)).within(Area::around(50, (52.52, 13.405))))
+        .output(Output::Skel)
+        .limit(1)
+        .build();
+    let endpoints = mirrors::endpoints();
+    let urls: Vec<String> = endpoints.iter().map(|e| format!("{e}?data={query}")).collect();
+    let res = net::get_any(Service::Overpass, &urls, &PROBE_POLICY).await;
+    bus.broadcast(Event::ProviderStatus { provider: Provider::Search, ok: res.is_ok() });
+    match res {
+        Ok((i, _)) => {
+            mirrors::remember(endpoints[i]);
+            Finding::pass(format!("Reached {}.", endpoints[i]))
+        }
+        Err(e) => Finding::fail(e.to_string(), "All servers failed; check your connection, or try again in a few minutes."),
+    }
+}
+
+async fn check_geocoder(bus: Bus) -> Finding {
+    let res = geocode::search("Berlin", 1, &geocode::SearchOptions::default()).await;
+    bus.broadcast(Event::ProviderStatus { provider: Provider::Geocoder, ok: res.is_ok() });
+    match res {
+        Ok(found) if !found.is_empty() => Finding::pass("Found a test address."),
+        Ok(_) => Finding::warn("Answered, but found nothing for a test address.", "Address search may be degraded; coordinates still work."),
+        Err(e) => Finding::fail(e.to_string(), "Type coordinates like 52.52,13.40 instead, or try again later."),
+    }
+}
+
+fn check_notifications() -> Finding {
+    let global = js_sys::global();
+    let permission = js_sys::Reflect::get(&global, &"Notification".into())
+        .ok()
+        .filter(|n| !n.is_undefined())
+        .and_then(|n| js_sys::Reflect::get(&n, &"permission".into()).ok())
+        .and_then(|p| p.as_string());
+    notification_finding(permission.as_deref())
+}
+
+fn check_word_address(capabilities: &Capabilities) -> Finding {
+    match &capabilities.word_address {
+        Some(provider) => Finding::pass(format!("{} is configured.", provider.name())),
+        None => Finding::warn("Not included in this build.", "Optional; the app works the same without it."),
+    }
+}
+
+async fn run(check: Check, capabilities: Capabilities, bus: Bus) -> Finding {
+    match check {
+        Check::Storage => check_storage(),
+        Check::OfflineStorage => check_offline_storage().await,
+        Check::Geolocation => check_geolocation().await,
+        Check::Overpass => check_overpass(bus).await,
+        Check::Geocoder => check_geocoder(bus).await,
+        Check::Notifications => check_notifications(),
+        Check::WordAddress => check_word_address(&capabilities),
+    }
+}
+
+#[component]
+pub fn Diagnostics(cx: Scope) -> impl IntoView {
+    let capabilities = use_capabilities(cx);
+    let bus = use_bus(cx);
+    let findings = create_rw_signal(cx, Vec::<(Check, Option<Finding>)>::new());
+    // Checks run side by side and fill in as they finish.
+    let run_all = move |_| {
+        findings.set(Check::ALL.into_iter().map(|c| (c, None)).collect());
+        for check in Check::ALL {
+            let capabilities = capabilities.clone();
+            spawn_local(async move {
+                let finding = run(check, capabilities, bus).await;
+                findings.update(|f| {
+                    if let Some(entry) = f.iter_mut().find(|(c, _)| *c == check) {
+                        entry.1 = Some(finding);
+                    }
+                });
+            });
+        }
+    };
+    let rows = move || {
+        findings.with(|f| {
+            f.iter()
+                .map(|(check, finding)| match finding {
+                    None => view! { cx, <li class="check">{check.label()}": checking..."</li> },
+                    Some(finding) => view! { cx,
+                        <li class=format!("check {}", finding.outcome.class())>
+                            <strong>{check.label()}</strong>": "{finding.detail.clone()}
+                            {finding.hint.map(|hint| view! { cx, <p class="hint">{hint}</p> })}
+                        </li>
+                    },
+                })
+                .collect_view(cx)
+        })
+    };
+    view! { cx,
+        <section class="diagnostics">
+            <h2>"Diagnostics"</h2>
+            <p>"Tests location, storage and the services the app depends on. Your browser may ask for location access."</p>
+            <button on:click=run_all>"Run checks"</button>
+            <ul>{rows}</ul>
+        </section>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_come_with_hints() {
+        assert_eq!(storage_finding(Ok(true)).outcome, Outcome::Pass);
+        assert_eq!(storage_finding(Err(StorageError::Unavailable)).outcome, Outcome::Fail);
+        assert!(storage_finding(Err(StorageError::Write("x".into()))).hint.is_some());
+        let denied = geolocation_finding(Err(BathroomError::LocationDenied));
+        assert_eq!((denied.outcome, denied.detail.as_str()), (Outcome::Fail, "Location access was denied."));
+        assert!(denied.hint.unwrap().contains("site settings"));
+    }
+
+    #[test]
+    fn notifications_are_never_required() {
+        assert_eq!(notification_finding(Some("granted")).outcome, Outcome::Pass);
+        assert_eq!(notification_finding(Some("denied")).outcome, Outcome::Warn);
+        assert_eq!(notification_finding(None).outcome, Outcome::Warn);
+    }
+}
@@ -0,0 +1,57 @@
+//! Elevation lookups from Open-Elevation, for "the nearest toilet is up a hill" situations.
+
+use leptos::error::Result;
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
+const OPEN_ELEVATION_URL: &str = "https://api.open-elevation.com/api/v1/lookup";
+
+/// Differences smaller than this are within the noise of the elevation model.
+const FLAT_M: f64 = 3.0;
+
+#[derive(Error, Clone, Debug)]
+pub enum ElevationError {
+    #[error("No elevation data for this location.")]
+    Missing,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LookupResponse {
+    pub results: Vec<LookupResult>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LookupResult {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: f64,
+}
+
+/// Elevations in meters for each of `points`, in order.
+pub async fn fetch_elevations(points: &[(f64, f64)]) -> Result<Vec<f64>> {
+    let locations: Vec<String> = points.iter().map(|(lat, lon)| format!("{lat},{lon}")).collect();
+    let url = format!("{OPEN_ELEVATION_URL}?locations={}", locations.join("%7C"));
+    let res: LookupResponse = reqwasm::http::Request::get(&url).send().await?.json().await?;
+    if res.results.len() != points.len() {
+        return Err(ElevationError::Missing.into());
+    }
+    Ok(res.results.into_iter().map(|r| r.elevation).collect())
+}
+
+/// Elevation change walking from `from` to `to`, in meters.
+pub async fn fetch_climb(from: (f64, f64), to: (f64, f64)) -> Result<f64> {
+    match fetch_elevations(&[from, to]).await?[..] {
+        [from, to] => Ok(to - from),
+        _ => Err(ElevationError::Missing.into()),
+    }
+}
+
+pub fn climb_label(delta_m: f64) -> String {
+    if delta_m.abs() < FLAT_M {
+        "About level".to_string()
+    } else if delta_m > 0.0 {
+        format!("+{delta_m:.0} m climb")
+    } else {
+        format!("{:.0} m descent", delta_m.abs())
+    }
+}
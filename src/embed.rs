@@ -0,0 +1,177 @@
+//! Embeddable widget mode (`?embed=1`): a compact results list for iframes on
+//! other sites. The location comes only from the URL (`at=lat,lon`, plus the
+//! usual `around`), never from geolocation, which embedders' visitors didn't ask for.
+
+use crate::embed_api::{self, HostMessage, WidgetMessage};
+use crate::packs::{self, AmenityPack};
+use crate::tasks::TaskRunner;
+use crate::theme::{self, Section};
+use crate::{fetch_bathrooms_at, geo, links, narrow_radius, search_params, search_radius, Bathrooms, SearchArea};
+use leptos::{error::Result, *};
+use thiserror::Error;
+
+/// Enough to fill a small iframe; the full app is a link away.
+pub const MAX_RESULTS: usize = 8;
+
+#[derive(Error, Clone, Debug)]
+pub enum EmbedError {
+    #[error("This widget needs a location: add at=lat,lon to its URL.")]
+    NoLocation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmbedConfig {
+    pub location: Option<(f64, f64)>,
+}
+
+impl EmbedConfig {
+    pub fn from_params(flag: Option<&str>, at: Option<&str>) -> Option<EmbedConfig> {
+        matches!(flag, Some("1" | "true")).then(|| EmbedConfig {
+            location: at.and_then(geo::parse_lat_lon),
+        })
+    }
+}
+
+pub fn config() -> Option<EmbedConfig> {
+    let params = search_params();
+    EmbedConfig::from_params(params.get("embed").as_deref(), params.get("at").as_deref())
+}
+
+pub fn enabled() -> bool {
+    config().is_some()
+}
+
+/// Nearest first, as `(element index, distance m, duration s)`.
+pub fn nearest(bathrooms: &Bathrooms, limit: usize) -> Vec<(usize, f64, f64)> {
+    let (_, table, _) = bathrooms;
+    let dists = table.distances.first().cloned().unwrap_or_default();
+    let durs = table.durations.first().cloned().unwrap_or_default();
+    let mut rows: Vec<(usize, f64, f64)> = dists
+        .into_iter()
+        .skip(1)
+        .zip(durs.into_iter().skip(1))
+        .enumerate()
+        .map(|(i, (dist, dur))| (i, dist, dur))
+        .collect();
+    rows.sort_by(|a, b| a.1.total_cmp(&b.1));
+    rows.truncate(limit);
+    rows
+}
+
+async fn fetch(location: Option<(f64, f64)>, radius: i64) -> Result<Bathrooms> {
+    let origin = location.ok_or(EmbedError::NoLocation)?;
+    let pack = AmenityPack::default();
+    // Hosts pick the radius, so it gets the same check as any other search.
+    let (radius, _) = narrow_radius(origin, radius, pack).await;
+    let area = SearchArea::Around(radius);
+    fetch_bathrooms_at(origin, area, pack, &TaskRunner::new(1), |_| {}).await
+}
+
+#[component]
+pub fn EmbedWidget(cx: Scope) -> impl IntoView {
+    let (location, set_location) = create_signal(cx, config().and_then(|c| c.location));
+    let (radius, set_radius) = create_signal(cx, search_radius(AmenityPack::default(), None));
+    let bathrooms = create_local_resource(cx, move || (location.get(), radius.get()), |(location, radius)| {
+        fetch(location, radius)
+    });
+
+    // Host pages that declared their origin can drive the widget and hear about selections.
+    let host = embed_api::host_origin();
+    if let Some(host) = host.clone() {
+        window_event_listener(ev::message, move |ev| {
+            if let Some(HostMessage::Configure { lat, lon, radius }) =
+                embed_api::parse_message(&host, &ev.origin(), ev.data())
+            {
+                set_location.set(Some((lat, lon)));
+                if let Some(radius) = radius {
+                    set_radius.set(radius);
+                }
+            }
+        });
+    }
+    let host = store_value(cx, host);
+    let notify = move |message: WidgetMessage| {
+        host.with_value(|host| {
+            if let Some(host) = host {
+                embed_api::post(host, &message);
+            }
+        })
+    };
+    notify(WidgetMessage::Ready);
+    create_effect(cx, move |_| {
+        if let Some(Ok(b)) = bathrooms.read(cx) {
+            notify(WidgetMessage::Loaded { count: b.0.elements.len() });
+        }
+    });
+
+    let list = move || {
+        bathrooms.read(cx).map(|res| match res {
+            Err(e) => view! { cx, <p class="error">{e.to_string()}</p> }.into_view(cx),
+            Ok(bathrooms) => {
+                let rows = nearest(&bathrooms, MAX_RESULTS)
+                    .into_iter()
+                    .filter_map(|(i, dist, dur)| {
+                        let element = bathrooms.0.elements.get(i)?.clone();
+                        let name = element.tags.get("name").map(str::to_string);
+                        let selected = WidgetMessage::Selected {
+                            osm_type: element.type_field,
+                            id: element.id,
+                            lat: element.lat,
+                            lon: element.lon,
+                            name: name.clone(),
+                            distance_m: dist,
+                        };
+                        let name = name.unwrap_or_else(|| "Toilet".to_string());
+                        Some(view! { cx,
+                            <li on:click=move |_| notify(selected.clone())>
+                                <a href={links::google_directions((element.lat, element.lon))} target="_blank">
+                                    {packs::icon(&element.tags)} " " {name}
+                                </a>
+                                {format!(" {dist:.0} m, {:.0} min", dur / 60.0)}
+                            </li>
+                        })
+                    })
+                    .collect_view(cx);
+                view! { cx, <ol class="embed-results">{rows}</ol> }.into_view(cx)
+            }
+        })
+    };
+
+    view! { cx,
+        <div class="embed">
+            <Transition fallback=move || view! { cx, <p>"Loading..."</p> }>
+                {list}
+            </Transition>
+            {theme::use_theme(cx).shows(Section::Credit).then(|| view! { cx,
+                <a class="embed-credit" href="./" target="_blank">"Free2Pee"</a>
+            })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OverpassResponse, TableRoot};
+
+    #[test]
+    fn needs_the_flag() {
+        assert_eq!(EmbedConfig::from_params(None, Some("1,2")), None);
+        assert_eq!(
+            EmbedConfig::from_params(Some("1"), Some("1,2")),
+            Some(EmbedConfig { location: Some((1.0, 2.0)) })
+        );
+        assert_eq!(EmbedConfig::from_params(Some("1"), None), Some(EmbedConfig { location: None }));
+    }
+
+    #[test]
+    fn nearest_skips_origin_and_sorts() {
+        let table = TableRoot {
+            distances: vec![vec![0.0, 300.0, 100.0, 200.0]],
+            durations: vec![vec![0.0, 30.0, 10.0, 20.0]],
+            ..Default::default()
+        };
+        let bathrooms = (OverpassResponse::default(), table, (0.0, 0.0));
+        assert_eq!(nearest(&bathrooms, 2), vec![(1, 100.0, 10.0), (2, 200.0, 20.0)]);
+    }
+}
@@ -0,0 +1,139 @@
+//! `postMessage` API between the embed widget and its host page.
+//!
+//! The host opts in by naming its origin in the widget URL (`origin=https://example.com`).
+//! Only messages from that origin are accepted, and events are only ever posted to it.
+
+use crate::{geo, radius, OsmType};
+use serde_derive::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::window;
+
+/// Host page to widget.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HostMessage {
+    /// Search around a new location, optionally with a new radius in meters.
+    Configure { lat: f64, lon: f64, radius: Option<i64> },
+}
+
+impl HostMessage {
+    /// `None` for a location that isn't on earth; radii are clamped to what the
+    /// radius slider allows.
+    pub fn validated(self) -> Option<HostMessage> {
+        match self {
+            HostMessage::Configure { lat, lon, radius } => geo::is_valid_lat_lon(lat, lon).then(|| HostMessage::Configure {
+                lat,
+                lon,
+                radius: radius.map(|r| r.clamp(radius::MIN_M, radius::MAX_M)),
+            }),
+        }
+    }
+}
+
+/// Widget to host page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WidgetMessage {
+    /// Listening for [`HostMessage`]s.
+    Ready,
+    Loaded { count: usize },
+    /// `id` is only unique together with `osm_type`.
+    Selected { osm_type: OsmType, id: i64, lat: f64, lon: f64, name: Option<String>, distance_m: f64 },
+}
+
+/// Normalize a `scheme://host[:port]` origin, rejecting anything with a path,
+/// query or credentials, and `*`.
+pub fn parse_origin(s: &str) -> Option<String> {
+    let s = s.trim().trim_end_matches('/').to_ascii_lowercase();
+    let (scheme, host) = s.split_once("://")?;
+    let valid_host = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'));
+    (matches!(scheme, "https" | "http") && valid_host).then_some(s)
+}
+
+/// The host's declared origin, from the `origin` URL parameter.
+pub fn host_origin() -> Option<String> {
+    crate::search_params().get("origin").as_deref().and_then(parse_origin)
+}
+
+/// Whether a message from `origin` should be trusted, given the declared host origin.
+pub fn origin_allowed(host: &str, origin: &str) -> bool {
+    parse_origin(origin).is_some_and(|origin| origin == host)
+}
+
+pub fn parse_message(host: &str, origin: &str, data: JsValue) -> Option<HostMessage> {
+    if !origin_allowed(host, origin) {
+        log::warn!("ignoring message from {origin}");
+        return None;
+    }
+    let message: HostMessage = serde_wasm_bindgen::from_value(data)
+        .map_err(|e| log::warn!("ignoring malformed message: {e}"))
+        .ok()?;
+    let valid = message.clone().validated();
+    if valid.is_none() {
+        log::warn!("ignoring invalid message: {message:?}");
+    }
+    valid
+}
+
+pub fn post(host: &str, message: &WidgetMessage) {
+    let Some(parent) = window().and_then(|w| w.parent().ok().flatten()) else {
+        return;
+    };
+    let Ok(data) = serde_wasm_bindgen::to_value(message) else {
+        return;
+    };
+    if let Err(e) = parent.post_message(&data, host) {
+        log::warn!("postMessage failed: {e:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_origins() {
+        assert_eq!(parse_origin("https://Example.com/"), Some("https://example.com".to_string()));
+        assert_eq!(parse_origin("http://localhost:8080"), Some("http://localhost:8080".to_string()));
+        assert_eq!(parse_origin("*"), None);
+        assert_eq!(parse_origin("https://example.com/path"), None);
+        assert_eq!(parse_origin("https://user@example.com"), None);
+        assert_eq!(parse_origin("javascript://alert(1)"), None);
+    }
+
+    #[test]
+    fn only_the_declared_origin_is_allowed() {
+        let host = "https://example.com";
+        assert!(origin_allowed(host, "https://example.com"));
+        assert!(!origin_allowed(host, "https://example.com.evil.net"));
+        assert!(!origin_allowed(host, "http://example.com"));
+        assert!(!origin_allowed(host, "null"));
+    }
+
+    #[test]
+    fn message_schema() {
+        let configure: HostMessage =
+            serde_json::from_str(r#"{"type":"configure","lat":1.5,"lon":2.5,"radius":500}"#).unwrap();
+        assert_eq!(configure, HostMessage::Configure { lat: 1.5, lon: 2.5, radius: Some(500) });
+        let selected = WidgetMessage::Selected { osm_type: OsmType::Way, id: 7, lat: 1.0, lon: 2.0, name: None, distance_m: 120.0 };
+        assert_eq!(
+            serde_json::to_string(&selected).unwrap(),
+            r#"{"type":"selected","osm_type":"way","id":7,"lat":1.0,"lon":2.0,"name":null,"distance_m":120.0}"#
+        );
+    }
+
+    #[test]
+    fn configure_needs_a_real_place_and_radius() {
+        let configure = |lat, lon, radius| HostMessage::Configure { lat, lon, radius };
+        assert_eq!(configure(52.5, 13.4, Some(500)).validated(), Some(configure(52.5, 13.4, Some(500))));
+        assert_eq!(configure(52.5, 13.4, Some(1_000_000)).validated(), Some(configure(52.5, 13.4, Some(radius::MAX_M))));
+        assert_eq!(configure(52.5, 13.4, Some(-5)).validated(), Some(configure(52.5, 13.4, Some(radius::MIN_M))));
+        assert_eq!(configure(52.5, 13.4, None).validated(), Some(configure(52.5, 13.4, None)));
+        assert_eq!(configure(91.0, 13.4, None).validated(), None);
+        assert_eq!(configure(52.5, -180.5, None).validated(), None);
+        assert_eq!(configure(f64::NAN, 13.4, None).validated(), None);
+    }
+}
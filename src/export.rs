@@ -0,0 +1,99 @@
+//! File exports (GPX, CSV, GeoJSON) and triggering browser downloads for them.
+
+use serde_json::{json, Map, Value};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{window, Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+/// A named point to export, as `(lat, lon, name)`.
+pub type Waypoint = (f64, f64, String);
+
+pub fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// A GPX 1.1 document with `waypoints` as `<wpt>`s and `track` (if not empty) as a single `<trk>`.
+pub fn gpx(name: &str, waypoints: &[Waypoint], track: &[(f64, f64)]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gpx version=\"1.1\" creator=\"Free2Pee\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+    out.push_str(&format!("  <metadata><name>{}</name></metadata>\n", escape_xml(name)));
+    for (lat, lon, name) in waypoints {
+        out.push_str(&format!(
+            "  <wpt lat=\"{lat}\" lon=\"{lon}\"><name>{}</name></wpt>\n",
+            escape_xml(name)
+        ));
+    }
+    if !track.is_empty() {
+        out.push_str(&format!("  <trk><name>{}</name><trkseg>\n", escape_xml(name)));
+        for (lat, lon) in track {
+            out.push_str(&format!("    <trkpt lat=\"{lat}\" lon=\"{lon}\"/>\n"));
+        }
+        out.push_str("  </trkseg></trk>\n");
+    }
+    out.push_str("</gpx>\n");
+    out
+}
+
+/// Offer `contents` to the user as a file download.
+pub fn download(filename: &str, mime: &str, contents: &str) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(contents));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    download_blob(filename, &blob)
+}
+
+/// Offer `blob` to the user as a file download.
+pub fn download_blob(filename: &str, blob: &Blob) -> Result<(), JsValue> {
+    let url = Url::create_object_url_with_blob(blob)?;
+
+    let document = window().and_then(|w| w.document()).ok_or("no document")?;
+    let anchor: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    Url::revoke_object_url(&url)
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A CSV document (RFC 4180) with a header row.
+pub fn csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    let header: Vec<String> = header.iter().map(|h| escape_csv(h)).collect();
+    out.push_str(&header.join(","));
+    out.push_str("\r\n");
+    for row in rows {
+        let row: Vec<String> = row.iter().map(|f| escape_csv(f)).collect();
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// A GeoJSON `FeatureCollection` of points, each `(lat, lon, properties)`.
+pub fn geojson_points(points: &[(f64, f64, Map<String, Value>)]) -> String {
+    let features: Vec<Value> = points
+        .iter()
+        .map(|(lat, lon, properties)| {
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [lon, lat] },
+                "properties": properties,
+            })
+        })
+        .collect();
+    json!({ "type": "FeatureCollection", "features": features }).to_string()
+}
@@ -0,0 +1,85 @@
+//! What parents look for: changing tables and somewhere to feed a baby. OSM has
+//! a few generations of tags for these, so they're read in one place.
+
+use crate::tags::Tags;
+
+/// How much closer a place with a changing table counts in the family ranking.
+pub const CHANGING_TABLE_FACTOR: f64 = 0.5;
+/// And one with a feeding room or a changing table in a dedicated family room.
+pub const FAMILY_ROOM_FACTOR: f64 = 0.4;
+
+/// `changing_table=yes`/`limited`, or the older `diaper=yes` or `diaper=<count>`.
+pub fn has_changing_table(tags: &Tags) -> bool {
+    match tags.get("changing_table") {
+        Some(value) => matches!(value, "yes" | "limited"),
+        None => tags.get("diaper").is_some_and(|v| v == "yes" || v.parse::<u32>().is_ok_and(|n| n > 0)),
+    }
+}
+
+/// A feeding room (`baby_feeding=*`) or a changing table in its own room.
+pub fn has_family_room(tags: &Tags) -> bool {
+    let feeding = tags.get("baby_feeding").is_some_and(|v| v != "no");
+    let dedicated = has_changing_table(tags)
+        && tags.get("changing_table:location").is_some_and(|l| l.split(';').any(|l| l.trim() == "dedicated_room"));
+    feeding || dedicated
+}
+
+/// Multiplier on a row's rank for the family ranking; lower ranks come first.
+pub fn rank_factor(tags: &Tags) -> f64 {
+    if has_family_room(tags) {
+        FAMILY_ROOM_FACTOR
+    } else if has_changing_table(tags) {
+        CHANGING_TABLE_FACTOR
+    } else {
+        1.0
+    }
+}
+
+fn location_label(value: &str) -> &str {
+    match value {
+        "female_toilet" => "women's toilet",
+        "male_toilet" => "men's toilet",
+        "unisex_toilet" => "unisex toilet",
+        "wheelchair_toilet" => "wheelchair toilet",
+        "dedicated_room" => "own room",
+        "room" => "separate room",
+        "sales_area" => "sales area",
+        other => other,
+    }
+}
+
+/// Where the changing table is, from `changing_table:location`: "women's toilet, men's toilet".
+pub fn changing_table_location(tags: &Tags) -> Option<String> {
+    let locations: Vec<&str> = tags
+        .get("changing_table:location")?
+        .split(';')
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(location_label)
+        .collect();
+    (!locations.is_empty()).then(|| locations.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::tests::tags;
+
+    #[test]
+    fn reads_current_and_legacy_changing_table_tags() {
+        assert!(has_changing_table(&tags(&[("changing_table", "yes")])));
+        assert!(!has_changing_table(&tags(&[("changing_table", "no"), ("diaper", "yes")])));
+        assert!(has_changing_table(&tags(&[("diaper", "2")])));
+        assert!(!has_changing_table(&tags(&[("diaper", "0")])));
+        assert_eq!(rank_factor(&tags(&[("baby_feeding", "room")])), FAMILY_ROOM_FACTOR);
+        assert_eq!(rank_factor(&Tags::default()), 1.0);
+    }
+
+    #[test]
+    fn describes_changing_table_locations() {
+        let room = tags(&[("changing_table", "yes"), ("changing_table:location", "female_toilet; dedicated_room")]);
+        assert_eq!(changing_table_location(&room).as_deref(), Some("women's toilet, own room"));
+        assert!(has_family_room(&room));
+        assert_eq!(changing_table_location(&Tags::default()), None);
+    }
+}
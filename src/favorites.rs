@@ -0,0 +1,48 @@
+//! The user's starred places, listed from their saved copies (see
+//! [`annotations`]) so they're there wherever the current search is,
+//! with the straight-line distance from its origin when there is one.
+
+use crate::annotations::{self, use_annotations};
+use crate::geo::haversine_m;
+use crate::measure::format_distance;
+use crate::prefs::use_preferences;
+use crate::toast::use_toasts;
+use crate::{links, osm_links, packs};
+use leptos::*;
+
+#[component]
+pub fn FavoritesSection(cx: Scope, origin: Memo<Option<(f64, f64)>>) -> impl IntoView {
+    let annotations = use_annotations(cx);
+    let prefs = use_preferences(cx);
+    let toasts = use_toasts(cx);
+    let items = move || {
+        annotations.with(|a| {
+            annotations::favorites(a)
+                .into_iter()
+                .map(|(key, element)| {
+                    let key = key.to_string();
+                    let name = element.tags.get("name").unwrap_or("Unnamed").to_string();
+                    let location = (element.lat, element.lon);
+                    let directions = links::google_directions_by(prefs.with_untracked(|p| p.travel_mode), location);
+                    let osm = osm_links::view_url(element);
+                    view! { cx,
+                        <li>
+                            {packs::icon(&element.tags)}" "{name}
+                            {move || origin.get().map(|from| format!(" · {}", format_distance(haversine_m(from, location))))}
+                            " "<a href=directions target="_blank">"Directions"</a>
+                            " "<a href=osm target="_blank">"OSM"</a>
+                            " "<button on:click=move |_| annotations::change(annotations, toasts, &key, |a| a.favorite = false)>"Remove"</button>
+                        </li>
+                    }
+                })
+                .collect_view(cx)
+        })
+    };
+    let empty = move || annotations.with(|a| annotations::favorites(a).is_empty());
+    view! { cx,
+        <section class="favorites" prop:hidden=empty>
+            <h3>"★ Favorites"</h3>
+            <ul>{items}</ul>
+        </section>
+    }
+}
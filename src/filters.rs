@@ -0,0 +1,389 @@
+//! Result filters and named presets of them ("free + accessible + open now").
+//!
+//! Presets live in [`Preferences`](crate::prefs::Preferences). The active
+//! filters, and optionally a preset name, round-trip through the URL
+//! (`?filters=free,open_now&preset=Commute`) so they can be shared. A text
+//! filter narrows results down by name, [fuzzily](crate::fuzzy). Places the
+//! user has or hasn't been to can be picked out too; that stays out of the URL.
+
+use crate::family;
+use crate::fuzzy;
+use crate::opening_hours::{self, OpenStatus, WeekTime};
+use crate::prefs;
+use crate::tags::Tags;
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    Free,
+    Accessible,
+    ChangingTable,
+    OpenNow,
+    /// Hides places known to be closed, keeping ones without usable hours.
+    NotClosed,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 5] =
+        [Filter::Free, Filter::Accessible, Filter::ChangingTable, Filter::OpenNow, Filter::NotClosed];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Filter::Free => "free",
+            Filter::Accessible => "accessible",
+            Filter::ChangingTable => "changing_table",
+            Filter::OpenNow => "open_now",
+            Filter::NotClosed => "not_closed",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Filter::Free => "Free",
+            Filter::Accessible => "Accessible",
+            Filter::ChangingTable => "Changing table",
+            Filter::OpenNow => "Open now",
+            Filter::NotClosed => "Hide closed",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Filter> {
+        Filter::ALL.into_iter().find(|f| f.key() == key.trim())
+    }
+
+    /// Untagged elements don't match: a filter asks for places known to qualify.
+    /// Except [`Filter::NotClosed`], which only drops places known not to.
+    pub fn matches(&self, tags: &Tags, now: WeekTime) -> bool {
+        let tag = |key: &str| tags.get(key);
+        match self {
+            Filter::Free => tag("fee") == Some("no"),
+            Filter::Accessible => matches!(tag("wheelchair"), Some("yes" | "designated")),
+            Filter::ChangingTable => family::has_changing_table(tags),
+            Filter::OpenNow => opening_hours::status_for_tag(tag("opening_hours"), now) == OpenStatus::Open,
+            Filter::NotClosed => opening_hours::status_for_tag(tag("opening_hours"), now) != OpenStatus::Closed,
+        }
+    }
+}
+
+pub type Filters = BTreeSet<Filter>;
+
+pub fn matches_all(filters: &Filters, tags: &Tags, now: WeekTime) -> bool {
+    filters.iter().all(|f| f.matches(tags, now))
+}
+
+/// Whether the name, brand or operator is close to `text`; an empty `text` keeps everything.
+pub fn matches_text(text: &str, tags: &Tags) -> bool {
+    text.trim().is_empty() || ["name", "brand", "operator"].into_iter().any(|key| tags.get(key).is_some_and(|v| fuzzy::find(text, v).is_some()))
+}
+
+/// `free,open_now`; unknown keys are dropped.
+pub fn encode(filters: &Filters) -> String {
+    filters.iter().map(Filter::key).collect::<Vec<_>>().join(",")
+}
+
+pub fn decode(s: &str) -> Filters {
+    s.split(',').filter_map(Filter::from_key).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterPreset {
+    pub name: String,
+    pub filters: Filters,
+}
+
+/// Add or replace the preset with this name.
+pub fn save_preset(presets: &mut Vec<FilterPreset>, preset: FilterPreset) {
+    presets.retain(|p| p.name != preset.name);
+    presets.push(preset);
+}
+
+/// Filters and preset name from the page URL.
+pub fn from_url() -> (Filters, Option<String>) {
+    let params = crate::search_params();
+    let filters = params.get("filters").map(|f| decode(&f)).unwrap_or_default();
+    let preset = params.get("preset").map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    (filters, preset)
+}
+
+/// This page's URL with `filters` (and `preset`) replaced.
+pub fn share_url(filters: &Filters, preset: Option<&str>) -> Option<String> {
+    let url = web_sys::Url::new(&window().location().href().ok()?).ok()?;
+    let params = url.search_params();
+    params.set("filters", &encode(filters));
+    match preset {
+        Some(name) => params.set("preset", name),
+        None => params.delete("preset"),
+    }
+    Some(url.href())
+}
+
+/// The active filters, in context, so every view of the results filters them the same way.
+#[derive(Clone, Copy)]
+pub struct FilterState {
+    pub active: RwSignal<Filters>,
+    /// Typed into the filter box.
+    pub text: RwSignal<String>,
+    /// Only places the user has (`Some(true)`) or hasn't been to.
+    pub visited: RwSignal<Option<bool>>,
+}
+
+impl FilterState {
+    pub fn toggle(&self, filter: Filter) {
+        self.active.update(|a| {
+            if !a.remove(&filter) {
+                a.insert(filter);
+            }
+        });
+    }
+
+    pub fn remove(&self, filter: Filter) {
+        self.active.update(|a| {
+            a.remove(&filter);
+        });
+    }
+
+    pub fn clear(&self) {
+        self.active.set(Filters::new());
+    }
+
+    /// Reactive: whether an element with these tags passes the active filters and the text filter.
+    pub fn keeps(&self, tags: &Tags, now: WeekTime) -> bool {
+        self.active.with(|a| matches_all(a, tags, now)) && self.text.with(|t| matches_text(t, tags))
+    }
+}
+
+/// Whether a place the user has or hasn't `visited` passes `want`.
+pub fn matches_visited(want: Option<bool>, visited: bool) -> bool {
+    want.is_none_or(|want| want == visited)
+}
+
+/// Starts from the filters in the URL.
+pub fn provide_filter_state(cx: Scope) -> FilterState {
+    let state = FilterState {
+        active: create_rw_signal(cx, from_url().0),
+        text: create_rw_signal(cx, String::new()),
+        visited: create_rw_signal(cx, None),
+    };
+    provide_context(cx, state);
+    state
+}
+
+pub fn use_filter_state(cx: Scope) -> FilterState {
+    use_context(cx).unwrap_or_else(|| FilterState {
+        active: create_rw_signal(cx, Filters::new()),
+        text: create_rw_signal(cx, String::new()),
+        visited: create_rw_signal(cx, None),
+    })
+}
+
+/// The active filters as removable chips, above the results.
+#[component]
+pub fn ActiveFilterBar(cx: Scope) -> impl IntoView {
+    let state = use_filter_state(cx);
+    move || {
+        let active = state.active.get();
+        let text = state.text.get();
+        let visited = state.visited.get();
+        (!active.is_empty() || !text.trim().is_empty() || visited.is_some()).then(|| {
+            let chips = active
+                .into_iter()
+                .map(|filter| view! { cx,
+                    <button class="chip active" title="Remove filter" on:click=move |_| state.remove(filter)>
+                        {filter.label()} " ×"
+                    </button>
+                })
+                .collect_view(cx);
+            let text_chip = (!text.trim().is_empty()).then(|| view! { cx,
+                <button class="chip active" title="Remove filter" on:click=move |_| state.text.set(String::new())>
+                    {format!("“{}” ×", text.trim())}
+                </button>
+            });
+            let visited_chip = visited.map(|visited| view! { cx,
+                <button class="chip active" title="Remove filter" on:click=move |_| state.visited.set(None)>
+                    {if visited { "Visited ×" } else { "Not visited ×" }}
+                </button>
+            });
+            view! { cx,
+                <div class="active-filters">
+                    {chips}
+                    {text_chip}
+                    {visited_chip}
+                    <button on:click=move |_| {
+                        state.clear();
+                        state.text.set(String::new());
+                        state.visited.set(None);
+                    }>"Clear all"</button>
+                </div>
+            }
+        })
+    }
+}
+
+/// Filter toggles, preset chips and preset saving.
+#[component]
+pub fn FilterChips(cx: Scope) -> impl IntoView {
+    let prefs = prefs::use_preferences(cx);
+    let state = use_filter_state(cx);
+    let active = state.active;
+    // A shared preset the user hasn't saved yet is offered under its name.
+    let shared = from_url()
+        .1
+        .filter(|name| prefs.with_untracked(|p| p.filter_presets.iter().all(|x| &x.name != name)));
+    let (new_name, set_new_name) = create_signal(cx, shared.unwrap_or_default());
+    let save = move |_| {
+        let name = new_name.get().trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let preset = FilterPreset { name, filters: active.get() };
+        prefs.update(|p| save_preset(&mut p.filter_presets, preset));
+        set_new_name.set(String::new());
+    };
+    let toggles = Filter::ALL
+        .into_iter()
+        .map(|filter| {
+            let on = move || active.with(|a| a.contains(&filter));
+            view! { cx,
+                <button
+                    class="chip"
+                    class:active=on
+                    aria-pressed=move || on().to_string()
+                    on:click=move |_| state.toggle(filter)
+                >
+                    {filter.label()}
+                </button>
+            }
+        })
+        .collect_view(cx);
+    let visited_toggles = [(true, "Visited"), (false, "Not visited")]
+        .into_iter()
+        .map(|(visited, label)| {
+            let on = move || state.visited.get() == Some(visited);
+            view! { cx,
+                <button
+                    class="chip"
+                    class:active=on
+                    aria-pressed=move || on().to_string()
+                    on:click=move |_| state.visited.update(|v| *v = (*v != Some(visited)).then_some(visited))
+                >
+                    {label}
+                </button>
+            }
+        })
+        .collect_view(cx);
+    let presets = move || {
+        prefs.with(|p| p.filter_presets.clone()).into_iter().map(|preset| {
+            let name = preset.name.clone();
+            let applied = {
+                let filters = preset.filters.clone();
+                move || active.with(|a| *a == filters)
+            };
+            view! { cx,
+                <span class="chip preset" class:active=applied>
+                    <button on:click=move |_| active.set(preset.filters.clone())>{preset.name.clone()}</button>
+                    <button
+                        title="Delete preset"
+                        on:click=move |_| prefs.update(|p| p.filter_presets.retain(|x| x.name != name))
+                    >
+                        "×"
+                    </button>
+                </span>
+            }
+        }).collect_view(cx)
+    };
+    let share = move || {
+        let preset = prefs.with(|p| {
+            let filters = active.get();
+            p.filter_presets.iter().find(|x| x.filters == filters).map(|x| x.name.clone())
+        });
+        share_url(&active.get(), preset.as_deref())
+    };
+
+    view! { cx,
+        <div class="filter-chips">
+            <input
+                type="search"
+                placeholder="Filter by name"
+                prop:value=move || state.text.get()
+                on:input=move |ev| state.text.set(event_target_value(&ev))
+            />
+            {toggles}
+            {visited_toggles}
+            {presets}
+            <input
+                placeholder="Preset name"
+                prop:value=move || new_name.get()
+                on:input=move |ev| set_new_name.set(event_target_value(&ev))
+            />
+            <button on:click=save>"Save filters"</button>
+            <a href=share>"Share filters"</a>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::tests::tags;
+
+    #[test]
+    fn round_trips_through_the_url() {
+        let filters: Filters = [Filter::OpenNow, Filter::Free].into();
+        assert_eq!(encode(&filters), "free,open_now");
+        assert_eq!(decode("free, open_now,bogus"), filters);
+        assert_eq!(decode(""), Filters::new());
+    }
+
+    #[test]
+    fn filters_need_positive_tags() {
+        let now = WeekTime::new(0, 12, 0);
+        let free_always_open = tags(&[("fee", "no"), ("opening_hours", "24/7")]);
+        let all: Filters = [Filter::Free, Filter::OpenNow].into();
+        assert!(matches_all(&all, &free_always_open, now));
+        assert!(!Filter::Accessible.matches(&free_always_open, now));
+        assert!(Filter::Accessible.matches(&tags(&[("wheelchair", "designated")]), now));
+        assert!(Filter::ChangingTable.matches(&tags(&[("changing_table", "yes")]), now));
+        assert!(!Filter::ChangingTable.matches(&tags(&[("changing_table", "no")]), now));
+        assert!(!Filter::Free.matches(&tags(&[]), now));
+        assert!(!Filter::OpenNow.matches(&tags(&[("opening_hours", "sunrise-sunset")]), now));
+        assert!(matches_all(&Filters::new(), &tags(&[]), now));
+    }
+
+    #[test]
+    fn visited_filter_picks_either_side() {
+        assert!(matches_visited(None, true) && matches_visited(None, false));
+        assert!(matches_visited(Some(true), true) && !matches_visited(Some(true), false));
+        assert!(matches_visited(Some(false), false) && !matches_visited(Some(false), true));
+    }
+
+    #[test]
+    fn hide_closed_keeps_unknown_hours() {
+        let now = WeekTime::new(0, 12, 0);
+        assert!(Filter::NotClosed.matches(&tags(&[]), now));
+        assert!(Filter::NotClosed.matches(&tags(&[("opening_hours", "sunrise-sunset")]), now));
+        assert!(Filter::NotClosed.matches(&tags(&[("opening_hours", "Mo-Fr 08:00-18:00")]), now));
+        assert!(!Filter::NotClosed.matches(&tags(&[("opening_hours", "Mo-Fr 14:00-18:00")]), now));
+    }
+
+    #[test]
+    fn text_matches_names_loosely() {
+        let starbucks = tags(&[("name", "Starbucks"), ("amenity", "cafe")]);
+        assert!(matches_text("", &starbucks));
+        assert!(matches_text("stabucks", &starbucks));
+        assert!(!matches_text("mcdonalds", &starbucks));
+        assert!(matches_text("wall", &tags(&[("amenity", "toilets"), ("operator", "Wall GmbH")])));
+        assert!(!matches_text("wall", &tags(&[("amenity", "toilets")])));
+    }
+
+    #[test]
+    fn saving_replaces_by_name() {
+        let mut presets = vec![FilterPreset { name: "a".into(), filters: [Filter::Free].into() }];
+        save_preset(&mut presets, FilterPreset { name: "a".into(), filters: [Filter::Accessible].into() });
+        save_preset(&mut presets, FilterPreset { name: "b".into(), filters: Filters::new() });
+        assert_eq!(presets.len(), 2);
+        assert_eq!(presets[0].filters, [Filter::Accessible].into());
+    }
+}
@@ -0,0 +1,140 @@
+//! Follow-me mode: while walking, rows show the straight-line distance from
+//! where the user is now. Each row owns a memo of its distance, so a position
+//! update only patches the distance cells, and the rows are only reordered
+//! when someone actually overtook someone. Arrivals and the new order are
+//! looked up in the results' [index](RowIndex) rather than by scanning them.
+
+use crate::geo::haversine_m;
+use crate::results::{RowIndex, ScoredRow};
+use leptos::*;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Position};
+
+/// The user's position while `follow` is on, from `watchPosition`; `None` otherwise.
+pub fn use_live_position(cx: Scope, follow: Signal<bool>) -> ReadSignal<Option<(f64, f64)>> {
+    let (position, set_position) = create_signal(cx, None::<(f64, f64)>);
+    let on_position = Closure::<dyn FnMut(Position)>::new(move |pos: Position| {
+        set_position.set(Some((pos.coords().latitude(), pos.coords().longitude())));
+    });
+    let callback: js_sys::Function = on_position.into_js_value().unchecked_into();
+    let geolocation = window().and_then(|w| w.navigator().geolocation().ok());
+    let watch = store_value(cx, None::<i32>);
+    let stop = {
+        let geolocation = geolocation.clone();
+        move || {
+            if let (Some(id), Some(geolocation)) = (watch.get_value(), &geolocation) {
+                geolocation.clear_watch(id);
+            }
+            watch.set_value(None);
+        }
+    };
+    create_effect(cx, {
+        let stop = stop.clone();
+        move |_| {
+            stop();
+            set_position.set(None);
+            if follow.get() {
+                let id = geolocation.as_ref().and_then(|g| g.watch_position(&callback).ok());
+                watch.set_value(id);
+            }
+        }
+    });
+    on_cleanup(cx, stop);
+    position
+}
+
+/// Whether having walked to `at` warrants a new search: more than `threshold_m`
+/// from where the results are for. A threshold of 0 turns this off.
+pub fn should_refetch(results_from: (f64, f64), at: (f64, f64), threshold_m: i64) -> bool {
+    threshold_m > 0 && haversine_m(results_from, at) > threshold_m as f64
+}
+
+/// Closer than this to a place counts as having arrived.
+pub const ARRIVAL_M: f64 = 30.0;
+
+/// The row nearest `at`, if it's within [`ARRIVAL_M`].
+pub fn arrived_at(index: &RowIndex, at: (f64, f64)) -> Option<&ScoredRow> {
+    index.nearest(at, 1).into_iter().find(|(d, _)| *d <= ARRIVAL_M).map(|(_, row)| row)
+}
+
+pub fn distance_from(at: (f64, f64), row: &ScoredRow) -> f64 {
+    haversine_m(at, (row.row.element.lat, row.row.element.lon))
+}
+
+/// `rows` nearest first from `at`, with `index` holding the same rows. When
+/// `previous` holds them too and is still in order, that order is kept, so the
+/// table isn't touched.
+pub fn by_distance(rows: &[ScoredRow], index: &RowIndex, previous: Option<&Vec<ScoredRow>>, at: (f64, f64)) -> Vec<ScoredRow> {
+    let by_key: HashMap<_, _> = rows.iter().map(|r| (r.row.key(), r)).collect();
+    let previous_order = previous
+        .filter(|p| p.len() == rows.len())
+        .and_then(|p| p.iter().map(|r| by_key.get(&r.row.key()).map(|&r| r.clone())).collect::<Option<Vec<_>>>());
+    let ordered = previous_order.unwrap_or_else(|| rows.to_vec());
+    let distances: Vec<f64> = ordered.iter().map(|r| distance_from(at, r)).collect();
+    match distances.windows(2).any(|w| w[0] > w[1]) {
+        true => index.nearest(at, rows.len()).into_iter().map(|(_, row)| row.clone()).collect(),
+        false => ordered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::ResultRow;
+    use crate::scoring::Confidence;
+    use crate::{Element, OsmType};
+    use std::rc::Rc;
+
+    fn row(id: i64, lat: f64) -> ScoredRow {
+        ScoredRow {
+            row: ResultRow {
+                element: Rc::new(Element { id, lat, lon: 13.0, type_field: OsmType::Node, ..Default::default() }),
+                distance_m: 0.0,
+                duration_s: 0.0,
+            },
+            confidence: Confidence::Normal,
+            rank: 0.0,
+        }
+    }
+
+    fn ids(rows: &[ScoredRow]) -> Vec<i64> {
+        rows.iter().map(|r| r.row.element.id).collect()
+    }
+
+    #[test]
+    fn sorts_by_distance_from_the_live_position() {
+        let rows = [row(1, 52.0), row(2, 52.01), row(3, 52.02)];
+        let index = RowIndex::new(&rows);
+        assert_eq!(ids(&by_distance(&rows, &index, None, (52.0, 13.0))), [1, 2, 3]);
+        assert_eq!(ids(&by_distance(&rows, &index, None, (52.019, 13.0))), [3, 2, 1]);
+    }
+
+    #[test]
+    fn keeps_the_previous_order_while_it_holds() {
+        let rows = [row(1, 52.0), row(2, 52.01), row(3, 52.02)];
+        let index = RowIndex::new(&rows);
+        let previous = by_distance(&rows, &index, None, (52.019, 13.0));
+        assert_eq!(ids(&by_distance(&rows, &index, Some(&previous), (52.018, 13.0))), [3, 2, 1]);
+        // Walked past row 2.
+        assert_eq!(ids(&by_distance(&rows, &index, Some(&previous), (52.0, 13.0))), [1, 2, 3]);
+        // A refetch brought different rows.
+        let fetched = [row(4, 52.03), row(2, 52.01)];
+        assert_eq!(ids(&by_distance(&fetched, &RowIndex::new(&fetched), Some(&previous), (52.0, 13.0))), [2, 4]);
+    }
+
+    #[test]
+    fn refetches_past_the_threshold() {
+        assert!(!should_refetch((52.0, 13.0), (52.002, 13.0), 250));
+        assert!(should_refetch((52.0, 13.0), (52.003, 13.0), 250));
+        assert!(!should_refetch((52.0, 13.0), (53.0, 13.0), 0));
+    }
+
+    #[test]
+    fn arrives_within_a_few_meters() {
+        let index = RowIndex::new(&[row(1, 52.0), row(2, 52.01)]);
+        assert_eq!(arrived_at(&index, (52.0101, 13.0)).map(|r| r.row.element.id), Some(2));
+        assert!(arrived_at(&index, (52.005, 13.0)).is_none());
+    }
+}
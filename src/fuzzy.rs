@@ -0,0 +1,139 @@
+//! Typo-tolerant matching for the results text filter. The query is aligned
+//! against a name Smith-Waterman style, so a letter left out or swapped in a
+//! hurry ("stabucks") still finds "Starbucks", and the letters that lined up
+//! can be highlighted.
+
+use leptos::*;
+
+const MATCH: i32 = 2;
+const MISMATCH: i32 = -1;
+const GAP: i32 = -1;
+/// Share of a perfect score a name needs; about one slip in five letters passes.
+const THRESHOLD: f64 = 0.6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: i32,
+    /// Positions of the characters in the text that lined up with the query, ascending.
+    pub positions: Vec<usize>,
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Each word of `query` aligned within `text`, if every one is close enough
+/// to count as a match. An empty query matches nothing.
+pub fn find(query: &str, text: &str) -> Option<Match> {
+    let text: Vec<char> = text.chars().map(fold).collect();
+    let mut found = Match { score: 0, positions: Vec::new() };
+    for word in query.split_whitespace() {
+        let word: Vec<char> = word.chars().map(fold).collect();
+        let Match { score, positions } = align(&word, &text)?;
+        found.score += score;
+        found.positions.extend(positions);
+    }
+    found.positions.sort_unstable();
+    found.positions.dedup();
+    (found.score > 0).then_some(found)
+}
+
+/// The best local alignment of `query` within `text`, if it's good enough.
+fn align(query: &[char], text: &[char]) -> Option<Match> {
+    if text.is_empty() {
+        return None;
+    }
+    let width = text.len() + 1;
+    let mut scores = vec![0; (query.len() + 1) * width];
+    let mut best = (0, 0, 0);
+    for i in 1..=query.len() {
+        for j in 1..=text.len() {
+            let pair = if query[i - 1] == text[j - 1] { MATCH } else { MISMATCH };
+            let score = (scores[(i - 1) * width + j - 1] + pair)
+                .max(scores[(i - 1) * width + j] + GAP)
+                .max(scores[i * width + j - 1] + GAP)
+                .max(0);
+            scores[i * width + j] = score;
+            if score > best.0 {
+                best = (score, i, j);
+            }
+        }
+    }
+    let (score, mut i, mut j) = best;
+    if (score as f64) < THRESHOLD * (MATCH as usize * query.len()) as f64 {
+        return None;
+    }
+    // Walk back along the alignment to find which letters matched.
+    let mut positions = Vec::new();
+    while i > 0 && j > 0 && scores[i * width + j] > 0 {
+        let here = scores[i * width + j];
+        let pair = if query[i - 1] == text[j - 1] { MATCH } else { MISMATCH };
+        if here == scores[(i - 1) * width + j - 1] + pair {
+            if pair == MATCH {
+                positions.push(j - 1);
+            }
+            i -= 1;
+            j -= 1;
+        } else if here == scores[(i - 1) * width + j] + GAP {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    positions.reverse();
+    Some(Match { score, positions })
+}
+
+/// `text` split into runs that were (`true`) or weren't matched.
+pub fn segments(text: &str, positions: &[usize]) -> Vec<(String, bool)> {
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.binary_search(&i).is_ok();
+        match runs.last_mut() {
+            Some((run, m)) if *m == matched => run.push(c),
+            _ => runs.push((c.to_string(), matched)),
+        }
+    }
+    runs
+}
+
+/// `text` with the letters matching `query` marked.
+#[component]
+pub fn Highlighted(cx: Scope, text: String, query: Signal<String>) -> impl IntoView {
+    move || {
+        let positions = query.with(|q| find(q, &text)).map(|m| m.positions).unwrap_or_default();
+        segments(&text, &positions)
+            .into_iter()
+            .map(|(run, matched)| match matched {
+                true => view! { cx, <mark>{run}</mark> }.into_view(cx),
+                false => run.into_view(cx),
+            })
+            .collect_view(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tolerates_typos() {
+        assert!(find("starbucks", "Starbucks Reserve").is_some());
+        assert!(find("stabucks", "Starbucks").is_some());
+        assert!(find("strabucks", "Starbucks").is_some());
+        assert!(find("cafe fern", "Café am Fernsehturm").is_some());
+        assert!(find("mcdonalds", "Starbucks").is_none());
+        assert!(find("", "Starbucks").is_none());
+    }
+
+    #[test]
+    fn marks_the_matched_letters() {
+        let found = find("stabucks", "Starbucks").unwrap();
+        assert_eq!(found.positions, [0, 1, 2, 4, 5, 6, 7, 8]);
+        assert_eq!(
+            segments("Starbucks", &found.positions),
+            [("Sta".to_string(), true), ("r".to_string(), false), ("bucks".to_string(), true)]
+        );
+        assert_eq!(find("loo", "Joe's Loo").unwrap().positions, [6, 7, 8]);
+    }
+}
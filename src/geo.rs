@@ -0,0 +1,406 @@
+//! Small geographic helpers that don't need the browser.
+
+/// A lat/lon bounding box in Overpass order (south, west, north, east).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl BBox {
+    /// Parse `"south,west,north,east"`, as used in the `bbox` query parameter.
+    pub fn parse(s: &str) -> Option<BBox> {
+        let parts: Vec<f64> = s
+            .split(',')
+            .map(|p| p.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        match parts[..] {
+            [south, west, north, east] if south < north && west < east => Some(BBox {
+                south,
+                west,
+                north,
+                east,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Area in km², using an equirectangular approximation which is plenty for
+    /// order-of-magnitude estimates.
+    pub fn area_km2(&self) -> f64 {
+        const KM_PER_DEG: f64 = 111.32;
+        let mid_lat = ((self.south + self.north) / 2.0).to_radians();
+        let height = (self.north - self.south) * KM_PER_DEG;
+        let width = (self.east - self.west) * KM_PER_DEG * mid_lat.cos();
+        height * width
+    }
+
+    pub fn contains(&self, (lat, lon): (f64, f64)) -> bool {
+        (self.south..=self.north).contains(&lat) && (self.west..=self.east).contains(&lon)
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        ((self.south + self.north) / 2.0, (self.west + self.east) / 2.0)
+    }
+
+    /// Split into a `rows` x `cols` grid of equally sized boxes, row by row from the south-west.
+    pub fn split(&self, rows: usize, cols: usize) -> Vec<BBox> {
+        let d_lat = (self.north - self.south) / rows as f64;
+        let d_lon = (self.east - self.west) / cols as f64;
+        let mut tiles = Vec::with_capacity(rows * cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                tiles.push(BBox {
+                    south: self.south + d_lat * row as f64,
+                    west: self.west + d_lon * col as f64,
+                    north: self.south + d_lat * (row + 1) as f64,
+                    east: self.west + d_lon * (col + 1) as f64,
+                });
+            }
+        }
+        tiles
+    }
+
+    /// Split into the smallest near-square grid with at least `n` tiles.
+    pub fn tiles(&self, n: usize) -> Vec<BBox> {
+        let n = n.max(1);
+        let cols = (n as f64).sqrt().ceil() as usize;
+        let rows = n.div_ceil(cols);
+        self.split(rows, cols)
+    }
+}
+
+/// Whether `(lat, lon)` is somewhere on earth; NaN isn't.
+pub fn is_valid_lat_lon(lat: f64, lon: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+}
+
+/// Parse `"lat,lon"` as typed or pasted by a user.
+pub fn parse_lat_lon(s: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = s.split_once(',')?;
+    let (lat, lon): (f64, f64) = (lat.trim().parse().ok()?, lon.trim().parse().ok()?);
+    is_valid_lat_lon(lat, lon).then_some((lat, lon))
+}
+
+/// Mean earth radius in meters.
+pub const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Great-circle distance in meters between two `(lat, lon)` points.
+pub fn haversine_m(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+/// Initial bearing in degrees clockwise from north, 0 to 360, for the great circle from `from` to `to`.
+pub fn bearing_deg(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (from.0.to_radians(), to.0.to_radians());
+    let dlon = (to.1 - from.1).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// The nearest of the eight compass points, with an arrow: `"↗ NE"`.
+pub fn compass_point(bearing_deg: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["↑ N", "↗ NE", "→ E", "↘ SE", "↓ S", "↙ SW", "← W", "↖ NW"];
+    POINTS[((bearing_deg.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8]
+}
+
+/// Decode an encoded polyline (as returned by OSRM, precision 5) into `(lat, lon)` points.
+pub fn decode_polyline(encoded: &str, precision: u32) -> Option<Vec<(f64, f64)>> {
+    let factor = 10f64.powi(precision as i32);
+    let mut bytes = encoded.bytes();
+    let mut next_value = || -> Option<Option<i64>> {
+        let mut result: i64 = 0;
+        let mut shift = 0;
+        loop {
+            let Some(byte) = bytes.next() else {
+                // Running out of input is only fine between values.
+                return if shift == 0 { Some(None) } else { None };
+            };
+            let chunk = (byte as i64).checked_sub(63)?;
+            result |= (chunk & 0x1f) << shift;
+            shift += 5;
+            if chunk < 0x20 {
+                break;
+            }
+            if shift > 60 {
+                return None;
+            }
+        }
+        Some(Some(if result & 1 == 1 { !(result >> 1) } else { result >> 1 }))
+    };
+
+    let (mut lat, mut lon) = (0i64, 0i64);
+    let mut points = vec![];
+    while let Some(d_lat) = next_value()? {
+        let d_lon = next_value()??;
+        lat += d_lat;
+        lon += d_lon;
+        points.push((lat as f64 / factor, lon as f64 / factor));
+    }
+    Some(points)
+}
+
+/// Distance along `line` in meters at each of its vertices.
+pub fn cumulative_distances(line: &[(f64, f64)]) -> Vec<f64> {
+    let mut total = 0.0;
+    let mut out = Vec::with_capacity(line.len());
+    for (i, point) in line.iter().enumerate() {
+        if i > 0 {
+            total += haversine_m(line[i - 1], *point);
+        }
+        out.push(total);
+    }
+    out
+}
+
+/// Project `point` to meters on a plane tangent at `origin`. Good enough for the
+/// short distances involved in simplifying a track.
+fn local_xy(origin: (f64, f64), point: (f64, f64)) -> (f64, f64) {
+    let m_per_deg = EARTH_RADIUS_M.to_radians();
+    let x = (point.1 - origin.1) * m_per_deg * origin.0.to_radians().cos();
+    let y = (point.0 - origin.0) * m_per_deg;
+    (x, y)
+}
+
+/// Distance in meters from `point` to the segment `a`-`b`.
+fn segment_distance_m(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (px, py) = local_xy(a, point);
+    let (bx, by) = local_xy(a, b);
+    let len2 = bx * bx + by * by;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        ((px * bx + py * by) / len2).clamp(0.0, 1.0)
+    };
+    let (dx, dy) = (px - t * bx, py - t * by);
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Douglas-Peucker simplification: drop points that are within `tolerance_m` of
+/// the simplified line. The first and last points are always kept.
+pub fn simplify_douglas_peucker(line: &[(f64, f64)], tolerance_m: f64) -> Vec<(f64, f64)> {
+    if line.len() < 3 {
+        return line.to_vec();
+    }
+    let mut keep = vec![false; line.len()];
+    keep[0] = true;
+    keep[line.len() - 1] = true;
+
+    // Iterative to avoid deep recursion on long tracks.
+    let mut stack = vec![(0, line.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let farthest = (start + 1..end)
+            .map(|i| (i, segment_distance_m(line[i], line[start], line[end])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance_m {
+                keep[i] = true;
+                stack.push((start, i));
+                stack.push((i, end));
+            }
+        }
+    }
+
+    line.iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Radial-distance simplification: drop points closer than `tolerance_m` to the
+/// last kept point. Cheap, so it's a good first pass before Douglas-Peucker.
+pub fn simplify_radial_distance(line: &[(f64, f64)], tolerance_m: f64) -> Vec<(f64, f64)> {
+    let Some((&first, rest)) = line.split_first() else {
+        return vec![];
+    };
+    let mut out = vec![first];
+    for &point in rest {
+        if haversine_m(*out.last().unwrap(), point) > tolerance_m {
+            out.push(point);
+        }
+    }
+    // Always end on the original last point.
+    let last = *line.last().unwrap();
+    if line.len() > 1 && *out.last().unwrap() != last {
+        out.push(last);
+    }
+    out
+}
+
+/// Radial-distance then Douglas-Peucker, both with `tolerance_m`.
+pub fn simplify(line: &[(f64, f64)], tolerance_m: f64) -> Vec<(f64, f64)> {
+    simplify_douglas_peucker(&simplify_radial_distance(line, tolerance_m), tolerance_m)
+}
+
+/// Simplify with the smallest tolerance (doubling from `tolerance_m`) that leaves at most `max_points`.
+pub fn simplify_to_at_most(line: &[(f64, f64)], max_points: usize, tolerance_m: f64) -> Vec<(f64, f64)> {
+    let max_points = max_points.max(2);
+    let mut tolerance = tolerance_m.max(1.0);
+    loop {
+        let simplified = simplify(line, tolerance);
+        if simplified.len() <= max_points {
+            return simplified;
+        }
+        tolerance *= 2.0;
+    }
+}
+
+/// Even-odd point-in-polygon test on `(lat, lon)` points. Treats coordinates as planar,
+/// which is fine at the scale of a walking isochrone.
+pub fn point_in_polygon(point: (f64, f64), ring: &[(f64, f64)]) -> bool {
+    let (y, x) = point;
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+    for i in 0..ring.len() {
+        let (yi, xi) = ring[i];
+        let (yj, xj) = ring[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BERLIN: BBox = BBox {
+        south: 52.45,
+        west: 13.35,
+        north: 52.55,
+        east: 13.45,
+    };
+
+    #[test]
+    fn bearings_and_compass_points() {
+        assert!((bearing_deg((0.0, 0.0), (1.0, 0.0)) - 0.0).abs() < 1e-9);
+        assert!((bearing_deg((0.0, 0.0), (0.0, 1.0)) - 90.0).abs() < 1e-9);
+        assert!((bearing_deg((0.0, 0.0), (0.0, -1.0)) - 270.0).abs() < 1e-9);
+        assert_eq!(compass_point(0.0), "↑ N");
+        assert_eq!(compass_point(350.0), "↑ N");
+        assert_eq!(compass_point(44.0), "↗ NE");
+        assert_eq!(compass_point(bearing_deg((52.5, 13.4), (52.4, 13.4))), "↓ S");
+    }
+
+    #[test]
+    fn parse_bbox() {
+        assert_eq!(BBox::parse("52.45, 13.35,52.55,13.45"), Some(BERLIN));
+        assert_eq!(BBox::parse("52.55,13.35,52.45,13.45"), None);
+        assert_eq!(BBox::parse("1,2,3"), None);
+        assert_eq!(BBox::parse("a,b,c,d"), None);
+    }
+
+    #[test]
+    fn parse_coordinates() {
+        assert_eq!(parse_lat_lon(" 52.5, 13.4 "), Some((52.5, 13.4)));
+        assert_eq!(parse_lat_lon("95,13"), None);
+        assert_eq!(parse_lat_lon("52.5"), None);
+    }
+
+    #[test]
+    fn area_roughly_matches_reference() {
+        // ~0.1° x 0.1° around Berlin is about 11.1 km x 6.8 km.
+        let area = BERLIN.area_km2();
+        assert!((area - 75.5).abs() < 1.0, "{area}");
+    }
+
+    #[test]
+    fn tiles_cover_the_box() {
+        let tiles = BERLIN.tiles(5);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0].south, BERLIN.south);
+        assert_eq!(tiles[0].west, BERLIN.west);
+        let last = tiles.last().unwrap();
+        assert!((last.north - BERLIN.north).abs() < 1e-9);
+        assert!((last.east - BERLIN.east).abs() < 1e-9);
+        let total: f64 = tiles.iter().map(BBox::area_km2).sum();
+        assert!((total - BERLIN.area_km2()).abs() < 0.1);
+    }
+
+    #[test]
+    fn point_in_square() {
+        let square = [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0), (0.0, 0.0)];
+        assert!(point_in_polygon((0.5, 0.5), &square));
+        assert!(!point_in_polygon((1.5, 0.5), &square));
+        assert!(!point_in_polygon((0.5, 0.5), &[]));
+    }
+
+    #[test]
+    fn haversine_london_paris() {
+        let d = haversine_m((51.5074, -0.1278), (48.8566, 2.3522));
+        assert!((d - 343_500.0).abs() < 1000.0, "{d}");
+        assert_eq!(haversine_m((1.0, 2.0), (1.0, 2.0)), 0.0);
+    }
+
+    #[test]
+    fn decode_reference_polyline() {
+        let points = decode_polyline("_p~iF~ps|U_ulLnnqC_mqNvxq`@", 5).unwrap();
+        assert_eq!(points, vec![(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)]);
+        assert_eq!(decode_polyline("", 5), Some(vec![]));
+        assert_eq!(decode_polyline("_p~iF", 5), None);
+    }
+
+    #[test]
+    fn distances_along_line() {
+        let line = [(0.0, 0.0), (0.0, 1.0), (0.0, 2.0)];
+        let cumulative = cumulative_distances(&line);
+        assert_eq!(cumulative[0], 0.0);
+        assert!((cumulative[2] - 2.0 * cumulative[1]).abs() < 1e-6);
+    }
+
+    /// A ~1 km eastward track along the equator with small (~1 m) wiggles and one
+    /// 200 m detour to the north in the middle.
+    fn wiggly_track() -> Vec<(f64, f64)> {
+        let deg_per_m = 1.0 / EARTH_RADIUS_M.to_radians();
+        (0..=100)
+            .map(|i| {
+                let wiggle = if i % 2 == 0 { 1.0 } else { -1.0 };
+                let detour = if i == 50 { 200.0 } else { 0.0 };
+                ((wiggle + detour) * deg_per_m, i as f64 * 10.0 * deg_per_m)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn douglas_peucker_keeps_only_significant_points() {
+        let track = wiggly_track();
+        let simplified = simplify_douglas_peucker(&track, 5.0);
+        assert_eq!(simplified, vec![track[0], track[49], track[50], track[51], track[100]]);
+        assert_eq!(simplify_douglas_peucker(&track, 500.0), vec![track[0], track[100]]);
+        assert_eq!(simplify_douglas_peucker(&track, 0.1).len(), track.len());
+    }
+
+    #[test]
+    fn radial_distance_drops_close_points() {
+        let track = wiggly_track();
+        let simplified = simplify_radial_distance(&track, 25.0);
+        assert_eq!(simplified.first(), track.first());
+        assert_eq!(simplified.last(), track.last());
+        assert!(simplified.len() < 40, "{}", simplified.len());
+        assert!(simplified
+            .windows(2)
+            .take(simplified.len() - 2)
+            .all(|w| haversine_m(w[0], w[1]) > 25.0));
+        assert_eq!(simplify_radial_distance(&track[..1], 25.0), vec![track[0]]);
+        assert!(simplify_radial_distance(&[], 25.0).is_empty());
+    }
+
+    #[test]
+    fn simplify_to_point_budget() {
+        let track = wiggly_track();
+        let simplified = simplify_to_at_most(&track, 3, 1.0);
+        assert!(simplified.len() <= 3);
+        assert_eq!(simplified.first(), track.first());
+        assert_eq!(simplified.last(), track.last());
+        assert_eq!(simplify(&track[..2], 10.0), track[..2].to_vec());
+    }
+}
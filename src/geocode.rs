@@ -0,0 +1,404 @@
+//! Address search through Nominatim, OpenStreetMap's geocoder. Its usage
+//! policy allows one request a second, so the search box only asks once typing
+//! has settled, and [`policy`] spaces out any that still come too fast.
+//! Names come back in the app's language unless the user turned that off, in
+//! which case Nominatim goes by the browser's `Accept-Language`. Results near
+//! where the user was last seen rank first, and settings can limit them to
+//! some countries. Places picked are remembered, so they're suggested again
+//! without asking.
+
+use crate::bus::{use_bus, Event, Provider};
+use crate::geo;
+use crate::i18n::{use_i18n, Locale};
+use crate::policy::{self, Service};
+use crate::prefs::use_preferences;
+use crate::storage;
+use leptos::{error::Result, *};
+use serde_derive::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+use thiserror::Error;
+
+const NOMINATIM_URL: &str = "https://nominatim.openstreetmap.org/search";
+
+pub const MAX_CANDIDATES: usize = 5;
+/// Picked places remembered for instant reuse.
+pub const MAX_RECENT: usize = 5;
+const RECENT_KEY: &str = "recent_addresses";
+/// Half the side of the box results are biased toward, about 100 km.
+const BIAS_DEG: f64 = 1.0;
+/// Shorter queries match half the planet.
+pub const MIN_QUERY_CHARS: usize = 3;
+/// How long typing has to pause before searching.
+pub const SETTLE: Duration = Duration::from_millis(700);
+
+#[derive(Error, Clone, Debug)]
+pub enum GeocodeError {
+    #[error("Couldn't find \"{0}\".")]
+    NotFound(String),
+}
+
+/// One result of `/search?format=json`; coordinates come as strings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NominatimPlace {
+    pub display_name: String,
+    pub lat: String,
+    pub lon: String,
+    /// What it is (`house`, `city`, `station`...), when Nominatim says.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    pub label: String,
+    pub location: (f64, f64),
+}
+
+/// Places with usable coordinates, in Nominatim's order (best match first).
+pub fn candidates(places: Vec<NominatimPlace>) -> Vec<Candidate> {
+    places
+        .into_iter()
+        .filter_map(|place| {
+            let location = (place.lat.parse().ok()?, place.lon.parse().ok()?);
+            let label = match place.kind {
+                Some(kind) if kind != "yes" => format!("{} ({})", place.display_name, kind.replace('_', " ")),
+                _ => place.display_name,
+            };
+            Some(Candidate { label, location })
+        })
+        .collect()
+}
+
+/// How to steer a search, as Nominatim parameters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchOptions {
+    /// Names in this language; without one Nominatim goes by the `Accept-Language` header.
+    pub language: Option<Locale>,
+    /// Prefer results around here, without excluding others.
+    pub near: Option<(f64, f64)>,
+    /// Only results in these countries (ISO 3166-1 alpha-2, lowercase); all when empty.
+    pub countries: Vec<String>,
+}
+
+impl SearchOptions {
+    pub fn params(&self) -> String {
+        let mut params = String::new();
+        if let Some(language) = self.language {
+            params.push_str(&format!("&accept-language={}", language.code()));
+        }
+        if let Some((lat, lon)) = self.near {
+            let (south, north) = ((lat - BIAS_DEG).max(-90.0), (lat + BIAS_DEG).min(90.0));
+            let (west, east) = ((lon - BIAS_DEG).max(-180.0), (lon + BIAS_DEG).min(180.0));
+            params.push_str(&format!("&viewbox={west:.2},{north:.2},{east:.2},{south:.2}"));
+        }
+        if !self.countries.is_empty() {
+            params.push_str(&format!("&countrycodes={}", self.countries.join(",")));
+        }
+        params
+    }
+}
+
+/// `"DE, at"` as `["de", "at"]`; anything that isn't a two-letter code is dropped.
+pub fn country_codes(input: &str) -> Vec<String> {
+    input
+        .split([',', ' '])
+        .map(|c| c.trim().to_ascii_lowercase())
+        .filter(|c| c.len() == 2 && c.chars().all(|ch| ch.is_ascii_alphabetic()))
+        .collect()
+}
+
+thread_local! {
+    /// Where the device last said it was; only kept for this visit.
+    static LAST_POSITION: Cell<Option<(f64, f64)>> = const { Cell::new(None) };
+}
+
+pub fn note_position(position: (f64, f64)) {
+    LAST_POSITION.with(|p| p.set(Some(position)));
+}
+
+/// Where to bias searches toward: the device's last position, else the last place picked.
+fn last_known(recent: &[Candidate]) -> Option<(f64, f64)> {
+    LAST_POSITION.with(Cell::get).or_else(|| recent.first().map(|c| c.location))
+}
+
+/// Options from the settings and what's known of where the user is; see
+/// [`crate::prefs::Preferences::localized_place_names`] and `geocoder_countries`.
+pub fn use_search_options(cx: Scope) -> Signal<SearchOptions> {
+    let prefs = use_preferences(cx);
+    let i18n = use_i18n(cx);
+    Signal::derive(cx, move || SearchOptions {
+        language: prefs.with(|p| p.localized_place_names).then(|| i18n.0.get()),
+        near: last_known(&storage::load::<Vec<Candidate>>(RECENT_KEY).unwrap_or_default()),
+        countries: prefs.with(|p| country_codes(&p.geocoder_countries)),
+    })
+}
+
+pub async fn search(query: &str, limit: usize, options: &SearchOptions) -> Result<Vec<Candidate>> {
+    let url = format!(
+        "{NOMINATIM_URL}?format=json&limit={limit}&q={}{}",
+        js_sys::encode_uri_component(query.trim()),
+        options.params()
+    );
+    let places: Vec<NominatimPlace> = policy::get(Service::Nominatim, &url).await.send().await?.json().await?;
+    Ok(candidates(places))
+}
+
+/// The best match for an address or place name.
+pub async fn geocode(query: &str, options: &SearchOptions) -> Result<(f64, f64)> {
+    let best = search(query, 1, options).await?.into_iter().next();
+    Ok(best.map(|c| c.location).ok_or_else(|| GeocodeError::NotFound(query.trim().to_string()))?)
+}
+
+/// `lat,lon` as is, or the geocoded address.
+pub async fn resolve(input: &str, options: &SearchOptions) -> Result<(f64, f64)> {
+    match geo::parse_lat_lon(input) {
+        Some(location) => Ok(location),
+        None => geocode(input, options).await,
+    }
+}
+
+/// Suggestions before anything's been looked up: typed coordinates, then
+/// recent picks whose label contains what's typed (all of them for nothing typed).
+pub fn instant_suggestions(recent: &[Candidate], input: &str) -> Vec<Candidate> {
+    let typed = input.trim().to_lowercase();
+    let coordinates = geo::parse_lat_lon(input).map(|(lat, lon)| Candidate { label: format!("{lat}, {lon}"), location: (lat, lon) });
+    coordinates
+        .into_iter()
+        .chain(recent.iter().filter(|c| c.label.to_lowercase().contains(&typed)).cloned())
+        .collect()
+}
+
+/// Most recent first, without duplicates, at most [`MAX_RECENT`].
+pub fn remember(recent: &mut Vec<Candidate>, picked: Candidate) {
+    recent.retain(|c| c.label != picked.label);
+    recent.insert(0, picked);
+    recent.truncate(MAX_RECENT);
+}
+
+/// Where the highlight goes on an arrow key, wrapping through the input itself (`None`).
+pub fn step(current: Option<usize>, len: usize, down: bool) -> Option<usize> {
+    match (current, down) {
+        _ if len == 0 => None,
+        (None, true) => Some(0),
+        (None, false) => Some(len - 1),
+        (Some(i), true) => (i + 1 < len).then_some(i + 1),
+        (Some(i), false) => i.checked_sub(1),
+    }
+}
+
+thread_local! {
+    static NEXT_ID: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A combobox suggesting places as the user types, recent picks and typed
+/// coordinates right away and Nominatim's once typing settles. Arrow keys
+/// move through them, Enter picks one (or looks up what's typed), Escape
+/// closes the list. Picking calls `on_pick`.
+#[component]
+pub fn AddressSearch<F>(cx: Scope, on_pick: F, placeholder: &'static str) -> impl IntoView
+where
+    F: Fn((f64, f64)) + Copy + 'static,
+{
+    let list_id = NEXT_ID.with(|id| {
+        id.set(id.get() + 1);
+        format!("address-candidates-{}", id.get())
+    });
+    let (input, set_input) = create_signal(cx, String::new());
+    let (query, set_query) = create_signal(cx, String::new());
+    let (open, set_open) = create_signal(cx, false);
+    let (highlighted, set_highlighted) = create_signal(cx, None::<usize>);
+    let (error, set_error) = create_signal(cx, None::<String>);
+    let recent = create_rw_signal(cx, storage::load::<Vec<Candidate>>(RECENT_KEY).unwrap_or_default());
+    let update = RefCell::new(debounce(cx, SETTLE, move |q: String| set_query.set(q)));
+    create_effect(cx, move |_| update.borrow_mut()(input.get()));
+    let bus = use_bus(cx);
+    let options = use_search_options(cx);
+    let results = create_local_resource(cx, move || (query.get(), options.get()), move |(query, options)| async move {
+        if query.trim().chars().count() < MIN_QUERY_CHARS || geo::parse_lat_lon(&query).is_some() {
+            return Ok(Vec::new());
+        }
+        let found = search(&query, MAX_CANDIDATES, &options).await;
+        bus.broadcast(Event::ProviderStatus { provider: Provider::Geocoder, ok: found.is_ok() });
+        found
+    });
+    let suggestions = create_memo(cx, move |_| {
+        let mut all = recent.with(|r| input.with(|i| instant_suggestions(r, i)));
+        if let Some(Ok(found)) = results.read(cx) {
+            for candidate in found {
+                if all.iter().all(|c| c.label != candidate.label) {
+                    all.push(candidate);
+                }
+            }
+        }
+        all
+    });
+    create_effect(cx, move |_| {
+        suggestions.track();
+        set_highlighted.set(None);
+    });
+    let pick = move |candidate: Candidate| {
+        let location = candidate.location;
+        recent.update(|r| {
+            remember(r, candidate);
+            storage::save(RECENT_KEY, r);
+        });
+        set_input.set(String::new());
+        set_query.set(String::new());
+        set_open.set(false);
+        on_pick(location);
+    };
+    // Enter before any suggestion arrived looks up what's typed directly.
+    let submit = move || {
+        let chosen = suggestions.with(|s| highlighted.get().or((!s.is_empty()).then_some(0)).and_then(|i| s.get(i).cloned()));
+        if let Some(candidate) = chosen {
+            pick(candidate);
+            return;
+        }
+        let text = input.get();
+        if text.trim().is_empty() {
+            return;
+        }
+        set_error.set(None);
+        let options = options.get_untracked();
+        spawn_local(async move {
+            match resolve(&text, &options).await {
+                Ok(location) => pick(Candidate { label: text.trim().to_string(), location }),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    };
+    let on_keydown = move |ev: ev::KeyboardEvent| match ev.key().as_str() {
+        "ArrowDown" | "ArrowUp" => {
+            ev.prevent_default();
+            set_open.set(true);
+            let len = suggestions.with(Vec::len);
+            set_highlighted.update(|h| *h = step(*h, len, ev.key() == "ArrowDown"));
+        }
+        "Enter" => {
+            ev.prevent_default();
+            submit();
+        }
+        "Escape" => set_open.set(false),
+        _ => {}
+    };
+    let option_id = {
+        let list_id = list_id.clone();
+        move |i: usize| format!("{list_id}-{i}")
+    };
+    let options = {
+        let option_id = option_id.clone();
+        move || {
+            suggestions
+                .get()
+                .into_iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let is_highlighted = move || highlighted.get() == Some(i);
+                    let label = c.label.clone();
+                    view! { cx,
+                        <li
+                            id=option_id(i)
+                            role="option"
+                            class:highlighted=is_highlighted
+                            aria-selected=move || is_highlighted().to_string()
+                            // Before the input's blur closes the list.
+                            on:mousedown=move |ev| {
+                                ev.prevent_default();
+                                pick(c.clone());
+                            }
+                        >
+                            {label}
+                        </li>
+                    }
+                })
+                .collect_view(cx)
+        }
+    };
+    let no_matches = move || {
+        let searched = query.with(|q| q.trim().chars().count() >= MIN_QUERY_CHARS);
+        matches!(results.read(cx), Some(Ok(found)) if found.is_empty()) && searched && suggestions.with(Vec::is_empty)
+    };
+    let expanded = move || open.get() && !suggestions.with(Vec::is_empty);
+    view! { cx,
+        <div class="address-search">
+            <input
+                type="search"
+                role="combobox"
+                aria-autocomplete="list"
+                aria-controls=list_id.clone()
+                aria-expanded=move || expanded().to_string()
+                aria-activedescendant=move || highlighted.get().map(&option_id)
+                placeholder=placeholder
+                prop:value=move || input.get()
+                on:input=move |ev| {
+                    set_input.set(event_target_value(&ev));
+                    set_open.set(true);
+                    set_error.set(None);
+                }
+                on:focus=move |_| set_open.set(true)
+                on:blur=move |_| set_open.set(false)
+                on:keydown=on_keydown
+            />
+            <ul id=list_id class="candidates" role="listbox" prop:hidden=move || !expanded()>{options}</ul>
+            <Show when=move || open.get() && no_matches() fallback=|_| ()>
+                <p class="candidates">"No matches."</p>
+            </Show>
+            {move || match results.read(cx) {
+                Some(Err(e)) => Some(e.to_string()),
+                _ => error.get(),
+            }.map(|e| view! { cx, <p class="error">{e}</p> })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_places_with_coordinates() {
+        let places: Vec<NominatimPlace> = serde_json::from_str(
+            r#"[{"display_name":"Nowhere","lat":"x","lon":"1"},
+                {"display_name":"Alexanderplatz, Berlin","lat":"52.52","lon":"13.41","type":"railway_station","importance":0.6},
+                {"display_name":"Berlin","lat":"52.5","lon":"13.4"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            candidates(places),
+            [
+                Candidate { label: "Alexanderplatz, Berlin (railway station)".into(), location: (52.52, 13.41) },
+                Candidate { label: "Berlin".into(), location: (52.5, 13.4) },
+            ]
+        );
+    }
+
+    #[test]
+    fn suggests_coordinates_and_recent_picks_first() {
+        let berlin = Candidate { label: "Berlin".into(), location: (52.5, 13.4) };
+        let bonn = Candidate { label: "Bonn".into(), location: (50.7, 7.1) };
+        let mut recent = vec![bonn.clone()];
+        remember(&mut recent, berlin.clone());
+        remember(&mut recent, bonn.clone());
+        assert_eq!(recent, [bonn.clone(), berlin.clone()]);
+        assert_eq!(instant_suggestions(&recent, "ber"), [berlin]);
+        assert_eq!(instant_suggestions(&recent, "").len(), 2);
+        assert_eq!(instant_suggestions(&recent, "52.52, 13.405")[0].location, (52.52, 13.405));
+    }
+
+    #[test]
+    fn arrow_keys_wrap_through_the_input() {
+        assert_eq!(step(None, 3, true), Some(0));
+        assert_eq!(step(Some(2), 3, true), None);
+        assert_eq!(step(None, 3, false), Some(2));
+        assert_eq!(step(Some(0), 3, false), None);
+        assert_eq!(step(None, 0, true), None);
+    }
+
+    #[test]
+    fn steers_searches_with_parameters() {
+        assert_eq!(SearchOptions::default().params(), "");
+        let options = SearchOptions { language: Some(Locale::De), near: Some((52.52, 13.4)), countries: country_codes("DE, at,xyz") };
+        assert_eq!(options.params(), "&accept-language=de&viewbox=12.40,53.52,14.40,51.52&countrycodes=de,at");
+    }
+}
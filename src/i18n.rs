@@ -0,0 +1,225 @@
+//! Interface language. The browser's preference wins unless the URL forces a
+//! locale with a route prefix (`#/de/trip`), so shared links open in the
+//! language they were meant for.
+
+use crate::msgfmt::{self, Arg};
+use leptos::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 4] = [Locale::En, Locale::De, Locale::Fr, Locale::Es];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+            Locale::Es => "es",
+        }
+    }
+
+    /// Match a BCP 47 tag like `de-AT` on its language.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+        Locale::ALL.into_iter().find(|l| l.code() == language)
+    }
+
+    /// The first supported language in the browser's preferences.
+    pub fn detect() -> Locale {
+        let Some(navigator) = web_sys::window().map(|w| w.navigator()) else {
+            return Locale::default();
+        };
+        navigator
+            .languages()
+            .iter()
+            .filter_map(|tag| tag.as_string())
+            .chain(navigator.language())
+            .find_map(|tag| Locale::from_tag(&tag))
+            .unwrap_or_default()
+    }
+}
+
+/// Interface strings. English is the key and the fallback.
+pub fn translate(locale: Locale, english: &'static str) -> &'static str {
+    match (locale, english) {
+        (Locale::De, "Find") => "Finden",
+        (Locale::De, "Plan a trip") => "Route planen",
+        (Locale::De, "Mapper mode") => "Kartierungsmodus",
+        (Locale::De, "Settings") => "Einstellungen",
+        (Locale::De, "Here") => "Hier",
+        (Locale::De, "Name this place") => "Ort benennen",
+        (Locale::De, "Save location") => "Ort speichern",
+        (Locale::Fr, "Find") => "Trouver",
+        (Locale::Fr, "Plan a trip") => "Planifier un trajet",
+        (Locale::Fr, "Mapper mode") => "Mode cartographe",
+        (Locale::Fr, "Settings") => "Réglages",
+        (Locale::Fr, "Here") => "Ici",
+        (Locale::Fr, "Name this place") => "Nommer ce lieu",
+        (Locale::Fr, "Save location") => "Enregistrer le lieu",
+        (Locale::Es, "Find") => "Buscar",
+        (Locale::Es, "Plan a trip") => "Planear un viaje",
+        (Locale::Es, "Mapper mode") => "Modo cartógrafo",
+        (Locale::Es, "Settings") => "Ajustes",
+        (Locale::Es, "Here") => "Aquí",
+        (Locale::Es, "Name this place") => "Nombrar este lugar",
+        (Locale::Es, "Save location") => "Guardar lugar",
+        _ => english,
+    }
+}
+
+/// Number of results, as an ICU message. `pack` is an [`AmenityPack::key`](crate::packs::AmenityPack::key).
+pub const RESULTS_FOUND: &str = "{pack, select, \
+    outdoors {{count, plural, =0 {No places found} one {# place found} other {# places found}}} \
+    drinking_water {{count, plural, =0 {No drinking water found} one {# drinking fountain found} other {# drinking fountains found}}} \
+    bench {{count, plural, =0 {No benches found} one {# bench found} other {# benches found}}} \
+    atm {{count, plural, =0 {No ATMs found} one {# ATM found} other {# ATMs found}}} \
+    pharmacy {{count, plural, =0 {No pharmacies found} one {# pharmacy found} other {# pharmacies found}}} \
+    other {{count, plural, =0 {No bathrooms found} one {# bathroom found} other {# bathrooms found}}}}";
+
+/// The nearest result, for sharing; see [`crate::share`]. `fee` and `hours`
+/// select on what's known, `closes` is the closing time when `hours` is `until`.
+pub const NEAREST_SUMMARY: &str = "Nearest {amenity, select, toilets {public toilet} other {toilet}}: {distance} {direction}\
+    {fee, select, free {, free} paid {, paid} other {}}\
+    {hours, select, always {, open 24/7} until {, open until {closes}} closed {, closed now} other {}} — {link}";
+
+/// When the results were fetched; `ago` is from [`crate::time::ago`].
+pub const UPDATED: &str = "Updated {ago}";
+
+/// How current OpenStreetMap was when the results were fetched, like [`UPDATED`].
+pub const MAP_DATA_AGE: &str = "Map data: {ago}";
+
+/// Messages with plural or select arguments; see [`msgfmt`].
+pub fn translate_message(locale: Locale, english: &'static str) -> &'static str {
+    match (locale, english) {
+        (Locale::De, RESULTS_FOUND) => "{pack, select, \
+            outdoors {{count, plural, =0 {Keine Orte gefunden} one {# Ort gefunden} other {# Orte gefunden}}} \
+            drinking_water {{count, plural, =0 {Kein Trinkwasser gefunden} one {# Trinkwasserstelle gefunden} other {# Trinkwasserstellen gefunden}}} \
+            bench {{count, plural, =0 {Keine Bänke gefunden} one {# Bank gefunden} other {# Bänke gefunden}}} \
+            atm {{count, plural, =0 {Keine Geldautomaten gefunden} one {# Geldautomat gefunden} other {# Geldautomaten gefunden}}} \
+            pharmacy {{count, plural, =0 {Keine Apotheken gefunden} one {# Apotheke gefunden} other {# Apotheken gefunden}}} \
+            other {{count, plural, =0 {Keine Toiletten gefunden} one {# Toilette gefunden} other {# Toiletten gefunden}}}}",
+        (Locale::Fr, RESULTS_FOUND) => "{pack, select, \
+            outdoors {{count, plural, =0 {Aucun lieu trouvé} one {# lieu trouvé} many {# de lieux trouvés} other {# lieux trouvés}}} \
+            drinking_water {{count, plural, =0 {Aucun point d’eau trouvé} one {# point d’eau trouvé} many {# de points d’eau trouvés} other {# points d’eau trouvés}}} \
+            bench {{count, plural, =0 {Aucun banc trouvé} one {# banc trouvé} many {# de bancs trouvés} other {# bancs trouvés}}} \
+            atm {{count, plural, =0 {Aucun distributeur trouvé} one {# distributeur trouvé} many {# de distributeurs trouvés} other {# distributeurs trouvés}}} \
+            pharmacy {{count, plural, =0 {Aucune pharmacie trouvée} one {# pharmacie trouvée} many {# de pharmacies trouvées} other {# pharmacies trouvées}}} \
+            other {{count, plural, =0 {Aucune toilette trouvée} one {# toilette trouvée} many {# de toilettes trouvées} other {# toilettes trouvées}}}}",
+        (Locale::Es, RESULTS_FOUND) => "{pack, select, \
+            outdoors {{count, plural, =0 {No se encontraron lugares} one {# lugar encontrado} many {# de lugares encontrados} other {# lugares encontrados}}} \
+            drinking_water {{count, plural, =0 {No se encontraron fuentes} one {# fuente encontrada} many {# de fuentes encontradas} other {# fuentes encontradas}}} \
+            bench {{count, plural, =0 {No se encontraron bancos} one {# banco encontrado} many {# de bancos encontrados} other {# bancos encontrados}}} \
+            atm {{count, plural, =0 {No se encontraron cajeros} one {# cajero encontrado} many {# de cajeros encontrados} other {# cajeros encontrados}}} \
+            pharmacy {{count, plural, =0 {No se encontraron farmacias} one {# farmacia encontrada} many {# de farmacias encontradas} other {# farmacias encontradas}}} \
+            other {{count, plural, =0 {No se encontraron baños} one {# baño encontrado} many {# de baños encontrados} other {# baños encontrados}}}}",
+        (Locale::De, NEAREST_SUMMARY) => "Nächste {amenity, select, toilets {öffentliche Toilette} other {Toilette}}: {distance} {direction}\
+            {fee, select, free {, kostenlos} paid {, kostenpflichtig} other {}}\
+            {hours, select, always {, rund um die Uhr geöffnet} until {, geöffnet bis {closes}} closed {, gerade geschlossen} other {}} — {link}",
+        (Locale::Fr, NEAREST_SUMMARY) => "Toilettes {amenity, select, toilets {publiques } other {}}les plus proches : {distance} {direction}\
+            {fee, select, free {, gratuites} paid {, payantes} other {}}\
+            {hours, select, always {, ouvertes 24h/24} until {, ouvertes jusqu’à {closes}} closed {, fermées en ce moment} other {}} — {link}",
+        (Locale::Es, NEAREST_SUMMARY) => "{amenity, select, toilets {Baño público} other {Baño}} más cercano: {distance} {direction}\
+            {fee, select, free {, gratis} paid {, de pago} other {}}\
+            {hours, select, always {, abierto 24 h} until {, abierto hasta las {closes}} closed {, cerrado ahora} other {}} — {link}",
+        (Locale::De, UPDATED) => "Aktualisiert {ago}",
+        (Locale::Fr, UPDATED) => "Mis à jour {ago}",
+        (Locale::Es, UPDATED) => "Actualizado {ago}",
+        (Locale::De, MAP_DATA_AGE) => "Kartendaten: {ago}",
+        (Locale::Fr, MAP_DATA_AGE) => "Données cartographiques : {ago}",
+        (Locale::Es, MAP_DATA_AGE) => "Datos del mapa: {ago}",
+        _ => english,
+    }
+}
+
+/// Format a message, falling back to the English pattern if the translation is broken.
+pub fn format(locale: Locale, english: &'static str, args: &[(&str, Arg)]) -> String {
+    msgfmt::format(locale, translate_message(locale, english), args)
+        .or_else(|e| {
+            log::warn!("bad {} message {english:?}: {e}", locale.code());
+            msgfmt::format(Locale::En, english, args)
+        })
+        .unwrap_or_else(|_| english.to_string())
+}
+
+/// The active locale, in context.
+#[derive(Clone, Copy)]
+pub struct I18n(pub Memo<Locale>);
+
+impl I18n {
+    /// Reactive: re-renders when the locale changes.
+    pub fn t(&self, english: &'static str) -> &'static str {
+        translate(self.0.get(), english)
+    }
+
+    /// Reactive, like [`I18n::t`].
+    pub fn format(&self, english: &'static str, args: &[(&str, Arg)]) -> String {
+        format(self.0.get(), english, args)
+    }
+}
+
+/// `forced` is the locale from the route, when there is one.
+pub fn provide_i18n(cx: Scope, forced: Signal<Option<Locale>>) -> I18n {
+    let browser = Locale::detect();
+    let i18n = I18n(create_memo(cx, move |_| forced.get().unwrap_or(browser)));
+    provide_context(cx, i18n);
+    i18n
+}
+
+pub fn use_i18n(cx: Scope) -> I18n {
+    use_context(cx).unwrap_or_else(|| I18n(create_memo(cx, |_| Locale::default())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_language_part_of_tags() {
+        assert_eq!(Locale::from_tag("de-AT"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("FR"), Some(Locale::Fr));
+        assert_eq!(Locale::from_tag("es_MX"), Some(Locale::Es));
+        assert_eq!(Locale::from_tag("ja-JP"), None);
+    }
+
+    #[test]
+    fn falls_back_to_english() {
+        assert_eq!(translate(Locale::De, "Find"), "Finden");
+        assert_eq!(translate(Locale::De, "Not translated"), "Not translated");
+        assert_eq!(translate(Locale::En, "Find"), "Find");
+    }
+
+    #[test]
+    fn results_found_in_every_locale() {
+        let found = |locale, n| format(locale, RESULTS_FOUND, &[("pack", Arg::Str("toilets")), ("count", Arg::Num(n))]);
+        assert_eq!(found(Locale::En, 1.0), "1 bathroom found");
+        assert_eq!(found(Locale::En, 12.0), "12 bathrooms found");
+        assert_eq!(found(Locale::De, 0.0), "Keine Toiletten gefunden");
+        assert_eq!(found(Locale::De, 1.0), "1 Toilette gefunden");
+        assert_eq!(found(Locale::Fr, 1.0), "1 toilette trouvée");
+        assert_eq!(found(Locale::Fr, 2.0), "2 toilettes trouvées");
+        assert_eq!(found(Locale::Es, 1.0), "1 baño encontrado");
+        assert_eq!(found(Locale::Es, 5.0), "5 baños encontrados");
+        let outdoors = |locale, n| format(locale, RESULTS_FOUND, &[("pack", Arg::Str("outdoors")), ("count", Arg::Num(n))]);
+        assert_eq!(outdoors(Locale::En, 1.0), "1 place found");
+        assert_eq!(outdoors(Locale::De, 3.0), "3 Orte gefunden");
+        assert_eq!(outdoors(Locale::Fr, 0.0), "Aucun lieu trouvé");
+        assert_eq!(outdoors(Locale::Es, 1_000_000.0), "1000000 de lugares encontrados");
+        let benches = |locale, n| format(locale, RESULTS_FOUND, &[("pack", Arg::Str("bench")), ("count", Arg::Num(n))]);
+        assert_eq!(benches(Locale::En, 1.0), "1 bench found");
+        assert_eq!(benches(Locale::De, 2.0), "2 Bänke gefunden");
+        assert_eq!(benches(Locale::Fr, 0.0), "Aucun banc trouvé");
+    }
+
+    #[test]
+    fn broken_message_falls_back_to_english() {
+        assert_eq!(format(Locale::De, "{count, plural, one {# x}}", &[]), "{count, plural, one {# x}}");
+    }
+}
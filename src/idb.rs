@@ -0,0 +1,123 @@
+//! A minimal IndexedDB wrapper: one database of object stores keyed by an `id`
+//! field, holding serde values. For data that has to survive a closed tab and
+//! may outgrow `localStorage`.
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, IdbDatabase, IdbObjectStore, IdbObjectStoreParameters, IdbRequest, IdbTransactionMode};
+
+const DB_NAME: &str = "free2pee";
+const DB_VERSION: u32 = 1;
+
+/// Every store, created on first open.
+pub const STORES: [&str; 1] = ["pending_uploads"];
+
+#[derive(Error, Clone, Debug)]
+pub enum IdbError {
+    #[error("This browser can't store data offline.")]
+    Unavailable,
+    #[error("Offline storage failed.")]
+    Failed,
+}
+
+impl From<JsValue> for IdbError {
+    fn from(e: JsValue) -> Self {
+        log::warn!("IndexedDB error: {e:?}");
+        IdbError::Failed
+    }
+}
+
+/// Resolve once `req` succeeds or fails.
+async fn wait(req: &IdbRequest) -> Result<JsValue, IdbError> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let done = req.clone();
+        let on_success = Closure::once_into_js(move || {
+            let _ = resolve.call1(&JsValue::UNDEFINED, &done.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        let on_error = Closure::once_into_js(move || {
+            let _ = reject.call0(&JsValue::UNDEFINED);
+        });
+        req.set_onsuccess(Some(on_success.unchecked_ref()));
+        req.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    Ok(JsFuture::from(promise).await?)
+}
+
+async fn open() -> Result<IdbDatabase, IdbError> {
+    let factory = window()
+        .and_then(|w| w.indexed_db().ok().flatten())
+        .ok_or(IdbError::Unavailable)?;
+    let req = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+    let upgrading = req.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        let Ok(db) = upgrading.result().map(|db| db.unchecked_into::<IdbDatabase>()) else {
+            return;
+        };
+        for store in STORES {
+            if !db.object_store_names().contains(store) {
+                let params = IdbObjectStoreParameters::new();
+                params.set_key_path(&JsValue::from_str("id"));
+                let _ = db.create_object_store_with_optional_parameters(store, &params);
+            }
+        }
+    });
+    req.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+    Ok(wait(&req).await?.unchecked_into())
+}
+
+async fn store(name: &str, mode: IdbTransactionMode) -> Result<IdbObjectStore, IdbError> {
+    let db = open().await?;
+    Ok(db.transaction_with_str_and_mode(name, mode)?.object_store(name)?)
+}
+
+/// Insert or replace `value`, which must serialize to an object with an `id`.
+pub async fn put<T: Serialize>(store_name: &str, value: &T) -> Result<(), IdbError> {
+    let value = serde_wasm_bindgen::to_value(value).map_err(|_| IdbError::Failed)?;
+    let store = store(store_name, IdbTransactionMode::Readwrite).await?;
+    wait(&store.put(&value)?).await?;
+    Ok(())
+}
+
+/// Everything in the store, in key order. Values that no longer deserialize are skipped.
+pub async fn get_all<T: DeserializeOwned>(store_name: &str) -> Result<Vec<T>, IdbError> {
+    let store = store(store_name, IdbTransactionMode::Readonly).await?;
+    let values: js_sys::Array = wait(&store.get_all()?).await?.unchecked_into();
+    Ok(values
+        .iter()
+        .filter_map(|v| serde_wasm_bindgen::from_value(v).ok())
+        .collect())
+}
+
+pub async fn delete(store_name: &str, id: &str) -> Result<(), IdbError> {
+    let store = store(store_name, IdbTransactionMode::Readwrite).await?;
+    wait(&store.delete(&JsValue::from_str(id))?).await?;
+    Ok(())
+}
+
+/// How many values each store holds and roughly how many bytes they take as JSON.
+pub async fn usage() -> Result<Vec<(&'static str, usize, usize)>, IdbError> {
+    let mut usage = Vec::with_capacity(STORES.len());
+    for name in STORES {
+        let values: Vec<serde_json::Value> = get_all(name).await?;
+        let bytes = values.iter().map(|v| v.to_string().len()).sum();
+        usage.push((name, values.len(), bytes));
+    }
+    Ok(usage)
+}
+
+/// Empty every store in a single transaction: all or nothing.
+pub async fn clear_all() -> Result<(), IdbError> {
+    let db = open().await?;
+    let names: js_sys::Array = STORES.iter().map(|s| JsValue::from_str(s)).collect();
+    let transaction = db.transaction_with_str_sequence_and_mode(&names, IdbTransactionMode::Readwrite)?;
+    let mut last = None;
+    for name in STORES {
+        last = Some(transaction.object_store(name)?.clear()?);
+    }
+    if let Some(req) = last {
+        wait(&req).await?;
+    }
+    Ok(())
+}
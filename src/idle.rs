@@ -0,0 +1,94 @@
+//! Idle detection by activity: no taps, clicks, keys or scrolling, and no
+//! movement while following, for [`IDLE_AFTER_MS`] means the user has put the
+//! phone down. Follow-me tracking (and with it the searches it triggers) pauses
+//! until the next interaction, saving battery and requests.
+//!
+//! The Idle Detection API would also notice activity outside the page, but it
+//! needs a permission prompt and isn't widely supported, so this stays a
+//! heuristic.
+
+use crate::geo::haversine_m;
+use crate::session::JITTER_M;
+use leptos::*;
+use std::time::Duration;
+
+pub const IDLE_AFTER_MS: f64 = 10.0 * 60.0 * 1000.0;
+const CHECK_EVERY: Duration = Duration::from_secs(30);
+
+pub fn is_idle(last_activity_ms: f64, now_ms: f64) -> bool {
+    now_ms - last_activity_ms >= IDLE_AFTER_MS
+}
+
+#[derive(Clone, Copy)]
+pub struct Activity {
+    last_ms: StoredValue<f64>,
+    last_fix: StoredValue<Option<(f64, f64)>>,
+    idle: RwSignal<bool>,
+}
+
+impl Activity {
+    /// Reactive.
+    pub fn is_idle(&self) -> bool {
+        self.idle.get()
+    }
+
+    pub fn poke(&self) {
+        self.last_ms.set_value(js_sys::Date::now());
+        if self.idle.get_untracked() {
+            self.idle.set(false);
+        }
+    }
+
+    /// Moving counts as activity; GPS noise doesn't.
+    pub fn moved_to(&self, at: (f64, f64)) {
+        let moved = self.last_fix.get_value().is_none_or(|last| haversine_m(last, at) >= JITTER_M);
+        if moved {
+            self.last_fix.set_value(Some(at));
+            self.poke();
+        }
+    }
+
+    fn check(&self) {
+        let idle = is_idle(self.last_ms.get_value(), js_sys::Date::now());
+        if idle != self.idle.get_untracked() {
+            self.idle.set(idle);
+        }
+    }
+}
+
+pub fn provide_activity(cx: Scope) -> Activity {
+    let activity = Activity {
+        last_ms: store_value(cx, js_sys::Date::now()),
+        last_fix: store_value(cx, None),
+        idle: create_rw_signal(cx, false),
+    };
+    window_event_listener(ev::pointerdown, move |_| activity.poke());
+    window_event_listener(ev::keydown, move |_| activity.poke());
+    window_event_listener(ev::wheel, move |_| activity.poke());
+    window_event_listener(ev::touchmove, move |_| activity.poke());
+    if let Ok(handle) = set_interval_with_handle(move || activity.check(), CHECK_EVERY) {
+        on_cleanup(cx, move || handle.clear());
+    }
+    provide_context(cx, activity);
+    activity
+}
+
+/// Without a provider the user never goes idle.
+pub fn use_activity(cx: Scope) -> Activity {
+    use_context(cx).unwrap_or_else(|| Activity {
+        last_ms: store_value(cx, f64::INFINITY),
+        last_fix: store_value(cx, None),
+        idle: create_rw_signal(cx, false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_after_ten_minutes() {
+        assert!(!is_idle(0.0, IDLE_AFTER_MS - 1.0));
+        assert!(is_idle(0.0, IDLE_AFTER_MS));
+    }
+}
@@ -0,0 +1,159 @@
+//! Importing tracks (GPX or GeoJSON LineStrings) to search along.
+
+use serde_json::Value;
+use thiserror::Error;
+use wasm_bindgen_futures::JsFuture;
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum ImportError {
+    #[error("Could not read the file.")]
+    Unreadable,
+    #[error("The file contains no track points.")]
+    NoTrack,
+    #[error("Unsupported file, expected GPX or a GeoJSON LineString.")]
+    Unsupported,
+}
+
+/// Value of `name="..."` (or single-quoted) inside a single XML tag.
+fn xml_attr(tag: &str, name: &str) -> Option<f64> {
+    let mut rest = tag;
+    while let Some(i) = rest.find(name) {
+        let before = rest[..i].chars().last();
+        let after = rest[i + name.len()..].trim_start();
+        rest = &rest[i + name.len()..];
+        if !matches!(before, Some(c) if c.is_whitespace()) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        let value = &value[1..];
+        return value[..value.find(quote)?].parse().ok();
+    }
+    None
+}
+
+/// Track points, falling back to route points, of a GPX document. There's no XML
+/// parser in the tree, but GPX points are simple enough to pick out by tag.
+fn parse_gpx(contents: &str) -> Vec<(f64, f64)> {
+    let points_of = |tag: &str| -> Vec<(f64, f64)> {
+        contents
+            .split(tag)
+            .skip(1)
+            .filter_map(|rest| {
+                let attrs = &rest[..rest.find('>')?];
+                Some((xml_attr(attrs, "lat")?, xml_attr(attrs, "lon")?))
+            })
+            .collect()
+    };
+    let track = points_of("<trkpt");
+    if track.is_empty() {
+        points_of("<rtept")
+    } else {
+        track
+    }
+}
+
+fn line_coords(coords: &Value) -> Vec<(f64, f64)> {
+    coords
+        .as_array()
+        .map(|points| {
+            points
+                .iter()
+                .filter_map(|p| Some((p.get(1)?.as_f64()?, p.get(0)?.as_f64()?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The first LineString (or the concatenated parts of a MultiLineString) found
+/// in a GeoJSON geometry, Feature, or FeatureCollection.
+fn parse_geojson(value: &Value) -> Vec<(f64, f64)> {
+    match value.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => value
+            .get("features")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(parse_geojson)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default(),
+        Some("Feature") => value.get("geometry").map(parse_geojson).unwrap_or_default(),
+        Some("LineString") => value.get("coordinates").map(line_coords).unwrap_or_default(),
+        Some("MultiLineString") => value
+            .get("coordinates")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .flat_map(line_coords)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Parse a GPX or GeoJSON document into `(lat, lon)` points.
+pub fn parse_track(contents: &str) -> Result<Vec<(f64, f64)>, ImportError> {
+    let trimmed = contents.trim_start();
+    let line = if trimmed.starts_with('<') {
+        parse_gpx(contents)
+    } else if trimmed.starts_with('{') {
+        let value: Value = serde_json::from_str(contents).map_err(|_| ImportError::Unsupported)?;
+        parse_geojson(&value)
+    } else {
+        return Err(ImportError::Unsupported);
+    };
+    if line.len() < 2 {
+        return Err(ImportError::NoTrack);
+    }
+    Ok(line)
+}
+
+pub async fn read_track(file: web_sys::File) -> Result<Vec<(f64, f64)>, ImportError> {
+    let text = JsFuture::from(file.text())
+        .await
+        .ok()
+        .and_then(|t| t.as_string())
+        .ok_or(ImportError::Unreadable)?;
+    parse_track(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpx_track_points() {
+        let gpx = r#"<?xml version="1.0"?>
+            <gpx><trk><trkseg>
+                <trkpt lat="52.5" lon="13.4"><ele>30</ele></trkpt>
+                <trkpt lon='13.41' lat='52.51'/>
+            </trkseg></trk></gpx>"#;
+        assert_eq!(parse_track(gpx), Ok(vec![(52.5, 13.4), (52.51, 13.41)]));
+    }
+
+    #[test]
+    fn gpx_route_points_fallback() {
+        let gpx = r#"<gpx><rte><rtept lat="1" lon="2"/><rtept lat="3" lon="4"/></rte></gpx>"#;
+        assert_eq!(parse_track(gpx), Ok(vec![(1.0, 2.0), (3.0, 4.0)]));
+    }
+
+    #[test]
+    fn geojson_feature_collection() {
+        let json = r#"{"type":"FeatureCollection","features":[
+            {"type":"Feature","geometry":{"type":"Point","coordinates":[0,0]}},
+            {"type":"Feature","geometry":{"type":"LineString","coordinates":[[13.4,52.5],[13.41,52.51,34]]}}
+        ]}"#;
+        assert_eq!(parse_track(json), Ok(vec![(52.5, 13.4), (52.51, 13.41)]));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(parse_track("lat,lon\n1,2"), Err(ImportError::Unsupported));
+        assert_eq!(
+            parse_track(r#"{"type":"Point","coordinates":[0,0]}"#),
+            Err(ImportError::NoTrack)
+        );
+    }
+}
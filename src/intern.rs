@@ -0,0 +1,59 @@
+//! Shared strings for tag keys and common values. A big result set repeats
+//! `amenity`, `toilets`, `yes` and `no` thousands of times; interning stores
+//! each once per session and hands out cheap `Arc` clones.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+thread_local! {
+    static STRINGS: RefCell<HashSet<Arc<str>>> = RefCell::new(HashSet::new());
+    static REUSED_BYTES: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn intern(s: &str) -> Arc<str> {
+    STRINGS.with(|strings| {
+        let mut strings = strings.borrow_mut();
+        if let Some(existing) = strings.get(s) {
+            REUSED_BYTES.with(|r| r.set(r.get() + s.len()));
+            return existing.clone();
+        }
+        let interned: Arc<str> = Arc::from(s);
+        strings.insert(interned.clone());
+        interned
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InternStats {
+    pub strings: usize,
+    /// Text held by the interner.
+    pub bytes: usize,
+    /// Text that would otherwise have been allocated again, over the session.
+    pub reused_bytes: usize,
+}
+
+/// For the debug panel.
+pub fn stats() -> InternStats {
+    InternStats {
+        strings: STRINGS.with(|s| s.borrow().len()),
+        bytes: STRINGS.with(|s| s.borrow().iter().map(|s| s.len()).sum()),
+        reused_bytes: REUSED_BYTES.with(Cell::get),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_share_one_allocation() {
+        let before = stats();
+        let a = intern("changing_table");
+        let b = intern(String::from("changing_table").as_str());
+        assert!(Arc::ptr_eq(&a, &b));
+        let after = stats();
+        assert_eq!(after.strings, before.strings + 1);
+        assert_eq!(after.reused_bytes, before.reused_bytes + "changing_table".len());
+    }
+}
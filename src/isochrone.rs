@@ -0,0 +1,63 @@
+//! Walking isochrones from the public Valhalla instance.
+
+use crate::geo;
+use crate::policy::{self, Service};
+use leptos::error::Result;
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
+const VALHALLA_URL: &str = "https://valhalla1.openstreetmap.de/isochrone";
+
+const RING_SIMPLIFY_M: f64 = 5.0;
+
+/// Minutes of walking shown around the selected element.
+pub const WALK_MINUTES: u32 = 5;
+
+#[derive(Error, Clone, Debug)]
+pub enum IsochroneError {
+    #[error("The isochrone service returned no area.")]
+    Empty,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IsochroneResponse {
+    pub features: Vec<Feature>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feature {
+    pub geometry: Geometry,
+}
+
+/// GeoJSON polygon, `coordinates` is a list of rings of `[lon, lat]` pairs.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Geometry {
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub coordinates: Vec<Vec<Vec<f64>>>,
+}
+
+/// The outer ring of the area within `minutes` walk of `(lat, lon)`, as `(lat, lon)` points.
+/// Walking is symmetric enough that this is also "where you can reach it from".
+pub async fn fetch_walking_isochrone(lat: f64, lon: f64, minutes: u32) -> Result<Vec<(f64, f64)>> {
+    let request = format!(
+        r#"{{"locations":[{{"lat":{lat},"lon":{lon}}}],"costing":"pedestrian","contours":[{{"time":{minutes}}}],"polygons":true}}"#
+    );
+    let url = format!("{VALHALLA_URL}?json={}", js_sys::encode_uri_component(&request));
+    let res = policy::get(Service::Fossgis, &url)
+        .await
+        .send()
+        .await?
+        .json::<IsochroneResponse>()
+        .await?;
+
+    let ring = res
+        .features
+        .into_iter()
+        .filter(|f| f.geometry.type_field == "Polygon")
+        .find_map(|f| f.geometry.coordinates.into_iter().next())
+        .ok_or(IsochroneError::Empty)?;
+    let ring: Vec<(f64, f64)> = ring.into_iter().filter_map(|p| Some((*p.get(1)?, *p.first()?))).collect();
+    // Valhalla rings are far denser than needed for shading or containment checks.
+    Ok(geo::simplify(&ring, RING_SIMPLIFY_M))
+}
@@ -0,0 +1,88 @@
+//! Kiosk mode, for a venue info screen: read-only, no personalization, a fixed
+//! location and periodic refreshes.
+//!
+//! Build with `--features kiosk` (location from `KIOSK_LOCATION="lat,lon"` at
+//! build time) or add `?kiosk=1&kiosk_at=lat,lon` to the URL of a normal build.
+
+use crate::geo;
+use std::time::Duration;
+
+pub const DEFAULT_REFRESH: Duration = Duration::from_secs(5 * 60);
+
+/// Refreshing more often than this would just hammer Overpass.
+const MIN_REFRESH: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KioskConfig {
+    /// Where the screen is. Without one, it falls back to geolocation.
+    pub location: Option<(f64, f64)>,
+    pub refresh: Duration,
+}
+
+impl KioskConfig {
+    /// `compiled` is whether the `kiosk` feature is on; the rest are the URL
+    /// parameters, which take precedence over build-time settings.
+    pub fn from_params(
+        compiled: bool,
+        flag: Option<&str>,
+        at: Option<&str>,
+        refresh_minutes: Option<&str>,
+    ) -> Option<KioskConfig> {
+        let enabled = compiled || matches!(flag, Some("1" | "true"));
+        if !enabled {
+            return None;
+        }
+        let location = at.or(option_env!("KIOSK_LOCATION")).and_then(geo::parse_lat_lon);
+        let refresh = refresh_minutes
+            .and_then(|m| m.parse::<u64>().ok())
+            .map(|m| Duration::from_secs(m * 60).max(MIN_REFRESH))
+            .unwrap_or(DEFAULT_REFRESH);
+        Some(KioskConfig { location, refresh })
+    }
+}
+
+/// The kiosk configuration, if this is a kiosk.
+pub fn config() -> Option<KioskConfig> {
+    let params = crate::search_params();
+    let config = KioskConfig::from_params(
+        cfg!(feature = "kiosk"),
+        params.get("kiosk").as_deref(),
+        params.get("kiosk_at").as_deref(),
+        params.get("kiosk_refresh").as_deref(),
+    );
+    if config.is_some_and(|c| c.location.is_none()) {
+        log::warn!("kiosk mode without a location; set kiosk_at=lat,lon");
+    }
+    config
+}
+
+pub fn enabled() -> bool {
+    config().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn off_by_default() {
+        assert_eq!(KioskConfig::from_params(false, None, Some("1,2"), None), None);
+        assert_eq!(KioskConfig::from_params(false, Some("0"), None, None), None);
+    }
+
+    #[test]
+    fn url_flag_with_location() {
+        let config = KioskConfig::from_params(false, Some("1"), Some("52.5,13.4"), Some("10")).unwrap();
+        assert_eq!(config.location, Some((52.5, 13.4)));
+        assert_eq!(config.refresh, Duration::from_secs(600));
+    }
+
+    #[test]
+    fn refresh_is_clamped_and_defaulted() {
+        let config = KioskConfig::from_params(true, None, Some("bad"), Some("0")).unwrap();
+        assert_eq!(config.location, None);
+        assert_eq!(config.refresh, MIN_REFRESH);
+        let config = KioskConfig::from_params(true, None, None, None).unwrap();
+        assert_eq!(config.refresh, DEFAULT_REFRESH);
+    }
+}
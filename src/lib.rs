@@ -0,0 +1,1102 @@
+//! Free2Pee: public toilets and other amenities near you, from OpenStreetMap.
+//! The binary just calls [`run`]; everything else lives here so the data
+//! layer ([`models`], [`api`], [`geo`], [`overpass`]) can be tested on its own.
+
+use leptos::{error::Result, *};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{console, window, Position, PositionError};
+use futures::channel::oneshot;
+use std::sync::{Arc, Mutex};
+
+mod address;
+mod annotations;
+pub mod api;
+mod badges;
+mod bus;
+mod cache;
+mod capabilities;
+mod changeset;
+mod chunked;
+mod collate;
+mod compass;
+mod components;
+mod coords;
+mod cost;
+mod debug;
+mod diagnostics;
+mod elevation;
+mod embed;
+mod embed_api;
+mod export;
+mod family;
+mod favorites;
+mod filters;
+mod follow;
+mod fuzzy;
+pub mod geo;
+mod geocode;
+mod i18n;
+mod idle;
+mod intern;
+mod idb;
+mod import;
+mod isochrone;
+mod kiosk;
+mod links;
+mod manual_location;
+mod map;
+mod measure;
+mod media;
+mod metrics;
+mod migrations;
+pub mod models;
+mod mirrors;
+mod msgfmt;
+mod net;
+mod notify;
+mod opening_hours;
+mod optimistic;
+mod osm_auth;
+mod osm_edit;
+mod osm_links;
+pub mod overpass;
+mod packs;
+mod pagination;
+mod perf;
+mod places;
+mod policy;
+mod prefs;
+mod profiles;
+mod pwa;
+mod quality;
+mod query_template;
+mod quests;
+mod radius;
+mod ranking;
+mod reset;
+mod results;
+mod router;
+mod scoring;
+mod session;
+mod share;
+mod sorting;
+mod sound;
+mod spatial;
+mod status;
+mod step_free;
+mod storage;
+mod tabs;
+mod tags;
+mod tasks;
+mod theme;
+mod time;
+mod toast;
+mod trip;
+mod vocabulary;
+#[cfg(feature = "what3words")]
+mod what3words;
+#[cfg(test)]
+mod bench;
+
+use api::osrm::fetch_table_data_batched;
+use api::overpass::{fetch_bbox_tiled, fetch_overpass, narrow_radius};
+use components::{error_view::ErrorView, loading::Loading, results_table::ResultsTable};
+use geo::BBox;
+use overpass::Area;
+use capabilities::WordAddress;
+use compass::MiniCompass;
+use debug::DebugPanel;
+use diagnostics::Diagnostics;
+use embed::EmbedWidget;
+use favorites::FavoritesSection;
+use filters::{ActiveFilterBar, FilterChips};
+use geocode::AddressSearch;
+use packs::{AmenityPack, ResultClass};
+use pagination::Pager;
+use map::ResultsMap;
+use osm_edit::PendingIndicator;
+use places::SavedPlace;
+use prefs::PreferencesPanel;
+use radius::RadiusSlider;
+use session::SessionSummary;
+use share::CopySummary;
+use sorting::{Direction, SortKey, SortState};
+use status::{MirrorHealth, ServiceStatus};
+use step_free::StairsHint;
+use profiles::ProfileSwitcher;
+use pwa::OfflineBanner;
+use quality::MapperMode;
+use quests::QuestPanel;
+use results::ScoredRow;
+use router::Page;
+use theme::Section;
+use toast::ToastHost;
+use trip::TripPlanner;
+use tasks::{CancelToken, Progress, TaskRunner};
+
+pub use models::{BathroomError, Bathrooms, Element, OsmType, OverpassResponse, Route, RouteRoot, TableRoot};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchArea {
+    /// Radius in meters around the user's location.
+    Around(i64),
+    BBox(BBox),
+}
+
+impl SearchArea {
+    pub fn cost(&self, pack: AmenityPack) -> cost::QueryCost {
+        let area_km2 = match self {
+            SearchArea::Around(radius) => cost::circle_area_km2(*radius as f64),
+            SearchArea::BBox(bbox) => bbox.area_km2(),
+        };
+        cost::estimate_query_cost(area_km2, &pack.density_keys(), pack.density_factor())
+    }
+
+    /// How many tiles this search is split into. Only bbox searches get split.
+    pub fn tile_count(&self, pack: AmenityPack) -> usize {
+        match self {
+            SearchArea::Around(_) => 1,
+            SearchArea::BBox(_) => self.cost(pack).suggested_tiles(),
+        }
+    }
+}
+
+fn search_params() -> web_sys::UrlSearchParams {
+    let location = window().unwrap().location();
+    let search = location.search().unwrap();
+
+    // Parse the query parameters.
+    web_sys::UrlSearchParams::new_with_str(&search).unwrap()
+}
+
+/// The search radius in meters from the `around` query parameter, else the
+/// `preferred` one, defaulting to the pack's radius.
+pub fn search_radius(pack: AmenityPack, preferred: Option<i64>) -> i64 {
+    search_params()
+        .get("around")
+        .and_then(|r| r.parse().ok())
+        .or(preferred)
+        .unwrap_or_else(|| pack.default_radius())
+}
+
+/// A `bbox=south,west,north,east` query parameter takes precedence over the radius search.
+pub fn search_area(radius: i64) -> SearchArea {
+    match search_params().get("bbox").as_deref().and_then(BBox::parse) {
+        Some(bbox) => SearchArea::BBox(bbox),
+        None => SearchArea::Around(radius),
+    }
+}
+
+pub async fn current_position() -> Result<(f64, f64)> {
+    let (sender, receiver) = oneshot::channel::<Result<(f64, f64), BathroomError>>();
+    let sender = Arc::new(Mutex::new(Some(sender)));
+
+    let sender_clone = Arc::clone(&sender);
+    let success_callback = Closure::wrap(Box::new(move |pos: Position| {
+        let lat = pos.coords().latitude();
+        let lon = pos.coords().longitude();
+        log!("lat: {}, lon: {}", lat, lon);
+        perf::mark(perf::Mark::GeolocationAcquired);
+        geocode::note_position((lat, lon));
+        if let Some(sender) = sender_clone.lock().unwrap().take() {
+            let _ = sender.send(Ok((lat, lon)));
+        }
+    }) as Box<dyn FnMut(Position)>);
+
+    let sender_clone = Arc::clone(&sender);
+    let error_callback = Closure::wrap(Box::new(move |err: PositionError| {
+        if let Some(sender) = sender_clone.lock().unwrap().take() {
+            let _ = sender.send(Err(BathroomError::from_position_error(err.code())));
+        }
+    }) as Box<dyn FnMut(PositionError)>);
+
+    let geolocation = window()
+        .and_then(|w| w.navigator().geolocation().ok())
+        .ok_or(BathroomError::LocationUnavailable)?;
+    geolocation.get_current_position_with_error_callback(
+        success_callback.as_ref().unchecked_ref(),
+        Some(error_callback.as_ref().unchecked_ref()),
+    ).map_err(|_| BathroomError::LocationUnavailable)?;
+
+    success_callback.forget();
+    error_callback.forget();
+
+    let coords = receiver.await.unwrap()?; // Propagate the BathroomError if we got one
+    Ok(coords)
+
+}
+
+pub async fn fetch_bathrooms_at(origin: (f64, f64), area: SearchArea, pack: AmenityPack, runner: &TaskRunner, progress: impl FnMut(Progress)) -> Result<Bathrooms> {
+    let (lat, lon) = origin;
+
+    let res = match area {
+        SearchArea::Around(radius) => fetch_overpass(&pack.query(origin, &Area::around(radius, origin))).await?,
+        SearchArea::BBox(bbox) => fetch_bbox_tiled(bbox, pack, area.tile_count(pack), runner, progress).await?,
+    };
+    let destinations = res.elements.iter().map(|e| (e.lat, e.lon)).collect();
+
+    let json = fetch_table_data_batched((lat, lon), destinations, runner).await?;
+    let val = serde_wasm_bindgen::to_value(&json).unwrap();
+
+    console::log_1(&val);
+    Ok((res, json, (lat, lon)))
+}
+
+/// A search whose cached results were shown stale, to refresh in the background.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Revalidation {
+    pub origin: (f64, f64),
+    pub radius: i64,
+    pub pack: AmenityPack,
+}
+
+/// Where a search tells the page how it's going.
+#[derive(Clone, Copy)]
+pub struct SearchReport {
+    pub progress: WriteSignal<Progress>,
+    /// What the cache decided, when it had results for the search.
+    pub freshness: WriteSignal<Option<cache::Freshness>>,
+    /// When the results were fetched from the servers.
+    pub fetched_at: WriteSignal<Option<f64>>,
+    /// Set when stale results were served, so fresh ones get fetched.
+    pub revalidate: WriteSignal<Option<Revalidation>>,
+    /// Set when the radius was cut down; see [`narrow_radius`].
+    pub narrowed: WriteSignal<Option<cost::Narrowed>>,
+    /// Set when saved results were shown because there's no network.
+    pub offline: WriteSignal<bool>,
+}
+
+/// Results around a saved place, or around `around` (the center of a shared
+/// viewport), or around the user's position. Radius searches go through the
+/// [`cache`]; kiosks always fetch, since they refresh on a timer anyway.
+pub async fn fetch_bathrooms(place: Option<SavedPlace>, around: Option<(f64, f64)>, pack: AmenityPack, radius: i64, max_age_min: u32, report: SearchReport, cancel: CancelToken) -> Result<Bathrooms> {
+    let runner = TaskRunner::unbounded().with_cancel(cancel);
+    let SearchReport { progress, freshness, fetched_at, revalidate, narrowed, offline } = report;
+    freshness.set(None);
+    revalidate.set(None);
+    narrowed.set(None);
+    offline.set(false);
+
+    let (origin, area) = match place {
+        Some(place) => (place.coords(), SearchArea::Around(radius)),
+        None => match kiosk::config().and_then(|k| k.location).or(around) {
+            Some(location) => (location, search_area(radius)),
+            None => (current_position().await?, search_area(radius)),
+        },
+    };
+    let cacheable = matches!(area, SearchArea::Around(_)) && kiosk::config().is_none();
+    let key = cacheable.then(|| cache::cache_key(origin, radius, pack));
+    if let Some((verdict, cached)) = key.as_deref().and_then(|key| cache::lookup(key, max_age_min)) {
+        freshness.set(Some(verdict));
+        fetched_at.set(Some(cached.fetched_at));
+        if let cache::Freshness::Stale { .. } = verdict {
+            revalidate.set(Some(Revalidation { origin, radius, pack }));
+        }
+        return Ok(cached.bathrooms);
+    }
+
+    // Offline, the last results for this search, or failing that for the pack, beat none.
+    let last_results = || {
+        let cached = key.as_deref().and_then(cache::load_any).or_else(|| cache::newest(pack))?;
+        fetched_at.set(Some(cached.fetched_at));
+        offline.set(true);
+        Some(cached.bathrooms)
+    };
+    if !pwa::online() {
+        return last_results().ok_or_else(|| net::NetError::Network("you're offline".to_string()).into());
+    }
+    let area = match area {
+        SearchArea::Around(radius) => {
+            let (radius, narrowing) = narrow_radius(origin, radius, pack).await;
+            narrowed.set(narrowing);
+            SearchArea::Around(radius)
+        }
+        bbox => bbox,
+    };
+    let bathrooms = match fetch_bathrooms_at(origin, area, pack, &runner, |p| progress.set(p)).await {
+        Ok(bathrooms) => bathrooms,
+        Err(e) => return if pwa::online() { Err(e) } else { last_results().ok_or(e) },
+    };
+    fetched_at.set(Some(js_sys::Date::now()));
+    if let Some(key) = key {
+        cache::store(&key, &bathrooms);
+    }
+    Ok(bathrooms)
+}
+
+pub fn fetch_example(cx: Scope) -> impl IntoView {
+    let (tile_progress, set_tile_progress) = create_signal(cx, Progress::default());
+    let cancel = CancelToken::new();
+    on_cleanup(cx, {
+        let cancel = cancel.clone();
+        move || cancel.cancel()
+    });
+    let prefs = prefs::use_preferences(cx);
+    let route = router::use_route(cx);
+    let filter_state = filters::use_filter_state(cx);
+    // The search round-trips through the URL: opening a shared link sets it
+    // up, and changes are written back so the address bar always shares it.
+    let linked = kiosk::config().is_none();
+    if linked {
+        create_effect(cx, move |_| {
+            let router::SearchState { pack, radius_m, filters } = route.with(|r| r.search.clone());
+            if let Some(pack) = pack.filter(|&pack| prefs.with_untracked(|p| p.amenity_pack != pack)) {
+                prefs.update(|p| p.amenity_pack = pack);
+            }
+            if let Some(radius) = radius_m.filter(|&radius| prefs.with_untracked(|p| p.search_radius_m != Some(radius))) {
+                prefs.update(|p| p.search_radius_m = Some(radius));
+            }
+            if let Some(filters) = filters.filter(|f| filter_state.active.with_untracked(|a| a != f)) {
+                filter_state.active.set(filters);
+            }
+        });
+    }
+    let pack = create_memo(cx, move |_| prefs.with(|p| p.amenity_pack));
+    let radius = create_memo(cx, move |_| search_radius(pack.get(), prefs.with(|p| p.search_radius_m)));
+    if linked {
+        create_effect(cx, move |_| {
+            let search = router::SearchState {
+                pack: Some(pack.get()),
+                radius_m: Some(radius.get()),
+                filters: Some(filter_state.active.get()).filter(|f| !f.is_empty()),
+            };
+            let current = route.get_untracked();
+            if current.page == Page::Finder && current.search != search {
+                router::replace(&current.with_search(search));
+            }
+        });
+    }
+    let settled_radius = radius::settled(cx, radius);
+    let (places, set_places) = create_signal(cx, places::load());
+    let (selected_place, set_selected_place) = create_signal(cx, None::<SavedPlace>);
+    let viewport = create_memo(cx, move |_| route.get().viewport);
+    // A saved place wins over the viewport, which then only tracks where the user looks.
+    let search_source = move || {
+        let place = selected_place.get();
+        let around = place.is_none().then(|| viewport.get().map(|v| v.center())).flatten();
+        (place, around, pack.get(), settled_radius.get())
+    };
+    let (freshness, set_freshness) = create_signal(cx, None::<cache::Freshness>);
+    let (fetched_at, set_fetched_at) = create_signal(cx, None::<f64>);
+    let (revalidate, set_revalidate) = create_signal(cx, None::<Revalidation>);
+    let (narrowed, set_narrowed) = create_signal(cx, None::<cost::Narrowed>);
+    let (served_offline, set_served_offline) = create_signal(cx, false);
+    let report = SearchReport {
+        progress: set_tile_progress,
+        freshness: set_freshness,
+        fetched_at: set_fetched_at,
+        revalidate: set_revalidate,
+        narrowed: set_narrowed,
+        offline: set_served_offline,
+    };
+    // Refreshing treats saved results as stale, so they stay up while new ones load.
+    let force_refresh = store_value(cx, false);
+    let bathrooms = create_local_resource(cx, search_source, move |(place, around, pack, radius)| {
+        let max_age_min = match force_refresh.get_value() {
+            true => 0,
+            false => prefs.with_untracked(|p| p.max_cache_age_min),
+        };
+        force_refresh.set_value(false);
+        fetch_bathrooms(place, around, pack, radius, max_age_min, report, cancel.clone())
+    });
+    window_event_listener(ev::online, move |_| {
+        if served_offline.get_untracked() {
+            bathrooms.refetch();
+        }
+    });
+    // Stale cached results stay up while fresh ones load, which replace them
+    // unless another search has started meanwhile.
+    let (refreshing, set_refreshing) = create_signal(cx, false);
+    create_effect(cx, move |_| {
+        let Some(search) = revalidate.get() else {
+            return;
+        };
+        set_refreshing.set(true);
+        spawn_local(async move {
+            let Revalidation { origin, radius, pack } = search;
+            let runner = TaskRunner::unbounded();
+            let (narrowed_radius, _) = narrow_radius(origin, radius, pack).await;
+            match fetch_bathrooms_at(origin, SearchArea::Around(narrowed_radius), pack, &runner, |_| {}).await {
+                Ok(fresh) => {
+                    cache::store(&cache::cache_key(origin, radius, pack), &fresh);
+                    if revalidate.get_untracked() == Some(search) {
+                        set_freshness.set(None);
+                        set_fetched_at.set(Some(js_sys::Date::now()));
+                        bathrooms.set(Ok(fresh));
+                    }
+                }
+                Err(e) => log::warn!("couldn't refresh stale results: {e}"),
+            }
+            set_refreshing.set(false);
+        });
+    });
+    let refresh = move || {
+        force_refresh.set_value(true);
+        bathrooms.refetch();
+    };
+    let busy = move || refreshing.get() || bathrooms.loading().get();
+    let (clock, set_clock) = create_signal(cx, js_sys::Date::now());
+    if let Ok(handle) = set_interval_with_handle(move || set_clock.set(js_sys::Date::now()), std::time::Duration::from_secs(30)) {
+        on_cleanup(cx, move || handle.clear());
+    }
+    let i18n = i18n::use_i18n(cx);
+    let data_age = move || {
+        let at = fetched_at.get()?;
+        Some(view! { cx,
+            <p class="data-age">
+                {move || i18n.format(i18n::UPDATED, &[("ago", msgfmt::Arg::Str(&time::ago(i18n.0.get(), clock.get() - at)))])}
+                {move || refreshing.get().then_some(" · refreshing…")}
+                " "
+                <button class="refresh" class:spinning=busy prop:disabled=busy on:click=move |_| refresh()>"Refresh"</button>
+            </p>
+        })
+    };
+
+    let kiosk = kiosk::config();
+    let theme = theme::use_theme(cx);
+    let (show_places, show_pack) = (theme.shows(Section::Places), theme.shows(Section::Pack));
+    let (show_selected, show_secondary) = (theme.shows(Section::Selected), theme.shows(Section::Secondary));
+    // Several kiosk tabs on one device share the Overpass budget; only the leader refreshes.
+    let tabs = tabs::use_tabs(cx);
+    if let Some(kiosk) = kiosk {
+        let refresh = move || {
+            if tabs.is_leader_untracked() {
+                bathrooms.refetch();
+            }
+        };
+        if let Ok(handle) = set_interval_with_handle(refresh, kiosk.refresh) {
+            on_cleanup(cx, move || handle.clear());
+        }
+    }
+
+    // Opening the app near a saved place starts from its cached results and filters.
+    let (welcome_back, set_welcome_back) = create_signal(cx, None::<(String, filters::Filters)>);
+    let radius_m = prefs.with_untracked(|p| p.welcome_back_radius_m);
+    if kiosk.is_none() && viewport.get_untracked().is_none() && radius_m > 0 && !places.with_untracked(Vec::is_empty) {
+        spawn_local(async move {
+            let Ok(position) = current_position().await else {
+                return;
+            };
+            let Some(place) = places.with_untracked(|p| places::nearby(p, position, radius_m as f64).cloned()) else {
+                return;
+            };
+            // Unless the user already picked something while we were locating them.
+            if selected_place.get_untracked().is_some() {
+                return;
+            }
+            set_welcome_back.set(Some((place.name.clone(), filter_state.active.get_untracked())));
+            if !place.filters.is_empty() {
+                filter_state.active.set(place.filters.clone());
+            }
+            set_selected_place.set(Some(place));
+        });
+    }
+    let welcome_view = move || {
+        let (name, previous_filters) = welcome_back.get()?;
+        let stay_here = move |_| {
+            set_welcome_back.set(None);
+            filter_state.active.set(previous_filters.clone());
+            set_selected_place.set(None);
+        };
+        Some(view! { cx,
+            <p class="welcome-back">
+                {format!("Welcome back near {name}. ")}
+                {move || freshness.get().map(|f| format!("{} ", f.describe(prefs.with(|p| p.max_cache_age_min))))}
+                <button on:click=stay_here>"Use my location instead"</button>
+                <button on:click=move |_| set_welcome_back.set(None)>"Dismiss"</button>
+            </p>
+        })
+    };
+
+    // Give the initial search a head start before prefetching saved places.
+    if kiosk.is_none() {
+        set_timeout(
+        move || {
+            let (pack, max_age_min) = (pack.get_untracked(), prefs.with_untracked(|p| p.max_cache_age_min));
+            spawn_local(places::warm_cache(places.get_untracked(), radius.get_untracked(), pack, max_age_min))
+        },
+        std::time::Duration::from_secs(5),
+    );
+    }
+
+    let (place_name, set_place_name) = create_signal(cx, String::new());
+    let save_place = move |_| {
+        let name = place_name.get().trim().to_string();
+        let origin = bathrooms.read(cx).and_then(|b| b.ok()).map(|b| b.2);
+        if let (false, Some((lat, lon))) = (name.is_empty(), origin) {
+            let viewport = viewport.get_untracked();
+            set_places.update(|places| {
+                places.retain(|p| p.name != name);
+                places.push(SavedPlace { name, lat, lon, viewport, filters: filter_state.active.get_untracked() });
+                places::save(places);
+            });
+            set_place_name.set(String::new());
+        }
+    };
+    let places_bar = move || {
+        if kiosk.is_some() || !show_places {
+            return None;
+        }
+        Some(view! { cx,
+            <nav class="places">
+                <button on:click=move |_| {
+                    set_selected_place.set(None);
+                    router::navigate(&route.get_untracked().with_viewport(None));
+                }>{move || i18n.t("Here")}</button>
+                <AddressSearch placeholder="Search an address" on_pick=move |(lat, lon)| {
+                    set_selected_place.set(None);
+                    let viewport = router::Viewport { lat, lon, zoom: manual_location::ZOOM };
+                    router::navigate(&route.get_untracked().with_viewport(Some(viewport)));
+                }/>
+                {move || places.get().into_iter().map(|place| {
+                    let name = place.name.clone();
+                    view! { cx,
+                        <button on:click=move |_| {
+                            router::navigate(&route.get_untracked().with_viewport(place.viewport));
+                            set_selected_place.set(Some(place.clone()));
+                        }>{name}</button>
+                    }
+                }).collect_view(cx)}
+                <input
+                    placeholder=move || i18n.t("Name this place")
+                    prop:value=move || place_name.get()
+                    on:input=move |ev| set_place_name.set(event_target_value(&ev))
+                />
+                <button on:click=save_place>{move || i18n.t("Save location")}</button>
+            </nav>
+        })
+    };
+
+    // Whatever the user looks at around a saved place is where they return to next time.
+    // Only follows the viewport: switching places navigates first and the hash catches up later.
+    create_effect(cx, move |_| {
+        let (Some(viewport), Some(place)) = (viewport.get(), selected_place.get_untracked()) else {
+            return;
+        };
+        let mut updated = places.get_untracked();
+        if places::remember_viewport(&mut updated, &place.name, viewport) {
+            places::save(&updated);
+            set_places.set(updated);
+        }
+    });
+
+    // Saved places and cached results from other tabs; a fresh cache entry for this place shows here too.
+    tabs.on_stored_elsewhere(cx, |key| key == places::STORAGE_KEY, move |_| set_places.set(places::load()));
+    tabs.on_stored_elsewhere(cx, |key| key.starts_with("cache."), move |key| {
+        let current = selected_place.with_untracked(|p| p.as_ref().map(|p| cache::cache_key(p.coords(), settled_radius.get_untracked(), pack.get_untracked())));
+        if current.as_deref() == Some(key) {
+            bathrooms.refetch();
+        }
+    });
+
+    // Tiled searches are split precisely so they don't hit the limits the warning is about.
+    // Once a count has cut a search down, that's what's worth saying instead.
+    let cost_warning = move || {
+        if let Some(narrowed) = narrowed.get() {
+            return Some(view! { cx, <p class="warning">{narrowed.describe()}</p> });
+        }
+        let pack = pack.get();
+        let area = search_area(radius.get());
+        (area.tile_count(pack) == 1)
+            .then(|| area.cost(pack).warning())
+            .flatten()
+            .map(|warning| view! { cx, <p class="warning">{warning}</p> })
+    };
+    let pack_select = move || {
+        if kiosk.is_some() || !show_pack {
+            return None;
+        }
+        Some(view! { cx,
+            <select class="pack" on:change=move |ev| {
+                let pack = AmenityPack::from_label(&event_target_value(&ev));
+                prefs.update(|p| p.amenity_pack = pack);
+            }>
+                {AmenityPack::ALL.into_iter().map(|p| view! { cx,
+                    <option value=p.label() selected=move || pack.get() == p>{p.label()}</option>
+                }).collect_view(cx)}
+            </select>
+        })
+    };
+    let fallback = move |cx, errors: RwSignal<Errors>| {
+        view! { cx, <ErrorView errors on_retry=move || bathrooms.refetch()/> }
+    };
+
+    let (selected, set_selected) = create_signal(cx, None::<Element>);
+    let (show_isochrone, set_show_isochrone) = create_signal(cx, false);
+    let (sort_order, set_sort_order) = create_signal(cx, SortState::default());
+    let (follow, set_follow) = create_signal(cx, false);
+    // Only the leader tab watches the position; following here makes this tab the leader.
+    // Tracking pauses while the user is idle and resumes with the next interaction.
+    let activity = idle::use_activity(cx);
+    let following_here = Signal::derive(cx, move || follow.get() && tabs.is_leader() && !activity.is_idle());
+    let live_position = follow::use_live_position(cx, following_here);
+    // Not while the user is away or offline; kiosks have their own timer.
+    let auto_refresh = create_memo(cx, move |_| prefs.with(|p| p.auto_refresh_min));
+    let refresh_timer = store_value(cx, None::<IntervalHandle>);
+    create_effect(cx, move |_| {
+        if let Some(handle) = refresh_timer.get_value() {
+            handle.clear();
+        }
+        let minutes = auto_refresh.get();
+        let tick = move || {
+            if !activity.is_idle() && pwa::online() {
+                refresh();
+            }
+        };
+        let handle = (minutes > 0 && kiosk.is_none())
+            .then(|| set_interval_with_handle(tick, std::time::Duration::from_secs(60 * minutes as u64)).ok())
+            .flatten();
+        refresh_timer.set_value(handle);
+    });
+    on_cleanup(cx, move || {
+        if let Some(handle) = refresh_timer.get_value() {
+            handle.clear();
+        }
+    });
+    let ranking = create_memo(cx, move |_| prefs.with(|p| p.ranking));
+    // Road trips list places in the order they come along a planned trip.
+    let loaded_route = trip::use_loaded_route(cx);
+    let along_route = Signal::derive(cx, move || (pack.get() == AmenityPack::RoadTrip).then(|| loaded_route.get()).flatten());
+    let (sorted, index) = results::pipeline(cx, bathrooms, filter_state, sort_order, ranking, along_route, i18n.0);
+    if kiosk.is_none() {
+        annotations::track_favorites(cx, move || sorted.with(|rows| rows.iter().map(|r| r.row.element.clone()).collect()));
+    }
+    let walk_area = create_local_resource(
+        cx,
+        move || (show_isochrone.get(), selected.with(|s| s.as_ref().map(|e| (e.lat, e.lon)))),
+        |(show, target)| async move {
+            match (show, target) {
+                (true, Some((lat, lon))) => {
+                    isochrone::fetch_walking_isochrone(lat, lon, isochrone::WALK_MINUTES).await.map(Some)
+                }
+                _ => Ok(None),
+            }
+        },
+    );
+    // Gone as soon as the selection changes, not once the next area arrives.
+    let walk_ring = create_memo(cx, move |_| {
+        (!walk_area.loading().get()).then(|| walk_area.read(cx).and_then(Result::ok).flatten()).flatten()
+    });
+    let reachability = move || {
+        let origin = bathrooms.read(cx).and_then(|b| b.ok()).map(|b| b.2)?;
+        let text = match walk_area.read(cx)? {
+            Ok(Some(ring)) if geo::point_in_polygon(origin, &ring) => {
+                format!("You are within a {}-minute walk.", isochrone::WALK_MINUTES)
+            }
+            Ok(Some(_)) => format!("More than a {}-minute walk away.", isochrone::WALK_MINUTES),
+            Ok(None) => return None,
+            Err(e) => e.to_string(),
+        };
+        Some(view! { cx, <span class="reachability">{text}</span> })
+    };
+    let climb = create_local_resource(
+        cx,
+        move || {
+            let target = selected.with(|s| s.as_ref().map(|e| (e.lat, e.lon)));
+            let origin = bathrooms.read(cx).and_then(|b| b.ok()).map(|b| b.2);
+            (prefs.with(|p| p.show_elevation), origin, target)
+        },
+        |(show, origin, target)| async move {
+            match (show, origin, target) {
+                (true, Some(origin), Some(target)) => elevation::fetch_climb(origin, target).await.map(Some),
+                _ => Ok(None),
+            }
+        },
+    );
+    let climb_view = move || {
+        let text = match climb.read(cx)? {
+            Ok(Some(delta)) => elevation::climb_label(delta),
+            Ok(None) => return None,
+            Err(e) => e.to_string(),
+        };
+        Some(view! { cx, <span class="climb">{text}</span> })
+    };
+    let pin = compass::use_pin(cx);
+    let selected_view = move || {
+        selected.get().filter(|_| show_selected).map(|element| {
+            let origin = bathrooms.read(cx).and_then(|b| b.ok()).map(|b| b.2);
+            view! { cx,
+                <div class="selected">
+                    <strong>{format!("Selected: OSM:{}", element.id)}</strong>
+                    " "
+                    <span>{move || coords::format(element.lat, element.lon, prefs.with(|p| p.coord_format))}</span>
+                    " "
+                    <WordAddress lat=element.lat lon=element.lon/>
+                    {kiosk.is_none().then(|| {
+                        let place = compass::Pin {
+                            name: element.tags.get("name").unwrap_or("Unnamed").to_string(),
+                            lat: element.lat,
+                            lon: element.lon,
+                        };
+                        view! { cx, " "<button on:click=move |_| compass::set_pin(pin, Some(place.clone()))>"📌 Pin"</button> }
+                    })}
+                    {origin.map(|(lat, lon)| view! { cx, " You: " <WordAddress lat lon/> })}
+                    <label>
+                        <input
+                            type="checkbox"
+                            prop:checked=move || show_isochrone.get()
+                            on:change=move |ev| set_show_isochrone.set(event_target_checked(&ev))
+                        />
+                        {format!("Show {}-minute walking area", isochrone::WALK_MINUTES)}
+                    </label>
+                    {reachability}
+                    {climb_view}
+                    {origin.map(|origin| view! { cx, <StairsHint origin target=(element.lat, element.lon)/> })}
+                    <QuestPanel element=element.clone()/>
+                </div>
+            }
+        })
+    };
+
+    // Keyed by (type, id), so a refetch or filter change only patches the rows that changed.
+    // While following, nearest to the live position first unless sorted by another column;
+    // unchanged orders don't notify.
+    let ordered = create_memo(cx, move |previous: Option<&Vec<ScoredRow>>| match live_position.get() {
+        Some(at) if matches!(sort_order.get(), SortState { key: SortKey::Rank | SortKey::Distance, direction: Direction::Ascending }) => sorted.with(|rows| index.with(|index| follow::by_distance(rows, index, previous, at))),
+        _ => sorted.get(),
+    });
+    let notifier = notify::use_notifier(cx);
+    // Selections come over the bus, from the table or the map.
+    let bus = bus::use_bus(cx);
+    bus.subscribe(cx, move |event| {
+        if let bus::Event::Selected { key } = event {
+            let element = key.and_then(|key| ordered.with_untracked(|rows| rows.iter().find(|r| r.row.key() == key).map(|r| (*r.row.element).clone())));
+            set_selected.set(element);
+        }
+    });
+    // Kiosks are shared screens, so nothing's counted there.
+    let session = kiosk.is_none().then(|| session::use_session(cx));
+    // `None` while loading, so each finished (re)fetch plays once.
+    create_effect(cx, move |_| match bathrooms.with(cx, |b| b.is_ok()) {
+        Some(true) => {
+            bus.broadcast(bus::Event::ProviderStatus { provider: bus::Provider::Search, ok: true });
+            notifier.play(sound::Cue::ResultsReady);
+            if let Some(session) = session {
+                session.record(|s| s.searches += 1);
+            }
+        }
+        Some(false) => {
+            // Not finding the user's location isn't the services' fault.
+            let location = bathrooms.with(cx, |b| b.as_ref().err().is_some_and(|e| e.downcast_ref::<BathroomError>().is_some()));
+            if location == Some(false) {
+                bus.broadcast(bus::Event::ProviderStatus { provider: bus::Provider::Search, ok: false });
+            }
+            notifier.play(sound::Cue::Error);
+        }
+        None => {}
+    });
+    let origin = create_memo(cx, move |_| bathrooms.with(cx, |b| b.as_ref().ok().map(|b| b.2)).flatten());
+    // Results around "here" follow the user once they've walked far enough; saved places and viewports stay put.
+    let refetched_at = store_value(cx, None::<(f64, f64)>);
+    create_effect(cx, move |_| {
+        let Some(at) = live_position.get() else {
+            return;
+        };
+        let around_here = selected_place.with_untracked(Option::is_none) && viewport.get_untracked().is_none();
+        let Some(origin) = refetched_at.get_value().or_else(|| origin.get_untracked()) else {
+            return;
+        };
+        if around_here && follow::should_refetch(origin, at, prefs.with_untracked(|p| p.follow_refetch_m)) {
+            refetched_at.set_value(Some(at));
+            bathrooms.refetch();
+        }
+    });
+    let announced = store_value(cx, None::<(OsmType, i64)>);
+    // Arriving somewhere the user hasn't marked as visited offers to mark it.
+    let annotations = annotations::use_annotations(cx);
+    let toasts = toast::use_toasts(cx);
+    let (arrived, set_arrived) = create_signal(cx, None::<(String, String)>);
+    create_effect(cx, move |_| {
+        let Some(at) = live_position.get() else {
+            return;
+        };
+        activity.moved_to(at);
+        if let Some(session) = session {
+            session.record(|s| s.walked_to(at));
+        }
+        let arrived = index.with(|index| {
+            follow::arrived_at(index, at).map(|r| (r.row.key(), annotations::element_key(&r.row.element), r.row.element.tags.get("name").map(str::to_string)))
+        });
+        if let Some((key, annotation_key, name)) = arrived {
+            if announced.get_value().as_ref() != Some(&key) {
+                if let Some(session) = session {
+                    session.record(|s| {
+                        s.visited.insert(format!("{}/{}", key.0, key.1));
+                    });
+                }
+                announced.set_value(Some(key));
+                notifier.play(sound::Cue::Arrival);
+                let name = name.unwrap_or_else(|| "the bathroom".to_string());
+                notifier.notify(format!("You've arrived at {name}"));
+                let visited = annotations.with_untracked(|a| a.get(&annotation_key).is_some_and(|a| a.visited.is_some()));
+                if kiosk.is_none() && !visited {
+                    set_arrived.set(Some((annotation_key, name)));
+                }
+            }
+        }
+    });
+    let is_primary = |r: &ScoredRow| packs::classify(&r.row.element.tags) == ResultClass::Primary;
+    let primary = create_memo(cx, move |_| ordered.with(|rows| rows.iter().filter(|r| is_primary(r)).cloned().collect::<Vec<_>>()));
+    // The table shows a page of the results at a time; new results or filters start over.
+    let page_size = Signal::derive(cx, move || prefs.with(|p| p.page_size));
+    let page = create_rw_signal(cx, 0);
+    let result_count = create_memo(cx, move |_| ordered.with(Vec::len));
+    create_effect(cx, move |_| {
+        result_count.track();
+        page.set(0);
+    });
+    let paged = create_memo(cx, move |_| ordered.with(|rows| rows[pagination::page_range(rows.len(), page_size.get(), page.get())].to_vec()));
+    let page_primary = create_memo(cx, move |_| paged.with(|rows| rows.iter().filter(|r| is_primary(r)).cloned().collect::<Vec<_>>()));
+    let page_secondary = create_memo(cx, move |_| paged.with(|rows| rows.iter().filter(|r| !is_primary(r)).cloned().collect::<Vec<_>>()));
+    let (primary_rows, secondary_rows) = (chunked::render_in_chunks(cx, page_primary), chunked::render_in_chunks(cx, page_secondary));
+    // The map's markers catch up the same way, with all the results rather than a page.
+    let map_rows = chunked::render_in_chunks(cx, primary);
+    let rendering = move || {
+        let Progress { done, total } = primary_rows.progress.get()
+            .or_else(|| secondary_rows.progress.get())
+            .or_else(|| map_rows.progress.get())?;
+        Some(view! { cx, <p class="rendering">{format!("Showing {done} of {total} rows...")}</p> })
+    };
+    create_effect(cx, move |painted: Option<bool>| {
+        if painted == Some(true) || primary.with(Vec::is_empty) {
+            return painted.unwrap_or(false);
+        }
+        // The frame after next, once the rows have been laid out and painted.
+        request_animation_frame(|| request_animation_frame(|| perf::mark(perf::Mark::FirstRowPainted)));
+        true
+    });
+    let leaflet = map::use_available(cx);
+    let kiosked = kiosk.is_some();
+    let results_map = move || (!kiosked && leaflet.get()).then(|| view! { cx,
+        <ResultsMap rows=map_rows.visible index origin area=walk_ring/>
+    });
+    let export_image = move |_| {
+        let Some((_, _, origin)) = bathrooms.read(cx).and_then(|b| b.ok()) else {
+            return;
+        };
+        let markers: Vec<((f64, f64), &'static str)> = ordered.with(|rows| {
+            rows.iter().map(|r| ((r.row.element.lat, r.row.element.lon), packs::icon(&r.row.element.tags))).collect()
+        });
+        spawn_local(async move {
+            let exported = match media::render_png(origin, &markers).await {
+                Ok(png) => export::download_blob("free2pee-map.png", &png),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = exported {
+                log::error!("image export failed: {e:?}");
+            }
+        });
+    };
+    let results_table = view! { cx,
+        {media::supported().then(|| view! { cx, <button class="export-image" on:click=export_image>"Export image"</button> })}
+        <label class="follow">
+            <input type="checkbox" prop:checked=move || follow.get() on:change=move |ev| {
+                let on = event_target_checked(&ev);
+                if on {
+                    tabs.take_over();
+                }
+                set_follow.set(on);
+            }/>
+            "Follow me (straight-line distances from where you are)"
+        </label>
+        <Show when=move || along_route.with(Option::is_some) fallback=|_| ()>
+            <p class="note">"In the order they come along your planned trip."</p>
+        </Show>
+        <Show when=move || follow.get() && !tabs.is_leader() fallback=|_| ()>
+            <p class="note">"Following in another tab."</p>
+        </Show>
+        <Show when=move || follow.get() && tabs.is_leader() && activity.is_idle() fallback=|_| ()>
+            <p class="note">"Paused while you're away; tap anywhere to resume."</p>
+        </Show>
+        <ResultsTable
+            primary=primary_rows.visible
+            secondary=show_secondary.then_some(secondary_rows.visible)
+            hidden=Signal::derive(cx, move || !bathrooms.with(cx, |b| b.is_ok()).unwrap_or(false))
+            pack
+            sort_order
+            set_sort_order
+            selected
+            live_position
+            origin
+            clock
+        />
+        <Show when=move || bathrooms.with(cx, |b| b.is_ok()).unwrap_or(false) fallback=|_| ()>
+            <Pager page len=result_count.into() size=page_size/>
+        </Show>
+        {rendering}
+    };
+
+    let bathrooms_view = move || {
+        bathrooms.read(cx).map(|data| {
+            data.map(|data| {
+                let (_, _, (lat, lon)) = data;
+                    let osm_base = time::parse_osm(&data.0.osm3s.timestamp_osm_base);
+                    let accessed_at = fetched_at.get_untracked().unwrap_or_else(js_sys::Date::now);
+                    // let routes = routing_json["routes"].as_array().unwrap();
+                    // log!("routes: {:?}", routes);
+                    // let route_str = format!("{:?}", routing_json.clone());
+                    // let route_str = serde_json::to_string_pretty(&routing_json).unwrap();
+
+                    view! { cx,
+                        <h2> {move || format!("FREE2PEE: Bathrooms accessed at {} around {}", time::clock(i18n.0.get(), accessed_at),
+                            coords::format(lat, lon, prefs.with(|p| p.coord_format)))} </h2>
+                        <p class="results-count">{move || i18n.format(i18n::RESULTS_FOUND, &[
+                            ("pack", msgfmt::Arg::Str(pack.get().key())),
+                            ("count", msgfmt::Arg::Num(primary.with(Vec::len) as f64)),
+                        ])}</p>
+                        {data_age}
+                        {osm_base.map(|at| view! { cx,
+                            <p class="data-age">{move || i18n.format(i18n::MAP_DATA_AGE, &[("ago", msgfmt::Arg::Str(&time::ago(i18n.0.get(), clock.get() - at)))])}</p>
+                        })}
+                        <CopySummary rows=primary origin/>
+                        <a href={format!("https://mapcomplete.osm.be/toilets.html?z=18&lat={lat}&lon=-{lon}")} target="_blank">Open in MapComplete</a>
+                        {selected_view}
+                        // <p>{route_str}</p>
+
+                }
+            })
+        })
+    };
+
+    let visit_prompt = move || {
+        arrived.get().map(|(key, name)| {
+            let mark = move |_| {
+                let now = js_sys::Date::now();
+                annotations::change(annotations, toasts, &key, |a| a.visited = Some(now));
+                set_arrived.set(None);
+            };
+            view! { cx,
+                <div class="visit-prompt" role="status">
+                    {format!("You've arrived at {name}. ")}
+                    <button on:click=mark>"I've been here"</button>
+                    " "
+                    <button on:click=move |_| set_arrived.set(None)>"Not now"</button>
+                </div>
+            }
+        })
+    };
+
+    view! { cx,
+        <div>
+            {places_bar}
+            {welcome_view}
+            {pack_select}
+            {kiosk.is_none().then(|| view! { cx, <RadiusSlider radius/> })}
+            {kiosk.is_none().then(|| view! { cx, <FilterChips/> })}
+            {cost_warning}
+            {visit_prompt}
+            <ActiveFilterBar/>
+            <ErrorBoundary fallback>
+                <Transition fallback=move || view! { cx, <Loading progress=tile_progress/> }>
+                <div>
+                    {bathrooms_view}
+                </div>
+                </Transition>
+            </ErrorBoundary>
+            {results_map}
+            {results_table}
+            {kiosk.is_none().then(|| view! { cx, <FavoritesSection origin/> })}
+        </div>
+    }
+}
+
+pub fn app(cx: Scope) -> impl IntoView {
+    capabilities::provide_capabilities(cx);
+    let bus = bus::provide_bus(cx);
+    tabs::provide_tabs(cx);
+    idle::provide_activity(cx);
+    prefs::provide_preferences(cx);
+    filters::provide_filter_state(cx);
+    toast::provide_toasts(cx);
+    storage::on_quarantine(move |key| {
+        let message = format!("{} couldn't be read and were reset; a copy was kept.", reset::describe(key));
+        bus.publish(bus::Event::Toast { message });
+    });
+    annotations::provide_annotations(cx);
+    session::provide_session(cx);
+    compass::provide_pin(cx);
+    trip::provide_loaded_route(cx);
+    let theme = theme::provide_theme(cx);
+    let route = router::provide_route(cx);
+    i18n::provide_i18n(cx, Signal::derive(cx, move || route.get().locale));
+    address::provide_addresses(cx);
+    view! { cx,
+        <div class="app" style=theme.style()>
+            <ServiceStatus/>
+            <OfflineBanner/>
+            {pages(cx, &theme, route)}
+            <ToastHost/>
+        </div>
+    }
+}
+
+fn pages(cx: Scope, theme: &theme::Theme, route: ReadSignal<router::Route>) -> View {
+    if embed::enabled() {
+        return view! { cx, <EmbedWidget/> }.into_view(cx);
+    }
+    if kiosk::enabled() {
+        // Just the finder: no navigation, settings, editing or saved state.
+        return fetch_example(cx).into_view(cx);
+    }
+    // Only a different page rebuilds it, not every search or viewport written to the URL.
+    let page = create_memo(cx, move |_| route.get().page);
+    let i18n = i18n::use_i18n(cx);
+    let pending_uploads = osm_edit::provide_pending_uploads(cx);
+    spawn_local(async move {
+        match osm_auth::complete_login().await {
+            Ok(()) => pending_uploads.replay(),
+            Err(e) => log::error!("OSM login failed: {e}"),
+        }
+    });
+
+    let nav = theme.shows(Section::Nav).then(|| view! { cx,
+        <nav class="pages">
+            <a href=move || route.get().href(Page::Finder) class:active=move || page.get() == Page::Finder>{move || i18n.t("Find")}</a>
+            " | "
+            <a href=move || route.get().href(Page::Trip) class:active=move || page.get() == Page::Trip>{move || i18n.t("Plan a trip")}</a>
+            " | "
+            <a href=move || route.get().href(Page::Mapper) class:active=move || page.get() == Page::Mapper>{move || i18n.t("Mapper mode")}</a>
+            " "
+            <PendingIndicator/>
+            " "
+            <ProfileSwitcher/>
+            " "
+            <MirrorHealth/>
+        </nav>
+    });
+    let settings = theme.shows(Section::Settings).then(|| view! { cx, <PreferencesPanel/><SessionSummary/> });
+    let page_scope = store_value(cx, None::<ScopeDisposer>);
+    view! { cx,
+        {nav}
+        <MiniCompass/>
+        {settings}
+        {debug::enabled().then(|| view! { cx, <DebugPanel/> })}
+        {move || {
+            // Each page gets its own scope, disposed when another replaces it,
+            // so its resources, timers and watchers go with it.
+            let page = page.get();
+            if let Some(previous) = page_scope.try_update_value(Option::take).flatten() {
+                previous.dispose();
+            }
+            let (view, disposer) = cx.run_child_scope(|cx| match page {
+                Page::Finder => fetch_example(cx).into_view(cx),
+                Page::Trip => view! { cx, <TripPlanner/> }.into_view(cx),
+                Page::Mapper => view! { cx, <MapperMode/> }.into_view(cx),
+                Page::Diagnostics => view! { cx, <Diagnostics/> }.into_view(cx),
+            });
+            page_scope.set_value(Some(disposer));
+            view
+        }}
+    }
+    .into_view(cx)
+}
+
+pub fn run() {
+    _ = console_log::init_with_level(log::Level::Debug);
+    console_error_panic_hook::set_once();
+    query_template::precompile();
+    migrations::run();
+    pwa::register_service_worker();
+    mount_to_body(app)
+}
@@ -0,0 +1,152 @@
+//! Deep links into third-party maps and navigation apps.
+
+use crate::trip::TravelMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub enum DirectionsProvider {
+    Google,
+    Apple,
+    Osm,
+}
+
+impl DirectionsProvider {
+    pub const ALL: [DirectionsProvider; 3] = [
+        DirectionsProvider::Google,
+        DirectionsProvider::Apple,
+        DirectionsProvider::Osm,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DirectionsProvider::Google => "Google Maps",
+            DirectionsProvider::Apple => "Apple Maps",
+            DirectionsProvider::Osm => "OpenStreetMap",
+        }
+    }
+
+    pub fn from_label(label: &str) -> DirectionsProvider {
+        DirectionsProvider::ALL
+            .into_iter()
+            .find(|p| p.label() == label)
+            .unwrap_or(DirectionsProvider::Google)
+    }
+}
+
+fn lat_lon((lat, lon): (f64, f64)) -> String {
+    format!("{lat},{lon}")
+}
+
+/// Directions to a single point from wherever the user is.
+pub fn google_directions(destination: (f64, f64)) -> String {
+    format!(
+        "https://www.google.com/maps/dir/?api=1&destination={}",
+        lat_lon(destination)
+    )
+}
+
+/// Like [`google_directions`], getting there by `mode`.
+pub fn google_directions_by(mode: TravelMode, destination: (f64, f64)) -> String {
+    format!("{}&travelmode={}", google_directions(destination), google_travelmode(mode))
+}
+
+fn google_travelmode(mode: TravelMode) -> &'static str {
+    match mode {
+        TravelMode::Foot => "walking",
+        TravelMode::Bike => "bicycling",
+        TravelMode::Car => "driving",
+    }
+}
+
+/// Directions from `origin` through `stops` (in order) to `destination`.
+pub fn multi_stop_directions(
+    provider: DirectionsProvider,
+    mode: TravelMode,
+    origin: (f64, f64),
+    stops: &[(f64, f64)],
+    destination: (f64, f64),
+) -> String {
+    match provider {
+        DirectionsProvider::Google => {
+            let travelmode = google_travelmode(mode);
+            let mut url = format!(
+                "https://www.google.com/maps/dir/?api=1&origin={}&destination={}&travelmode={travelmode}",
+                lat_lon(origin),
+                lat_lon(destination)
+            );
+            if !stops.is_empty() {
+                let waypoints: Vec<String> = stops.iter().copied().map(lat_lon).collect();
+                url.push_str("&waypoints=");
+                url.push_str(&waypoints.join("%7C"));
+            }
+            url
+        }
+        DirectionsProvider::Apple => {
+            // Apple has no cycling directions; walking is the closer fit.
+            let dirflg = match mode {
+                TravelMode::Car => "d",
+                TravelMode::Foot | TravelMode::Bike => "w",
+            };
+            let daddr: Vec<String> = stops
+                .iter()
+                .copied()
+                .chain(std::iter::once(destination))
+                .map(lat_lon)
+                .collect();
+            format!(
+                "https://maps.apple.com/?saddr={}&daddr={}&dirflg={dirflg}",
+                lat_lon(origin),
+                daddr.join("+to:")
+            )
+        }
+        DirectionsProvider::Osm => {
+            // openstreetmap.org only routes between two points, the FOSSGIS OSRM frontend takes any number.
+            let srv = match mode {
+                TravelMode::Car => 0,
+                TravelMode::Bike => 1,
+                TravelMode::Foot => 2,
+            };
+            let locs: Vec<String> = std::iter::once(origin)
+                .chain(stops.iter().copied())
+                .chain(std::iter::once(destination))
+                .map(|p| format!("loc={}", lat_lon(p)))
+                .collect();
+            format!("https://routing.openstreetmap.de/?{}&srv={srv}", locs.join("&"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FROM: (f64, f64) = (52.5, 13.4);
+    const STOPS: [(f64, f64); 2] = [(52.51, 13.41), (52.52, 13.42)];
+    const TO: (f64, f64) = (52.53, 13.43);
+
+    #[test]
+    fn google_waypoints() {
+        let url = multi_stop_directions(DirectionsProvider::Google, TravelMode::Foot, FROM, &STOPS, TO);
+        assert_eq!(
+            url,
+            "https://www.google.com/maps/dir/?api=1&origin=52.5,13.4&destination=52.53,13.43&travelmode=walking&waypoints=52.51,13.41%7C52.52,13.42"
+        );
+        let url = multi_stop_directions(DirectionsProvider::Google, TravelMode::Car, FROM, &[], TO);
+        assert!(!url.contains("waypoints"));
+    }
+
+    #[test]
+    fn apple_chains_stops() {
+        let url = multi_stop_directions(DirectionsProvider::Apple, TravelMode::Car, FROM, &STOPS, TO);
+        assert_eq!(
+            url,
+            "https://maps.apple.com/?saddr=52.5,13.4&daddr=52.51,13.41+to:52.52,13.42+to:52.53,13.43&dirflg=d"
+        );
+    }
+
+    #[test]
+    fn osm_lists_every_point() {
+        let url = multi_stop_directions(DirectionsProvider::Osm, TravelMode::Bike, FROM, &STOPS, TO);
+        assert_eq!(url.matches("loc=").count(), 4);
+        assert!(url.ends_with("&srv=1"));
+    }
+}
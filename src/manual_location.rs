@@ -0,0 +1,41 @@
+//! Searching somewhere typed in, for when the device won't say where it is:
+//! pasted coordinates are used as they are, anything else is
+//! [geocoded](crate::geocode). Either way the result becomes the viewport in
+//! the URL, which the finder already searches around.
+
+use crate::geocode::AddressSearch;
+use crate::router::{self, Viewport};
+use leptos::*;
+
+/// Close enough to see the street, like a shared link.
+pub const ZOOM: u8 = 16;
+
+/// An address or `lat,lon` to search around instead of the device's location.
+#[component]
+pub fn ManualLocation(cx: Scope) -> impl IntoView {
+    let route = router::use_route(cx);
+    let on_pick = move |(lat, lon)| {
+        router::navigate(&route.get_untracked().with_viewport(Some(Viewport { lat, lon, zoom: ZOOM })));
+    };
+    view! { cx,
+        <div class="manual-location">
+            <label>
+                "Search near an address or coordinates instead: "
+                <AddressSearch placeholder="Street, city or 52.52,13.405" on_pick/>
+            </label>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BathroomError;
+    use web_sys::PositionError;
+
+    #[test]
+    fn position_errors_are_told_apart() {
+        assert_eq!(BathroomError::from_position_error(PositionError::PERMISSION_DENIED), BathroomError::LocationDenied);
+        assert_eq!(BathroomError::from_position_error(PositionError::TIMEOUT), BathroomError::LocationTimeout);
+        assert_eq!(BathroomError::from_position_error(9), BathroomError::LocationUnavailable);
+    }
+}
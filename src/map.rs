@@ -0,0 +1,493 @@
+//! The results on a Leaflet slippy map, centered on the search origin. Leaflet
+//! is loaded from a deferred `<script>` in `index.html`; without it (offline, blocked)
+//! the map simply isn't shown.
+//!
+//! Markers are keyed like the table rows, so a refetch only adds and removes
+//! the ones that changed, and everything is torn down with the component.
+//! Only rows in and around the visible part of the map get one; panning looks
+//! them up in the results' [index](RowIndex).
+//! Selections go both ways over the [bus](crate::bus): clicking a marker
+//! selects its row, and selecting a row opens its marker's popup.
+//!
+//! The walking area around the selected place, when asked for, is shaded in.
+//!
+//! Where the map looks is kept in the route's [viewport](Viewport), so links
+//! open on the same area; without one it centers on the search origin.
+
+use crate::bus::{use_bus, Event};
+use crate::export::escape_xml;
+use crate::geo::BBox;
+use crate::links;
+use crate::measure;
+use crate::osm_links;
+use crate::policy;
+use crate::prefs::use_preferences;
+use crate::results::{RowIndex, ScoredRow};
+use crate::router::{self, Viewport};
+use crate::trip::TravelMode;
+use crate::OsmType;
+use leptos::*;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+pub const DEFAULT_ZOOM: f64 = 16.0;
+/// Markers are kept this far past each edge of the view, as a share of its size,
+/// so short pans don't pop them in and out.
+const VIEW_PADDING: f64 = 0.5;
+/// How long the map has to stay put before the URL follows it.
+const SETTLE: Duration = Duration::from_millis(500);
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = L, js_name = Map)]
+    type LeafletMap;
+
+    #[wasm_bindgen(catch, js_namespace = L, js_name = map)]
+    fn new_map(container: &web_sys::HtmlElement) -> Result<LeafletMap, JsValue>;
+
+    #[wasm_bindgen(method, js_name = setView)]
+    fn set_view(this: &LeafletMap, center: &js_sys::Array, zoom: f64) -> LeafletMap;
+
+    #[wasm_bindgen(method, js_name = remove)]
+    fn remove_map(this: &LeafletMap) -> LeafletMap;
+
+    #[wasm_bindgen(method, js_name = on)]
+    fn on_map(this: &LeafletMap, event: &str, handler: &js_sys::Function) -> LeafletMap;
+
+    #[wasm_bindgen(method, js_name = getBounds)]
+    fn get_bounds(this: &LeafletMap) -> LatLngBounds;
+
+    #[wasm_bindgen(catch, method, js_name = getCenter)]
+    fn get_center(this: &LeafletMap) -> Result<LatLng, JsValue>;
+
+    #[wasm_bindgen(method, js_name = getZoom)]
+    fn get_zoom(this: &LeafletMap) -> f64;
+
+    #[wasm_bindgen(js_namespace = L, js_name = LatLng)]
+    type LatLng;
+
+    #[wasm_bindgen(method, getter)]
+    fn lat(this: &LatLng) -> f64;
+
+    #[wasm_bindgen(method, getter)]
+    fn lng(this: &LatLng) -> f64;
+
+    #[wasm_bindgen(js_namespace = L, js_name = LatLngBounds)]
+    type LatLngBounds;
+
+    #[wasm_bindgen(method)]
+    fn pad(this: &LatLngBounds, ratio: f64) -> LatLngBounds;
+
+    #[wasm_bindgen(method, js_name = getSouth)]
+    fn get_south(this: &LatLngBounds) -> f64;
+
+    #[wasm_bindgen(method, js_name = getWest)]
+    fn get_west(this: &LatLngBounds) -> f64;
+
+    #[wasm_bindgen(method, js_name = getNorth)]
+    fn get_north(this: &LatLngBounds) -> f64;
+
+    #[wasm_bindgen(method, js_name = getEast)]
+    fn get_east(this: &LatLngBounds) -> f64;
+
+    #[wasm_bindgen(js_namespace = L, js_name = TileLayer)]
+    type TileLayer;
+
+    #[wasm_bindgen(js_namespace = L, js_name = tileLayer)]
+    fn tile_layer(url: &str, options: &JsValue) -> TileLayer;
+
+    #[wasm_bindgen(method, js_name = addTo)]
+    fn add_tile_layer_to(this: &TileLayer, map: &LeafletMap) -> TileLayer;
+
+    #[wasm_bindgen(js_namespace = L, js_name = Marker)]
+    type Marker;
+
+    #[wasm_bindgen(js_namespace = L, js_name = marker)]
+    fn new_marker(lat_lng: &js_sys::Array) -> Marker;
+
+    #[wasm_bindgen(method, js_name = bindPopup)]
+    fn bind_popup(this: &Marker, html: &str) -> Marker;
+
+    #[wasm_bindgen(method, js_name = addTo)]
+    fn add_marker_to(this: &Marker, map: &LeafletMap) -> Marker;
+
+    #[wasm_bindgen(method, js_name = on)]
+    fn on_marker(this: &Marker, event: &str, handler: &js_sys::Function) -> Marker;
+
+    #[wasm_bindgen(method, js_name = openPopup)]
+    fn open_popup(this: &Marker) -> Marker;
+
+    #[wasm_bindgen(method, js_name = remove)]
+    fn remove_marker(this: &Marker) -> Marker;
+
+    #[wasm_bindgen(js_namespace = L, js_name = Polyline)]
+    type Polyline;
+
+    #[wasm_bindgen(js_namespace = L, js_name = polyline)]
+    fn new_polyline(lat_lngs: &js_sys::Array) -> Polyline;
+
+    #[wasm_bindgen(method, js_name = setLatLngs)]
+    fn set_lat_lngs(this: &Polyline, lat_lngs: &js_sys::Array) -> Polyline;
+
+    #[wasm_bindgen(method, js_name = addTo)]
+    fn add_polyline_to(this: &Polyline, map: &LeafletMap) -> Polyline;
+
+    #[wasm_bindgen(method, js_name = remove)]
+    fn remove_polyline(this: &Polyline) -> Polyline;
+
+    #[wasm_bindgen(js_namespace = L, js_name = Polygon)]
+    type Polygon;
+
+    #[wasm_bindgen(js_namespace = L, js_name = polygon)]
+    fn new_polygon(lat_lngs: &js_sys::Array, options: &JsValue) -> Polygon;
+
+    #[wasm_bindgen(method, js_name = addTo)]
+    fn add_polygon_to(this: &Polygon, map: &LeafletMap) -> Polygon;
+
+    #[wasm_bindgen(method, js_name = remove)]
+    fn remove_polygon(this: &Polygon) -> Polygon;
+}
+
+pub fn available() -> bool {
+    js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("L")).unwrap_or(false)
+}
+
+/// [`available`], catching up if the deferred script only runs after we start.
+pub fn use_available(cx: Scope) -> ReadSignal<bool> {
+    let (loaded, set_loaded) = create_signal(cx, available());
+    if !loaded.get_untracked() {
+        window_event_listener(ev::load, move |_| set_loaded.set(available()));
+    }
+    loaded
+}
+
+fn lat_lng((lat, lon): (f64, f64)) -> js_sys::Array {
+    js_sys::Array::of2(&lat.into(), &lon.into())
+}
+
+/// What the map shows, and a bit around it.
+fn view_bounds(map: &LeafletMap) -> BBox {
+    let bounds = map.get_bounds().pad(VIEW_PADDING);
+    BBox { south: bounds.get_south(), west: bounds.get_west(), north: bounds.get_north(), east: bounds.get_east() }
+}
+
+/// A map view as the route writes it: rounded, with the longitude wrapped back
+/// into range after panning around the world.
+pub fn viewport_at((lat, lon): (f64, f64), zoom: f64) -> Viewport {
+    let round = |x: f64| (x * 1e5).round() / 1e5;
+    Viewport {
+        lat: round(lat.clamp(-90.0, 90.0)),
+        lon: round((lon + 180.0).rem_euclid(360.0) - 180.0),
+        zoom: zoom.round().clamp(0.0, f64::from(Viewport::MAX_ZOOM)) as u8,
+    }
+}
+
+/// Where `map` looks; nothing until it has been given a view.
+fn current_viewport(map: &LeafletMap) -> Option<Viewport> {
+    let center = map.get_center().ok()?;
+    Some(viewport_at((center.lat(), center.lng()), map.get_zoom()))
+}
+
+/// The keys of `rows` to put markers for: those `index` has within `view`, or all before the map has one.
+pub fn keys_in_view(rows: &[ScoredRow], index: &RowIndex, view: Option<&BBox>) -> Vec<(OsmType, i64)> {
+    let keys = rows.iter().map(|r| r.row.key());
+    let Some(view) = view else {
+        return keys.collect();
+    };
+    let in_view: HashSet<_> = index.within(view).into_iter().map(|(_, r)| r.row.key()).collect();
+    keys.filter(|k| in_view.contains(k)).collect()
+}
+
+/// `(lat, lon)` of a Leaflet mouse event (taps included).
+fn event_lat_lng(event: &JsValue) -> Option<(f64, f64)> {
+    let lat_lng = js_sys::Reflect::get(event, &"latlng".into()).ok()?;
+    let get = |key: &str| js_sys::Reflect::get(&lat_lng, &key.into()).ok()?.as_f64();
+    Some((get("lat")?, get("lng")?))
+}
+
+/// Popup contents: the name and the same links as the table row.
+pub fn popup_html(row: &ScoredRow, mode: TravelMode) -> String {
+    let element = &row.row.element;
+    let name = element.tags.get("name").map(|n| format!("<strong>{}</strong><br>", escape_xml(n))).unwrap_or_default();
+    format!(
+        "{name}<a href=\"{osm}\" target=\"_blank\">OSM:{id}</a> · <a href=\"{directions}\" target=\"_blank\">Google Maps</a> · <a href=\"{note}\" target=\"_blank\" title=\"Report a problem with this place\">⚑</a>",
+        osm = osm_links::view_url(element),
+        id = element.id,
+        directions = escape_xml(&links::google_directions_by(mode, (element.lat, element.lon))),
+        note = escape_xml(&osm_links::note_url(element)),
+    )
+}
+
+/// Which of `wanted` aren't shown yet, and which shown keys aren't wanted any more.
+pub fn diff_keys<'a, K: Eq + Hash + Clone, V>(shown: &HashMap<K, V>, wanted: &'a [K]) -> (Vec<&'a K>, Vec<K>) {
+    let wanted_set: HashSet<&K> = wanted.iter().collect();
+    let added = wanted.iter().filter(|k| !shown.contains_key(k)).collect();
+    let removed = shown.keys().filter(|k| !wanted_set.contains(k)).cloned().collect();
+    (added, removed)
+}
+
+#[component]
+pub fn ResultsMap(
+    cx: Scope,
+    rows: Memo<Vec<ScoredRow>>,
+    index: Memo<RowIndex>,
+    origin: Memo<Option<(f64, f64)>>,
+    /// The ring of the walking area to shade, if any.
+    area: Memo<Option<Vec<(f64, f64)>>>,
+) -> impl IntoView {
+    let container = create_node_ref::<html::Div>(cx);
+    let map = store_value(cx, None::<LeafletMap>);
+    let markers = store_value(cx, HashMap::<(OsmType, i64), Marker>::new());
+    let (ready, set_ready) = create_signal(cx, false);
+    let (view, set_view) = create_signal(cx, None::<BBox>);
+    let prefs = use_preferences(cx);
+    let bus = use_bus(cx);
+    let (measuring, set_measuring) = create_signal(cx, false);
+    let measured = create_rw_signal(cx, Vec::<(f64, f64)>::new());
+    let line = store_value(cx, None::<Polyline>);
+    let shaded = store_value(cx, None::<Polygon>);
+    let route = router::use_route(cx);
+    let route_viewport = create_memo(cx, move |_| route.get().viewport);
+    // Set around our own moves, so only the user's end up in the URL.
+    let moving = store_value(cx, false);
+
+    container.on_load(cx, move |div| {
+        match new_map(&div) {
+            Ok(leaflet) => {
+                // `?tiles=cyclosm` and the like; see `policy::TILE_PROVIDERS`.
+                let tiles = policy::tile_provider(crate::search_params().get("tiles").as_deref());
+                let options = js_sys::Object::new();
+                let _ = js_sys::Reflect::set(&options, &"attribution".into(), &tiles.attribution.into());
+                let _ = js_sys::Reflect::set(&options, &"maxZoom".into(), &tiles.max_zoom.into());
+                // Tile servers want to know which site is using them.
+                let _ = js_sys::Reflect::set(&options, &"referrerPolicy".into(), &"strict-origin-when-cross-origin".into());
+                tile_layer(tiles.url, &options).add_tile_layer_to(&leaflet);
+                let on_click = Closure::<dyn Fn(JsValue)>::new(move |event: JsValue| {
+                    if let (true, Some(point)) = (measuring.get_untracked(), event_lat_lng(&event)) {
+                        measured.update(|points| points.push(point));
+                    }
+                });
+                leaflet.on_map("click", on_click.as_ref().unchecked_ref());
+                on_click.forget();
+                let remember = RefCell::new(debounce(cx, SETTLE, move |viewport: Viewport| {
+                    if route_viewport.get_untracked() != Some(viewport) {
+                        router::replace(&route.get_untracked().with_viewport(Some(viewport)));
+                    }
+                }));
+                let on_move = Closure::<dyn Fn()>::new(move || {
+                    set_view.set(map.with_value(|m| m.as_ref().map(view_bounds)));
+                    if moving.get_value() {
+                        moving.set_value(false);
+                    } else if let Some(viewport) = map.with_value(|m| m.as_ref().and_then(current_viewport)) {
+                        remember.borrow_mut()(viewport);
+                    }
+                });
+                leaflet.on_map("moveend", on_move.as_ref().unchecked_ref());
+                on_move.forget();
+                map.set_value(Some(leaflet));
+                set_ready.set(true);
+            }
+            Err(e) => log::warn!("couldn't create the map: {e:?}"),
+        }
+    });
+
+    // Follows the route's viewport; only without one does it center on the origin.
+    create_effect(cx, move |_| {
+        if !ready.get() {
+            return;
+        }
+        let target = match (route_viewport.get(), origin.get()) {
+            (Some(viewport), _) => Some((viewport.center(), f64::from(viewport.zoom))),
+            (None, Some(center)) => Some((center, DEFAULT_ZOOM)),
+            (None, None) => None,
+        };
+        map.with_value(|m| {
+            let (Some(m), Some((center, zoom))) = (m, target) else { return };
+            // The map set the viewport itself, or it's already there.
+            if route_viewport.get_untracked().is_some_and(|v| current_viewport(m) == Some(v)) {
+                return;
+            }
+            moving.set_value(true);
+            m.set_view(&lat_lng(center), zoom);
+        });
+    });
+
+    create_effect(cx, move |_| {
+        if !ready.get() {
+            return;
+        }
+        rows.with(|rows| {
+            let keys = index.with(|index| view.with(|view| keys_in_view(rows, index, view.as_ref())));
+            let by_key: HashMap<_, _> = rows.iter().map(|r| (r.row.key(), r)).collect();
+            markers.update_value(|markers| {
+                let (added, removed) = diff_keys(markers, &keys);
+                for key in removed {
+                    if let Some(marker) = markers.remove(&key) {
+                        marker.remove_marker();
+                    }
+                }
+                map.with_value(|m| {
+                    let Some(m) = m else { return };
+                    for key in added {
+                        let row = by_key[key];
+                        let element = &row.row.element;
+                        let marker = new_marker(&lat_lng((element.lat, element.lon)));
+                        marker.bind_popup(&popup_html(row, prefs.with_untracked(|p| p.travel_mode))).add_marker_to(m);
+                        let key = *key;
+                        let on_click = Closure::<dyn Fn()>::new(move || bus.publish(Event::Selected { key: Some(key) }));
+                        marker.on_marker("click", on_click.as_ref().unchecked_ref());
+                        on_click.forget();
+                        markers.insert(key, marker);
+                    }
+                });
+            });
+        });
+    });
+
+    // Measuring starts from the search origin; turning it off clears the line.
+    create_effect(cx, move |_| {
+        let start = measuring.get().then(|| origin.get_untracked()).flatten();
+        measured.set(start.into_iter().collect());
+    });
+    create_effect(cx, move |_| {
+        let lat_lngs: js_sys::Array = measured.with(|points| points.iter().map(|&p| JsValue::from(lat_lng(p))).collect());
+        line.update_value(|line| match (line.as_ref(), lat_lngs.length()) {
+            (Some(existing), 0) => {
+                existing.remove_polyline();
+                *line = None;
+            }
+            (Some(existing), _) => {
+                existing.set_lat_lngs(&lat_lngs);
+            }
+            (None, 0) => {}
+            (None, _) => {
+                *line = map.with_value(|m| m.as_ref().map(|m| new_polyline(&lat_lngs).add_polyline_to(m)));
+            }
+        });
+    });
+    create_effect(cx, move |_| {
+        if !ready.get() {
+            return;
+        }
+        if let Some(previous) = shaded.try_update_value(Option::take).flatten() {
+            previous.remove_polygon();
+        }
+        let polygon = area.with(|ring| {
+            let ring = ring.as_ref()?;
+            let lat_lngs: js_sys::Array = ring.iter().map(|&p| JsValue::from(lat_lng(p))).collect();
+            // Clicks go through to the map, for measuring across it.
+            let options = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&options, &"interactive".into(), &false.into());
+            map.with_value(|m| m.as_ref().map(|m| new_polygon(&lat_lngs, &options).add_polygon_to(m)))
+        });
+        shaded.set_value(polygon);
+    });
+    let measure_summary = move || measured.with(|points| measure::summary(points, prefs.with(|p| p.travel_mode)));
+
+    bus.subscribe(cx, move |event| {
+        if let Event::Selected { key: Some(key) } = event {
+            markers.with_value(|markers| markers.get(key).map(Marker::open_popup));
+        }
+    });
+
+    on_cleanup(cx, move || {
+        markers.update_value(|markers| markers.drain().for_each(|(_, marker)| {
+            marker.remove_marker();
+        }));
+        line.update_value(|line| {
+            if let Some(line) = line.take() {
+                line.remove_polyline();
+            }
+        });
+        shaded.update_value(|shaded| {
+            if let Some(shaded) = shaded.take() {
+                shaded.remove_polygon();
+            }
+        });
+        map.update_value(|m| {
+            if let Some(m) = m.take() {
+                m.remove_map();
+            }
+        });
+    });
+
+    view! { cx,
+        <div class="map" node_ref=container></div>
+        <div class="measure" prop:hidden=move || !ready.get()>
+            <button aria-pressed=move || measuring.get().to_string() on:click=move |_| set_measuring.update(|m| *m = !*m)>
+                {move || if measuring.get() { "Stop measuring" } else { "Measure distance" }}
+            </button>
+            <Show when=move || measuring.get() fallback=|_| ()>
+                " "
+                <button on:click=move |_| measured.update(|points| {
+                    if points.len() > 1 {
+                        points.pop();
+                    }
+                })>"Undo"</button>
+                " "
+                <span>{measure_summary}</span>
+            </Show>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::ResultRow;
+    use crate::scoring::Confidence;
+    use crate::Element;
+    use std::rc::Rc;
+
+    #[test]
+    fn diffs_shown_markers_against_rows() {
+        let shown: HashMap<i64, ()> = [(1, ()), (2, ())].into();
+        let (added, mut removed) = diff_keys(&shown, &[2, 3]);
+        removed.sort();
+        assert_eq!(added, [&3]);
+        assert_eq!(removed, [1]);
+    }
+
+    #[test]
+    fn markers_only_for_rows_in_view() {
+        let row = |id, lat| ScoredRow {
+            row: ResultRow { element: Rc::new(Element { id, lat, lon: 13.0, ..Default::default() }), distance_m: 0.0, duration_s: 0.0 },
+            confidence: Confidence::Normal,
+            rank: 0.0,
+        };
+        let rows = [row(1, 52.0), row(2, 52.1), row(3, 52.01)];
+        let index = RowIndex::new(&rows);
+        let view = BBox { south: 51.99, west: 12.99, north: 52.02, east: 13.01 };
+        let ids = |keys: Vec<(OsmType, i64)>| keys.into_iter().map(|(_, id)| id).collect::<Vec<_>>();
+        assert_eq!(ids(keys_in_view(&rows, &index, Some(&view))), [1, 3]);
+        assert_eq!(ids(keys_in_view(&rows, &index, None)), [1, 2, 3]);
+    }
+
+    #[test]
+    fn viewports_round_and_wrap() {
+        let viewport = viewport_at((52.123456789, 373.5), 15.6);
+        assert_eq!(viewport, Viewport { lat: 52.12346, lon: 13.5, zoom: 16 });
+        assert_eq!(Viewport::parse(&viewport.to_string()), Some(viewport));
+        assert_eq!(viewport_at((0.0, 0.0), 23.0).zoom, Viewport::MAX_ZOOM);
+    }
+
+    #[test]
+    fn popups_escape_names() {
+        let mut element = Element { id: 7, lat: 1.0, lon: 2.0, ..Default::default() };
+        element.tags.insert("name".to_string(), "Tom & Jerry's".to_string());
+        let row = ScoredRow {
+            row: ResultRow { element: Rc::new(element), distance_m: 0.0, duration_s: 0.0 },
+            confidence: Confidence::Normal,
+            rank: 0.0,
+        };
+        let html = popup_html(&row, TravelMode::Car);
+        assert!(html.starts_with("<strong>Tom &amp; Jerry&apos;s</strong>"), "{html}");
+        assert!(html.contains("node/7"));
+        assert!(html.contains("/note/new?lat="));
+        assert!(html.contains("travelmode=driving"));
+    }
+}
@@ -0,0 +1,44 @@
+//! Measuring on the map: tap points to see the distance along them, and
+//! roughly how long that is on foot (or however the user travels). Starts at
+//! the search origin, since the question is usually "how far is that one?".
+
+use crate::geo;
+use crate::trip::TravelMode;
+
+/// Distance along `points` in meters.
+pub fn total_m(points: &[(f64, f64)]) -> f64 {
+    geo::cumulative_distances(points).last().copied().unwrap_or(0.0)
+}
+
+/// `"640 m"`, `"1.25 km"`.
+pub fn format_distance(meters: f64) -> String {
+    if meters < 1000.0 {
+        format!("{meters:.0} m")
+    } else {
+        format!("{:.2} km", meters / 1000.0)
+    }
+}
+
+/// `"640 m, about 8 min walking"`, or a prompt before there's anything to measure.
+pub fn summary(points: &[(f64, f64)], mode: TravelMode) -> String {
+    if points.len() < 2 {
+        return "Tap the map to measure.".to_string();
+    }
+    let meters = total_m(points);
+    let minutes = (meters / mode.speed_m_s() / 60.0).ceil();
+    format!("{}, about {minutes:.0} min {}", format_distance(meters), mode.label().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_legs_and_estimates_time() {
+        let points = [(52.0, 13.0), (52.003, 13.0), (52.006, 13.0)];
+        assert!((total_m(&points) - 667.0).abs() < 1.0, "{}", total_m(&points));
+        assert_eq!(summary(&points, TravelMode::Foot), "667 m, about 8 min walking");
+        assert_eq!(summary(&points[..1], TravelMode::Foot), "Tap the map to measure.");
+        assert_eq!(format_distance(1250.0), "1.25 km");
+    }
+}
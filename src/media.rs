@@ -0,0 +1,106 @@
+//! Shareable images of the results: every place drawn around the search origin
+//! with an attribution strip, rendered into a PNG on an `OffscreenCanvas` so
+//! nothing flashes on screen. Browsers without one don't get the button.
+
+use crate::geo::haversine_m;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, OffscreenCanvas, OffscreenCanvasRenderingContext2d};
+
+pub const WIDTH: u32 = 800;
+pub const HEIGHT: u32 = 600;
+const STRIP_HEIGHT: u32 = 28;
+const MARGIN_PX: f64 = 32.0;
+const ATTRIBUTION: &str = "Map data © OpenStreetMap contributors · free2pee";
+
+pub fn supported() -> bool {
+    js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("OffscreenCanvas")).unwrap_or(false)
+}
+
+/// Meters per pixel so the farthest point still fits inside the margins.
+pub fn fit_scale(origin: (f64, f64), points: &[(f64, f64)], size: (f64, f64)) -> f64 {
+    let half = ((size.0.min(size.1) / 2.0) - MARGIN_PX).max(1.0);
+    let farthest = points.iter().map(|p| haversine_m(origin, *p)).fold(0.0, f64::max);
+    // At least ~100 m across, so a single nearby place isn't drawn on the edge.
+    farthest.max(50.0) / half
+}
+
+/// Pixel position of `point` in an image of `size` centered on `origin`, north up.
+pub fn project(origin: (f64, f64), point: (f64, f64), size: (f64, f64), m_per_px: f64) -> (f64, f64) {
+    let m_per_deg = 111_320.0;
+    let dx = (point.1 - origin.1) * m_per_deg * origin.0.to_radians().cos();
+    let dy = (point.0 - origin.0) * m_per_deg;
+    (size.0 / 2.0 + dx / m_per_px, size.1 / 2.0 - dy / m_per_px)
+}
+
+/// A PNG of `markers` (position and icon) around `origin`.
+pub async fn render_png(origin: (f64, f64), markers: &[((f64, f64), &str)]) -> Result<Blob, JsValue> {
+    let canvas = OffscreenCanvas::new(WIDTH, HEIGHT)?;
+    let ctx: OffscreenCanvasRenderingContext2d = canvas.get_context("2d")?.ok_or("no 2d context")?.unchecked_into();
+    let map_size = (WIDTH as f64, (HEIGHT - STRIP_HEIGHT) as f64);
+    let points: Vec<(f64, f64)> = markers.iter().map(|(p, _)| *p).collect();
+    let m_per_px = fit_scale(origin, &points, map_size);
+
+    ctx.set_fill_style_str("#f2efe9");
+    ctx.fill_rect(0.0, 0.0, map_size.0, map_size.1);
+
+    // A 100 m ring for scale.
+    ctx.set_stroke_style_str("#c8c2b8");
+    ctx.begin_path();
+    ctx.arc(map_size.0 / 2.0, map_size.1 / 2.0, 100.0 / m_per_px, 0.0, std::f64::consts::TAU)?;
+    ctx.stroke();
+
+    ctx.set_font("24px sans-serif");
+    ctx.set_text_align("center");
+    ctx.set_text_baseline("middle");
+    for (point, icon) in markers {
+        let (x, y) = project(origin, *point, map_size, m_per_px);
+        ctx.fill_text(icon, x, y)?;
+    }
+    ctx.set_fill_style_str("#1a73e8");
+    ctx.begin_path();
+    ctx.arc(map_size.0 / 2.0, map_size.1 / 2.0, 7.0, 0.0, std::f64::consts::TAU)?;
+    ctx.fill();
+
+    ctx.set_fill_style_str("#ffffff");
+    ctx.fill_rect(0.0, map_size.1, WIDTH as f64, STRIP_HEIGHT as f64);
+    ctx.set_fill_style_str("#333333");
+    ctx.set_font("14px sans-serif");
+    ctx.set_text_align("left");
+    ctx.fill_text(ATTRIBUTION, 8.0, map_size.1 + STRIP_HEIGHT as f64 / 2.0)?;
+    ctx.set_text_align("right");
+    ctx.fill_text("ring: 100 m", WIDTH as f64 - 8.0, map_size.1 + STRIP_HEIGHT as f64 / 2.0)?;
+
+    // `convertToBlob` defaults to PNG.
+    let blob = JsFuture::from(canvas.convert_to_blob()?).await?;
+    Ok(blob.unchecked_into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn farthest_point_fits_the_image() {
+        let origin = (52.5, 13.4);
+        let points = [(52.501, 13.4), (52.5, 13.41)];
+        let size = (800.0, 572.0);
+        let scale = fit_scale(origin, &points, size);
+        for p in points {
+            let (x, y) = project(origin, p, size, scale);
+            assert!((MARGIN_PX - 1.0..=size.0 - MARGIN_PX + 1.0).contains(&x), "{x}");
+            assert!((MARGIN_PX - 1.0..=size.1 - MARGIN_PX + 1.0).contains(&y), "{y}");
+        }
+        // East is right, north is up.
+        let (x, _) = project(origin, points[1], size, scale);
+        let (_, y) = project(origin, points[0], size, scale);
+        assert!(x > size.0 / 2.0 && y < size.1 / 2.0);
+    }
+
+    #[test]
+    fn nearby_points_are_not_blown_up() {
+        let origin = (52.5, 13.4);
+        let scale = fit_scale(origin, &[(52.5001, 13.4)], (800.0, 600.0));
+        assert!(scale * 268.0 >= 50.0 - 1e-9);
+    }
+}
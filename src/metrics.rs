@@ -0,0 +1,29 @@
+//! Optional reporting of client-side measurements. Builds without `METRICS_URL`
+//! report nothing; with it, events are sent as JSON beacons so they survive
+//! the page being closed.
+
+use serde::Serialize;
+
+fn endpoint() -> Option<&'static str> {
+    option_env!("METRICS_URL")
+}
+
+#[derive(Serialize)]
+struct Event<'a, T> {
+    event: &'a str,
+    data: &'a T,
+}
+
+pub fn report<T: Serialize>(event: &str, data: &T) {
+    let Some(url) = endpoint() else {
+        return;
+    };
+    let Ok(body) = serde_json::to_string(&Event { event, data }) else {
+        return;
+    };
+    if let Some(navigator) = web_sys::window().map(|w| w.navigator()) {
+        if !navigator.send_beacon_with_opt_str(url, Some(&body)).unwrap_or(false) {
+            log::debug!("metrics beacon for {event} not queued");
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Schema versions for what's persisted in local storage, and the upgrades
+//! between them.
+//!
+//! Versioned values are stored as `{"schema": N, "data": ...}`; anything from
+//! before versioning is version 0. [`storage`](crate::storage) stamps values on
+//! save and upgrades them on load, and [`run`] upgrades every stored value once
+//! on startup so old blobs don't linger. Changing a persisted type in a way
+//! `#[serde(default)]` can't absorb means appending a step to its [`Schema`].
+
+use crate::{cache, storage};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// Upgrades a value from the version at its index in [`Schema::steps`] to the next.
+pub type Migration = fn(Value) -> Value;
+
+pub struct Schema {
+    steps: &'static [Migration],
+}
+
+impl Schema {
+    pub fn version(&self) -> u64 {
+        self.steps.len() as u64
+    }
+
+    /// Upgrade `data`, saved at version `from`, to the current version.
+    pub fn upgrade(&self, from: u64, data: Value) -> Result<Value, MigrationError> {
+        let steps = self.steps.get(from as usize..).ok_or(MigrationError::TooNew(from))?;
+        Ok(steps.iter().fold(data, |data, step| step(data)))
+    }
+}
+
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum MigrationError {
+    /// Saved by a newer release; left alone rather than guessed at.
+    #[error("Saved data is from a newer version (schema {0}).")]
+    TooNew(u64),
+}
+
+/// Version 1 only added the version stamp.
+fn unchanged(data: Value) -> Value {
+    data
+}
+
+/// Version 1: results cached before elements had a type have `"type": ""`; those were all nodes.
+fn cache_types_nodes(mut data: Value) -> Value {
+    // A `CachedBathrooms`; its `bathrooms` are `[OverpassResponse, TableRoot, origin]`.
+    if let Some(elements) = data.pointer_mut("/bathrooms/0/elements").and_then(Value::as_array_mut) {
+        for element in elements {
+            if element.get("type").and_then(Value::as_str).unwrap_or("").is_empty() {
+                element["type"] = json!("node");
+            }
+        }
+    }
+    data
+}
+
+const PREFERENCES: Schema = Schema { steps: &[unchanged] };
+const ANNOTATIONS: Schema = Schema { steps: &[unchanged] };
+const SAVED_PLACES: Schema = Schema { steps: &[unchanged] };
+const SESSION: Schema = Schema { steps: &[unchanged] };
+const CACHE: Schema = Schema { steps: &[cache_types_nodes] };
+
+/// The schema of a stored key; `None` for plain values like tokens, which aren't versioned.
+pub fn schema_for(key: &str) -> Option<&'static Schema> {
+    match key {
+        "preferences" => Some(&PREFERENCES),
+        "annotations" => Some(&ANNOTATIONS),
+        "saved_places" => Some(&SAVED_PLACES),
+        "session" => Some(&SESSION),
+        cache::INDEX_KEY => None,
+        key if key.starts_with("cache.") => Some(&CACHE),
+        _ => None,
+    }
+}
+
+/// Split a stored value into its version and data.
+fn unwrap(raw: Value) -> (u64, Value) {
+    match raw {
+        Value::Object(mut map) if map.len() == 2 && map.contains_key("data") => match map.get("schema").and_then(Value::as_u64) {
+            Some(version) => (version, map.remove("data").unwrap_or_default()),
+            None => (0, Value::Object(map)),
+        },
+        raw => (0, raw),
+    }
+}
+
+/// `data` at the current version of `schema`, ready to store.
+pub fn stamp(schema: &Schema, data: Value) -> Value {
+    json!({ "schema": schema.version(), "data": data })
+}
+
+/// The current-version data of a stored value; unversioned keys pass through.
+pub fn upgrade(key: &str, raw: Value) -> Result<Value, MigrationError> {
+    match schema_for(key) {
+        Some(schema) => {
+            let (version, data) = unwrap(raw);
+            schema.upgrade(version, data)
+        }
+        None => Ok(raw),
+    }
+}
+
+/// Upgrade every stored value that's behind its schema, and save it back.
+pub fn run() {
+    for (key, _) in storage::entries() {
+        let Some(schema) = schema_for(&key) else {
+            continue;
+        };
+        let Some(raw) = storage::load_raw(&key) else {
+            continue;
+        };
+        let (version, data) = unwrap(raw);
+        if version == schema.version() {
+            continue;
+        }
+        match schema.upgrade(version, data) {
+            Ok(data) => {
+                if let Err(e) = storage::save_raw(&key, &stamp(schema, data)) {
+                    log::warn!("couldn't save migrated {key}: {e}");
+                }
+            }
+            Err(e) => log::warn!("{key}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CachedBathrooms;
+    use crate::prefs::Preferences;
+    use crate::places::SavedPlace;
+    use crate::session::SessionStats;
+    use crate::{annotations::Annotations, OsmType};
+
+    fn migrate<T: serde::de::DeserializeOwned>(key: &str, fixture: &str) -> T {
+        let data = upgrade(key, serde_json::from_str(fixture).unwrap()).unwrap();
+        serde_json::from_value(data).unwrap()
+    }
+
+    #[test]
+    fn unversioned_blobs_load_as_version_zero() {
+        let prefs: Preferences = migrate("preferences", r#"{"coord_format":"Decimal","show_elevation":true}"#);
+        assert!(prefs.show_elevation);
+        let annotations: Annotations = migrate("annotations", r#"{"node/42":{"favorite":true,"note":"clean"}}"#);
+        assert_eq!(annotations["node/42"].note, "clean");
+        let places: Vec<SavedPlace> = migrate("saved_places", r#"[{"name":"Home","lat":52.5,"lon":13.4}]"#);
+        assert_eq!(places[0].name, "Home");
+        let session: SessionStats = migrate("session", r#"{"searches":3,"walked_m":120.0,"visited":["node/1"]}"#);
+        assert_eq!(session.searches, 3);
+    }
+
+    #[test]
+    fn version_zero_cache_entries_get_node_types() {
+        let fixture = r#"{"fetched_at":1.0,"bathrooms":[
+            {"elements":[{"type":"","id":1,"lat":1.0,"lon":2.0},{"type":"way","id":2,"lat":1.0,"lon":2.0}],
+             "generator":"","osm3s":{"copyright":"","timestamp_osm_base":""},"version":0.6},
+            {"code":"Ok","distances":[],"destinations":[],"durations":[],"sources":[]},[1.0,2.0]]}"#;
+        let data = upgrade("cache.toilets.1.000,2.000,1000", serde_json::from_str(fixture).unwrap()).unwrap();
+        let types: Vec<&str> =
+            data["bathrooms"][0]["elements"].as_array().unwrap().iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["node", "way"]);
+        let cached: CachedBathrooms = serde_json::from_value(data).unwrap();
+        assert_eq!(cached.bathrooms.0.elements[0].type_field, OsmType::Node);
+    }
+
+    #[test]
+    fn stamped_values_round_trip_and_newer_ones_are_refused() {
+        let stamped = stamp(&PREFERENCES, json!({ "sound_cues": true }));
+        assert_eq!(stamped, json!({ "schema": 1, "data": { "sound_cues": true } }));
+        assert_eq!(upgrade("preferences", stamped).unwrap(), json!({ "sound_cues": true }));
+        assert_eq!(upgrade("preferences", json!({ "schema": 9, "data": {} })), Err(MigrationError::TooNew(9)));
+        assert_eq!(upgrade("osm_token", json!("abc")).unwrap(), json!("abc"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Public Overpass servers to fail over between. overpass-api.de is the
+//! reference instance and the busiest; when it rate-limits or is down the
+//! next mirror is tried, and whichever answered is asked first next time.
+//! Periodic [`probe`]s of the mirror in use move ones that are down to the
+//! back of the order for a while.
+
+use crate::net::{self, RetryPolicy};
+use crate::overpass::{Area, Output, QueryBuilder, Select};
+use crate::policy::Service;
+use crate::storage;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const STORAGE_KEY: &str = "overpass_endpoint";
+
+/// Slower than this and the mirror is struggling.
+const SLOW_MS: f64 = 3000.0;
+/// How long a mirror that failed a probe goes to the back of the order.
+const DOWN_FOR_MS: f64 = 15.0 * 60.0 * 1000.0;
+const PROBE_POLICY: RetryPolicy = RetryPolicy {
+    timeout: Duration::from_secs(10),
+    attempts: 1,
+    base_delay: Duration::ZERO,
+    max_delay: Duration::ZERO,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Healthy,
+    Slow,
+    Down,
+}
+
+impl Health {
+    pub fn judge(ok: bool, latency_ms: f64) -> Health {
+        match (ok, latency_ms < SLOW_MS) {
+            (false, _) => Health::Down,
+            (true, true) => Health::Healthy,
+            (true, false) => Health::Slow,
+        }
+    }
+
+    pub fn class(&self) -> &'static str {
+        match self {
+            Health::Healthy => "healthy",
+            Health::Slow => "slow",
+            Health::Down => "down",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Health::Healthy => "responding normally",
+            Health::Slow => "responding slowly",
+            Health::Down => "not responding",
+        }
+    }
+}
+
+thread_local! {
+    /// When each mirror last failed a probe.
+    static DOWN_SINCE: RefCell<HashMap<&'static str, f64>> = RefCell::new(HashMap::new());
+}
+
+pub const ENDPOINTS: &[&str] = &[
+    "https://overpass-api.de/api/interpreter",
+    "https://overpass.kumi.systems/api/interpreter",
+    "https://maps.mail.ru/osm/tools/overpass/api/interpreter",
+    "https://overpass.private.coffee/api/interpreter",
+];
+
+/// All endpoints, `preferred` first if it's one of them, the rest in list
+/// order, and any that are `down` after the others.
+pub fn order(preferred: Option<&str>, down: &[&str]) -> Vec<&'static str> {
+    let mut endpoints = ENDPOINTS.to_vec();
+    if let Some(i) = endpoints.iter().position(|e| Some(*e) == preferred) {
+        endpoints[..=i].rotate_right(1);
+    }
+    // Stable, so both groups keep their order.
+    endpoints.sort_by_key(|e| down.contains(e));
+    endpoints
+}
+
+fn down_now() -> Vec<&'static str> {
+    let now = js_sys::Date::now();
+    DOWN_SINCE.with(|d| d.borrow().iter().filter(|(_, &since)| now - since < DOWN_FOR_MS).map(|(e, _)| *e).collect())
+}
+
+/// Endpoints in the order to try them, last healthy one first.
+pub fn endpoints() -> Vec<&'static str> {
+    order(storage::load::<String>(STORAGE_KEY).as_deref(), &down_now())
+}
+
+pub fn remember(endpoint: &str) {
+    if storage::load::<String>(STORAGE_KEY).as_deref() != Some(endpoint) {
+        storage::save(STORAGE_KEY, &endpoint);
+    }
+}
+
+/// A tiny `out count` query against `endpoint`, recorded for [`endpoints`].
+pub async fn probe(endpoint: &'static str) -> Health {
+    let query = QueryBuilder::new()
+        .timeout(5)
+        .select(Select::new(None).within(Area::around(1, (0.0, 0.0))))
+        .output(Output::Count)
+        .build();
+    let started = js_sys::Date::now();
+    let ok = net::get_any(Service::Overpass, &[format!("{endpoint}?data={query}")], &PROBE_POLICY).await.is_ok();
+    let health = Health::judge(ok, js_sys::Date::now() - started);
+    DOWN_SINCE.with(|d| {
+        let mut down = d.borrow_mut();
+        if health == Health::Down {
+            down.insert(endpoint, started);
+        } else {
+            down.remove(endpoint);
+        }
+    });
+    health
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preferred_endpoint_goes_first() {
+        assert_eq!(order(None, &[]), ENDPOINTS);
+        assert_eq!(order(Some(ENDPOINTS[2]), &[]), [ENDPOINTS[2], ENDPOINTS[0], ENDPOINTS[1], ENDPOINTS[3]]);
+        assert_eq!(order(Some("https://gone.example/api/interpreter"), &[]), ENDPOINTS);
+    }
+
+    #[test]
+    fn mirrors_that_are_down_go_last() {
+        assert_eq!(order(Some(ENDPOINTS[2]), &[ENDPOINTS[2], ENDPOINTS[0]]), [ENDPOINTS[1], ENDPOINTS[3], ENDPOINTS[2], ENDPOINTS[0]]);
+        assert_eq!(Health::judge(true, 400.0), Health::Healthy);
+        assert_eq!(Health::judge(true, 8000.0), Health::Slow);
+        assert_eq!(Health::judge(false, 10.0), Health::Down);
+    }
+}
@@ -0,0 +1,250 @@
+//! What the services send back: Overpass elements and OSRM routes and tables,
+//! plus the errors a search can end in. Plain data, so it can be tested natively.
+
+use crate::tags::Tags;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use thiserror::Error;
+use web_sys::PositionError;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverpassResponse {
+    #[serde(deserialize_with = "located_elements")]
+    pub elements: Vec<Element>,
+    pub generator: String,
+    pub osm3s: Osm3s,
+    pub version: f64,
+}
+
+impl OverpassResponse {
+    /// Append the elements of `other`, skipping ones we already have (tiles share their edges).
+    pub fn merge(&mut self, other: OverpassResponse) {
+        let mut seen: HashSet<(OsmType, i64)> = self.elements.iter().map(|e| (e.type_field, e.id)).collect();
+        self.elements
+            .extend(other.elements.into_iter().filter(|e| seen.insert((e.type_field, e.id))));
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Osm3s {
+    pub copyright: String,
+    #[serde(rename = "timestamp_osm_base")]
+    pub timestamp_osm_base: String,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsmType {
+    #[default]
+    Node,
+    Way,
+    Relation,
+}
+
+impl OsmType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OsmType::Node => "node",
+            OsmType::Way => "way",
+            OsmType::Relation => "relation",
+        }
+    }
+}
+
+impl std::fmt::Display for OsmType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A node, or a way or relation placed at its center (`out center`).
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "RawElement")]
+pub struct Element {
+    pub id: i64,
+    pub lat: f64,
+    pub lon: f64,
+    /// Untagged nodes from the OSM API come without a `tags` object.
+    #[serde(default)]
+    pub tags: Tags,
+    #[serde(rename = "type")]
+    pub type_field: OsmType,
+    /// Last edit, only present in `out meta` responses.
+    pub timestamp: Option<String>,
+    /// Also only in `out meta` responses.
+    pub version: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Center {
+    lat: f64,
+    lon: f64,
+}
+
+/// An element as Overpass sends it: nodes have coordinates, ways and relations
+/// have a `center` when asked for one.
+#[derive(Deserialize)]
+struct RawElement {
+    id: i64,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    center: Option<Center>,
+    #[serde(default)]
+    tags: Tags,
+    #[serde(rename = "type", default)]
+    type_field: OsmType,
+    timestamp: Option<String>,
+    version: Option<u64>,
+}
+
+#[derive(Error, Clone, Debug)]
+#[error("{0} {1} has no coordinates.")]
+pub struct Unlocated(OsmType, i64);
+
+impl TryFrom<RawElement> for Element {
+    type Error = Unlocated;
+
+    fn try_from(raw: RawElement) -> std::result::Result<Element, Unlocated> {
+        let (lat, lon) = match (raw.lat, raw.lon, raw.center) {
+            (Some(lat), Some(lon), _) => (lat, lon),
+            (_, _, Some(Center { lat, lon })) => (lat, lon),
+            _ => return Err(Unlocated(raw.type_field, raw.id)),
+        };
+        Ok(Element {
+            id: raw.id,
+            lat,
+            lon,
+            tags: raw.tags,
+            type_field: raw.type_field,
+            timestamp: raw.timestamp,
+            version: raw.version,
+        })
+    }
+}
+
+/// Elements that can be placed on a map; others (a way without a center) are skipped.
+fn located_elements<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<Element>, D::Error> {
+    let raw: Vec<RawElement> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(raw.into_iter().filter_map(|e| Element::try_from(e).ok()).collect())
+}
+
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum BathroomError {
+    #[error("Location access was denied.")]
+    LocationDenied,
+    #[error("Your location isn't available right now.")]
+    LocationUnavailable,
+    #[error("Finding your location took too long.")]
+    LocationTimeout,
+}
+
+impl BathroomError {
+    /// From a `PositionError` code; unknown codes count as unavailable.
+    pub fn from_position_error(code: u16) -> BathroomError {
+        match code {
+            PositionError::PERMISSION_DENIED => BathroomError::LocationDenied,
+            PositionError::TIMEOUT => BathroomError::LocationTimeout,
+            _ => BathroomError::LocationUnavailable,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteRoot {
+    pub code: String,
+    pub routes: Vec<Route>,
+    pub waypoints: Vec<Waypoint>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Route {
+    pub distance: f64,
+    pub duration: f64,
+    pub geometry: String,
+    pub legs: Vec<Leg>,
+    pub weight: f64,
+    #[serde(rename = "weight_name")]
+    pub weight_name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Leg {
+    pub distance: f64,
+    pub duration: f64,
+    pub steps: Vec<Value>,
+    pub summary: String,
+    pub weight: f64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Waypoint {
+    pub distance: f64,
+    pub hint: String,
+    pub location: Vec<f64>,
+    pub name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRoot {
+    pub code: String,
+    pub distances: Vec<Vec<f64>>,
+    pub destinations: Vec<OSRMLocation>,
+    pub durations: Vec<Vec<f64>>,
+    pub sources: Vec<OSRMLocation>,
+}
+
+impl TableRoot {
+    /// Append another single-source table for the same origin. Each batch starts
+    /// with the origin itself as destination 0, which is skipped for all but the first.
+    pub fn append_batch(&mut self, other: TableRoot) {
+        if self.distances.is_empty() {
+            *self = other;
+            return;
+        }
+        if let (Some(row), Some(other_row)) = (self.distances.first_mut(), other.distances.first()) {
+            row.extend(other_row.iter().skip(1));
+        }
+        if let (Some(row), Some(other_row)) = (self.durations.first_mut(), other.durations.first()) {
+            row.extend(other_row.iter().skip(1));
+        }
+        self.destinations.extend(other.destinations.into_iter().skip(1));
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OSRMLocation {
+    pub hint: String,
+    pub distance: f64,
+    pub name: String,
+    pub location: Vec<f64>,
+}
+
+/// Elements found, walking table from the search origin, and the origin itself.
+pub type Bathrooms = (OverpassResponse, TableRoot, (f64, f64));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(distances: Vec<f64>) -> TableRoot {
+        TableRoot { distances: vec![distances.clone()], durations: vec![distances], ..TableRoot::default() }
+    }
+
+    #[test]
+    fn batches_skip_the_repeated_origin() {
+        let mut merged = TableRoot::default();
+        merged.append_batch(table(vec![0.0, 10.0, 20.0]));
+        merged.append_batch(table(vec![0.0, 30.0]));
+        assert_eq!(merged.distances, [[0.0, 10.0, 20.0, 30.0]]);
+        assert_eq!(merged.durations, [[0.0, 10.0, 20.0, 30.0]]);
+    }
+}
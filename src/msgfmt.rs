@@ -0,0 +1,346 @@
+//! ICU MessageFormat-style messages: `{name}` arguments, `plural` and `select`.
+//!
+//! `"{count, plural, =0 {No bathrooms} one {# bathroom} other {# bathrooms}} found"`
+//!
+//! Apostrophe quoting, `offset:` and ordinal plurals aren't supported.
+
+use crate::i18n::Locale;
+use thiserror::Error;
+
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum MsgError {
+    #[error("Message syntax error at character {0}.")]
+    Syntax(usize),
+    #[error("Message argument {0} is missing or has the wrong type.")]
+    Argument(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg<'a> {
+    Num(f64),
+    Str(&'a str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    One,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    fn keyword(&self) -> &'static str {
+        match self {
+            PluralCategory::One => "one",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+}
+
+/// CLDR cardinal plural rules for the supported locales.
+pub fn plural_category(locale: Locale, n: f64) -> PluralCategory {
+    let integer = n.fract() == 0.0;
+    let i = n.abs().trunc();
+    match locale {
+        // one: i = 1 and v = 0
+        Locale::En | Locale::De => {
+            if i == 1.0 && integer {
+                PluralCategory::One
+            } else {
+                PluralCategory::Other
+            }
+        }
+        // one: i = 0,1; many: e = 0 and i != 0 and i % 1000000 = 0 and v = 0
+        Locale::Fr => {
+            if i == 0.0 || i == 1.0 {
+                PluralCategory::One
+            } else if integer && i % 1_000_000.0 == 0.0 {
+                PluralCategory::Many
+            } else {
+                PluralCategory::Other
+            }
+        }
+        // one: n = 1; many: e = 0 and i != 0 and i % 1000000 = 0 and v = 0
+        Locale::Es => {
+            if n.abs() == 1.0 {
+                PluralCategory::One
+            } else if integer && i != 0.0 && i % 1_000_000.0 == 0.0 {
+                PluralCategory::Many
+            } else {
+                PluralCategory::Other
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Arg(String),
+    /// `#` inside a plural case: the number.
+    Hash,
+    Plural { arg: String, cases: Vec<(String, Vec<Part>)> },
+    Select { arg: String, cases: Vec<(String, Vec<Part>)> },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), MsgError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(MsgError::Syntax(self.pos))
+        }
+    }
+
+    /// Up to (not including) any of `stops`, trimmed.
+    fn word(&mut self, stops: &[char]) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !stops.contains(&c)) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().trim().to_string()
+    }
+
+    fn message(&mut self, nested: bool, in_plural: bool) -> Result<Vec<Part>, MsgError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '{' => {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(self.argument(in_plural)?);
+                }
+                '}' if nested => break,
+                '}' => return Err(MsgError::Syntax(self.pos)),
+                '#' if in_plural => {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Hash);
+                    self.pos += 1;
+                }
+                c => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(parts)
+    }
+
+    fn argument(&mut self, in_plural: bool) -> Result<Part, MsgError> {
+        self.expect('{')?;
+        let arg = self.word(&[',', '}']);
+        if arg.is_empty() {
+            return Err(MsgError::Syntax(self.pos));
+        }
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Part::Arg(arg));
+        }
+        self.expect(',')?;
+        let kind = self.word(&[',', '}']);
+        self.expect(',')?;
+        let plural = match kind.as_str() {
+            "plural" => true,
+            "select" => false,
+            _ => return Err(MsgError::Syntax(self.pos)),
+        };
+        let mut cases = Vec::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                None => return Err(MsgError::Syntax(self.pos)),
+                _ => {}
+            }
+            let selector = self.word(&['{', '}']);
+            if selector.is_empty() || selector.contains(char::is_whitespace) {
+                return Err(MsgError::Syntax(self.pos));
+            }
+            self.expect('{')?;
+            let case = self.message(true, plural || in_plural)?;
+            self.expect('}')?;
+            cases.push((selector, case));
+        }
+        if !cases.iter().any(|(selector, _)| selector == "other") {
+            return Err(MsgError::Syntax(self.pos));
+        }
+        Ok(if plural { Part::Plural { arg, cases } } else { Part::Select { arg, cases } })
+    }
+}
+
+fn parse(pattern: &str) -> Result<Vec<Part>, MsgError> {
+    let mut parser = Parser {
+        chars: pattern.chars().collect(),
+        pos: 0,
+    };
+    parser.message(false, false)
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 {
+        format!("{n:.0}")
+    } else {
+        n.to_string()
+    }
+}
+
+fn lookup<'a>(args: &'a [(&str, Arg<'a>)], name: &str) -> Result<&'a Arg<'a>, MsgError> {
+    args.iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, a)| a)
+        .ok_or_else(|| MsgError::Argument(name.to_string()))
+}
+
+fn render(
+    locale: Locale,
+    parts: &[Part],
+    args: &[(&str, Arg)],
+    number: Option<f64>,
+    out: &mut String,
+) -> Result<(), MsgError> {
+    for part in parts {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Hash => out.push_str(&number.map(format_number).unwrap_or_else(|| "#".to_string())),
+            Part::Arg(name) => match lookup(args, name)? {
+                Arg::Num(n) => out.push_str(&format_number(*n)),
+                Arg::Str(s) => out.push_str(s),
+            },
+            Part::Plural { arg, cases } => {
+                let Arg::Num(n) = lookup(args, arg)? else {
+                    return Err(MsgError::Argument(arg.clone()));
+                };
+                let exact = format!("={}", format_number(*n));
+                let category = plural_category(locale, *n).keyword();
+                let case = [exact.as_str(), category, "other"]
+                    .iter()
+                    .find_map(|key| cases.iter().find(|(selector, _)| selector == key))
+                    .map(|(_, case)| case)
+                    .ok_or_else(|| MsgError::Argument(arg.clone()))?;
+                render(locale, case, args, Some(*n), out)?;
+            }
+            Part::Select { arg, cases } => {
+                let Arg::Str(value) = lookup(args, arg)? else {
+                    return Err(MsgError::Argument(arg.clone()));
+                };
+                let case = cases
+                    .iter()
+                    .find(|(selector, _)| selector == value)
+                    .or_else(|| cases.iter().find(|(selector, _)| selector == "other"))
+                    .map(|(_, case)| case)
+                    .ok_or_else(|| MsgError::Argument(arg.clone()))?;
+                render(locale, case, args, number, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn format(locale: Locale, pattern: &str, args: &[(&str, Arg)]) -> Result<String, MsgError> {
+    let parts = parse(pattern)?;
+    let mut out = String::new();
+    render(locale, &parts, args, None, &mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOUND: &str = "{count, plural, =0 {No bathrooms} one {# bathroom} other {# bathrooms}} found";
+
+    fn found(locale: Locale, n: f64) -> String {
+        format(locale, FOUND, &[("count", Arg::Num(n))]).unwrap()
+    }
+
+    #[test]
+    fn english_rules() {
+        assert_eq!(plural_category(Locale::En, 1.0), PluralCategory::One);
+        assert_eq!(plural_category(Locale::En, 0.0), PluralCategory::Other);
+        assert_eq!(plural_category(Locale::En, 1.5), PluralCategory::Other);
+        assert_eq!(plural_category(Locale::En, 2.0), PluralCategory::Other);
+        assert_eq!(found(Locale::En, 0.0), "No bathrooms found");
+        assert_eq!(found(Locale::En, 1.0), "1 bathroom found");
+        assert_eq!(found(Locale::En, 12.0), "12 bathrooms found");
+    }
+
+    #[test]
+    fn german_rules() {
+        assert_eq!(plural_category(Locale::De, 1.0), PluralCategory::One);
+        assert_eq!(plural_category(Locale::De, 0.0), PluralCategory::Other);
+        assert_eq!(plural_category(Locale::De, 1_000_000.0), PluralCategory::Other);
+    }
+
+    #[test]
+    fn french_rules() {
+        assert_eq!(plural_category(Locale::Fr, 0.0), PluralCategory::One);
+        assert_eq!(plural_category(Locale::Fr, 1.0), PluralCategory::One);
+        assert_eq!(plural_category(Locale::Fr, 1.5), PluralCategory::One);
+        assert_eq!(plural_category(Locale::Fr, 2.0), PluralCategory::Other);
+        assert_eq!(plural_category(Locale::Fr, 1_000_000.0), PluralCategory::Many);
+        // Without a `many` case, the `other` case is used.
+        assert_eq!(found(Locale::Fr, 1_000_000.0), "1000000 bathrooms found");
+    }
+
+    #[test]
+    fn spanish_rules() {
+        assert_eq!(plural_category(Locale::Es, 1.0), PluralCategory::One);
+        assert_eq!(plural_category(Locale::Es, 0.0), PluralCategory::Other);
+        assert_eq!(plural_category(Locale::Es, 2_000_000.0), PluralCategory::Many);
+    }
+
+    #[test]
+    fn select_and_arguments() {
+        let pattern = "{kind, select, camp_site {Camp site} other {Toilet}} near {place}";
+        let args = [("kind", Arg::Str("camp_site")), ("place", Arg::Str("Berlin"))];
+        assert_eq!(format(Locale::En, pattern, &args).unwrap(), "Camp site near Berlin");
+        let args = [("kind", Arg::Str("toilets")), ("place", Arg::Str("Berlin"))];
+        assert_eq!(format(Locale::En, pattern, &args).unwrap(), "Toilet near Berlin");
+    }
+
+    #[test]
+    fn hash_in_nested_select() {
+        let pattern = "{n, plural, one {{k, select, a {# A} other {# thing}}} other {# things}}";
+        let args = [("n", Arg::Num(1.0)), ("k", Arg::Str("b"))];
+        assert_eq!(format(Locale::En, pattern, &args).unwrap(), "1 thing");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(format(Locale::En, "{count", &[]), Err(MsgError::Syntax(6)));
+        assert!(matches!(format(Locale::En, "{n, plural, one {x}}", &[]), Err(MsgError::Syntax(_))));
+        assert!(matches!(format(Locale::En, "oops}", &[]), Err(MsgError::Syntax(4))));
+        assert_eq!(format(Locale::En, "{name}", &[]), Err(MsgError::Argument("name".to_string())));
+        assert_eq!(
+            format(Locale::En, FOUND, &[("count", Arg::Str("x"))]),
+            Err(MsgError::Argument("count".to_string()))
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Requests to the public APIs that fail gracefully: each attempt is aborted
+//! after a timeout, failures that might go away on their own move on to the
+//! next mirror and are retried with exponential backoff, and what's left is a
+//! [`NetError`] the error boundary can explain (and offer to retry).
+
+use crate::policy::{self, Service};
+use futures::channel::oneshot;
+use reqwasm::http::Response;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use thiserror::Error;
+use web_sys::AbortController;
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum NetError {
+    #[error("The server is busy with other requests (429). Try again in a minute.")]
+    RateLimited,
+    #[error("The server gave up on the query (504). Try again, or search a smaller area.")]
+    GatewayTimeout,
+    #[error("No answer after {0} seconds.")]
+    Timeout(u64),
+    #[error("The server answered {0}.")]
+    Status(u16),
+    #[error("Network error: {0}")]
+    Network(String),
+}
+
+impl NetError {
+    /// `None` for success.
+    pub fn from_status(status: u16) -> Option<NetError> {
+        match status {
+            200..=299 => None,
+            429 => Some(NetError::RateLimited),
+            504 => Some(NetError::GatewayTimeout),
+            status => Some(NetError::Status(status)),
+        }
+    }
+
+    /// Overload, timeouts, server errors and dropped connections may clear up;
+    /// a bad request won't.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NetError::Status(status) => *status >= 500,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Per attempt.
+    pub timeout: Duration,
+    /// Rounds through the URLs, including the first.
+    pub attempts: u32,
+    /// Before the first retry; doubled for each one after.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Overpass answers 429 while all its slots are taken, which lasts seconds
+    /// rather than milliseconds, so it's given room between attempts.
+    pub const OVERPASS: RetryPolicy = RetryPolicy {
+        timeout: Duration::from_secs(45),
+        attempts: 3,
+        base_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(16),
+    };
+
+    /// How long to wait after failed attempt number `attempt` (0 for the first).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+pub async fn sleep(duration: Duration) {
+    let (sender, receiver) = oneshot::channel();
+    leptos::set_timeout(
+        move || {
+            let _ = sender.send(());
+        },
+        duration,
+    );
+    let _ = receiver.await;
+}
+
+async fn attempt(service: Service, url: &str, timeout: Duration) -> Result<Response, NetError> {
+    let controller = AbortController::new().map_err(|e| NetError::Network(format!("{e:?}")))?;
+    let timed_out = Rc::new(Cell::new(false));
+    let handle = leptos::set_timeout_with_handle(
+        {
+            let (controller, timed_out) = (controller.clone(), Rc::clone(&timed_out));
+            move || {
+                timed_out.set(true);
+                controller.abort();
+            }
+        },
+        timeout,
+    )
+    .ok();
+    let sent = policy::get(service, url).await.abort_signal(Some(&controller.signal())).send().await;
+    if let Some(handle) = handle {
+        handle.clear();
+    }
+    match sent {
+        Err(_) if timed_out.get() => Err(NetError::Timeout(timeout.as_secs())),
+        Err(e) => Err(NetError::Network(e.to_string())),
+        Ok(response) => match NetError::from_status(response.status()) {
+            Some(e) => Err(e),
+            None => Ok(response),
+        },
+    }
+}
+
+/// GET the first of `urls` (mirrors of one service) that answers, moving on to
+/// the next on a retryable failure. Once all have failed, start over after a
+/// backoff, for `policy.attempts` rounds. Returns the index of the URL that
+/// answered; the body is left to the caller.
+pub async fn get_any(service: Service, urls: &[String], policy: &RetryPolicy) -> Result<(usize, Response), NetError> {
+    let mut round = 0;
+    loop {
+        let mut last = NetError::Network("no URL to try".into());
+        for (i, url) in urls.iter().enumerate() {
+            match attempt(service, url, policy.timeout).await {
+                Ok(response) => return Ok((i, response)),
+                Err(e) if e.is_retryable() => {
+                    log::warn!("{url}: {e}");
+                    last = e;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if urls.is_empty() || round + 1 >= policy.attempts {
+            return Err(last);
+        }
+        let delay = policy.backoff(round);
+        log::warn!("all {} servers failed; retrying in {delay:?}", urls.len());
+        sleep(delay).await;
+        round += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_overpass_statuses() {
+        assert_eq!(NetError::from_status(200), None);
+        assert_eq!(NetError::from_status(429), Some(NetError::RateLimited));
+        assert_eq!(NetError::from_status(504), Some(NetError::GatewayTimeout));
+        assert!(NetError::Status(502).is_retryable());
+        assert!(!NetError::Status(400).is_retryable());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::OVERPASS;
+        let delays: Vec<u64> = (0..5).map(|i| policy.backoff(i).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 16]);
+        assert_eq!(policy.backoff(40), policy.max_delay);
+    }
+}
@@ -0,0 +1,125 @@
+//! Notices the app raises on its own (arriving somewhere, sound cues), as opposed
+//! to responses to something the user did. They all go through here so quiet
+//! hours are enforced in one place.
+
+use crate::bus::{use_bus, Bus, Event};
+use crate::prefs::{use_preferences, Preferences};
+use crate::sound::{self, Cue};
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// A daily window, in minutes since local midnight, that may wrap past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: u16,
+    pub end: u16,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        QuietHours {
+            enabled: false,
+            start: 22 * 60,
+            end: 7 * 60,
+        }
+    }
+}
+
+impl QuietHours {
+    /// Start inclusive, end exclusive; an empty window when they're equal.
+    pub fn contains(&self, minute: u16) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+/// `"22:30"`, as an `<input type="time">` value.
+pub fn format_hhmm(minute: u16) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+pub fn parse_hhmm(s: &str) -> Option<u16> {
+    let (h, m) = s.split_once(':')?;
+    let (h, m): (u16, u16) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+fn local_minute() -> u16 {
+    let now = js_sys::Date::new_0();
+    (now.get_hours() * 60 + now.get_minutes()) as u16 % MINUTES_PER_DAY
+}
+
+#[derive(Clone, Copy)]
+pub struct Notifier {
+    bus: Bus,
+    prefs: RwSignal<Preferences>,
+}
+
+impl Notifier {
+    pub fn quiet(&self) -> bool {
+        self.prefs.with_untracked(|p| p.quiet_hours.contains(local_minute()))
+    }
+
+    /// Show `message` unless it's quiet hours.
+    pub fn notify(&self, message: impl Into<String>) {
+        if !self.quiet() {
+            self.bus.publish(Event::Toast { message: message.into() });
+        }
+    }
+
+    /// Play `cue` if sounds are on and it isn't quiet hours.
+    pub fn play(&self, cue: Cue) {
+        let volume = self.prefs.with_untracked(|p| p.sound_cues.then_some(p.sound_volume));
+        let Some(volume) = volume.filter(|v| *v > 0.0) else {
+            return;
+        };
+        if self.quiet() {
+            return;
+        }
+        if let Err(e) = sound::play(cue, volume) {
+            log::warn!("couldn't play {cue:?}: {e:?}");
+        }
+    }
+}
+
+pub fn use_notifier(cx: Scope) -> Notifier {
+    Notifier {
+        bus: use_bus(cx),
+        prefs: use_preferences(cx),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_can_wrap_past_midnight() {
+        let night = QuietHours { enabled: true, start: 22 * 60, end: 7 * 60 };
+        assert!(night.contains(23 * 60));
+        assert!(night.contains(0));
+        assert!(!night.contains(7 * 60));
+        assert!(!night.contains(12 * 60));
+        let lunch = QuietHours { enabled: true, start: 12 * 60, end: 13 * 60 };
+        assert!(lunch.contains(12 * 60 + 30));
+        assert!(!lunch.contains(13 * 60));
+        assert!(!QuietHours { enabled: false, ..night }.contains(0));
+    }
+
+    #[test]
+    fn round_trips_time_inputs() {
+        assert_eq!(parse_hhmm("22:30"), Some(22 * 60 + 30));
+        assert_eq!(format_hhmm(7 * 60 + 5), "07:05");
+        assert_eq!(parse_hhmm("24:00"), None);
+        assert_eq!(parse_hhmm("7"), None);
+    }
+}
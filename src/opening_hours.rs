@@ -0,0 +1,250 @@
+//! A parser for the common subset of the OSM `opening_hours` syntax
+//! (`24/7`, weekday ranges, time ranges, `off`) and evaluation at a given time.
+//!
+//! Anything outside that subset (public holidays, sunrise, month ranges...)
+//! fails to parse, and callers treat it as "unknown" rather than guessing.
+
+use std::str::FromStr;
+use thiserror::Error;
+
+const DAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("Unsupported opening_hours: {0}")]
+pub struct ParseError(pub String);
+
+/// A point in the week in local time. `weekday` is 0 for Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekTime {
+    pub weekday: u8,
+    pub minute: u16,
+}
+
+impl WeekTime {
+    pub fn new(weekday: u8, hour: u16, minute: u16) -> WeekTime {
+        WeekTime {
+            weekday: weekday % 7,
+            minute: hour * 60 + minute,
+        }
+    }
+
+    /// Local week time of a JS timestamp in milliseconds.
+    pub fn from_js_millis(ms: f64) -> WeekTime {
+        let date = js_sys::Date::new(&ms.into());
+        // JS weeks start on Sunday.
+        let weekday = (date.get_day() + 6) % 7;
+        WeekTime::new(weekday as u8, date.get_hours() as u16, date.get_minutes() as u16)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenStatus {
+    Open,
+    Closed,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    days: [bool; 7],
+    /// Minute ranges; `end` may exceed a day for ranges past midnight.
+    times: Vec<(u16, u16)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningHours {
+    rules: Vec<Rule>,
+}
+
+fn parse_day(s: &str) -> Option<usize> {
+    DAYS.iter().position(|d| *d == s)
+}
+
+fn parse_days(s: &str) -> Option<[bool; 7]> {
+    let mut days = [false; 7];
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse_day(from)?, parse_day(to)?);
+                // Ranges like Sa-Mo wrap around the end of the week.
+                let mut d = from;
+                loop {
+                    days[d] = true;
+                    if d == to {
+                        break;
+                    }
+                    d = (d + 1) % 7;
+                }
+            }
+            None => days[parse_day(part)?] = true,
+        }
+    }
+    Some(days)
+}
+
+fn parse_clock(s: &str) -> Option<u16> {
+    let (h, m) = s.split_once(':')?;
+    let (h, m): (u16, u16) = (h.parse().ok()?, m.parse().ok()?);
+    (h <= 24 && m < 60 && h * 60 + m <= MINUTES_PER_DAY).then_some(h * 60 + m)
+}
+
+fn parse_times(s: &str) -> Option<Vec<(u16, u16)>> {
+    s.split(',')
+        .map(|range| {
+            let (start, end) = range.split_once('-')?;
+            let (start, end) = (parse_clock(start)?, parse_clock(end)?);
+            // 22:00-02:00 runs into the next day.
+            let end = if end <= start { end + MINUTES_PER_DAY } else { end };
+            Some((start, end))
+        })
+        .collect()
+}
+
+fn parse_rule(s: &str) -> Option<Rule> {
+    let s = s.trim();
+    if s == "24/7" {
+        return Some(Rule {
+            days: [true; 7],
+            times: vec![(0, MINUTES_PER_DAY)],
+        });
+    }
+
+    let mut parts = s.split_whitespace();
+    let first = parts.next()?;
+    let (days, times) = if first.chars().next()?.is_ascii_digit() {
+        ([true; 7], Some(first))
+    } else {
+        (parse_days(first)?, parts.next())
+    };
+    let times = match times {
+        None => vec![(0, MINUTES_PER_DAY)],
+        Some("off" | "closed") => vec![],
+        Some(times) => parse_times(times)?,
+    };
+    parts.next().is_none().then_some(Rule { days, times })
+}
+
+impl FromStr for OpeningHours {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules = s
+            .split(';')
+            .filter(|r| !r.trim().is_empty())
+            .map(parse_rule)
+            .collect::<Option<Vec<_>>>()
+            .filter(|rules| !rules.is_empty())
+            .ok_or_else(|| ParseError(s.to_string()))?;
+        Ok(OpeningHours { rules })
+    }
+}
+
+impl OpeningHours {
+    /// As in OSM, a later rule replaces earlier ones for the days it names.
+    fn rule_for(&self, weekday: usize) -> Option<&Rule> {
+        self.rules.iter().rev().find(|r| r.days[weekday])
+    }
+
+    pub fn is_open_at(&self, t: WeekTime) -> bool {
+        self.closes_at(t).is_some()
+    }
+
+    /// When the opening `t` falls in ends, in minutes since `t`'s midnight
+    /// (past a day for openings that run past midnight); `None` when closed.
+    pub fn closes_at(&self, t: WeekTime) -> Option<u16> {
+        let today = t.weekday as usize % 7;
+        let yesterday = (today + 6) % 7;
+        let today_end = self
+            .rule_for(today)
+            .and_then(|r| r.times.iter().find(|&&(start, end)| (start..end).contains(&t.minute)))
+            .map(|&(_, end)| end);
+        let spill_from_yesterday = || {
+            self.rule_for(yesterday)
+                .and_then(|r| r.times.iter().find(|&&(_, end)| end > MINUTES_PER_DAY && t.minute < end - MINUTES_PER_DAY))
+                .map(|&(_, end)| end - MINUTES_PER_DAY)
+        };
+        today_end.or_else(spill_from_yesterday)
+    }
+
+    pub fn status_at(&self, t: WeekTime) -> OpenStatus {
+        if self.is_open_at(t) {
+            OpenStatus::Open
+        } else {
+            OpenStatus::Closed
+        }
+    }
+}
+
+/// Status for a raw `opening_hours` tag value, `Unknown` when missing or unparseable.
+pub fn status_for_tag(tag: Option<&str>, t: WeekTime) -> OpenStatus {
+    tag.and_then(|tag| tag.parse::<OpeningHours>().ok())
+        .map(|hours| hours.status_at(t))
+        .unwrap_or(OpenStatus::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(s: &str) -> OpeningHours {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn always_open() {
+        let h = hours("24/7");
+        assert!(h.is_open_at(WeekTime::new(0, 0, 0)));
+        assert!(h.is_open_at(WeekTime::new(6, 23, 59)));
+    }
+
+    #[test]
+    fn weekday_and_time_ranges() {
+        let h = hours("Mo-Fr 08:00-12:00,13:00-18:00; Sa 10:00-14:00");
+        assert!(h.is_open_at(WeekTime::new(0, 8, 0)));
+        assert!(!h.is_open_at(WeekTime::new(0, 12, 30)));
+        assert!(!h.is_open_at(WeekTime::new(4, 18, 0)));
+        assert!(h.is_open_at(WeekTime::new(5, 11, 0)));
+        assert!(!h.is_open_at(WeekTime::new(6, 11, 0)));
+    }
+
+    #[test]
+    fn later_rules_override_and_off() {
+        let h = hours("Mo-Su 06:00-22:00; We off");
+        assert!(h.is_open_at(WeekTime::new(1, 12, 0)));
+        assert!(!h.is_open_at(WeekTime::new(2, 12, 0)));
+    }
+
+    #[test]
+    fn overnight_and_wrapping_days() {
+        let h = hours("Fr-Sa 20:00-02:00");
+        assert!(h.is_open_at(WeekTime::new(4, 23, 0)));
+        assert!(h.is_open_at(WeekTime::new(6, 1, 0)));
+        assert!(!h.is_open_at(WeekTime::new(6, 3, 0)));
+        assert!(!h.is_open_at(WeekTime::new(0, 1, 0)));
+        assert_eq!(h.closes_at(WeekTime::new(4, 23, 0)), Some(26 * 60));
+        assert_eq!(h.closes_at(WeekTime::new(6, 1, 0)), Some(2 * 60));
+
+        let h = hours("Sa-Mo 10:00-12:00");
+        assert!(h.is_open_at(WeekTime::new(6, 11, 0)));
+        assert!(!h.is_open_at(WeekTime::new(1, 11, 0)));
+    }
+
+    #[test]
+    fn bare_times_and_days() {
+        assert!(hours("08:00-20:00").is_open_at(WeekTime::new(3, 9, 0)));
+        assert!(hours("Mo,We").is_open_at(WeekTime::new(2, 3, 0)));
+        assert!(!hours("Mo,We").is_open_at(WeekTime::new(1, 3, 0)));
+    }
+
+    #[test]
+    fn unsupported_syntax_is_unknown() {
+        assert!("sunrise-sunset".parse::<OpeningHours>().is_err());
+        assert!("Mo-Fr 08:00-18:00; PH off".parse::<OpeningHours>().is_err());
+        assert!("".parse::<OpeningHours>().is_err());
+        let t = WeekTime::new(0, 9, 0);
+        assert_eq!(status_for_tag(Some("Jan-Mar 10:00-12:00"), t), OpenStatus::Unknown);
+        assert_eq!(status_for_tag(None, t), OpenStatus::Unknown);
+        assert_eq!(status_for_tag(Some("Mo 08:00-10:00"), t), OpenStatus::Open);
+    }
+}
@@ -0,0 +1,29 @@
+//! Optimistic updates: change the UI now, persist in the background, and roll
+//! back with a toast if persisting fails.
+
+use crate::toast::Toasts;
+use leptos::*;
+use std::fmt::Display;
+use std::future::Future;
+
+/// Apply `change` to `signal` immediately, then persist the new value with `persist`.
+/// On failure the signal goes back to its value from before the change; a later
+/// change made while this one was in flight is rolled back with it.
+pub fn update<T, F, Fut, E>(signal: RwSignal<T>, toasts: Toasts, change: impl FnOnce(&mut T), persist: F)
+where
+    T: Clone + 'static,
+    F: FnOnce(T) -> Fut + 'static,
+    Fut: Future<Output = Result<(), E>> + 'static,
+    E: Display,
+{
+    let before = signal.get_untracked();
+    signal.update(change);
+    let after = signal.get_untracked();
+    spawn_local(async move {
+        if let Err(e) = persist(after).await {
+            log::warn!("rolling back: {e}");
+            signal.set(before);
+            toasts.push(format!("Couldn't save your change. {e}"));
+        }
+    });
+}
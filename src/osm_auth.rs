@@ -0,0 +1,167 @@
+//! Logging in to OpenStreetMap with OAuth 2.0 (authorization code + PKCE), so
+//! edits can be made as the user. Needs an app registered on openstreetmap.org
+//! whose client id is baked in at build time as `OSM_CLIENT_ID`.
+
+use crate::storage;
+use leptos::error::Result;
+use serde_derive::Deserialize;
+use thiserror::Error;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, UrlSearchParams};
+
+const AUTHORIZE_URL: &str = "https://www.openstreetmap.org/oauth2/authorize";
+const TOKEN_URL: &str = "https://www.openstreetmap.org/oauth2/token";
+const SCOPE: &str = "write_api";
+
+const TOKEN_KEY: &str = "osm_token";
+const VERIFIER_KEY: &str = "osm_pkce_verifier";
+
+#[derive(Error, Clone, Debug)]
+pub enum AuthError {
+    #[error("OpenStreetMap login isn't configured for this build.")]
+    NotConfigured,
+    #[error("Your browser can't do a secure OpenStreetMap login.")]
+    NoCrypto,
+    #[error("OpenStreetMap login failed.")]
+    Rejected,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+pub fn client_id() -> Option<&'static str> {
+    option_env!("OSM_CLIENT_ID")
+}
+
+/// The saved access token, if logged in.
+pub fn token() -> Option<String> {
+    storage::load(TOKEN_KEY)
+}
+
+pub fn logout() {
+    storage::remove(TOKEN_KEY);
+}
+
+/// Unpadded base64url, as PKCE wants for verifiers and challenges.
+pub fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+/// The page to come back to after authorizing, which must match the registered redirect URI.
+fn redirect_uri() -> Option<String> {
+    let location = window()?.location();
+    Some(format!("{}{}", location.origin().ok()?, location.pathname().ok()?))
+}
+
+async fn sha256(input: &str) -> Result<Vec<u8>> {
+    let crypto = window().and_then(|w| w.crypto().ok()).ok_or(AuthError::NoCrypto)?;
+    let digest = crypto
+        .subtle()
+        .digest_with_str_and_u8_array("SHA-256", input.as_bytes())
+        .map_err(|_| AuthError::NoCrypto)?;
+    let digest = JsFuture::from(digest).await.map_err(|_| AuthError::NoCrypto)?;
+    Ok(js_sys::Uint8Array::new(&digest).to_vec())
+}
+
+/// Send the user off to openstreetmap.org to authorize the app.
+pub async fn login() -> Result<()> {
+    let client_id = client_id().ok_or(AuthError::NotConfigured)?;
+    let redirect_uri = redirect_uri().ok_or(AuthError::NotConfigured)?;
+    let crypto = window().and_then(|w| w.crypto().ok()).ok_or(AuthError::NoCrypto)?;
+    let mut random = [0u8; 32];
+    crypto.get_random_values_with_u8_array(&mut random).map_err(|_| AuthError::NoCrypto)?;
+    let verifier = base64url(&random);
+    let challenge = base64url(&sha256(&verifier).await?);
+    storage::save(VERIFIER_KEY, &verifier);
+
+    let params = UrlSearchParams::new().map_err(|_| AuthError::NotConfigured)?;
+    params.append("response_type", "code");
+    params.append("client_id", client_id);
+    params.append("redirect_uri", &redirect_uri);
+    params.append("scope", SCOPE);
+    params.append("code_challenge", &challenge);
+    params.append("code_challenge_method", "S256");
+    let url = format!("{AUTHORIZE_URL}?{}", String::from(params.to_string()));
+    window()
+        .ok_or(AuthError::NotConfigured)?
+        .location()
+        .assign(&url)
+        .map_err(|_| AuthError::NotConfigured)?;
+    Ok(())
+}
+
+/// If we just came back from authorizing, trade the code for a token and tidy up the URL.
+pub async fn complete_login() -> Result<()> {
+    let Some(window) = window() else {
+        return Ok(());
+    };
+    let location = window.location();
+    let params = UrlSearchParams::new_with_str(&location.search().unwrap_or_default())
+        .map_err(|_| AuthError::Rejected)?;
+    let Some(code) = params.get("code") else {
+        return Ok(());
+    };
+    let verifier: String = storage::load(VERIFIER_KEY).ok_or(AuthError::Rejected)?;
+    storage::remove(VERIFIER_KEY);
+    let client_id = client_id().ok_or(AuthError::NotConfigured)?;
+    let redirect_uri = redirect_uri().ok_or(AuthError::NotConfigured)?;
+
+    // Drop `?code=...` so a reload doesn't try to reuse it.
+    if let Ok(history) = window.history() {
+        let clean = format!("{redirect_uri}{}", location.hash().unwrap_or_default());
+        let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&clean));
+    }
+
+    let body = UrlSearchParams::new().map_err(|_| AuthError::Rejected)?;
+    body.append("grant_type", "authorization_code");
+    body.append("code", &code);
+    body.append("redirect_uri", &redirect_uri);
+    body.append("client_id", client_id);
+    body.append("code_verifier", &verifier);
+    let res = reqwasm::http::Request::post(TOKEN_URL)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(String::from(body.to_string()))
+        .send()
+        .await?;
+    if !res.ok() {
+        return Err(AuthError::Rejected.into());
+    }
+    let token: TokenResponse = res.json().await?;
+    storage::save(TOKEN_KEY, &token.access_token);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64url_without_padding() {
+        assert_eq!(base64url(b""), "");
+        assert_eq!(base64url(b"f"), "Zg");
+        assert_eq!(base64url(b"fo"), "Zm8");
+        assert_eq!(base64url(b"foo"), "Zm9v");
+        assert_eq!(base64url(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn rfc7636_challenge_encoding() {
+        // SHA-256 of the RFC 7636 appendix B verifier.
+        let digest = [
+            19, 211, 30, 150, 26, 26, 216, 236, 47, 22, 177, 12, 76, 152, 46, 8, 118, 168, 120, 173, 109, 241, 68, 86,
+            110, 225, 137, 74, 203, 112, 249, 195,
+        ];
+        assert_eq!(base64url(&digest), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+}
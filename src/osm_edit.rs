@@ -0,0 +1,555 @@
+//! Edits through the OpenStreetMap API (tag changes in one small changeset each,
+//! and notes), plus an IndexedDB queue for ones made while offline that gets
+//! replayed when we're back online.
+
+use crate::export::escape_xml;
+use crate::tags::Tags;
+use crate::{changeset, idb, osm_auth, Element};
+use leptos::{error::Result, *};
+use reqwasm::http::{Method, Request};
+use serde_derive::{Deserialize, Serialize};
+use std::cell::RefCell;
+use thiserror::Error;
+use web_sys::window;
+
+const OSM_API: &str = "https://api.openstreetmap.org/api/0.6";
+const CREATED_BY: &str = "Free2Pee";
+const QUEUE_STORE: &str = "pending_uploads";
+
+#[derive(Error, Clone, Debug)]
+pub enum OsmEditError {
+    #[error("Log in to OpenStreetMap to edit.")]
+    NotLoggedIn,
+    #[error("OpenStreetMap has no such element.")]
+    Missing,
+    #[error("OpenStreetMap refused the edit (HTTP {0}).")]
+    Api(u16),
+    #[error("Someone else edited this since you looked (now version {}).", .0.version)]
+    Conflict(Conflict),
+    #[error("Couldn't tell which version of this place you edited; reload and try again.")]
+    UnknownVersion,
+}
+
+/// What's on OpenStreetMap now, when it's moved on from the version an edit was made against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub version: u64,
+    pub tags: Tags,
+}
+
+/// One tag of an edit, side by side with what's there now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagChange {
+    pub key: String,
+    pub current: Option<String>,
+    pub intended: String,
+}
+
+impl TagChange {
+    /// Someone else already set it to what we wanted.
+    pub fn already_applied(&self) -> bool {
+        self.current.as_ref() == Some(&self.intended)
+    }
+}
+
+/// The edit's tags against the conflicting version's, in edit order.
+pub fn diff(conflict: &Conflict, edit: &TagEdit) -> Vec<TagChange> {
+    edit.tags
+        .iter()
+        .map(|(key, intended)| TagChange {
+            key: key.clone(),
+            current: conflict.tags.get(key).map(str::to_string),
+            intended: intended.clone(),
+        })
+        .collect()
+}
+
+/// Tags to set on a node, with the changeset comment to upload them under.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagEdit {
+    pub node_id: i64,
+    /// The version the user was looking at when they made the edit. Required
+    /// to upload; [`submit`] looks it up when the results didn't have it.
+    pub base_version: Option<u64>,
+    pub tags: Vec<(String, String)>,
+    pub comment: String,
+    #[serde(default)]
+    pub hashtags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Upload {
+    Tags(TagEdit),
+    Note { lat: f64, lon: f64, text: String },
+}
+
+/// An upload waiting in the offline queue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingUpload {
+    /// Zero-padded creation time plus a random suffix, so key order is queue order.
+    pub id: String,
+    pub upload: Upload,
+    /// Set when uploading hit a version conflict; these wait for the user instead of retrying.
+    pub conflict: Option<Conflict>,
+}
+
+impl PendingUpload {
+    pub fn new(upload: Upload) -> PendingUpload {
+        let id = format!("{:015}-{:06}", js_sys::Date::now() as u64, (js_sys::Math::random() * 1e6) as u32);
+        PendingUpload { id, upload, conflict: None }
+    }
+}
+
+#[derive(Deserialize)]
+struct ElementsResponse {
+    elements: Vec<Element>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Submitted {
+    Uploaded,
+    /// Offline; it'll go up when we're back.
+    Queued,
+    /// Set aside for the user to rebase or abandon.
+    Conflicted,
+}
+
+pub fn changeset_xml(comment: &str, hashtags: &[String]) -> String {
+    let hashtags_tag = if hashtags.is_empty() {
+        String::new()
+    } else {
+        format!("<tag k=\"hashtags\" v=\"{}\"/>", escape_xml(&hashtags.join(";")))
+    };
+    format!(
+        "<osm><changeset><tag k=\"created_by\" v=\"{CREATED_BY}\"/><tag k=\"comment\" v=\"{}\"/>{hashtags_tag}</changeset></osm>",
+        escape_xml(&changeset::full_comment(comment, hashtags))
+    )
+}
+
+/// The node with `edit` applied, as the OSM API wants it uploaded in `changeset`.
+pub fn node_xml(node: &Element, edit: &TagEdit, changeset: u64) -> String {
+    let mut tags = node.tags.clone();
+    for (key, value) in &edit.tags {
+        tags.insert(key.clone(), value.clone());
+    }
+    let tag_xml: String = tags
+        .iter()
+        .map(|(k, v)| format!("<tag k=\"{}\" v=\"{}\"/>", escape_xml(k), escape_xml(v)))
+        .collect();
+    format!(
+        "<osm><node id=\"{}\" version=\"{}\" changeset=\"{changeset}\" lat=\"{}\" lon=\"{}\">{tag_xml}</node></osm>",
+        node.id,
+        node.version.unwrap_or_default(),
+        node.lat,
+        node.lon
+    )
+}
+
+async fn api(method: Method, path: &str, body: Option<String>) -> Result<String> {
+    let token = osm_auth::token().ok_or(OsmEditError::NotLoggedIn)?;
+    let mut req = Request::new(&format!("{OSM_API}{path}"))
+        .method(method)
+        .header("Authorization", &format!("Bearer {token}"));
+    if let Some(body) = body {
+        req = req.header("Content-Type", "text/xml").body(body);
+    }
+    let res = req.send().await?;
+    if !res.ok() {
+        if res.status() == 401 {
+            osm_auth::logout();
+            return Err(OsmEditError::NotLoggedIn.into());
+        }
+        return Err(OsmEditError::Api(res.status()).into());
+    }
+    Ok(res.text().await?)
+}
+
+/// The node as it is on OpenStreetMap right now, not as Overpass last saw it.
+pub async fn fetch_node(id: i64) -> Result<Element> {
+    let res: ElementsResponse = Request::get(&format!("{OSM_API}/node/{id}.json")).send().await?.json().await?;
+    Ok(res.elements.into_iter().next().ok_or(OsmEditError::Missing)?)
+}
+
+/// Whether `edit` still applies to `node` as it is now: only on top of the
+/// version it was made against.
+pub fn check_version(edit: &TagEdit, node: &Element) -> Result<(), OsmEditError> {
+    let base = edit.base_version.ok_or(OsmEditError::UnknownVersion)?;
+    match node.version {
+        Some(version) if version == base => Ok(()),
+        Some(version) => Err(OsmEditError::Conflict(Conflict { version, tags: node.tags.clone() })),
+        None => Err(OsmEditError::Api(500)),
+    }
+}
+
+pub async fn upload_tags(edit: &TagEdit) -> Result<()> {
+    changeset::validate(&edit.comment, &edit.hashtags)?;
+    let node = fetch_node(edit.node_id).await?;
+    check_version(edit, &node)?;
+    let changeset: u64 = api(Method::PUT, "/changeset/create", Some(changeset_xml(&edit.comment, &edit.hashtags)))
+        .await?
+        .trim()
+        .parse()
+        .map_err(|_| OsmEditError::Api(500))?;
+    let uploaded = api(Method::PUT, &format!("/node/{}", node.id), Some(node_xml(&node, edit, changeset))).await;
+    // Close it either way so a failed upload doesn't leave an empty changeset open for an hour.
+    // Failing to close doesn't undo the upload, and the server closes it after that hour.
+    if let Err(e) = api(Method::PUT, &format!("/changeset/{changeset}/close"), None).await {
+        log::warn!("couldn't close changeset {changeset}: {e}");
+    }
+    uploaded.map(|_| ())
+}
+
+pub async fn upload_note(lat: f64, lon: f64, text: &str) -> Result<()> {
+    let text = String::from(js_sys::encode_uri_component(text));
+    api(Method::POST, &format!("/notes?lat={lat}&lon={lon}&text={text}"), None).await?;
+    Ok(())
+}
+
+pub async fn upload(upload: &Upload) -> Result<()> {
+    match upload {
+        Upload::Tags(edit) => upload_tags(edit).await,
+        Upload::Note { lat, lon, text } => upload_note(*lat, *lon, text).await,
+    }
+}
+
+fn as_conflict(e: &leptos::error::Error) -> Option<Conflict> {
+    match e.downcast_ref::<OsmEditError>() {
+        Some(OsmEditError::Conflict(conflict)) => Some(conflict.clone()),
+        _ => None,
+    }
+}
+
+fn online() -> bool {
+    window().map(|w| w.navigator().on_line()).unwrap_or(false)
+}
+
+pub async fn pending() -> Vec<PendingUpload> {
+    idb::get_all(QUEUE_STORE).await.unwrap_or_else(|e| {
+        log::warn!("couldn't read pending uploads: {e}");
+        Vec::new()
+    })
+}
+
+/// Upload now, or queue it if we're offline.
+pub async fn submit(mut upload: Upload) -> Result<Submitted> {
+    if osm_auth::token().is_none() {
+        return Err(OsmEditError::NotLoggedIn.into());
+    }
+    // Don't queue something that's going to be rejected anyway.
+    if let Upload::Tags(edit) = &mut upload {
+        changeset::validate(&edit.comment, &edit.hashtags)?;
+        // Results fetched without metadata don't say which version was shown; the
+        // one on OpenStreetMap now is the closest, and edits can't go up without one.
+        if edit.base_version.is_none() {
+            if !online() {
+                return Err(OsmEditError::UnknownVersion.into());
+            }
+            edit.base_version = fetch_node(edit.node_id).await?.version;
+        }
+    }
+    if !online() {
+        idb::put(QUEUE_STORE, &PendingUpload::new(upload)).await?;
+        return Ok(Submitted::Queued);
+    }
+    match self::upload(&upload).await {
+        Ok(()) => Ok(Submitted::Uploaded),
+        Err(e) => match as_conflict(&e) {
+            Some(conflict) => {
+                let pending = PendingUpload { conflict: Some(conflict), ..PendingUpload::new(upload) };
+                idb::put(QUEUE_STORE, &pending).await?;
+                Ok(Submitted::Conflicted)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Keep the edit, now based on the conflicting version, and try it again.
+pub async fn rebase(mut pending: PendingUpload) -> Result<()> {
+    let Some(conflict) = pending.conflict.take() else {
+        return Ok(());
+    };
+    if let Upload::Tags(edit) = &mut pending.upload {
+        edit.base_version = Some(conflict.version);
+    }
+    idb::put(QUEUE_STORE, &pending).await?;
+    replay().await;
+    Ok(())
+}
+
+pub async fn abandon(pending: &PendingUpload) -> Result<()> {
+    Ok(idb::delete(QUEUE_STORE, &pending.id).await?)
+}
+
+/// Edits queued before they had to name a base version, set against the node
+/// as it is now, so the user checks them before they go up.
+async fn unknown_base_conflict(upload: &Upload) -> Option<Conflict> {
+    let Upload::Tags(edit) = upload else {
+        return None;
+    };
+    if edit.base_version.is_some() {
+        return None;
+    }
+    let node = fetch_node(edit.node_id).await.ok()?;
+    Some(Conflict { version: node.version?, tags: node.tags })
+}
+
+/// Lets one replay run at a time. Asking for another while it runs sends it
+/// round once more instead, so edits queued meanwhile aren't missed.
+#[derive(Debug, Default)]
+struct ReplayGuard {
+    running: bool,
+    again: bool,
+}
+
+impl ReplayGuard {
+    /// Whether the caller gets to run it.
+    fn start(&mut self) -> bool {
+        self.again = self.running;
+        !std::mem::replace(&mut self.running, true)
+    }
+
+    /// Whether to go round again; otherwise it's finished.
+    fn finish(&mut self) -> bool {
+        self.running = std::mem::take(&mut self.again);
+        self.running
+    }
+}
+
+thread_local! {
+    static REPLAY: RefCell<ReplayGuard> = RefCell::new(ReplayGuard::default());
+}
+
+/// Upload queued edits in order. Conflicts get set aside for the user; anything
+/// else that fails stays queued for next time. Only one runs at a time, so
+/// nothing goes up twice.
+pub async fn replay() {
+    if !REPLAY.with(|r| r.borrow_mut().start()) {
+        return;
+    }
+    loop {
+        replay_queue().await;
+        if !REPLAY.with(|r| r.borrow_mut().finish()) {
+            break;
+        }
+    }
+}
+
+async fn replay_queue() {
+    if !online() || osm_auth::token().is_none() {
+        return;
+    }
+    for mut pending in pending().await.into_iter().filter(|p| p.conflict.is_none()) {
+        if let Some(conflict) = unknown_base_conflict(&pending.upload).await {
+            pending.conflict = Some(conflict);
+            if let Err(e) = idb::put(QUEUE_STORE, &pending).await {
+                log::warn!("couldn't update pending uploads: {e}");
+            }
+            continue;
+        }
+        let res = match upload(&pending.upload).await {
+            Ok(()) => idb::delete(QUEUE_STORE, &pending.id).await,
+            Err(e) => match as_conflict(&e) {
+                Some(conflict) => {
+                    pending.conflict = Some(conflict);
+                    idb::put(QUEUE_STORE, &pending).await
+                }
+                None => {
+                    log::warn!("queued upload {} failed: {e}", pending.id);
+                    Ok(())
+                }
+            },
+        };
+        if let Err(e) = res {
+            log::warn!("couldn't update pending uploads: {e}");
+        }
+    }
+}
+
+/// The offline queue as a signal in context, for the pending-uploads indicator.
+#[derive(Clone, Copy)]
+pub struct PendingUploads(pub RwSignal<Vec<PendingUpload>>);
+
+impl PendingUploads {
+    pub fn refresh(self) {
+        spawn_local(async move { self.0.set(pending().await) });
+    }
+
+    /// Replay the queue, then refresh.
+    pub fn replay(self) {
+        spawn_local(async move {
+            replay().await;
+            self.0.set(pending().await);
+        });
+    }
+}
+
+pub fn provide_pending_uploads(cx: Scope) -> PendingUploads {
+    let pending = PendingUploads(create_rw_signal(cx, Vec::new()));
+    provide_context(cx, pending);
+    pending.replay();
+    window_event_listener(ev::online, move |_| pending.replay());
+    pending
+}
+
+pub fn use_pending_uploads(cx: Scope) -> PendingUploads {
+    use_context(cx).unwrap_or_else(|| PendingUploads(create_rw_signal(cx, Vec::new())))
+}
+
+#[component]
+fn ConflictView(cx: Scope, pending: PendingUpload) -> impl IntoView {
+    let uploads = use_pending_uploads(cx);
+    let (Some(conflict), Upload::Tags(edit)) = (&pending.conflict, &pending.upload) else {
+        return ().into_view(cx);
+    };
+    let rows = diff(conflict, edit)
+        .into_iter()
+        .map(|change| view! { cx,
+            <tr class:same=change.already_applied()>
+                <td>{change.key}</td>
+                <td>{change.current.unwrap_or_else(|| "(not set)".to_string())}</td>
+                <td>{change.intended}</td>
+            </tr>
+        })
+        .collect_view(cx);
+    let node_id = edit.node_id;
+    let (error, set_error) = create_signal(cx, None::<String>);
+    let resolve = move |rebase: bool| {
+        let pending = pending.clone();
+        spawn_local(async move {
+            let res = if rebase { self::rebase(pending).await } else { abandon(&pending).await };
+            match res {
+                Ok(()) => uploads.refresh(),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    };
+    let rebase = resolve.clone();
+    view! { cx,
+        <div class="conflict">
+            <a href={format!("https://www.openstreetmap.org/node/{node_id}/history")} target="_blank">
+                {format!("OSM:{node_id}")}
+            </a>
+            <table>
+                <thead><tr><th>"Tag"</th><th>"On OpenStreetMap now"</th><th>"Your edit"</th></tr></thead>
+                <tbody>{rows}</tbody>
+            </table>
+            <button on:click=move |_| rebase(true)>"Apply my edit on top"</button>
+            <button on:click=move |_| resolve(false)>"Abandon my edit"</button>
+            {move || error.get().map(|e| view! { cx, <p class="error">{e}</p> })}
+        </div>
+    }
+    .into_view(cx)
+}
+
+#[component]
+pub fn PendingIndicator(cx: Scope) -> impl IntoView {
+    let pending = use_pending_uploads(cx);
+    move || {
+        pending.0.with(|p| {
+            if p.is_empty() {
+                return ().into_view(cx);
+            }
+            let conflicts: Vec<PendingUpload> = p.iter().filter(|p| p.conflict.is_some()).cloned().collect();
+            let mut label = format!("⬆ {} pending upload{}", p.len(), if p.len() == 1 { "" } else { "s" });
+            if conflicts.is_empty() {
+                return view! { cx, <span class="pending-uploads">{label}</span> }.into_view(cx);
+            }
+            label.push_str(&format!(" ({} need attention)", conflicts.len()));
+            view! { cx,
+                <details class="pending-uploads">
+                    <summary>{label}</summary>
+                    {conflicts.into_iter().map(|pending| view! { cx, <ConflictView pending/> }).collect_view(cx)}
+                </details>
+            }
+            .into_view(cx)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_one_at_a_time() {
+        let mut guard = ReplayGuard::default();
+        assert!(guard.start());
+        assert!(!guard.start());
+        assert!(!guard.start());
+        // The two asked for meanwhile make one more round.
+        assert!(guard.finish());
+        assert!(!guard.finish());
+        assert!(guard.start());
+        assert!(!guard.finish());
+    }
+
+    #[test]
+    fn node_xml_merges_tags() {
+        let node = Element {
+            id: 42,
+            lat: 1.5,
+            lon: 2.5,
+            version: Some(3),
+            tags: [("amenity".to_string(), "toilets".to_string()), ("fee".to_string(), "yes".to_string())].into(),
+            ..Default::default()
+        };
+        let edit = TagEdit {
+            node_id: 42,
+            base_version: Some(3),
+            tags: vec![("fee".to_string(), "no".to_string()), ("name".to_string(), "A & B".to_string())],
+            comment: "Add fee".to_string(),
+            hashtags: Vec::new(),
+        };
+        assert_eq!(
+            node_xml(&node, &edit, 99),
+            "<osm><node id=\"42\" version=\"3\" changeset=\"99\" lat=\"1.5\" lon=\"2.5\">\
+             <tag k=\"amenity\" v=\"toilets\"/><tag k=\"fee\" v=\"no\"/><tag k=\"name\" v=\"A &amp; B\"/>\
+             </node></osm>"
+        );
+    }
+
+    #[test]
+    fn uploads_only_on_top_of_the_edited_version() {
+        let node = Element { id: 42, version: Some(4), tags: [("fee".to_string(), "no".to_string())].into(), ..Default::default() };
+        let edit = |base_version| TagEdit { node_id: 42, base_version, tags: Vec::new(), comment: String::new(), hashtags: Vec::new() };
+        assert!(check_version(&edit(Some(4)), &node).is_ok());
+        assert!(matches!(check_version(&edit(Some(3)), &node), Err(OsmEditError::Conflict(Conflict { version: 4, .. }))));
+        assert!(matches!(check_version(&edit(None), &node), Err(OsmEditError::UnknownVersion)));
+    }
+
+    #[test]
+    fn changeset_escapes_comment() {
+        assert!(changeset_xml("fee \"no\"", &[]).contains("v=\"fee &quot;no&quot;\""));
+        let xml = changeset_xml("Add fee", &["#a".to_string(), "#b".to_string()]);
+        assert!(xml.contains("<tag k=\"comment\" v=\"Add fee #a #b\"/><tag k=\"hashtags\" v=\"#a;#b\"/>"));
+    }
+
+    #[test]
+    fn diff_against_conflicting_version() {
+        let conflict = Conflict {
+            version: 4,
+            tags: [("fee".to_string(), "no".to_string()), ("wheelchair".to_string(), "no".to_string())].into(),
+        };
+        let edit = TagEdit {
+            node_id: 42,
+            base_version: Some(3),
+            tags: vec![
+                ("fee".to_string(), "no".to_string()),
+                ("wheelchair".to_string(), "yes".to_string()),
+                ("name".to_string(), "Kiosk".to_string()),
+            ],
+            comment: String::new(),
+            hashtags: Vec::new(),
+        };
+        let changes = diff(&conflict, &edit);
+        let summary: Vec<(&str, Option<&str>, bool)> = changes
+            .iter()
+            .map(|c| (c.key.as_str(), c.current.as_deref(), c.already_applied()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("fee", Some("no"), true), ("wheelchair", Some("no"), false), ("name", None, false)]
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! Links into openstreetmap.org for an element: its page, editing it in iD,
+//! and leaving a note for mappers at its location when something's wrong
+//! but the user would rather not edit. Ways and relations are placed at
+//! their center, like everywhere else in the app.
+
+use crate::Element;
+
+const OSM_URL: &str = "https://www.openstreetmap.org";
+/// Close enough to show the single building.
+const NOTE_ZOOM: u8 = 19;
+
+/// The element's page, with its tags and history.
+pub fn view_url(element: &Element) -> String {
+    format!("{OSM_URL}/{}/{}", element.type_field, element.id)
+}
+
+/// Open the element in the iD editor.
+pub fn edit_url(element: &Element) -> String {
+    format!("{OSM_URL}/edit?editor=id&{}={}", element.type_field, element.id)
+}
+
+/// The "add a note" form with the marker on the element. The website has no
+/// way to fill in the text, so the user describes the problem there.
+pub fn note_url(element: &Element) -> String {
+    let (lat, lon) = (element.lat, element.lon);
+    format!("{OSM_URL}/note/new?lat={lat:.6}&lon={lon:.6}#map={NOTE_ZOOM}/{lat:.6}/{lon:.6}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OsmType;
+
+    #[test]
+    fn links_nodes_and_ways() {
+        let node = Element { id: 7, lat: 52.52, lon: 13.405, ..Default::default() };
+        assert_eq!(view_url(&node), "https://www.openstreetmap.org/node/7");
+        assert_eq!(edit_url(&node), "https://www.openstreetmap.org/edit?editor=id&node=7");
+        assert_eq!(
+            note_url(&node),
+            "https://www.openstreetmap.org/note/new?lat=52.520000&lon=13.405000#map=19/52.520000/13.405000"
+        );
+        let way = Element { type_field: OsmType::Way, id: 42, lat: -33.8568, lon: 151.2153, ..Default::default() };
+        assert_eq!(view_url(&way), "https://www.openstreetmap.org/way/42");
+        assert_eq!(edit_url(&way), "https://www.openstreetmap.org/edit?editor=id&way=42");
+        assert!(note_url(&way).starts_with("https://www.openstreetmap.org/note/new?lat=-33.856800&lon=151.215300"));
+    }
+}
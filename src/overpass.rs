@@ -0,0 +1,280 @@
+//! Overpass QL, built from types instead of format strings. Every query the app
+//! sends is rendered here (pack queries by way of [`crate::query_template`]), so
+//! quoting and statement syntax only have to be right once.
+
+use crate::geo::BBox;
+use crate::OsmType;
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Where to look.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Area {
+    /// Within `radius_m` meters of a point, or of a line through several.
+    Around { radius_m: i64, points: Vec<(f64, f64)> },
+    BBox(BBox),
+    /// Left open, to be filled in later; see [`QueryTemplate`](crate::query_template::QueryTemplate).
+    Slot,
+}
+
+/// Marks [`Area::Slot`] in a rendered query. Can't appear in a real query.
+pub const SLOT: &str = "\u{0}AREA\u{0}";
+
+impl Area {
+    pub fn around(radius_m: i64, point: (f64, f64)) -> Area {
+        Area::Around { radius_m, points: vec![point] }
+    }
+
+    /// Around a polyline, with coordinates rounded to about a meter to keep long lines short.
+    pub fn around_line(radius_m: i64, line: &[(f64, f64)]) -> Area {
+        let round = |x: f64| (x * 1e5).round() / 1e5;
+        Area::Around {
+            radius_m,
+            points: line.iter().map(|&(lat, lon)| (round(lat), round(lon))).collect(),
+        }
+    }
+}
+
+/// `around:1000,52.5,13.4` or `52.4,13.3,52.6,13.5`.
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Area::Around { radius_m, points } => {
+                write!(f, "around:{radius_m}")?;
+                for (lat, lon) in points {
+                    write!(f, ",{lat},{lon}")?;
+                }
+                Ok(())
+            }
+            Area::BBox(b) => write!(f, "{},{},{},{}", b.south, b.west, b.north, b.east),
+            Area::Slot => f.write_str(SLOT),
+        }
+    }
+}
+
+/// One `node["k"="v"](area)` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    /// `None` for any type (`nwr`).
+    element: Option<OsmType>,
+    tags: Vec<(String, String)>,
+    area: Option<Area>,
+}
+
+impl Select {
+    pub fn new(element: Option<OsmType>) -> Select {
+        Select { element, tags: Vec::new(), area: None }
+    }
+
+    pub fn ways() -> Select {
+        Select::new(Some(OsmType::Way))
+    }
+
+    /// Nodes, ways and relations.
+    pub fn any() -> Select {
+        Select::new(None)
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Select {
+        self.tags.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn within(mut self, area: Area) -> Select {
+        self.area = Some(area);
+        self
+    }
+
+    fn render(&self, out: &mut String) {
+        out.push_str(self.element.map_or("nwr", |e| e.as_str()));
+        for (key, value) in &self.tags {
+            out.push_str(&format!("[\"{}\"=\"{}\"]", escape(key), escape(value)));
+        }
+        if let Some(area) = &self.area {
+            out.push_str(&format!("({area})"));
+        }
+        out.push(';');
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// How much of each element to return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Output {
+    /// Ids and coordinates.
+    Skel,
+    /// Tags and coordinates plus version and last-edit metadata.
+    #[default]
+    Meta,
+    /// Just how many elements matched.
+    Count,
+}
+
+impl Output {
+    fn keyword(&self) -> &'static str {
+        match self {
+            Output::Skel => "skel",
+            Output::Meta => "meta",
+            Output::Count => "count",
+        }
+    }
+}
+
+/// A whole query: the union of its selections, optionally recursed down to
+/// way nodes, then printed. Always asks for JSON.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QueryBuilder {
+    timeout_s: Option<u32>,
+    selects: Vec<Select>,
+    way_nodes: bool,
+    output: Output,
+    center: bool,
+    limit: Option<usize>,
+}
+
+impl QueryBuilder {
+    pub fn new() -> QueryBuilder {
+        QueryBuilder::default()
+    }
+
+    pub fn timeout(mut self, seconds: u32) -> QueryBuilder {
+        self.timeout_s = Some(seconds);
+        self
+    }
+
+    pub fn select(mut self, select: Select) -> QueryBuilder {
+        self.selects.push(select);
+        self
+    }
+
+    /// Replace the selected ways by their nodes (`node(w)`), which come with coordinates.
+    pub fn way_nodes(mut self) -> QueryBuilder {
+        self.way_nodes = true;
+        self
+    }
+
+    pub fn output(mut self, output: Output) -> QueryBuilder {
+        self.output = output;
+        self
+    }
+
+    /// Give ways and relations a `center` to place them at.
+    pub fn center(mut self) -> QueryBuilder {
+        self.center = true;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> QueryBuilder {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The same query printing only how many elements it matches, to size up
+    /// a search before running it. See [`CountResponse`].
+    pub fn count_only(&self) -> QueryBuilder {
+        QueryBuilder { output: Output::Count, center: false, limit: None, ..self.clone() }
+    }
+
+    pub fn build(&self) -> String {
+        let mut query = String::from("[out:json]");
+        if let Some(timeout) = self.timeout_s {
+            query.push_str(&format!("[timeout:{timeout}]"));
+        }
+        query.push(';');
+        match &self.selects[..] {
+            [select] => select.render(&mut query),
+            selects => {
+                query.push('(');
+                selects.iter().for_each(|s| s.render(&mut query));
+                query.push_str(");");
+            }
+        }
+        if self.way_nodes {
+            query.push_str("node(w);");
+        }
+        query.push_str("out ");
+        query.push_str(self.output.keyword());
+        if self.center {
+            query.push_str(" center");
+        }
+        if let Some(limit) = self.limit {
+            query.push_str(&format!(" {limit}"));
+        }
+        query.push(';');
+        query
+    }
+}
+
+/// The answer to an `out count` query: one `count` element whose tags hold
+/// the totals, as strings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CountResponse {
+    elements: Vec<CountElement>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CountElement {
+    tags: HashMap<String, String>,
+}
+
+impl CountResponse {
+    pub fn total(&self) -> Option<u64> {
+        self.elements.first()?.tags.get("total")?.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions_selections() {
+        let query = QueryBuilder::new()
+            .select(Select::new(Some(OsmType::Node)).tag("amenity", "toilets").within(Area::around(1000, (1.0, 2.0))))
+            .select(Select::new(Some(OsmType::Node)).tag("toilets", "yes").within(Area::around(1000, (1.0, 2.0))))
+            .build();
+        assert_eq!(
+            query,
+            "[out:json];(node[\"amenity\"=\"toilets\"](around:1000,1,2);node[\"toilets\"=\"yes\"](around:1000,1,2););out meta;"
+        );
+    }
+
+    #[test]
+    fn single_selections_with_options() {
+        let bbox = BBox { south: 1.0, west: 2.0, north: 3.0, east: 4.5 };
+        let query = QueryBuilder::new()
+            .timeout(10)
+            .select(Select::ways().tag("highway", "steps").within(Area::BBox(bbox)))
+            .way_nodes()
+            .output(Output::Skel)
+            .limit(1)
+            .build();
+        assert_eq!(query, "[out:json][timeout:10];way[\"highway\"=\"steps\"](1,2,3,4.5);node(w);out skel 1;");
+    }
+
+    #[test]
+    fn lines_are_rounded_and_values_escaped() {
+        let area = Area::around_line(5, &[(52.123456, 13.4), (52.2, 13.654321)]);
+        assert_eq!(area.to_string(), "around:5,52.12346,13.4,52.2,13.65432");
+        let query = QueryBuilder::new().select(Select::any().tag("name", "Joe's \"Loo\"")).center().build();
+        assert_eq!(query, "[out:json];nwr[\"name\"=\"Joe's \\\"Loo\\\"\"];out meta center;");
+    }
+
+    #[test]
+    fn counts_instead_of_printing() {
+        let query = QueryBuilder::new()
+            .timeout(25)
+            .select(Select::any().tag("amenity", "bench").within(Area::around(5000, (1.0, 2.0))))
+            .center()
+            .limit(10)
+            .count_only()
+            .build();
+        assert_eq!(query, "[out:json][timeout:25];nwr[\"amenity\"=\"bench\"](around:5000,1,2);out count;");
+        let json = r#"{"elements":[{"type":"count","id":0,"tags":{"nodes":"2712","ways":"4","relations":"0","total":"2716"}}]}"#;
+        assert_eq!(serde_json::from_str::<CountResponse>(json).unwrap().total(), Some(2716));
+    }
+}
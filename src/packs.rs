@@ -0,0 +1,302 @@
+//! Amenity packs: presets bundling which features to query, how to show them,
+//! and a sensible default radius for who they're aimed at.
+
+use crate::overpass::{Area, QueryBuilder, Select};
+use crate::query_template;
+use crate::tags::Tags;
+use crate::vocabulary::{self, BUILDING_TOILETS, TOILETS_YES};
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum AmenityPack {
+    /// Public toilets, for city users.
+    #[default]
+    Toilets,
+    /// Toilets plus campsites and shelters, for hikers.
+    Outdoors,
+    /// Toilets plus fuel stations and motorway rest areas, for drivers.
+    RoadTrip,
+    /// Fountains and taps to refill a bottle at.
+    DrinkingWater,
+    /// Somewhere to sit down.
+    Benches,
+    Atms,
+    Pharmacies,
+}
+
+impl AmenityPack {
+    pub const ALL: [AmenityPack; 7] = [
+        AmenityPack::Toilets,
+        AmenityPack::Outdoors,
+        AmenityPack::RoadTrip,
+        AmenityPack::DrinkingWater,
+        AmenityPack::Benches,
+        AmenityPack::Atms,
+        AmenityPack::Pharmacies,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AmenityPack::Toilets => "Toilets",
+            AmenityPack::Outdoors => "Outdoors",
+            AmenityPack::RoadTrip => "Road trip",
+            AmenityPack::DrinkingWater => "Drinking water",
+            AmenityPack::Benches => "Benches",
+            AmenityPack::Atms => "ATMs",
+            AmenityPack::Pharmacies => "Pharmacies",
+        }
+    }
+
+    /// For links: `amenity=drinking_water`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            AmenityPack::Toilets => "toilets",
+            AmenityPack::Outdoors => "outdoors",
+            AmenityPack::RoadTrip => "road_trip",
+            AmenityPack::DrinkingWater => "drinking_water",
+            AmenityPack::Benches => "bench",
+            AmenityPack::Atms => "atm",
+            AmenityPack::Pharmacies => "pharmacy",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<AmenityPack> {
+        AmenityPack::ALL.into_iter().find(|p| p.key() == key)
+    }
+
+    pub fn from_label(label: &str) -> AmenityPack {
+        AmenityPack::ALL
+            .into_iter()
+            .find(|p| p.label() == label)
+            .unwrap_or_default()
+    }
+
+    /// `(key, value)` tag filters, any of which makes an element part of the pack.
+    pub fn filters(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            AmenityPack::Toilets => &[("amenity", "toilets")],
+            AmenityPack::Outdoors => &[
+                ("amenity", "toilets"),
+                ("tourism", "camp_site"),
+                ("amenity", "shelter"),
+            ],
+            AmenityPack::RoadTrip => &[
+                ("amenity", "toilets"),
+                ("amenity", "fuel"),
+                ("highway", "rest_area"),
+                ("highway", "services"),
+            ],
+            AmenityPack::DrinkingWater => &[("amenity", "drinking_water")],
+            AmenityPack::Benches => &[("amenity", "bench")],
+            AmenityPack::Atms => &[("amenity", "atm")],
+            AmenityPack::Pharmacies => &[("amenity", "pharmacy")],
+        }
+    }
+
+    /// Outdoors facilities are sparse and drivers cover ground fast, so search further by default,
+    /// though road trips stay under [`SPLIT_AREA_KM2`](crate::cost::SPLIT_AREA_KM2) so they run in one query.
+    /// Benches are everywhere in towns, pharmacies are worth a longer walk.
+    pub fn default_radius(&self) -> i64 {
+        match self {
+            AmenityPack::Toilets | AmenityPack::DrinkingWater | AmenityPack::Atms => 1000,
+            AmenityPack::Outdoors => 5000,
+            AmenityPack::RoadTrip => 10_000,
+            AmenityPack::Benches => 500,
+            AmenityPack::Pharmacies => 2000,
+        }
+    }
+
+    /// Heading for the results column of [`badges`](crate::badges), which
+    /// describe toilets for most packs.
+    pub fn details_heading(&self) -> &'static str {
+        match self {
+            AmenityPack::Toilets | AmenityPack::Outdoors | AmenityPack::RoadTrip => "Facilities",
+            AmenityPack::DrinkingWater | AmenityPack::Benches | AmenityPack::Atms | AmenityPack::Pharmacies => "Details",
+        }
+    }
+
+    /// Tag values used to look up densities for cost estimates.
+    pub fn density_keys(&self) -> Vec<&'static str> {
+        self.filters().iter().map(|(_, value)| *value).collect()
+    }
+
+    /// How much of the city [density](crate::cost::density_per_km2) to expect.
+    /// Road trips mostly cover the country between towns, where there's far less.
+    pub fn density_factor(&self) -> f64 {
+        match self {
+            AmenityPack::RoadTrip => 0.1,
+            _ => 1.0,
+        }
+    }
+
+    /// The pack's filters plus, for packs with toilets, `toilets=yes` on other POIs
+    /// and any regional toilet patterns for a search at `origin`.
+    pub fn filters_at(&self, origin: (f64, f64)) -> Vec<(&'static str, &'static str)> {
+        self.filters_in(vocabulary::regions_at(origin))
+    }
+
+    /// Like [`AmenityPack::filters_at`], for a [`vocabulary::regions_at`] set.
+    pub fn filters_in(&self, regions: u32) -> Vec<(&'static str, &'static str)> {
+        let mut filters = self.filters().to_vec();
+        if filters.contains(&("amenity", "toilets")) {
+            for pattern in std::iter::once(TOILETS_YES).chain(vocabulary::toilet_patterns_in(regions)) {
+                if !filters.contains(&pattern) {
+                    filters.push(pattern);
+                }
+            }
+        }
+        filters
+    }
+
+    /// Overpass QL for the union of the pack's filters within `area` around
+    /// `origin`. Asks for metadata so results carry their last-edit time.
+    pub fn query(&self, origin: (f64, f64), area: &Area) -> String {
+        query_template::interned(*self, vocabulary::regions_at(origin)).render(area)
+    }
+
+    /// How many elements [`AmenityPack::query`] would return, for a cheap
+    /// look before a search that may be too big.
+    pub fn count_query(&self, origin: (f64, f64), area: &Area) -> String {
+        self.filters_at(origin)
+            .into_iter()
+            .fold(QueryBuilder::new().timeout(25), |q, (key, value)| q.select(Select::any().tag(key, value).within(area.clone())))
+            .count_only()
+            .build()
+    }
+}
+
+/// Whether an element is a toilet itself or some other place that happens to have one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultClass {
+    Primary,
+    /// A station, park, supermarket, ... tagged `toilets=yes`.
+    Secondary,
+}
+
+fn has_tag(tags: &Tags, (key, value): (&str, &str)) -> bool {
+    tags.get(key) == Some(value)
+}
+
+pub fn classify(tags: &Tags) -> ResultClass {
+    let is_amenity = has_tag(tags, BUILDING_TOILETS)
+        || AmenityPack::ALL.iter().flat_map(AmenityPack::filters).any(|&filter| has_tag(tags, filter));
+    if has_tag(tags, TOILETS_YES) && !is_amenity {
+        ResultClass::Secondary
+    } else {
+        ResultClass::Primary
+    }
+}
+
+/// Icon for an element based on what it is.
+pub fn icon(tags: &Tags) -> &'static str {
+    if has_tag(tags, ("tourism", "camp_site")) {
+        "⛺"
+    } else if has_tag(tags, ("amenity", "shelter")) {
+        "🛖"
+    } else if has_tag(tags, ("amenity", "fuel")) {
+        "⛽"
+    } else if has_tag(tags, ("highway", "rest_area")) || has_tag(tags, ("highway", "services")) {
+        "🅿️"
+    } else if has_tag(tags, ("amenity", "drinking_water")) {
+        "🚰"
+    } else if has_tag(tags, ("amenity", "bench")) {
+        "🪑"
+    } else if has_tag(tags, ("amenity", "atm")) {
+        "🏧"
+    } else if has_tag(tags, ("amenity", "pharmacy")) {
+        "💊"
+    } else if classify(tags) == ResultClass::Secondary {
+        "🚾"
+    } else {
+        "🚻"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost;
+    use crate::tags::tests::tags;
+
+    #[test]
+    fn toilets_query() {
+        assert_eq!(
+            AmenityPack::Toilets.query((1.0, 2.0), &Area::around(1000, (1.0, 2.0))),
+            "[out:json];(nwr[\"amenity\"=\"toilets\"](around:1000,1,2);nwr[\"toilets\"=\"yes\"](around:1000,1,2););out meta center;"
+        );
+    }
+
+    #[test]
+    fn outdoors_union() {
+        let query = AmenityPack::Outdoors.query((2.0, 3.0), &Area::BBox(crate::geo::BBox { south: 1.0, west: 2.0, north: 3.0, east: 4.0 }));
+        assert_eq!(query.matches("(1,2,3,4);").count(), 4);
+        assert!(query.contains("nwr[\"tourism\"=\"camp_site\"]"));
+        assert!(AmenityPack::Outdoors.default_radius() > AmenityPack::Toilets.default_radius());
+    }
+
+    #[test]
+    fn regional_patterns_extend_toilet_searches() {
+        let london = (51.507, -0.128);
+        assert!(AmenityPack::Toilets.filters_at(london).contains(&("building", "toilets")));
+        assert!(AmenityPack::Outdoors.filters_at(london).contains(&("building", "toilets")));
+        assert_eq!(
+            AmenityPack::Toilets.filters_at((40.71, -74.0)),
+            vec![("amenity", "toilets"), TOILETS_YES]
+        );
+        // Japan's regional toilets=yes isn't added twice.
+        assert_eq!(AmenityPack::Toilets.filters_at((35.68, 139.77)).len(), 3);
+    }
+
+    #[test]
+    fn toilets_yes_on_other_pois_is_secondary() {
+        let station = tags(&[("railway", "station"), ("toilets", "yes")]);
+        assert_eq!(classify(&station), ResultClass::Secondary);
+        assert_eq!(icon(&station), "🚾");
+        let toilet = tags(&[("amenity", "toilets"), ("toilets", "yes")]);
+        assert_eq!(classify(&toilet), ResultClass::Primary);
+        let campsite = tags(&[("tourism", "camp_site"), ("toilets", "yes")]);
+        assert_eq!(classify(&campsite), ResultClass::Primary);
+        assert_eq!(icon(&campsite), "⛺");
+    }
+
+    #[test]
+    fn road_trips_look_for_fuel_and_rest_areas() {
+        let radius = AmenityPack::RoadTrip.default_radius();
+        let query = AmenityPack::RoadTrip.query((2.0, 3.0), &Area::around(radius, (2.0, 3.0)));
+        assert!(query.contains("nwr[\"amenity\"=\"fuel\"]"));
+        assert!(query.contains("nwr[\"highway\"=\"rest_area\"]"));
+        let fuel = tags(&[("amenity", "fuel"), ("toilets", "yes")]);
+        assert_eq!(classify(&fuel), ResultClass::Primary);
+        assert_eq!(icon(&fuel), "⛽");
+    }
+
+    #[test]
+    fn default_radii_need_no_count_first() {
+        for pack in AmenityPack::ALL {
+            let area = cost::circle_area_km2(pack.default_radius() as f64);
+            let cost = cost::estimate_query_cost(area, &pack.density_keys(), pack.density_factor());
+            assert_eq!(cost.level, cost::CostLevel::Cheap, "{pack:?}");
+        }
+    }
+
+    #[test]
+    fn count_queries_match_the_search() {
+        let area = Area::around(5000, (2.0, 3.0));
+        let count = AmenityPack::Outdoors.count_query((2.0, 3.0), &area);
+        let search = AmenityPack::Outdoors.query((2.0, 3.0), &area);
+        assert!(count.ends_with(");out count;"));
+        assert_eq!(count.split_once(';').unwrap().1.trim_end_matches("out count;"), search.split_once(';').unwrap().1.trim_end_matches("out meta center;"));
+    }
+
+    #[test]
+    fn single_amenity_packs() {
+        let query = AmenityPack::DrinkingWater.query((2.0, 3.0), &Area::around(1000, (2.0, 3.0)));
+        assert_eq!(query, "[out:json];nwr[\"amenity\"=\"drinking_water\"](around:1000,2,3);out meta center;");
+        // No toilets in the pack, so no toilets=yes or regional patterns either.
+        assert_eq!(AmenityPack::Benches.filters_at((51.507, -0.128)), [("amenity", "bench")]);
+        assert_eq!(AmenityPack::from_label("ATMs"), AmenityPack::Atms);
+        let pharmacy = tags(&[("amenity", "pharmacy"), ("toilets", "yes")]);
+        assert_eq!(classify(&pharmacy), ResultClass::Primary);
+        assert_eq!(icon(&pharmacy), "💊");
+    }
+}
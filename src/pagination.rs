@@ -0,0 +1,75 @@
+//! Results a page at a time, so dense cities with hundreds of places don't
+//! make the table sluggish on phones. The page size is a setting; 0 shows
+//! everything (still [rendered in chunks](crate::chunked)).
+
+use leptos::*;
+use std::ops::Range;
+
+/// Offered in settings; 0 is "all".
+pub const PAGE_SIZES: [usize; 4] = [25, 50, 100, 0];
+
+pub fn page_count(len: usize, size: usize) -> usize {
+    match size {
+        0 => 1,
+        size => len.div_ceil(size).max(1),
+    }
+}
+
+/// The rows on `page`, the last page if it's past the end.
+pub fn page_range(len: usize, size: usize, page: usize) -> Range<usize> {
+    if size == 0 {
+        return 0..len;
+    }
+    let page = page.min(page_count(len, size) - 1);
+    (page * size).min(len)..((page + 1) * size).min(len)
+}
+
+/// `"Showing 51–100 of 312"`.
+pub fn summary(range: &Range<usize>, len: usize) -> String {
+    match len {
+        0 => String::new(),
+        len if range.len() == len => format!("Showing all {len}"),
+        len => format!("Showing {}–{} of {len}", range.start + 1, range.end),
+    }
+}
+
+/// Previous/next buttons and where in the results the page is.
+#[component]
+pub fn Pager(cx: Scope, page: RwSignal<usize>, len: Signal<usize>, size: Signal<usize>) -> impl IntoView {
+    let pages = move || page_count(len.get(), size.get());
+    let current = move || page.get().min(pages() - 1);
+    view! { cx,
+        <div class="pager">
+            <span>{move || summary(&page_range(len.get(), size.get(), page.get()), len.get())}</span>
+            <Show when=move || { pages() > 1 } fallback=|_| ()>
+                " "
+                <button prop:disabled=move || current() == 0 on:click=move |_| page.set(current().saturating_sub(1))>"‹ Previous"</button>
+                {move || format!(" Page {} of {} ", current() + 1, pages())}
+                <button prop:disabled=move || current() + 1 >= pages() on:click=move |_| page.set(current() + 1)>"Next ›"</button>
+            </Show>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_through_the_results() {
+        assert_eq!(page_count(312, 50), 7);
+        assert_eq!(page_count(0, 50), 1);
+        assert_eq!(page_range(312, 50, 1), 50..100);
+        assert_eq!(page_range(312, 50, 6), 300..312);
+        assert_eq!(page_range(312, 50, 9), 300..312);
+        assert_eq!(page_range(312, 0, 3), 0..312);
+        assert_eq!(page_range(0, 50, 0), 0..0);
+    }
+
+    #[test]
+    fn summarizes_the_page() {
+        assert_eq!(summary(&(50..100), 312), "Showing 51–100 of 312");
+        assert_eq!(summary(&(0..12), 12), "Showing all 12");
+        assert_eq!(summary(&(0..0), 0), "");
+    }
+}
@@ -0,0 +1,123 @@
+//! Startup latency marks, from geolocation to the first painted row. Each mark
+//! is recorded once per page load, as a `performance.mark` (so it shows up in
+//! browser profiles) and in a list for the debug panel.
+
+use serde_derive::Serialize;
+use std::cell::RefCell;
+
+/// Time to the first painted row we try to stay under.
+pub const FIRST_RESULT_BUDGET_MS: f64 = 4000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mark {
+    GeolocationAcquired,
+    FetchStart,
+    FirstByte,
+    ParseDone,
+    FirstRowPainted,
+}
+
+impl Mark {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mark::GeolocationAcquired => "free2pee:geolocation-acquired",
+            Mark::FetchStart => "free2pee:fetch-start",
+            Mark::FirstByte => "free2pee:first-byte",
+            Mark::ParseDone => "free2pee:parse-done",
+            Mark::FirstRowPainted => "free2pee:first-row-painted",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mark::GeolocationAcquired => "Geolocation acquired",
+            Mark::FetchStart => "Fetch start",
+            Mark::FirstByte => "First byte",
+            Mark::ParseDone => "Parse done",
+            Mark::FirstRowPainted => "First row painted",
+        }
+    }
+}
+
+/// A mark and its time in ms since navigation start.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Timing {
+    pub mark: Mark,
+    pub at_ms: f64,
+}
+
+thread_local! {
+    static TIMINGS: RefCell<Vec<Timing>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Record `mark` unless it already was; the first row also reports the whole set.
+pub fn mark(mark: Mark) {
+    if TIMINGS.with(|t| t.borrow().iter().any(|t| t.mark == mark)) {
+        return;
+    }
+    let Some(performance) = web_sys::window().and_then(|w| w.performance()) else {
+        return;
+    };
+    let _ = performance.mark(mark.name());
+    let timing = Timing { mark, at_ms: performance.now() };
+    TIMINGS.with(|t| t.borrow_mut().push(timing));
+    if mark == Mark::FirstRowPainted {
+        let timings = timings();
+        if let Some(over) = over_budget(&timings) {
+            log::warn!("first result took {over:.0} ms over the {FIRST_RESULT_BUDGET_MS} ms budget");
+        }
+        crate::metrics::report("startup_timings", &timings);
+    }
+}
+
+pub fn timings() -> Vec<Timing> {
+    TIMINGS.with(|t| t.borrow().clone())
+}
+
+/// Each timing with the time since the previous one.
+pub fn breakdown(timings: &[Timing]) -> Vec<(Timing, f64)> {
+    let mut sorted = timings.to_vec();
+    sorted.sort_by(|a, b| a.at_ms.total_cmp(&b.at_ms));
+    let mut previous = 0.0;
+    sorted
+        .into_iter()
+        .map(|t| {
+            let delta = t.at_ms - previous;
+            previous = t.at_ms;
+            (t, delta)
+        })
+        .collect()
+}
+
+/// How far past the budget the first row was painted, if it was.
+pub fn over_budget(timings: &[Timing]) -> Option<f64> {
+    let painted = timings.iter().find(|t| t.mark == Mark::FirstRowPainted)?;
+    (painted.at_ms > FIRST_RESULT_BUDGET_MS).then_some(painted.at_ms - FIRST_RESULT_BUDGET_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(mark: Mark, at_ms: f64) -> Timing {
+        Timing { mark, at_ms }
+    }
+
+    #[test]
+    fn breaks_down_in_time_order() {
+        let timings = [at(Mark::FetchStart, 300.0), at(Mark::GeolocationAcquired, 250.0), at(Mark::FirstByte, 900.0)];
+        let deltas: Vec<_> = breakdown(&timings).into_iter().map(|(t, d)| (t.mark, d)).collect();
+        assert_eq!(
+            deltas,
+            [(Mark::GeolocationAcquired, 250.0), (Mark::FetchStart, 50.0), (Mark::FirstByte, 600.0)]
+        );
+    }
+
+    #[test]
+    fn checks_the_budget() {
+        assert_eq!(over_budget(&[at(Mark::ParseDone, 9000.0)]), None);
+        assert_eq!(over_budget(&[at(Mark::FirstRowPainted, 3000.0)]), None);
+        assert_eq!(over_budget(&[at(Mark::FirstRowPainted, 4500.0)]), Some(500.0));
+    }
+}
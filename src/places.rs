@@ -0,0 +1,143 @@
+//! Saved places ("Home", "Work", ...) and background cache warming for them.
+
+use crate::filters::Filters;
+use crate::geo::haversine_m;
+use crate::router::Viewport;
+use crate::{cache, fetch_bathrooms_at, packs::AmenityPack, storage, tasks::TaskRunner, SearchArea};
+use serde_derive::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::window;
+
+pub const STORAGE_KEY: &str = "saved_places";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPlace {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    /// Last map view looked at here, restored when switching back.
+    #[serde(default)]
+    pub viewport: Option<Viewport>,
+    /// Active when the place was saved, and again when it's picked automatically.
+    #[serde(default)]
+    pub filters: Filters,
+}
+
+impl SavedPlace {
+    pub fn coords(&self) -> (f64, f64) {
+        (self.lat, self.lon)
+    }
+}
+
+/// The closest saved place within `radius_m` of `position`, to start from on opening the app.
+pub fn nearby(places: &[SavedPlace], position: (f64, f64), radius_m: f64) -> Option<&SavedPlace> {
+    places
+        .iter()
+        .map(|p| (haversine_m(position, p.coords()), p))
+        .filter(|(d, _)| *d <= radius_m)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, p)| p)
+}
+
+/// Remember `viewport` for the place called `name`; whether anything changed.
+pub fn remember_viewport(places: &mut [SavedPlace], name: &str, viewport: Viewport) -> bool {
+    match places.iter_mut().find(|p| p.name == name) {
+        Some(place) if place.viewport != Some(viewport) => {
+            place.viewport = Some(viewport);
+            true
+        }
+        _ => false,
+    }
+}
+
+pub fn load() -> Vec<SavedPlace> {
+    storage::load(STORAGE_KEY).unwrap_or_default()
+}
+
+pub fn save(places: &[SavedPlace]) {
+    storage::save(STORAGE_KEY, &places);
+}
+
+/// Only prefetch when online and not on a slow or data-saving connection.
+/// `navigator.connection` isn't available everywhere, so its absence counts as good.
+pub fn network_allows_prefetch() -> bool {
+    let Some(navigator) = window().map(|w| w.navigator()) else {
+        return false;
+    };
+    if !navigator.on_line() {
+        return false;
+    }
+    let Ok(connection) = js_sys::Reflect::get(&navigator, &JsValue::from_str("connection")) else {
+        return true;
+    };
+    if connection.is_undefined() {
+        return true;
+    }
+    let save_data = js_sys::Reflect::get(&connection, &JsValue::from_str("saveData"))
+        .ok()
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let effective_type = js_sys::Reflect::get(&connection, &JsValue::from_str("effectiveType"))
+        .ok()
+        .and_then(|v| v.as_string())
+        .unwrap_or_default();
+    !save_data && !matches!(effective_type.as_str(), "slow-2g" | "2g")
+}
+
+/// Refresh the cache for every saved place that doesn't have results younger
+/// than `max_age_min`, one request at a time so it never competes with what
+/// the user is looking at.
+pub async fn warm_cache(places: Vec<SavedPlace>, radius: i64, pack: AmenityPack, max_age_min: u32) {
+    if !network_allows_prefetch() {
+        return;
+    }
+    let runner = TaskRunner::new(1);
+    let stale: Vec<_> = places
+        .into_iter()
+        .filter(|place| cache::load_fresh(&cache::cache_key(place.coords(), radius, pack), max_age_min).is_none())
+        .collect();
+    let fetches = stale.iter().map(|place| {
+        let runner = runner.clone();
+        async move {
+            let res = fetch_bathrooms_at(place.coords(), SearchArea::Around(radius), pack, &runner, |_| {}).await;
+            match res {
+                Ok(bathrooms) => cache::store(&cache::cache_key(place.coords(), radius, pack), &bathrooms),
+                Err(e) => log::warn!("failed to warm cache for {}: {e}", place.name),
+            }
+        }
+    });
+    let _ = runner.run(fetches, |_| {}).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(name: &str, lat: f64, lon: f64) -> SavedPlace {
+        SavedPlace { name: name.to_string(), lat, lon, viewport: None, filters: Filters::new() }
+    }
+
+    #[test]
+    fn finds_the_closest_place_in_range() {
+        let places = [place("Home", 52.5, 13.4), place("Work", 52.502, 13.4), place("Gym", 52.6, 13.4)];
+        assert_eq!(nearby(&places, (52.5019, 13.4), 300.0).map(|p| p.name.as_str()), Some("Work"));
+        assert_eq!(nearby(&places, (52.55, 13.4), 300.0), None);
+        assert_eq!(nearby(&places, (52.5, 13.4), 0.0).map(|p| p.name.as_str()), Some("Home"));
+    }
+
+    #[test]
+    fn remembers_the_last_viewport() {
+        let mut places = vec![place("Work", 52.5, 13.4)];
+        let viewport = Viewport { lat: 52.51, lon: 13.41, zoom: 17 };
+        assert!(remember_viewport(&mut places, "Work", viewport));
+        assert!(!remember_viewport(&mut places, "Work", viewport));
+        assert!(!remember_viewport(&mut places, "Home", viewport));
+        assert_eq!(places[0].viewport, Some(viewport));
+    }
+
+    #[test]
+    fn loads_places_saved_before_viewports() {
+        let place: SavedPlace = serde_json::from_str(r#"{"name":"Home","lat":1.0,"lon":2.0}"#).unwrap();
+        assert_eq!(place.viewport, None);
+    }
+}
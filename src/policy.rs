@@ -0,0 +1,157 @@
+//! The usage policies of the third-party services the app calls, in one
+//! place: how often and how many at once each allows, and which map tiles
+//! are used with what attribution. Requests go through [`get`], which waits
+//! out the service's minimum interval and makes sure the page's origin goes
+//! along as `Referer`. Browsers don't let a page set `User-Agent`, so that's
+//! how the OSMF and FOSSGIS policies ask web apps to identify themselves.
+
+use crate::net;
+use reqwasm::http::{ReferrerPolicy, Request};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Service {
+    Overpass,
+    /// OSMF's geocoder.
+    Nominatim,
+    /// The OSRM project's demo server, for walking tables.
+    Osrm,
+    /// FOSSGIS's routing and isochrone servers on openstreetmap.de.
+    Fossgis,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UsagePolicy {
+    /// Least time between two requests from this tab.
+    pub min_interval: Duration,
+    /// Most requests in flight at once.
+    pub max_concurrent: usize,
+    /// Where the policy is written down.
+    pub terms: &'static str,
+}
+
+impl Service {
+    pub const fn policy(&self) -> UsagePolicy {
+        match self {
+            Service::Overpass => UsagePolicy {
+                min_interval: Duration::ZERO,
+                max_concurrent: 2,
+                terms: "https://dev.overpass-api.de/overpass-doc/en/preface/commons.html",
+            },
+            Service::Nominatim => UsagePolicy {
+                min_interval: Duration::from_secs(1),
+                max_concurrent: 1,
+                terms: "https://operations.osmfoundation.org/policies/nominatim/",
+            },
+            Service::Osrm => UsagePolicy {
+                min_interval: Duration::from_secs(1),
+                max_concurrent: 1,
+                terms: "https://github.com/Project-OSRM/osrm-backend/wiki/Api-usage-policy",
+            },
+            Service::Fossgis => UsagePolicy {
+                min_interval: Duration::ZERO,
+                max_concurrent: 2,
+                terms: "https://fossgis.de/arbeitsgruppen/osm-server/nutzungsbedingungen/",
+            },
+        }
+    }
+}
+
+thread_local! {
+    /// When each service may next be sent a request.
+    static NEXT_SLOT: RefCell<HashMap<Service, f64>> = RefCell::new(HashMap::new());
+}
+
+/// For a request wanted at `now`: how long it has to wait, and when the one
+/// after it may go.
+pub fn schedule(next_slot: Option<f64>, now: f64, min_interval_ms: f64) -> (f64, f64) {
+    let at = next_slot.map_or(now, |slot| slot.max(now));
+    (at - now, at + min_interval_ms)
+}
+
+/// Wait until `service` may be sent another request, and claim that slot.
+pub async fn throttle(service: Service) {
+    let min_interval = service.policy().min_interval;
+    if min_interval.is_zero() {
+        return;
+    }
+    let wait_ms = NEXT_SLOT.with(|slots| {
+        let mut slots = slots.borrow_mut();
+        let (wait_ms, next) = schedule(slots.get(&service).copied(), js_sys::Date::now(), min_interval.as_millis() as f64);
+        slots.insert(service, next);
+        wait_ms
+    });
+    if wait_ms > 0.0 {
+        net::sleep(Duration::from_millis(wait_ms as u64)).await;
+    }
+}
+
+/// A GET to `service`, once its policy allows one.
+pub async fn get(service: Service, url: &str) -> Request {
+    throttle(service).await;
+    // The default, but a page or embedder policy of `no-referrer` would leave requests anonymous.
+    Request::get(url).referrer_policy(ReferrerPolicy::StrictOriginWhenCrossOrigin)
+}
+
+/// A source of map tiles, with what its terms require to be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileProvider {
+    pub key: &'static str,
+    pub url: &'static str,
+    pub attribution: &'static str,
+    pub max_zoom: u8,
+    pub terms: &'static str,
+}
+
+pub const TILE_PROVIDERS: &[TileProvider] = &[
+    TileProvider {
+        key: "osm",
+        url: "https://tile.openstreetmap.org/{z}/{x}/{y}.png",
+        attribution: "&copy; <a href=\"https://www.openstreetmap.org/copyright\">OpenStreetMap</a> contributors",
+        max_zoom: 19,
+        terms: "https://operations.osmfoundation.org/policies/tiles/",
+    },
+    TileProvider {
+        key: "osmfr",
+        url: "https://{s}.tile.openstreetmap.fr/osmfr/{z}/{x}/{y}.png",
+        attribution: "&copy; OpenStreetMap France | &copy; <a href=\"https://www.openstreetmap.org/copyright\">OpenStreetMap</a> contributors",
+        max_zoom: 20,
+        terms: "https://www.openstreetmap.fr/fonds-de-carte/",
+    },
+    TileProvider {
+        key: "cyclosm",
+        url: "https://{s}.tile-cyclosm.openstreetmap.fr/cyclosm/{z}/{x}/{y}.png",
+        attribution: "<a href=\"https://www.cyclosm.org\">CyclOSM</a> | &copy; <a href=\"https://www.openstreetmap.org/copyright\">OpenStreetMap</a> contributors",
+        max_zoom: 20,
+        terms: "https://www.openstreetmap.fr/fonds-de-carte/",
+    },
+];
+
+/// The provider picked by `key` (the `tiles` URL parameter), else the first.
+pub fn tile_provider(key: Option<&str>) -> TileProvider {
+    TILE_PROVIDERS.iter().find(|p| Some(p.key) == key).copied().unwrap_or(TILE_PROVIDERS[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_requests_out() {
+        assert_eq!(schedule(None, 5000.0, 1000.0), (0.0, 6000.0));
+        // Two at once: the second waits for the first's interval.
+        assert_eq!(schedule(Some(6000.0), 5000.0, 1000.0), (1000.0, 7000.0));
+        assert_eq!(schedule(Some(6000.0), 9000.0, 1000.0), (0.0, 10_000.0));
+        assert_eq!(Service::Nominatim.policy().min_interval, Duration::from_secs(1));
+        assert_eq!((Service::Osrm.policy().min_interval, Service::Osrm.policy().max_concurrent), (Duration::from_secs(1), 1));
+    }
+
+    #[test]
+    fn picks_tile_providers() {
+        assert_eq!(tile_provider(Some("cyclosm")).key, "cyclosm");
+        assert_eq!(tile_provider(Some("nope")).key, "osm");
+        assert!(TILE_PROVIDERS.iter().all(|p| p.attribution.contains("OpenStreetMap")));
+    }
+}
@@ -0,0 +1,369 @@
+//! User preferences, persisted as one blob and shared through context.
+
+use crate::changeset::{self, DEFAULT_COMMENT_TEMPLATE, DEFAULT_HASHTAGS};
+use crate::coords::CoordFormat;
+use crate::filters::FilterPreset;
+use crate::links::DirectionsProvider;
+use crate::notify::{self, QuietHours};
+use crate::packs::AmenityPack;
+use crate::profiles::Profile;
+use crate::ranking::Ranking;
+use crate::trip::TravelMode;
+use crate::reset::ResetPanel;
+use crate::router::{self, Page};
+use crate::{cache, embed, geocode, i18n, kiosk, pagination, storage, tabs};
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "preferences";
+
+/// Minutes between automatic refreshes, and how they're offered.
+const AUTO_REFRESH_CHOICES: [(u32, &str); 3] = [(0, "only when asked"), (1, "every minute"), (5, "every 5 minutes")];
+
+/// Missing fields fall back to their defaults, so older saved blobs keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub coord_format: CoordFormat,
+    pub directions_provider: DirectionsProvider,
+    /// How directions links from the results get there.
+    pub travel_mode: TravelMode,
+    /// Check walking routes to the selected place for stairs (extra third-party requests).
+    pub step_free_hints: bool,
+    /// Look up the elevation difference to the selected element (an extra third-party request).
+    pub show_elevation: bool,
+    pub amenity_pack: AmenityPack,
+    /// `None` uses the amenity pack's default radius.
+    pub search_radius_m: Option<i64>,
+    pub ranking: Ranking,
+    /// The profile last switched to, for highlighting it; see [`crate::profiles`].
+    pub profile: Option<Profile>,
+    /// For edits made through the app; `{key}` and `{value}` are filled in.
+    pub changeset_comment: String,
+    pub changeset_hashtags: String,
+    pub filter_presets: Vec<FilterPreset>,
+    /// Opening the app within this many meters of a saved place starts there; 0 turns it off.
+    pub welcome_back_radius_m: i64,
+    /// While following, search again after moving this far from where the results are for; 0 never does.
+    pub follow_refetch_m: i64,
+    /// Cached results for a saved place older than this are fetched again when it's opened.
+    pub max_cache_age_min: u32,
+    /// No arrival notices or sounds in this window.
+    pub quiet_hours: QuietHours,
+    /// Short sounds when results are ready, on arrival and on errors.
+    pub sound_cues: bool,
+    /// 0 to 1.
+    pub sound_volume: f64,
+    /// Ask the geocoder for place names in the app's language rather than the browser's.
+    pub localized_place_names: bool,
+    /// Look up street addresses for unnamed places in the results.
+    pub street_addresses: bool,
+    /// Limit address search to these countries, as comma-separated codes like `de,at`; empty for anywhere.
+    pub geocoder_countries: String,
+    /// Results per page in the table; 0 shows them all.
+    pub page_size: usize,
+    /// Search again every this many minutes; 0 never does.
+    pub auto_refresh_min: u32,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            coord_format: CoordFormat::Decimal,
+            directions_provider: DirectionsProvider::Google,
+            travel_mode: TravelMode::Foot,
+            step_free_hints: false,
+            show_elevation: false,
+            amenity_pack: AmenityPack::Toilets,
+            search_radius_m: None,
+            ranking: Ranking::default(),
+            profile: None,
+            changeset_comment: DEFAULT_COMMENT_TEMPLATE.to_string(),
+            changeset_hashtags: DEFAULT_HASHTAGS.to_string(),
+            filter_presets: Vec::new(),
+            welcome_back_radius_m: 300,
+            follow_refetch_m: 250,
+            max_cache_age_min: cache::DEFAULT_MAX_AGE_MIN,
+            quiet_hours: QuietHours::default(),
+            sound_cues: false,
+            sound_volume: 0.5,
+            localized_place_names: true,
+            street_addresses: false,
+            geocoder_countries: String::new(),
+            page_size: 50,
+            auto_refresh_min: 0,
+        }
+    }
+}
+
+/// Load the preferences into a signal in context, saving them whenever they change.
+/// Kiosks and embeds always use the defaults, whatever an earlier visitor to the browser picked.
+pub fn provide_preferences(cx: Scope) -> RwSignal<Preferences> {
+    if kiosk::enabled() || embed::enabled() {
+        let prefs = create_rw_signal(cx, Preferences::default());
+        provide_context(cx, prefs);
+        return prefs;
+    }
+    let prefs = create_rw_signal(cx, storage::load::<Preferences>(STORAGE_KEY).unwrap_or_default());
+    create_effect(cx, move |_| prefs.with(|p| storage::save(STORAGE_KEY, p)));
+    tabs::use_tabs(cx).on_stored_elsewhere(cx, |key| key == STORAGE_KEY, move |_| {
+        if let Some(stored) = storage::load(STORAGE_KEY) {
+            prefs.set(stored);
+        }
+    });
+    provide_context(cx, prefs);
+    prefs
+}
+
+pub fn use_preferences(cx: Scope) -> RwSignal<Preferences> {
+    use_context::<RwSignal<Preferences>>(cx).unwrap_or_else(|| create_rw_signal(cx, Preferences::default()))
+}
+
+#[component]
+pub fn PreferencesPanel(cx: Scope) -> impl IntoView {
+    let prefs = use_preferences(cx);
+    let i18n = i18n::use_i18n(cx);
+    let route = router::use_route(cx);
+    let (changeset_error, set_changeset_error) = create_signal(cx, None::<changeset::ChangesetError>);
+    // Only valid settings are saved; the inputs keep showing what was typed.
+    let check_changeset = move |template: String, hashtags: String| {
+        let res = changeset::parse_hashtags(&hashtags).and_then(|tags| {
+            changeset::validate(&changeset::render_comment(&template, "wheelchair", "limited"), &tags)
+        });
+        match res {
+            Ok(()) => {
+                set_changeset_error.set(None);
+                prefs.update(|p| {
+                    p.changeset_comment = template;
+                    p.changeset_hashtags = hashtags;
+                });
+            }
+            Err(e) => set_changeset_error.set(Some(e)),
+        }
+    };
+
+    view! { cx,
+        <details class="preferences">
+            <summary>{move || i18n.t("Settings")}</summary>
+            <label>
+                "Coordinates "
+                <select on:change=move |ev| {
+                    let format = CoordFormat::from_label(&event_target_value(&ev));
+                    prefs.update(|p| p.coord_format = format);
+                }>
+                    {CoordFormat::ALL.into_iter().map(|f| view! { cx,
+                        <option value=f.label() selected=move || prefs.with(|p| p.coord_format == f)>{f.label()}</option>
+                    }).collect_view(cx)}
+                </select>
+            </label>
+            <label>
+                "Order results by "
+                <select on:change=move |ev| {
+                    let ranking = Ranking::from_label(&event_target_value(&ev));
+                    prefs.update(|p| p.ranking = ranking);
+                }>
+                    {Ranking::ALL.into_iter().map(|r| view! { cx,
+                        <option value=r.label() selected=move || prefs.with(|p| p.ranking == r)>{r.label()}</option>
+                    }).collect_view(cx)}
+                </select>
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || prefs.with(|p| p.show_elevation)
+                    on:change=move |ev| {
+                        let on = event_target_checked(&ev);
+                        prefs.update(|p| p.show_elevation = on);
+                    }
+                />
+                "Show elevation difference"
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || prefs.with(|p| p.step_free_hints)
+                    on:change=move |ev| {
+                        let on = event_target_checked(&ev);
+                        prefs.update(|p| p.step_free_hints = on);
+                    }
+                />
+                "Warn when the walking route may include stairs"
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || prefs.with(|p| p.localized_place_names)
+                    on:change=move |ev| {
+                        let on = event_target_checked(&ev);
+                        prefs.update(|p| p.localized_place_names = on);
+                    }
+                />
+                "Address search results in the app's language"
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || prefs.with(|p| p.street_addresses)
+                    on:change=move |ev| {
+                        let on = event_target_checked(&ev);
+                        prefs.update(|p| p.street_addresses = on);
+                    }
+                />
+                "Show street addresses for unnamed places (asks OpenStreetMap's geocoder)"
+            </label>
+            <label>
+                "Only find addresses in "
+                <input
+                    placeholder="any country, or e.g. de,at"
+                    prop:value=move || prefs.with(|p| p.geocoder_countries.clone())
+                    on:change=move |ev| {
+                        let countries = geocode::country_codes(&event_target_value(&ev)).join(",");
+                        prefs.update(|p| p.geocoder_countries = countries);
+                    }
+                />
+            </label>
+            <label>
+                "While following, search again after moving "
+                <input
+                    type="number"
+                    min="0"
+                    step="50"
+                    prop:value=move || prefs.with(|p| p.follow_refetch_m.to_string())
+                    on:change=move |ev| {
+                        if let Ok(meters) = event_target_value(&ev).parse::<i64>() {
+                            prefs.update(|p| p.follow_refetch_m = meters.max(0));
+                        }
+                    }
+                />
+                " m"
+            </label>
+            <label>
+                "Results per page "
+                <select on:change=move |ev| {
+                    if let Ok(size) = event_target_value(&ev).parse::<usize>() {
+                        prefs.update(|p| p.page_size = size);
+                    }
+                }>
+                    {pagination::PAGE_SIZES.into_iter().map(|size| view! { cx,
+                        <option value=size.to_string() selected=move || prefs.with(|p| p.page_size == size)>
+                            {if size == 0 { "all".to_string() } else { size.to_string() }}
+                        </option>
+                    }).collect_view(cx)}
+                </select>
+            </label>
+            <label>
+                "Refresh results "
+                <select on:change=move |ev| {
+                    if let Ok(minutes) = event_target_value(&ev).parse::<u32>() {
+                        prefs.update(|p| p.auto_refresh_min = minutes);
+                    }
+                }>
+                    {AUTO_REFRESH_CHOICES.into_iter().map(|(minutes, label)| view! { cx,
+                        <option value=minutes.to_string() selected=move || prefs.with(|p| p.auto_refresh_min == minutes)>{label}</option>
+                    }).collect_view(cx)}
+                </select>
+            </label>
+            <label>
+                "Reuse saved results for up to "
+                <input
+                    type="number"
+                    min="1"
+                    step="5"
+                    prop:value=move || prefs.with(|p| p.max_cache_age_min.to_string())
+                    on:change=move |ev| {
+                        if let Ok(minutes) = event_target_value(&ev).parse::<u32>() {
+                            prefs.update(|p| p.max_cache_age_min = minutes.max(1));
+                        }
+                    }
+                />
+                " min"
+            </label>
+            <label>
+                "Start at a saved place within "
+                <input
+                    type="number"
+                    min="0"
+                    step="50"
+                    prop:value=move || prefs.with(|p| p.welcome_back_radius_m.to_string())
+                    on:change=move |ev| {
+                        if let Ok(radius) = event_target_value(&ev).parse::<i64>() {
+                            prefs.update(|p| p.welcome_back_radius_m = radius.max(0));
+                        }
+                    }
+                />
+                " m"
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || prefs.with(|p| p.sound_cues)
+                    on:change=move |ev| {
+                        let on = event_target_checked(&ev);
+                        prefs.update(|p| p.sound_cues = on);
+                    }
+                />
+                "Sound cues, volume "
+                <input
+                    type="range"
+                    min="0"
+                    max="1"
+                    step="0.1"
+                    prop:disabled=move || prefs.with(|p| !p.sound_cues)
+                    prop:value=move || prefs.with(|p| p.sound_volume.to_string())
+                    on:change=move |ev| {
+                        if let Ok(volume) = event_target_value(&ev).parse::<f64>() {
+                            prefs.update(|p| p.sound_volume = volume.clamp(0.0, 1.0));
+                        }
+                    }
+                />
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || prefs.with(|p| p.quiet_hours.enabled)
+                    on:change=move |ev| {
+                        let on = event_target_checked(&ev);
+                        prefs.update(|p| p.quiet_hours.enabled = on);
+                    }
+                />
+                "Quiet hours from "
+                <input
+                    type="time"
+                    prop:value=move || prefs.with(|p| notify::format_hhmm(p.quiet_hours.start))
+                    on:change=move |ev| {
+                        if let Some(start) = notify::parse_hhmm(&event_target_value(&ev)) {
+                            prefs.update(|p| p.quiet_hours.start = start);
+                        }
+                    }
+                />
+                " to "
+                <input
+                    type="time"
+                    prop:value=move || prefs.with(|p| notify::format_hhmm(p.quiet_hours.end))
+                    on:change=move |ev| {
+                        if let Some(end) = notify::parse_hhmm(&event_target_value(&ev)) {
+                            prefs.update(|p| p.quiet_hours.end = end);
+                        }
+                    }
+                />
+            </label>
+            <label>
+                "OSM changeset comment "
+                <input
+                    prop:value=move || prefs.with(|p| p.changeset_comment.clone())
+                    on:change=move |ev| check_changeset(event_target_value(&ev), prefs.with(|p| p.changeset_hashtags.clone()))
+                />
+            </label>
+            <label>
+                "Hashtags "
+                <input
+                    prop:value=move || prefs.with(|p| p.changeset_hashtags.clone())
+                    on:change=move |ev| check_changeset(prefs.with(|p| p.changeset_comment.clone()), event_target_value(&ev))
+                />
+            </label>
+            {move || changeset_error.get().map(|e| view! { cx, <p class="error">{e.to_string()}</p> })}
+            <p><a href=move || route.get().href(Page::Diagnostics)>"Something not working? Run diagnostics"</a></p>
+            <ResetPanel/>
+        </details>
+    }
+}
@@ -0,0 +1,173 @@
+//! Scenario profiles ("Road trip", "Parent mode", ...): named overlays that set
+//! a handful of preferences and filters in one tap. They only overwrite what
+//! they're about, so everything else stays as the user left it, and any
+//! setting can still be changed afterwards.
+
+use crate::filters::{use_filter_state, Filter, Filters};
+use crate::links::DirectionsProvider;
+use crate::packs::AmenityPack;
+use crate::prefs::{use_preferences, Preferences};
+use crate::ranking::Ranking;
+use crate::trip::TravelMode;
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Profile {
+    CityWalking,
+    RoadTrip,
+    AccessibilityFirst,
+    ParentMode,
+}
+
+/// What a profile changes; `None` leaves the current value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overlay {
+    pub pack: Option<AmenityPack>,
+    pub radius_m: Option<i64>,
+    pub filters: Option<Filters>,
+    pub ranking: Option<Ranking>,
+    pub directions: Option<DirectionsProvider>,
+    pub travel_mode: Option<TravelMode>,
+    pub step_free_hints: Option<bool>,
+}
+
+impl Overlay {
+    pub fn apply(&self, prefs: &mut Preferences, filters: &mut Filters) {
+        if let Some(pack) = self.pack {
+            prefs.amenity_pack = pack;
+        }
+        if let Some(radius) = self.radius_m {
+            prefs.search_radius_m = Some(radius);
+        }
+        if let Some(ranking) = self.ranking {
+            prefs.ranking = ranking;
+        }
+        if let Some(directions) = self.directions {
+            prefs.directions_provider = directions;
+        }
+        if let Some(mode) = self.travel_mode {
+            prefs.travel_mode = mode;
+        }
+        if let Some(on) = self.step_free_hints {
+            prefs.step_free_hints = on;
+        }
+        if let Some(f) = &self.filters {
+            filters.clone_from(f);
+        }
+    }
+}
+
+impl Profile {
+    pub const ALL: [Profile; 4] = [Profile::CityWalking, Profile::RoadTrip, Profile::AccessibilityFirst, Profile::ParentMode];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Profile::CityWalking => "City walking",
+            Profile::RoadTrip => "Road trip",
+            Profile::AccessibilityFirst => "Accessibility-first",
+            Profile::ParentMode => "Parent mode",
+        }
+    }
+
+    pub fn overlay(&self) -> Overlay {
+        match self {
+            Profile::CityWalking => Overlay {
+                pack: Some(AmenityPack::Toilets),
+                radius_m: Some(1000),
+                filters: Some(Filters::new()),
+                ranking: Some(Ranking::WalkingTime),
+                directions: None,
+                travel_mode: Some(TravelMode::Foot),
+                step_free_hints: None,
+            },
+            // Fuel stations and rest areas, further out, with driving directions.
+            Profile::RoadTrip => Overlay {
+                pack: Some(AmenityPack::RoadTrip),
+                radius_m: Some(AmenityPack::RoadTrip.default_radius()),
+                filters: Some(Filters::new()),
+                ranking: Some(Ranking::Distance),
+                directions: Some(DirectionsProvider::Google),
+                travel_mode: Some(TravelMode::Car),
+                step_free_hints: None,
+            },
+            Profile::AccessibilityFirst => Overlay {
+                pack: Some(AmenityPack::Toilets),
+                radius_m: Some(2000),
+                filters: Some([Filter::Accessible].into()),
+                ranking: Some(Ranking::WalkingTime),
+                directions: None,
+                travel_mode: Some(TravelMode::Foot),
+                step_free_hints: Some(true),
+            },
+            Profile::ParentMode => Overlay {
+                pack: Some(AmenityPack::Toilets),
+                radius_m: Some(1500),
+                filters: Some(Filters::new()),
+                ranking: Some(Ranking::FamilyFriendly),
+                directions: None,
+                travel_mode: Some(TravelMode::Foot),
+                step_free_hints: None,
+            },
+        }
+    }
+}
+
+/// One button per profile, for the header.
+#[component]
+pub fn ProfileSwitcher(cx: Scope) -> impl IntoView {
+    let prefs = use_preferences(cx);
+    let filters = use_filter_state(cx);
+    let switch = move |profile: Profile| {
+        let overlay = profile.overlay();
+        let mut active = filters.active.get_untracked();
+        prefs.update(|p| {
+            overlay.apply(p, &mut active);
+            p.profile = Some(profile);
+        });
+        filters.active.set(active);
+    };
+    view! { cx,
+        <span class="profiles">
+            {Profile::ALL.into_iter().map(|profile| view! { cx,
+                <button
+                    class="chip"
+                    class:active=move || prefs.with(|p| p.profile == Some(profile))
+                    on:click=move |_| switch(profile)
+                >
+                    {profile.label()}
+                </button>
+            }).collect_view(cx)}
+        </span>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlays_only_touch_their_settings() {
+        let mut prefs = Preferences { show_elevation: true, ..Default::default() };
+        let mut filters: Filters = [Filter::Free].into();
+        Profile::AccessibilityFirst.overlay().apply(&mut prefs, &mut filters);
+        assert_eq!(prefs.search_radius_m, Some(2000));
+        assert_eq!(prefs.ranking, Ranking::WalkingTime);
+        assert!(prefs.step_free_hints);
+        assert!(prefs.show_elevation);
+        assert_eq!(filters, [Filter::Accessible].into());
+    }
+
+    #[test]
+    fn road_trips_drive_further() {
+        let mut prefs = Preferences::default();
+        let mut filters = Filters::new();
+        Profile::RoadTrip.overlay().apply(&mut prefs, &mut filters);
+        assert_eq!(prefs.amenity_pack, AmenityPack::RoadTrip);
+        assert_eq!(prefs.travel_mode, TravelMode::Car);
+        assert!(prefs.search_radius_m > Profile::CityWalking.overlay().radius_m);
+        Profile::CityWalking.overlay().apply(&mut prefs, &mut filters);
+        assert_eq!(prefs.amenity_pack, AmenityPack::Toilets);
+        assert_eq!(prefs.travel_mode, TravelMode::Foot);
+    }
+}
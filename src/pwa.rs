@@ -0,0 +1,43 @@
+//! Installable, offline-capable app: `sw.js` (next to `index.html`) keeps
+//! the shell, and searches made offline fall back to the [`cache`](crate::cache).
+
+use leptos::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::window;
+
+/// Relative, so it resolves under the GitHub Pages base path too.
+const SERVICE_WORKER: &str = "./sw.js";
+
+pub fn register_service_worker() {
+    let Some(container) = window().map(|w| w.navigator().service_worker()) else {
+        return;
+    };
+    spawn_local(async move {
+        if let Err(e) = JsFuture::from(container.register(SERVICE_WORKER)).await {
+            log::warn!("couldn't register the service worker: {e:?}");
+        }
+    });
+}
+
+/// What the browser thinks; `false` is reliable, `true` only means there's a network.
+pub fn online() -> bool {
+    window().map(|w| w.navigator().on_line()).unwrap_or(true)
+}
+
+/// [`online`], following `online` and `offline` events.
+pub fn use_online(cx: Scope) -> ReadSignal<bool> {
+    let (online_now, set_online) = create_signal(cx, online());
+    window_event_listener(ev::online, move |_| set_online.set(true));
+    window_event_listener(ev::offline, move |_| set_online.set(false));
+    online_now
+}
+
+#[component]
+pub fn OfflineBanner(cx: Scope) -> impl IntoView {
+    let online_now = use_online(cx);
+    view! { cx,
+        <Show when=move || !online_now.get() fallback=|_| ()>
+            <p class="offline" role="status">"You're offline. Saved results are shown where there are any."</p>
+        </Show>
+    }
+}
@@ -0,0 +1,229 @@
+//! Mapper mode: how complete the local data is, and where to go fix it.
+
+use crate::api::overpass::fetch_overpass;
+use crate::overpass::Area;
+use crate::packs::AmenityPack;
+use crate::prefs::use_preferences;
+use crate::{current_position, export, osm_links, search_radius, Element};
+use leptos::{error::Result, *};
+use serde_json::{json, Map, Value};
+
+/// Tags a visitor most wants to know before setting off.
+pub const CHECKED_KEYS: [&str; 3] = ["opening_hours", "wheelchair", "fee"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incomplete {
+    pub element: Element,
+    pub missing: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityReport {
+    pub total: usize,
+    /// How many elements lack each of [`CHECKED_KEYS`], in the same order.
+    pub missing_counts: Vec<(&'static str, usize)>,
+    pub incomplete: Vec<Incomplete>,
+}
+
+pub fn missing_keys(element: &Element) -> Vec<&'static str> {
+    CHECKED_KEYS
+        .into_iter()
+        .filter(|key| !element.tags.contains_key(key))
+        .collect()
+}
+
+pub fn report(elements: &[Element]) -> QualityReport {
+    let incomplete: Vec<Incomplete> = elements
+        .iter()
+        .map(|element| Incomplete {
+            element: element.clone(),
+            missing: missing_keys(element),
+        })
+        .filter(|i| !i.missing.is_empty())
+        .collect();
+    let missing_counts = CHECKED_KEYS
+        .into_iter()
+        .map(|key| (key, incomplete.iter().filter(|i| i.missing.contains(&key)).count()))
+        .collect();
+    QualityReport {
+        total: elements.len(),
+        missing_counts,
+        incomplete,
+    }
+}
+
+/// `node`, `way` or `relation`; results from before we asked for the type are all nodes.
+pub fn osm_type(element: &Element) -> &'static str {
+    element.type_field.as_str()
+}
+
+pub fn incomplete_csv(incomplete: &[Incomplete]) -> String {
+    let rows: Vec<Vec<String>> = incomplete
+        .iter()
+        .map(|i| {
+            vec![
+                i.element.id.to_string(),
+                i.element.lat.to_string(),
+                i.element.lon.to_string(),
+                i.element.tags.get("name").map(str::to_string).unwrap_or_default(),
+                i.missing.join(" "),
+                osm_links::edit_url(&i.element),
+            ]
+        })
+        .collect();
+    export::csv(&["id", "lat", "lon", "name", "missing", "edit_url"], &rows)
+}
+
+/// Incomplete elements as a GeoJSON file for creating a MapRoulette challenge:
+/// one task per feature, identified by its `@id` OSM reference.
+pub fn maproulette_geojson(incomplete: &[Incomplete]) -> String {
+    let points: Vec<(f64, f64, Map<String, Value>)> = incomplete
+        .iter()
+        .map(|i| {
+            let mut properties = Map::new();
+            properties.insert("@id".to_string(), json!(format!("{}/{}", osm_type(&i.element), i.element.id)));
+            for (key, value) in &i.element.tags {
+                properties.insert(key.to_string(), json!(value));
+            }
+            properties.insert("missing".to_string(), json!(i.missing.join(";")));
+            properties.insert(
+                "task_instructions".to_string(),
+                json!(format!("Survey or look up and add: {}", i.missing.join(", "))),
+            );
+            (i.element.lat, i.element.lon, properties)
+        })
+        .collect();
+    export::geojson_points(&points)
+}
+
+async fn fetch_report((pack, radius): (AmenityPack, i64)) -> Result<QualityReport> {
+    let origin = current_position().await?;
+    let res = fetch_overpass(&pack.query(origin, &Area::around(radius, origin))).await?;
+    Ok(report(&res.elements))
+}
+
+#[component]
+pub fn MapperMode(cx: Scope) -> impl IntoView {
+    let prefs = use_preferences(cx);
+    let report = create_local_resource(cx, move || prefs.with(|p| (p.amenity_pack, search_radius(p.amenity_pack, p.search_radius_m))), fetch_report);
+
+    let report_view = move || {
+        report.read(cx).map(|res| match res {
+            Err(e) => view! { cx, <p class="error">{e.to_string()}</p> }.into_view(cx),
+            Ok(report) => {
+                let counts = report
+                    .missing_counts
+                    .iter()
+                    .map(|(key, count)| view! { cx, <li>{format!("{count} of {} missing {key}", report.total)}</li> })
+                    .collect_view(cx);
+                let csv = incomplete_csv(&report.incomplete);
+                let download = move |_| {
+                    if let Err(e) = export::download("free2pee-incomplete.csv", "text/csv", &csv) {
+                        log::error!("CSV export failed: {e:?}");
+                    }
+                };
+                let geojson = maproulette_geojson(&report.incomplete);
+                let download_challenge = move |_| {
+                    if let Err(e) = export::download("free2pee-challenge.geojson", "application/geo+json", &geojson) {
+                        log::error!("GeoJSON export failed: {e:?}");
+                    }
+                };
+                let rows = report
+                    .incomplete
+                    .into_iter()
+                    .map(|i| view! { cx,
+                        <tr>
+                            <td>
+                                <a href={osm_links::view_url(&i.element)} target="_blank">
+                                    {i.element.tags.get("name").map(str::to_string).unwrap_or_else(|| format!("OSM:{}", i.element.id))}
+                                </a>
+                            </td>
+                            <td>{i.missing.join(", ")}</td>
+                            <td><a href={osm_links::edit_url(&i.element)} target="_blank">"Edit in iD"</a></td>
+                        </tr>
+                    })
+                    .collect_view(cx);
+                view! { cx,
+                    <ul class="quality">{counts}</ul>
+                    <button on:click=download>"Export incomplete as CSV"</button>
+                    <button on:click=download_challenge>"Export as MapRoulette challenge"</button>
+                    <table>
+                        <thead>
+                            <tr><th>"Element"</th><th>"Missing"</th><th></th></tr>
+                        </thead>
+                        <tbody>{rows}</tbody>
+                    </table>
+                }
+                .into_view(cx)
+            }
+        })
+    };
+
+    view! { cx,
+        <div class="mapper">
+            <h2>"Mapper mode: local data quality"</h2>
+            <p>"Help the next person out by filling in what's missing."</p>
+            <Transition fallback=move || view! { cx, <p>"Loading..."</p> }>
+                {report_view}
+            </Transition>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OsmType;
+
+    fn element(id: i64, tags: &[(&str, &str)]) -> Element {
+        Element {
+            id,
+            lat: 1.5,
+            lon: 2.5,
+            type_field: OsmType::Node,
+            tags: tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_missing_keys() {
+        let elements = [
+            element(1, &[("amenity", "toilets")]),
+            element(2, &[("amenity", "toilets"), ("fee", "no"), ("wheelchair", "yes"), ("opening_hours", "24/7")]),
+            element(3, &[("amenity", "toilets"), ("fee", "no")]),
+        ];
+        let report = report(&elements);
+        assert_eq!(report.total, 3);
+        assert_eq!(
+            report.missing_counts,
+            vec![("opening_hours", 2), ("wheelchair", 2), ("fee", 1)]
+        );
+        let ids: Vec<i64> = report.incomplete.iter().map(|i| i.element.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn csv_escapes_names() {
+        let report = report(&[element(7, &[("name", "Park \"North\", east side")])]);
+        let csv = incomplete_csv(&report.incomplete);
+        assert_eq!(
+            csv,
+            "id,lat,lon,name,missing,edit_url\r\n\
+             7,1.5,2.5,\"Park \"\"North\"\", east side\",opening_hours wheelchair fee,\
+             https://www.openstreetmap.org/edit?editor=id&node=7\r\n"
+        );
+    }
+
+    #[test]
+    fn maproulette_features() {
+        let report = report(&[element(7, &[("amenity", "toilets"), ("fee", "no")])]);
+        let geojson: Value = serde_json::from_str(&maproulette_geojson(&report.incomplete)).unwrap();
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let feature = &geojson["features"][0];
+        assert_eq!(feature["geometry"]["coordinates"], json!([2.5, 1.5]));
+        assert_eq!(feature["properties"]["@id"], "node/7");
+        assert_eq!(feature["properties"]["amenity"], "toilets");
+        assert_eq!(feature["properties"]["missing"], "opening_hours;wheelchair");
+    }
+}
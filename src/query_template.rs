@@ -0,0 +1,109 @@
+//! Precompiled Overpass queries. A pack's query only varies by its area clause,
+//! so each `(pack, regions)` combination is compiled once into the text around
+//! the area slots, and rendering is a single right-sized allocation. That keeps
+//! repeated refetches of the same search cheap.
+
+use crate::overpass::{self, Area, QueryBuilder, Select};
+use crate::packs::AmenityPack;
+use crate::vocabulary::REGIONS;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTemplate {
+    /// Static text; the area clause goes between each consecutive pair.
+    pieces: Vec<String>,
+    static_len: usize,
+}
+
+impl QueryTemplate {
+    /// `[out:json];(nwr["k"="v"](AREA);...);out meta center;` for each filter.
+    pub fn compile(filters: &[(&str, &str)]) -> QueryTemplate {
+        let query = filters
+            .iter()
+            .fold(QueryBuilder::new(), |q, (key, value)| q.select(Select::any().tag(key, value).within(Area::Slot)))
+            .center()
+            .build();
+        let pieces: Vec<String> = query.split(overpass::SLOT).map(str::to_string).collect();
+        let static_len = pieces.iter().map(String::len).sum();
+        QueryTemplate { pieces, static_len }
+    }
+
+    pub fn render(&self, area: &Area) -> String {
+        let area = area.to_string();
+        let slots = self.pieces.len() - 1;
+        let mut query = String::with_capacity(self.static_len + slots * area.len());
+        for (i, piece) in self.pieces.iter().enumerate() {
+            query.push_str(piece);
+            if i < slots {
+                query.push_str(&area);
+            }
+        }
+        query
+    }
+}
+
+thread_local! {
+    static INTERNED: RefCell<HashMap<(AmenityPack, u32), Rc<QueryTemplate>>> = RefCell::new(HashMap::new());
+}
+
+/// The compiled query for `pack` in a [`regions_at`](crate::vocabulary::regions_at) set, compiling it on first use.
+pub fn interned(pack: AmenityPack, regions: u32) -> Rc<QueryTemplate> {
+    INTERNED.with(|interned| {
+        interned
+            .borrow_mut()
+            .entry((pack, regions))
+            .or_insert_with(|| Rc::new(QueryTemplate::compile(&pack.filters_in(regions))))
+            .clone()
+    })
+}
+
+/// Compile the common cases up front: every pack outside any special region
+/// and inside each one.
+pub fn precompile() {
+    for pack in AmenityPack::ALL {
+        interned(pack, 0);
+        for i in 0..REGIONS.len() {
+            interned(pack, 1 << i);
+        }
+    }
+    log::debug!("precompiled {} query templates", INTERNED.with(|i| i.borrow().len()));
+}
+
+/// How many distinct templates are interned; for the debug panel.
+pub fn interned_count() -> usize {
+    INTERNED.with(|i| i.borrow().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vocabulary;
+
+    #[test]
+    fn renders_every_area_slot() {
+        let template = QueryTemplate::compile(&[("amenity", "toilets"), ("toilets", "yes")]);
+        assert_eq!(
+            template.render(&Area::around(10, (1.0, 2.0))),
+            "[out:json];(nwr[\"amenity\"=\"toilets\"](around:10,1,2);nwr[\"toilets\"=\"yes\"](around:10,1,2););out meta center;"
+        );
+    }
+
+    #[test]
+    fn rendering_allocates_exactly_once() {
+        let template = QueryTemplate::compile(&[("amenity", "toilets"), ("amenity", "shelter")]);
+        let query = template.render(&Area::BBox(crate::geo::BBox { south: 1.0, west: 2.0, north: 3.0, east: 4.0 }));
+        assert_eq!(query.capacity(), query.len());
+    }
+
+    #[test]
+    fn interning_reuses_templates() {
+        let regions = vocabulary::regions_at((51.507, -0.128));
+        let a = interned(AmenityPack::Toilets, regions);
+        let b = interned(AmenityPack::Toilets, regions);
+        assert!(Rc::ptr_eq(&a, &b));
+        precompile();
+        assert!(interned_count() >= AmenityPack::ALL.len() * (REGIONS.len() + 1));
+    }
+}
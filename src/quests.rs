@@ -0,0 +1,159 @@
+//! StreetComplete-style quests: one-tap questions that fill in missing tags.
+
+use crate::osm_edit::{self, Submitted, TagEdit, Upload};
+use crate::prefs::use_preferences;
+use crate::{changeset, kiosk, osm_auth, Element, OsmType};
+use leptos::*;
+
+pub struct Quest {
+    pub key: &'static str,
+    pub question: &'static str,
+    /// `(button label, tag value)`.
+    pub answers: &'static [(&'static str, &'static str)],
+}
+
+pub const QUESTS: [Quest; 2] = [
+    Quest {
+        key: "fee",
+        question: "Is there a fee?",
+        answers: &[("Yes", "yes"), ("No", "no")],
+    },
+    Quest {
+        key: "wheelchair",
+        question: "Is it wheelchair accessible?",
+        answers: &[("Yes", "yes"), ("Partly", "limited"), ("No", "no")],
+    },
+];
+
+/// Quests whose tag the element doesn't have yet. Answers are uploaded as node
+/// edits, so ways and relations get none.
+pub fn open_quests(element: &Element) -> Vec<&'static Quest> {
+    if element.type_field != OsmType::Node {
+        return Vec::new();
+    }
+    QUESTS.iter().filter(|q| !element.tags.contains_key(q.key)).collect()
+}
+
+#[component]
+pub fn QuestPanel(cx: Scope, element: Element) -> impl IntoView {
+    let logged_in = osm_auth::token().is_some();
+    let quests = open_quests(&element);
+    if quests.is_empty() || kiosk::enabled() {
+        return ().into_view(cx);
+    }
+    if !logged_in {
+        return osm_auth::client_id()
+            .map(|_| {
+                let login = move |_| {
+                    spawn_local(async {
+                        if let Err(e) = osm_auth::login().await {
+                            log::error!("OSM login failed: {e}");
+                        }
+                    })
+                };
+                view! { cx,
+                    <p class="quests">
+                        "Know this place? "
+                        <button on:click=login>"Log in to OpenStreetMap"</button>
+                        " to fill in what's missing."
+                    </p>
+                }
+            })
+            .into_view(cx);
+    }
+
+    let pending = osm_edit::use_pending_uploads(cx);
+    let prefs = use_preferences(cx);
+    let (node_id, base_version, lat, lon) = (element.id, element.version, element.lat, element.lon);
+    let (status, set_status) = create_signal(cx, None::<String>);
+    let submit = move |upload: Upload, on_error: Box<dyn FnOnce()>| {
+        spawn_local(async move {
+            let message = match osm_edit::submit(upload).await {
+                Ok(Submitted::Uploaded) => "Thanks! Saved to OpenStreetMap.".to_string(),
+                Ok(Submitted::Queued) => {
+                    pending.refresh();
+                    "Saved; it'll upload when you're back online.".to_string()
+                }
+                Ok(Submitted::Conflicted) => {
+                    pending.refresh();
+                    "Someone else just edited this; review it under pending uploads.".to_string()
+                }
+                Err(e) => {
+                    on_error();
+                    e.to_string()
+                }
+            };
+            set_status.set(Some(message));
+        });
+    };
+
+    let answered = create_rw_signal(cx, Vec::<&'static str>::new());
+    let answer = move |key: &'static str, value: &'static str| {
+        answered.update(|a| a.push(key));
+        let (template, hashtags) = prefs.with_untracked(|p| (p.changeset_comment.clone(), p.changeset_hashtags.clone()));
+        let edit = TagEdit {
+            node_id,
+            base_version,
+            tags: vec![(key.to_string(), value.to_string())],
+            comment: changeset::render_comment(&template, key, value),
+            // The preferences panel won't save invalid hashtags, so this only drops hand-edited storage.
+            hashtags: changeset::parse_hashtags(&hashtags).unwrap_or_default(),
+        };
+        submit(Upload::Tags(edit), Box::new(move || answered.update(|a| a.retain(|k| *k != key))));
+    };
+
+    let (note, set_note) = create_signal(cx, String::new());
+    let send_note = move |_| {
+        let text = note.get_untracked();
+        if text.trim().is_empty() {
+            return;
+        }
+        set_note.set(String::new());
+        submit(Upload::Note { lat, lon, text: text.clone() }, Box::new(move || set_note.set(text)));
+    };
+
+    let quest_views = quests
+        .into_iter()
+        .map(|quest| {
+            let buttons = quest
+                .answers
+                .iter()
+                .map(|(label, value)| view! { cx, <button on:click=move |_| answer(quest.key, value)>{*label}</button> })
+                .collect_view(cx);
+            view! { cx,
+                <li prop:hidden=move || answered.with(|a| a.contains(&quest.key))>
+                    {quest.question} " " {buttons}
+                </li>
+            }
+        })
+        .collect_view(cx);
+
+    view! { cx,
+        <ul class="quests">{quest_views}</ul>
+        <p class="note">
+            <input
+                placeholder="Something else wrong? Leave a note for mappers"
+                prop:value=move || note.get()
+                on:input=move |ev| set_note.set(event_target_value(&ev))
+            />
+            <button on:click=send_note>"Send note"</button>
+        </p>
+        {move || status.get().map(|s| view! { cx, <p class="quest-status">{s}</p> })}
+    }
+    .into_view(cx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answered_keys_have_no_quest() {
+        let element = Element {
+            tags: [("fee".to_string(), "no".to_string())].into(),
+            ..Default::default()
+        };
+        let keys: Vec<&str> = open_quests(&element).iter().map(|q| q.key).collect();
+        assert_eq!(keys, vec!["wheelchair"]);
+    }
+}
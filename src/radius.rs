@@ -0,0 +1,76 @@
+//! The search radius control. Dragging the slider updates the preference right
+//! away, but searches only follow it once it has settled, so a drag is one
+//! Overpass query instead of dozens.
+
+use crate::prefs::use_preferences;
+use leptos::*;
+use std::cell::RefCell;
+use std::time::Duration;
+
+pub const MIN_M: i64 = 250;
+pub const MAX_M: i64 = 10_000;
+pub const STEP_M: i64 = 250;
+/// Tick marks on the slider.
+pub const PRESETS_M: [i64; 4] = [500, 1000, 2000, 5000];
+/// How long the radius has to stay put before searching again.
+pub const SETTLE: Duration = Duration::from_millis(600);
+
+/// `"750 m"`, `"1 km"`, `"2.5 km"`.
+pub fn format_radius(meters: i64) -> String {
+    if meters < 1000 {
+        format!("{meters} m")
+    } else {
+        format!("{} km", meters as f64 / 1000.0)
+    }
+}
+
+/// `radius`, lagging behind until it hasn't changed for [`SETTLE`].
+pub fn settled(cx: Scope, radius: Memo<i64>) -> ReadSignal<i64> {
+    let (settled, set_settled) = create_signal(cx, radius.get_untracked());
+    let update = RefCell::new(debounce(cx, SETTLE, move |r| {
+        if settled.get_untracked() != r {
+            set_settled.set(r);
+        }
+    }));
+    create_effect(cx, move |_| update.borrow_mut()(radius.get()));
+    settled
+}
+
+#[component]
+pub fn RadiusSlider(cx: Scope, radius: Memo<i64>) -> impl IntoView {
+    let prefs = use_preferences(cx);
+    view! { cx,
+        <label class="radius">
+            "Within "
+            <input
+                type="range"
+                min=MIN_M
+                max=MAX_M
+                step=STEP_M
+                list="radius-presets"
+                prop:value=move || radius.get().to_string()
+                on:input=move |ev| {
+                    if let Ok(meters) = event_target_value(&ev).parse::<i64>() {
+                        prefs.update(|p| p.search_radius_m = Some(meters.clamp(MIN_M, MAX_M)));
+                    }
+                }
+            />
+            <datalist id="radius-presets">
+                {PRESETS_M.into_iter().map(|m| view! { cx, <option value=m label=format_radius(m)></option> }).collect_view(cx)}
+            </datalist>
+            " "{move || format_radius(radius.get())}
+        </label>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_meters_then_kilometers() {
+        assert_eq!(format_radius(750), "750 m");
+        assert_eq!(format_radius(1000), "1 km");
+        assert_eq!(format_radius(2500), "2.5 km");
+    }
+}
@@ -0,0 +1,112 @@
+//! How results are ordered. Each strategy turns a row into a rank (lower comes
+//! first); the settings pick one through [`Ranking`], and the results pipeline
+//! only ever sees the trait, so new strategies don't touch the views.
+
+use crate::family;
+use crate::results::ResultRow;
+use crate::scoring::{self, Confidence};
+use serde_derive::{Deserialize, Serialize};
+
+pub trait RankingStrategy {
+    /// Lower ranks come first.
+    fn rank(&self, row: &ResultRow, confidence: Confidence) -> f64;
+}
+
+/// Walking distance, nothing else.
+pub struct ByDistance;
+
+impl RankingStrategy for ByDistance {
+    fn rank(&self, row: &ResultRow, _: Confidence) -> f64 {
+        row.distance_m
+    }
+}
+
+/// Walking distance, with places that may be gone pushed back; see [`scoring::ranking_distance`].
+pub struct Heuristic;
+
+impl RankingStrategy for Heuristic {
+    fn rank(&self, row: &ResultRow, confidence: Confidence) -> f64 {
+        scoring::ranking_distance(row.distance_m, confidence)
+    }
+}
+
+/// Walking time, which differs from distance around stairs and crossings.
+pub struct ByWalkingTime;
+
+impl RankingStrategy for ByWalkingTime {
+    fn rank(&self, row: &ResultRow, _: Confidence) -> f64 {
+        row.duration_s
+    }
+}
+
+/// Like [`Heuristic`], with changing tables and family rooms pulled forward; see [`family::rank_factor`].
+pub struct FamilyFriendly;
+
+impl RankingStrategy for FamilyFriendly {
+    fn rank(&self, row: &ResultRow, confidence: Confidence) -> f64 {
+        Heuristic.rank(row, confidence) * family::rank_factor(&row.element.tags)
+    }
+}
+
+/// The built-in strategies, as saved in the preferences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Ranking {
+    Distance,
+    #[default]
+    Heuristic,
+    WalkingTime,
+    FamilyFriendly,
+}
+
+impl Ranking {
+    pub const ALL: [Ranking; 4] = [Ranking::Distance, Ranking::Heuristic, Ranking::WalkingTime, Ranking::FamilyFriendly];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Ranking::Distance => "Distance",
+            Ranking::Heuristic => "Distance, likely gone last",
+            Ranking::WalkingTime => "Walking time",
+            Ranking::FamilyFriendly => "Distance, changing tables first",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Ranking {
+        Ranking::ALL.into_iter().find(|r| r.label() == label).unwrap_or_default()
+    }
+
+    pub fn strategy(&self) -> &'static dyn RankingStrategy {
+        match self {
+            Ranking::Distance => &ByDistance,
+            Ranking::Heuristic => &Heuristic,
+            Ranking::WalkingTime => &ByWalkingTime,
+            Ranking::FamilyFriendly => &FamilyFriendly,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Element;
+    use std::rc::Rc;
+
+    #[test]
+    fn strategies_rank_by_their_own_measure() {
+        let row = ResultRow { element: Rc::new(Element::default()), distance_m: 100.0, duration_s: 90.0 };
+        assert_eq!(Ranking::Distance.strategy().rank(&row, Confidence::Low), 100.0);
+        assert_eq!(Ranking::WalkingTime.strategy().rank(&row, Confidence::Low), 90.0);
+        assert!(Ranking::Heuristic.strategy().rank(&row, Confidence::Low) > 100.0);
+        assert_eq!(Ranking::Heuristic.strategy().rank(&row, Confidence::Normal), 100.0);
+        assert_eq!(Ranking::from_label(Ranking::WalkingTime.label()), Ranking::WalkingTime);
+    }
+
+    #[test]
+    fn family_ranking_prefers_changing_tables() {
+        let mut element = Element::default();
+        element.tags.insert("changing_table".to_string(), "yes".to_string());
+        let near = ResultRow { element: Rc::new(Element::default()), distance_m: 100.0, duration_s: 90.0 };
+        let changing = ResultRow { element: Rc::new(element), distance_m: 150.0, duration_s: 130.0 };
+        let family = Ranking::FamilyFriendly.strategy();
+        assert!(family.rank(&changing, Confidence::Normal) < family.rank(&near, Confidence::Normal));
+    }
+}
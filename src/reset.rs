@@ -0,0 +1,171 @@
+//! "Delete all data": lists what the app keeps on this device and how big it is,
+//! then wipes all of it after a confirmation. The page reloads afterwards so
+//! nothing still in memory gets saved again. The user's own data (settings,
+//! places, favorites and visits...) can be downloaded first.
+
+use crate::{export, idb, storage};
+use serde_json::{Map, Value};
+use leptos::*;
+
+/// What a stored key holds, for the list; cached results are one group.
+pub fn describe(key: &str) -> &'static str {
+    match key {
+        "preferences" => "Settings",
+        "saved_places" => "Saved places",
+        "recent_addresses" => "Recent address searches",
+        "addresses" => "Street addresses looked up",
+        "annotations" => "Favorites, notes and ratings",
+        "session" => "Usage stats",
+        "osm_token" | "osm_pkce_verifier" => "OpenStreetMap login",
+        "what3words_api_key" => "what3words API key",
+        "pending_uploads" => "Edits waiting to be uploaded",
+        "tab_leader" => "Open-tab coordination",
+        "overpass_endpoint" => "Preferred Overpass server",
+        "pinned_destination" => "Pinned destination",
+        key if key.starts_with("cache.") => "Cached results",
+        key if key.starts_with(storage::QUARANTINE_PREFIX) => "Unreadable data kept for diagnostics",
+        _ => "Other",
+    }
+}
+
+/// Keys holding what the user entered or did, as opposed to caches and
+/// credentials; these go into the download.
+pub const PERSONAL: &[&str] = &["preferences", "saved_places", "recent_addresses", "annotations", "session", "pinned_destination"];
+
+/// The personal ones of `entries`, as one JSON object by key.
+pub fn personal_data(entries: impl IntoIterator<Item = (String, Value)>) -> Value {
+    Value::Object(entries.into_iter().filter(|(key, _)| PERSONAL.contains(&key.as_str())).collect::<Map<_, _>>())
+}
+
+fn download_personal_data() -> Result<(), String> {
+    let entries = storage::entries().into_iter().filter_map(|(key, _)| Some((key.clone(), storage::load_raw(&key)?)));
+    let json = serde_json::to_string_pretty(&personal_data(entries)).map_err(|e| e.to_string())?;
+    export::download("free2pee-my-data.json", "application/json", &json).map_err(|e| format!("{e:?}"))
+}
+
+/// `(description, entries, bytes)`, merged by description, in first-seen order.
+pub fn summarize<'a>(entries: impl IntoIterator<Item = (&'a str, usize, usize)>) -> Vec<(&'static str, usize, usize)> {
+    let mut groups: Vec<(&'static str, usize, usize)> = Vec::new();
+    for (key, count, bytes) in entries {
+        let label = describe(key);
+        match groups.iter_mut().find(|(l, _, _)| *l == label) {
+            Some(group) => {
+                group.1 += count;
+                group.2 += bytes;
+            }
+            None => groups.push((label, count, bytes)),
+        }
+    }
+    groups
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b < 1024 => format!("{b} B"),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+async fn inventory() -> Vec<(&'static str, usize, usize)> {
+    let local = storage::entries();
+    let mut entries: Vec<(&str, usize, usize)> = local.iter().map(|(key, bytes)| (key.as_str(), 1, *bytes)).collect();
+    let stores = idb::usage().await.unwrap_or_default();
+    entries.extend(stores.into_iter().filter(|(_, count, _)| *count > 0));
+    summarize(entries)
+}
+
+#[component]
+pub fn ResetPanel(cx: Scope) -> impl IntoView {
+    let (confirming, set_confirming) = create_signal(cx, false);
+    let stored = create_local_resource(cx, move || confirming.get(), |open| async move {
+        if open { inventory().await } else { Vec::new() }
+    });
+    let (error, set_error) = create_signal(cx, None::<String>);
+    let wipe = move |_| {
+        spawn_local(async move {
+            match storage::wipe_all().await {
+                Ok(()) => {
+                    if let Some(window) = web_sys::window() {
+                        let _ = window.location().reload();
+                    }
+                }
+                Err(e) => set_error.set(Some(format!("Nothing was deleted: {e}"))),
+            }
+        });
+    };
+    let list = move || {
+        stored.read(cx).map(|groups| {
+            if groups.is_empty() {
+                return view! { cx, <p>"Nothing is stored."</p> }.into_view(cx);
+            }
+            let total: usize = groups.iter().map(|(_, _, bytes)| bytes).sum();
+            view! { cx,
+                <ul>
+                    {groups.into_iter().map(|(label, count, bytes)| view! { cx,
+                        <li>{format!("{label}: {count} item(s), {}", format_bytes(bytes))}</li>
+                    }).collect_view(cx)}
+                </ul>
+                <p>{format!("{} in total.", format_bytes(total))}</p>
+            }
+            .into_view(cx)
+        })
+    };
+    view! { cx,
+        <div class="reset">
+            <button on:click=move |_| {
+                if let Err(e) = download_personal_data() {
+                    set_error.set(Some(format!("Couldn't download your data: {e}")));
+                }
+            }>"Download my data"</button>
+            " "
+            <button prop:hidden=move || confirming.get() on:click=move |_| set_confirming.set(true)>
+                "Delete all data..."
+            </button>
+            <Show when=move || confirming.get() fallback=|_| ()>
+                <p>"This deletes everything Free2Pee keeps on this device:"</p>
+                {list}
+                <button class="danger" on:click=wipe>"Delete everything"</button>
+                " "
+                <button on:click=move |_| set_confirming.set(false)>"Cancel"</button>
+            </Show>
+            {move || error.get().map(|e| view! { cx, <p class="error">{e}</p> })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_stored_keys() {
+        let groups = summarize([
+            ("preferences", 1, 200),
+            ("cache.toilets.52.520,13.405,1000", 1, 5000),
+            ("cache.outdoors.52.520,13.405,5000", 1, 3000),
+            ("pending_uploads", 2, 300),
+            ("quarantine.preferences", 1, 40),
+        ]);
+        assert_eq!(
+            groups,
+            [
+                ("Settings", 1, 200),
+                ("Cached results", 2, 8000),
+                ("Edits waiting to be uploaded", 2, 300),
+                ("Unreadable data kept for diagnostics", 1, 40)
+            ]
+        );
+        assert_eq!(format_bytes(2048), "2.0 KB");
+    }
+
+    #[test]
+    fn downloads_only_personal_data() {
+        let data = personal_data([
+            ("annotations".to_string(), serde_json::json!({"node/1": {"visited": 0.0}})),
+            ("osm_token".to_string(), serde_json::json!("secret")),
+            ("cache.toilets.52.520,13.405,1000".to_string(), serde_json::json!({})),
+        ]);
+        assert_eq!(data, serde_json::json!({"annotations": {"node/1": {"visited": 0.0}}}));
+    }
+}
@@ -0,0 +1,286 @@
+//! The results table's data, as a chain of memos: raw response → parsed rows →
+//! filtered → scored → sorted. Each stage only recomputes when its own inputs
+//! change, so sorting by a column doesn't re-filter, and a filter toggle
+//! doesn't re-parse the response.
+
+use crate::collate::Collator;
+use crate::family;
+use crate::filters::{self, FilterState};
+use crate::i18n::Locale;
+use crate::opening_hours::WeekTime;
+use crate::prefs::use_preferences;
+use crate::ranking::{Ranking, RankingStrategy};
+use crate::scoring::{self, Confidence};
+use crate::sorting::{self, SortKey, SortState};
+use crate::spatial::GridIndex;
+use crate::trip::AlongRoute;
+use crate::annotations::{self, AnnotationControls};
+use crate::{links, osm_links, quality, Bathrooms, Element, OsmType};
+use leptos::{error::Result, *};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultRow {
+    pub element: Rc<Element>,
+    pub distance_m: f64,
+    pub duration_s: f64,
+}
+
+impl ResultRow {
+    /// Stable identity across refetches; ids are only unique per element type.
+    pub fn key(&self) -> (OsmType, i64) {
+        (self.element.type_field, self.element.id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredRow {
+    pub row: ResultRow,
+    pub confidence: Confidence,
+    /// Lower comes first; see [`RankingStrategy`].
+    pub rank: f64,
+}
+
+/// About 500 m, a bit more than a phone's map at street zoom shows across.
+const INDEX_CELL_DEG: f64 = 0.005;
+
+/// The scored rows by location, for the map's viewport, arrivals and
+/// nearest-first order while following. Built once per set of rows, so a
+/// position fix or a map pan doesn't scan every row.
+#[derive(Clone)]
+pub struct RowIndex(Rc<GridIndex<ScoredRow>>);
+
+impl RowIndex {
+    pub fn new(rows: &[ScoredRow]) -> RowIndex {
+        let points = rows.iter().map(|r| ((r.row.element.lat, r.row.element.lon), r.clone()));
+        RowIndex(Rc::new(GridIndex::new(points, INDEX_CELL_DEG)))
+    }
+}
+
+impl std::ops::Deref for RowIndex {
+    type Target = GridIndex<ScoredRow>;
+
+    fn deref(&self) -> &GridIndex<ScoredRow> {
+        &self.0
+    }
+}
+
+/// Only ever rebuilt when the rows change, so a new index is a different one.
+impl PartialEq for RowIndex {
+    fn eq(&self, other: &RowIndex) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Pair elements with their distance and duration from the origin (column 0 of the table).
+pub fn parse(bathrooms: &Bathrooms) -> Vec<ResultRow> {
+    let (response, table, _) = bathrooms;
+    let dists = table.distances.first().map(Vec::as_slice).unwrap_or_default();
+    let durs = table.durations.first().map(Vec::as_slice).unwrap_or_default();
+    response
+        .elements
+        .iter()
+        .zip(dists.iter().skip(1))
+        .zip(durs.iter().skip(1))
+        .map(|((element, &distance_m), &duration_s)| ResultRow {
+            element: Rc::new(element.clone()),
+            distance_m,
+            duration_s,
+        })
+        .collect()
+}
+
+pub fn score(rows: &[ResultRow], now_ms: f64, strategy: &dyn RankingStrategy) -> Vec<ScoredRow> {
+    rows.iter()
+        .map(|row| {
+            let confidence = scoring::confidence(&row.element, now_ms);
+            ScoredRow {
+                row: row.clone(),
+                confidence,
+                rank: strategy.rank(row, confidence),
+            }
+        })
+        .collect()
+}
+
+/// Best rank first, then by the column in `order` if there is one. That sort
+/// is stable, so ties stay in rank order; see [`sorting::compare`] for the rest.
+pub fn sort(mut rows: Vec<ScoredRow>, order: SortState, collator: Option<&Collator>, visited: &HashSet<String>) -> Vec<ScoredRow> {
+    rows.sort_by(|a, b| a.rank.total_cmp(&b.rank));
+    if order != SortState::default() {
+        rows.sort_by(|a, b| sorting::compare(order, a, b, collator, visited));
+    }
+    rows
+}
+
+/// Sorted rows of the current response, and their [index](RowIndex); empty
+/// while loading or on error.
+pub fn pipeline<S: Clone + 'static>(
+    cx: Scope,
+    bathrooms: Resource<S, Result<Bathrooms>>,
+    filters: FilterState,
+    order: ReadSignal<SortState>,
+    ranking: Memo<Ranking>,
+    along_route: Signal<Option<Rc<AlongRoute>>>,
+    locale: Memo<Locale>,
+) -> (Memo<Vec<ScoredRow>>, Memo<RowIndex>) {
+    let parsed = create_memo(cx, move |_| {
+        bathrooms.with(cx, |b| b.as_ref().map(parse).unwrap_or_default()).unwrap_or_default()
+    });
+    let annotations = annotations::use_annotations(cx);
+    // Annotations are only read when they matter, so a new note doesn't re-filter.
+    let visited = move |needed: bool| match needed {
+        true => annotations.with(annotations::visited_keys),
+        false => HashSet::new(),
+    };
+    let filtered = create_memo(cx, move |_| {
+        let now = WeekTime::from_js_millis(js_sys::Date::now());
+        let want = filters.visited.get();
+        let been = visited(want.is_some());
+        parsed.with(|rows| {
+            rows.iter()
+                .filter(|r| filters.keeps(&r.element.tags, now))
+                .filter(|r| filters::matches_visited(want, been.contains(&annotations::element_key(&r.element))))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    });
+    // A loaded route takes over from the chosen ranking.
+    let scored = create_memo(cx, move |_| {
+        let route = along_route.get();
+        let strategy: &dyn RankingStrategy = match &route {
+            Some(route) => route.as_ref(),
+            None => ranking.get().strategy(),
+        };
+        filtered.with(|rows| score(rows, js_sys::Date::now(), strategy))
+    });
+    // Sorting only reorders, so the index is built from the scored rows.
+    let index = create_memo(cx, move |_| scored.with(|rows| RowIndex::new(rows)));
+    let sorted = create_memo(cx, move |_| {
+        let order = order.get();
+        let collator = (order.key == SortKey::Name).then(|| Collator::new(locale.get()));
+        sort(scored.get(), order, collator.as_ref(), &visited(order.key == SortKey::Visited))
+    });
+    (sorted, index)
+}
+
+/// Tags sorted by key, for display.
+pub fn sorted_tags(element: &Element) -> Vec<(String, String)> {
+    element.tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+/// Where a row's data came from, e.g. "node 42, version 3, last edited 2021-05-01".
+pub fn provenance(element: &Element) -> String {
+    let mut parts = vec![format!("{} {}", quality::osm_type(element), element.id)];
+    if let Some(version) = element.version {
+        parts.push(format!("version {version}"));
+    }
+    if let Some(day) = element.timestamp.as_deref().and_then(|t| t.get(..10)) {
+        parts.push(format!("last edited {day}"));
+    }
+    parts.join(", ")
+}
+
+/// The full tag list, provenance and actions for an expanded row.
+#[component]
+pub fn RowDetails(cx: Scope, element: Rc<Element>, columns: usize) -> impl IntoView {
+    let travel_mode = use_preferences(cx).with_untracked(|p| p.travel_mode);
+    let tags = sorted_tags(&element)
+        .into_iter()
+        .map(|(k, v)| view! { cx, <dt>{k}</dt><dd>{v}</dd> })
+        .collect_view(cx);
+    let changing_table = family::has_changing_table(&element.tags).then(|| {
+        let location = family::changing_table_location(&element.tags).unwrap_or_else(|| "location not mapped".to_string());
+        view! { cx, <p class="changing-table">"Changing table: " {location}</p> }
+    });
+    view! { cx,
+        <td colspan=columns.to_string()>
+            {changing_table}
+            <dl class="tags">{tags}</dl>
+            <p class="provenance">{provenance(&element)} " · © OpenStreetMap contributors"</p>
+            <a href=osm_links::view_url(&element) target="_blank">"View on OSM"</a>
+            " "
+            <a href=links::google_directions_by(travel_mode, (element.lat, element.lon)) target="_blank">"Directions"</a>
+            <p class="report">
+                "Something wrong or missing? "
+                <a href=osm_links::edit_url(&element) target="_blank">"Fix it in iD"</a>
+                " or "
+                <a href=osm_links::note_url(&element) target="_blank">"leave a note for mappers"</a>
+                "."
+            </p>
+            <AnnotationControls element=element.clone()/>
+        </td>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OverpassResponse, TableRoot};
+
+    fn element(id: i64, name: Option<&str>) -> Element {
+        let mut element = Element { id, ..Default::default() };
+        if let Some(name) = name {
+            element.tags.insert("name".to_string(), name.to_string());
+        }
+        element
+    }
+
+    #[test]
+    fn parse_skips_the_origin_column() {
+        let response = OverpassResponse {
+            elements: vec![element(1, None), element(2, None)],
+            ..Default::default()
+        };
+        let table = TableRoot {
+            distances: vec![vec![0.0, 300.0, 100.0]],
+            durations: vec![vec![0.0, 30.0, 10.0]],
+            ..Default::default()
+        };
+        let rows = parse(&(response, table, (0.0, 0.0)));
+        assert_eq!(rows.iter().map(|r| (r.element.id, r.distance_m)).collect::<Vec<_>>(), [(1, 300.0), (2, 100.0)]);
+    }
+
+    #[test]
+    fn sorts_by_rank_then_optionally_by_a_column() {
+        let rows = [(1, Some("Zoo"), 100.0), (2, None, 50.0), (3, Some("Äpfel"), 200.0)]
+            .into_iter()
+            .map(|(id, name, distance_m)| ResultRow { element: Rc::new(element(id, name)), distance_m, duration_s: 0.0 })
+            .collect::<Vec<_>>();
+        let scored = score(&rows, 0.0, Ranking::Heuristic.strategy());
+        let ids = |rows: Vec<ScoredRow>| rows.iter().map(|r| r.row.element.id).collect::<Vec<_>>();
+        let by_name = SortState::default().toggle(SortKey::Name);
+        let none = HashSet::new();
+        assert_eq!(ids(sort(scored.clone(), SortState::default(), None, &none)), [2, 1, 3]);
+        assert_eq!(ids(sort(scored.clone(), by_name, Some(&Collator::new(Locale::De)), &none)), [3, 1, 2]);
+        assert_eq!(ids(sort(scored, by_name.toggle(SortKey::Name), Some(&Collator::new(Locale::De)), &none)), [1, 3, 2]);
+    }
+
+    #[test]
+    fn places_ways_at_their_center_and_skips_unlocated_elements() {
+        let json = r#"{"version":0.6,"generator":"test","osm3s":{"timestamp_osm_base":"","copyright":""},"elements":[
+            {"type":"node","id":1,"lat":1.0,"lon":2.0,"tags":{"amenity":"toilets"}},
+            {"type":"way","id":1,"center":{"lat":3.0,"lon":4.0},"tags":{"building":"toilets"}},
+            {"type":"relation","id":5}
+        ]}"#;
+        let mut response: OverpassResponse = serde_json::from_str(json).unwrap();
+        let placed: Vec<_> = response.elements.iter().map(|e| (e.type_field, e.id, e.lat, e.lon)).collect();
+        assert_eq!(placed, [(OsmType::Node, 1, 1.0, 2.0), (OsmType::Way, 1, 3.0, 4.0)]);
+        // A node and a way can share an id.
+        response.merge(response.clone());
+        assert_eq!(response.elements.len(), 2);
+    }
+
+    #[test]
+    fn describes_provenance() {
+        let mut e = element(42, Some("x"));
+        assert_eq!(provenance(&e), "node 42");
+        e.type_field = OsmType::Way;
+        e.version = Some(3);
+        e.timestamp = Some("2021-05-01T10:00:00Z".to_string());
+        assert_eq!(provenance(&e), "way 42, version 3, last edited 2021-05-01");
+        e.tags.insert("amenity".to_string(), "toilets".to_string());
+        assert_eq!(sorted_tags(&e)[0], ("amenity".to_string(), "toilets".to_string()));
+    }
+}
@@ -0,0 +1,263 @@
+//! Hash-based page routing (`#/trip`), which keeps working on GitHub Pages
+//! where unknown paths under the repo's base URL would 404. An optional locale
+//! prefix (`#/de/trip`) forces the interface language, and a `map` parameter
+//! (`#/?map=16/52.52/13.405`) the area being looked at, for shareable links.
+//! Those also carry the search itself: `amenity`, radius `r` and `filters`.
+
+use crate::filters::{self, Filters};
+use crate::i18n::Locale;
+use crate::packs::AmenityPack;
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Finder,
+    Trip,
+    Mapper,
+    Diagnostics,
+}
+
+impl Page {
+    fn from_segment(segment: &str) -> Page {
+        match segment {
+            "trip" => Page::Trip,
+            "mapper" => Page::Mapper,
+            "diagnostics" => Page::Diagnostics,
+            _ => Page::Finder,
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self {
+            Page::Finder => "",
+            Page::Trip => "trip",
+            Page::Mapper => "mapper",
+            Page::Diagnostics => "diagnostics",
+        }
+    }
+}
+
+/// Map center and zoom, written `zoom/lat/lon` like on openstreetmap.org.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub lat: f64,
+    pub lon: f64,
+    pub zoom: u8,
+}
+
+impl Viewport {
+    pub const MAX_ZOOM: u8 = 19;
+
+    pub fn parse(s: &str) -> Option<Viewport> {
+        let mut parts = s.split('/');
+        let zoom: u8 = parts.next()?.parse().ok()?;
+        let lat: f64 = parts.next()?.parse().ok()?;
+        let lon: f64 = parts.next()?.parse().ok()?;
+        let valid = parts.next().is_none()
+            && zoom <= Viewport::MAX_ZOOM
+            && (-90.0..=90.0).contains(&lat)
+            && (-180.0..=180.0).contains(&lon);
+        valid.then_some(Viewport { lat, lon, zoom })
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (self.lat, self.lon)
+    }
+}
+
+impl std::fmt::Display for Viewport {
+    /// Five decimals is about a meter, plenty for any zoom.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}/{:.5}/{:.5}", self.zoom, self.lat, self.lon)
+    }
+}
+
+/// What to search for, as far as the link says; missing parts are left as they are.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchState {
+    pub pack: Option<AmenityPack>,
+    pub radius_m: Option<i64>,
+    pub filters: Option<Filters>,
+}
+
+impl SearchState {
+    fn parse(query: &str) -> SearchState {
+        let param = |name: &str| query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='));
+        SearchState {
+            pack: param("amenity").and_then(AmenityPack::from_key),
+            radius_m: param("r").and_then(|r| r.parse().ok()).filter(|r| *r > 0),
+            filters: param("filters").map(filters::decode).filter(|f| !f.is_empty()),
+        }
+    }
+
+    fn write(&self, params: &mut Vec<String>) {
+        if let Some(pack) = self.pack {
+            params.push(format!("amenity={}", pack.key()));
+        }
+        if let Some(radius) = self.radius_m {
+            params.push(format!("r={radius}"));
+        }
+        if let Some(filters) = self.filters.as_ref().filter(|f| !f.is_empty()) {
+            params.push(format!("filters={}", filters::encode(filters)));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub locale: Option<Locale>,
+    pub page: Page,
+    pub viewport: Option<Viewport>,
+    pub search: SearchState,
+}
+
+impl Route {
+    /// Parse a location hash, with or without the leading `#`.
+    pub fn from_hash(hash: &str) -> Route {
+        let hash = hash.trim_start_matches('#').trim_start_matches('/');
+        let (path, query) = hash.split_once('?').unwrap_or((hash, ""));
+        let param = |name: &str| query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='));
+        // `lat`/`lon` are easier to write by hand than `map`.
+        let viewport = param("map").and_then(Viewport::parse).or_else(|| {
+            let (lat, lon) = (param("lat")?, param("lon")?);
+            Viewport::parse(&format!("{}/{lat}/{lon}", crate::manual_location::ZOOM))
+        });
+        let search = SearchState::parse(query);
+        let mut segments = path.split('/');
+        let first = segments.next().unwrap_or_default();
+        // Only exact two-letter codes, so a page can never be mistaken for a locale.
+        match (first.len() == 2).then(|| Locale::from_tag(first)).flatten() {
+            Some(locale) => Route {
+                locale: Some(locale),
+                page: Page::from_segment(segments.next().unwrap_or_default()),
+                viewport,
+                search,
+            },
+            None => Route {
+                locale: None,
+                page: Page::from_segment(first),
+                viewport,
+                search,
+            },
+        }
+    }
+
+    /// Link to `page`, keeping this route's locale, viewport and search.
+    pub fn href(&self, page: Page) -> String {
+        let path = match self.locale {
+            Some(locale) => format!("#/{}/{}", locale.code(), page.path()),
+            None => format!("#/{}", page.path()),
+        };
+        let mut params: Vec<String> = self.viewport.map(|v| format!("map={v}")).into_iter().collect();
+        self.search.write(&mut params);
+        match params.is_empty() {
+            true => path,
+            false => format!("{path}?{}", params.join("&")),
+        }
+    }
+
+    /// This route looking at `viewport` instead.
+    pub fn with_viewport(&self, viewport: Option<Viewport>) -> Route {
+        Route { viewport, ..self.clone() }
+    }
+
+    /// This route with `search` instead.
+    pub fn with_search(&self, search: SearchState) -> Route {
+        Route { search, ..self.clone() }
+    }
+}
+
+/// The current route, following `hashchange` events, shared with the whole app.
+pub fn provide_route(cx: Scope) -> ReadSignal<Route> {
+    let (route, set_route) = create_signal(cx, Route::from_hash(&location_hash().unwrap_or_default()));
+    window_event_listener(ev::hashchange, move |_| {
+        set_route.set(Route::from_hash(&location_hash().unwrap_or_default()));
+    });
+    provide_context(cx, route);
+    route
+}
+
+/// The route [provided](provide_route) by the app; without one, follows the hash itself.
+pub fn use_route(cx: Scope) -> ReadSignal<Route> {
+    use_context(cx).unwrap_or_else(|| provide_route(cx))
+}
+
+/// Go to `route`. Setting the hash fires `hashchange`, so [`provide_route`] follows.
+pub fn navigate(route: &Route) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().set_hash(&route.href(route.page));
+    }
+}
+
+/// Like [`navigate`], without adding to the history, for keeping the URL in
+/// step with what's on screen.
+pub fn replace(route: &Route) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().replace(&route.href(route.page));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_routes() {
+        assert_eq!(Route::from_hash("#/trip"), Route { locale: None, page: Page::Trip, viewport: None, search: SearchState::default() });
+        assert_eq!(Route::from_hash(""), Route { locale: None, page: Page::Finder, viewport: None, search: SearchState::default() });
+        assert_eq!(Route::from_hash("#/mapper?x=1"), Route { locale: None, page: Page::Mapper, viewport: None, search: SearchState::default() });
+        assert_eq!(Route::from_hash("#/diagnostics").page, Page::Diagnostics);
+    }
+
+    #[test]
+    fn parses_locale_prefixes() {
+        assert_eq!(Route::from_hash("#/de/trip"), Route { locale: Some(Locale::De), page: Page::Trip, viewport: None, search: SearchState::default() });
+        assert_eq!(Route::from_hash("#/fr/"), Route { locale: Some(Locale::Fr), page: Page::Finder, viewport: None, search: SearchState::default() });
+        assert_eq!(Route::from_hash("#/xx/trip"), Route { locale: None, page: Page::Finder, viewport: None, search: SearchState::default() });
+    }
+
+    #[test]
+    fn links_keep_the_locale() {
+        let route = Route::from_hash("#/es/trip");
+        assert_eq!(route.href(Page::Mapper), "#/es/mapper");
+        assert_eq!(Route::from_hash("#/").href(Page::Trip), "#/trip");
+    }
+
+    #[test]
+    fn parses_and_keeps_the_viewport() {
+        let route = Route::from_hash("#/de/trip?map=16/52.52/13.405");
+        assert_eq!(route.viewport, Some(Viewport { lat: 52.52, lon: 13.405, zoom: 16 }));
+        assert_eq!(route.page, Page::Trip);
+        assert_eq!(route.href(Page::Finder), "#/de/?map=16/52.52000/13.40500");
+        assert_eq!(Route::from_hash(&route.href(Page::Finder)).viewport, route.viewport);
+        assert_eq!(route.with_viewport(None).href(Page::Finder), "#/de/");
+    }
+
+    #[test]
+    fn rejects_bad_viewports() {
+        assert_eq!(Viewport::parse("16/52.5"), None);
+        assert_eq!(Viewport::parse("25/52.5/13.4"), None);
+        assert_eq!(Viewport::parse("16/95/13.4"), None);
+        assert_eq!(Viewport::parse("16/52.5/13.4/1"), None);
+        assert_eq!(Route::from_hash("#/?map=x").viewport, None);
+    }
+
+    #[test]
+    fn round_trips_the_search() {
+        let route = Route::from_hash("#/?map=16/52.52/13.405&amenity=drinking_water&r=2000&filters=free,open_now");
+        assert_eq!(route.search.pack, Some(AmenityPack::DrinkingWater));
+        assert_eq!(route.search.radius_m, Some(2000));
+        assert_eq!(route.search.filters.as_ref().map(filters::encode).as_deref(), Some("free,open_now"));
+        assert_eq!(route.href(Page::Finder), "#/?map=16/52.52000/13.40500&amenity=drinking_water&r=2000&filters=free,open_now");
+        assert_eq!(Route::from_hash(&route.href(Page::Finder)), route);
+        assert_eq!(Route::from_hash("#/?r=-5&amenity=lava").search, SearchState::default());
+    }
+
+    #[test]
+    fn takes_lat_lon_for_the_viewport() {
+        let route = Route::from_hash("#/?lat=52.52&lon=13.405&r=500");
+        assert_eq!(route.viewport, Some(Viewport { lat: 52.52, lon: 13.405, zoom: crate::manual_location::ZOOM }));
+        assert_eq!(route.search.radius_m, Some(500));
+    }
+}
@@ -0,0 +1,128 @@
+//! Heuristics for how much to trust an element and where it ranks.
+
+use crate::Element;
+
+/// Tags that only say what an element is, not anything about it.
+const CLASSIFICATION_KEYS: [&str; 4] = ["amenity", "building", "tourism", "toilets"];
+
+/// Last edited longer ago than this counts as very old.
+pub const OLD_AFTER_DAYS: i64 = 5 * 365;
+
+/// Low-confidence results rank as if they were this much further away.
+pub const LOW_CONFIDENCE_DISTANCE_FACTOR: f64 = 1.5;
+
+const MS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    Normal,
+    /// No descriptive tags and not touched in years: it may well be gone.
+    Low,
+}
+
+pub fn descriptive_tag_count(element: &Element) -> usize {
+    element
+        .tags
+        .keys()
+        .filter(|key| !CLASSIFICATION_KEYS.contains(key))
+        .count()
+}
+
+/// Days since the Unix epoch for the date part of an ISO 8601 timestamp
+/// such as Overpass' `2019-03-04T12:00:00Z`.
+pub fn epoch_days(timestamp: &str) -> Option<i64> {
+    let date = timestamp.get(..10)?;
+    let mut parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+    // Howard Hinnant's days_from_civil.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+/// Age of the element's last edit in days, if Overpass told us when that was.
+pub fn age_days(element: &Element, now_ms: f64) -> Option<i64> {
+    let edited = epoch_days(element.timestamp.as_deref()?)?;
+    Some((now_ms / MS_PER_DAY).floor() as i64 - edited)
+}
+
+/// Both rules have to hold: a bare but recently checked node, or an old one with
+/// details, is still worth showing normally. Unknown edit dates never count as old.
+pub fn confidence(element: &Element, now_ms: f64) -> Confidence {
+    let old = age_days(element, now_ms).is_some_and(|days| days > OLD_AFTER_DAYS);
+    if old && descriptive_tag_count(element) == 0 {
+        Confidence::Low
+    } else {
+        Confidence::Normal
+    }
+}
+
+/// Distance used for ordering results.
+pub fn ranking_distance(distance_m: f64, confidence: Confidence) -> f64 {
+    match confidence {
+        Confidence::Normal => distance_m,
+        Confidence::Low => distance_m * LOW_CONFIDENCE_DISTANCE_FACTOR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01T00:00:00Z
+    const NOW_MS: f64 = 1_704_067_200_000.0;
+
+    fn element(tags: &[(&str, &str)], timestamp: Option<&str>) -> Element {
+        Element {
+            tags: tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            timestamp: timestamp.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn epoch_days_matches_known_dates() {
+        assert_eq!(epoch_days("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(epoch_days("2000-03-01"), Some(11_017));
+        assert_eq!(epoch_days("2024-01-01T00:00:00Z"), Some(19_723));
+        assert_eq!(epoch_days("yesterday"), None);
+        assert_eq!(epoch_days("2024-13-01"), None);
+    }
+
+    #[test]
+    fn bare_and_old_is_low() {
+        let e = element(&[("amenity", "toilets")], Some("2012-06-01T10:00:00Z"));
+        assert_eq!(confidence(&e, NOW_MS), Confidence::Low);
+    }
+
+    #[test]
+    fn descriptive_tags_keep_confidence() {
+        let e = element(&[("amenity", "toilets"), ("fee", "no")], Some("2012-06-01T10:00:00Z"));
+        assert_eq!(confidence(&e, NOW_MS), Confidence::Normal);
+    }
+
+    #[test]
+    fn recent_edits_keep_confidence() {
+        let e = element(&[("amenity", "toilets")], Some("2023-06-01T10:00:00Z"));
+        assert_eq!(confidence(&e, NOW_MS), Confidence::Normal);
+    }
+
+    #[test]
+    fn unknown_age_is_not_old() {
+        let e = element(&[("amenity", "toilets")], None);
+        assert_eq!(confidence(&e, NOW_MS), Confidence::Normal);
+    }
+
+    #[test]
+    fn low_confidence_is_demoted_not_dropped() {
+        assert!(ranking_distance(100.0, Confidence::Low) > ranking_distance(120.0, Confidence::Normal));
+        assert!(ranking_distance(100.0, Confidence::Low) < ranking_distance(200.0, Confidence::Normal));
+    }
+}
@@ -0,0 +1,120 @@
+//! A summary of how the app has been used, for people who like stats: searches,
+//! distance walked while following, places arrived at. It's kept in local
+//! storage only, never reported anywhere (unlike [`metrics`](crate::metrics)),
+//! and can be wiped.
+
+use crate::annotations::use_annotations;
+use crate::{geo, storage, tabs};
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+const STORAGE_KEY: &str = "session";
+
+/// Position fixes closer than this to the last counted one are GPS noise.
+pub const JITTER_M: f64 = 10.0;
+/// Fixes further than this from the last one are a jump (a lost fix, a ride), not a walk.
+pub const MAX_STEP_M: f64 = 500.0;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionStats {
+    pub searches: u32,
+    pub walked_m: f64,
+    /// Keys (`type/id`) of places arrived at while following.
+    pub visited: BTreeSet<String>,
+    /// Where walking was last counted from; not part of the summary.
+    #[serde(skip)]
+    pub last_fix: Option<(f64, f64)>,
+}
+
+impl SessionStats {
+    /// Count the walk to a new position fix, skipping noise and jumps.
+    pub fn walked_to(&mut self, at: (f64, f64)) {
+        let Some(last) = self.last_fix else {
+            self.last_fix = Some(at);
+            return;
+        };
+        let step = geo::haversine_m(last, at);
+        if step < JITTER_M {
+            return;
+        }
+        if step <= MAX_STEP_M {
+            self.walked_m += step;
+        }
+        self.last_fix = Some(at);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Session(RwSignal<SessionStats>);
+
+impl Session {
+    /// Change the stats and save them.
+    pub fn record(&self, f: impl FnOnce(&mut SessionStats)) {
+        self.0.update(|stats| {
+            f(stats);
+            storage::save(STORAGE_KEY, stats);
+        });
+    }
+
+    pub fn wipe(&self) {
+        storage::remove(STORAGE_KEY);
+        self.0.set(SessionStats::default());
+    }
+}
+
+pub fn provide_session(cx: Scope) {
+    let stats = create_rw_signal(cx, storage::load::<SessionStats>(STORAGE_KEY).unwrap_or_default());
+    tabs::use_tabs(cx).on_stored_elsewhere(cx, |key| key == STORAGE_KEY, move |_| {
+        let last_fix = stats.with_untracked(|s| s.last_fix);
+        stats.set(SessionStats { last_fix, ..storage::load(STORAGE_KEY).unwrap_or_default() });
+    });
+    provide_context(cx, Session(stats));
+}
+
+pub fn use_session(cx: Scope) -> Session {
+    use_context(cx).unwrap_or_else(|| Session(create_rw_signal(cx, SessionStats::default())))
+}
+
+#[component]
+pub fn SessionSummary(cx: Scope) -> impl IntoView {
+    let session = use_session(cx);
+    let annotations = use_annotations(cx);
+    let favorites = move || annotations.with(|a| a.values().filter(|a| a.favorite).count());
+    view! { cx,
+        <details class="session">
+            <summary>"Your stats"</summary>
+            <p>"Kept on this device only."</p>
+            <dl>
+                <dt>"Searches"</dt>
+                <dd>{move || session.0.with(|s| s.searches)}</dd>
+                <dt>"Walked while following"</dt>
+                <dd>{move || session.0.with(|s| format!("{:.1} km", s.walked_m / 1000.0))}</dd>
+                <dt>"Places visited"</dt>
+                <dd>{move || session.0.with(|s| s.visited.len())}</dd>
+                <dt>"Favorites"</dt>
+                <dd>{favorites}</dd>
+            </dl>
+            <button on:click=move |_| session.wipe()>"Wipe stats"</button>
+        </details>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_walking_but_not_noise_or_jumps() {
+        let mut stats = SessionStats::default();
+        stats.walked_to((52.5, 13.4));
+        stats.walked_to((52.50001, 13.4));
+        assert_eq!(stats.walked_m, 0.0);
+        stats.walked_to((52.501, 13.4));
+        assert!((stats.walked_m - 111.0).abs() < 1.0, "{}", stats.walked_m);
+        stats.walked_to((52.6, 13.4));
+        assert!((stats.walked_m - 111.0).abs() < 1.0);
+        assert_eq!(stats.last_fix, Some((52.6, 13.4)));
+    }
+}
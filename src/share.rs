@@ -0,0 +1,203 @@
+//! A one-line description of the nearest result for pasting into group chats:
+//! "Nearest public toilet: 180 m NE, free, open until 22:00 — osm.org/node/123".
+//! Rendered from an ICU message (see [`i18n::NEAREST_SUMMARY`]), with numbers
+//! and compass points in the reader's locale.
+
+use crate::i18n::{self, use_i18n, Locale};
+use crate::msgfmt::Arg;
+use crate::notify::format_hhmm;
+use crate::opening_hours::{OpeningHours, WeekTime};
+use crate::results::{ResultRow, ScoredRow};
+use crate::toast::use_toasts;
+use crate::{geo, OsmType};
+use leptos::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["navigator", "clipboard"], js_name = writeText)]
+    fn write_text(text: &str) -> js_sys::Promise;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hours {
+    Unknown,
+    Always,
+    /// Open now, until this minute of the day.
+    Until(u16),
+    Closed,
+}
+
+impl Hours {
+    pub fn from_tag(tag: Option<&str>, now: WeekTime) -> Hours {
+        let Some(tag) = tag else {
+            return Hours::Unknown;
+        };
+        if tag.trim() == "24/7" {
+            return Hours::Always;
+        }
+        match tag.parse::<OpeningHours>() {
+            Ok(hours) => hours.closes_at(now).map_or(Hours::Closed, |end| Hours::Until(end % (24 * 60))),
+            Err(_) => Hours::Unknown,
+        }
+    }
+}
+
+/// What the summary says about a place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Facts {
+    /// Tagged `amenity=toilets`, as opposed to a café or station that has some.
+    pub public: bool,
+    pub distance_m: f64,
+    pub bearing_deg: f64,
+    pub paid: Option<bool>,
+    pub hours: Hours,
+    pub key: (OsmType, i64),
+}
+
+impl Facts {
+    pub fn of(row: &ResultRow, origin: (f64, f64), now: WeekTime) -> Facts {
+        let element = &row.element;
+        Facts {
+            public: element.tags.get("amenity") == Some("toilets"),
+            distance_m: row.distance_m,
+            bearing_deg: geo::bearing_deg(origin, (element.lat, element.lon)),
+            paid: match element.tags.get("fee") {
+                Some("no") => Some(false),
+                Some("yes") => Some(true),
+                _ => None,
+            },
+            hours: Hours::from_tag(element.tags.get("opening_hours"), now),
+            key: row.key(),
+        }
+    }
+}
+
+/// `"180 m"`, `"1.2 km"`, with the locale's decimal separator.
+pub fn format_distance(locale: Locale, meters: f64) -> String {
+    if meters < 1000.0 {
+        return format!("{meters:.0} m");
+    }
+    let km = format!("{:.1} km", meters / 1000.0);
+    match locale {
+        Locale::En => km,
+        Locale::De | Locale::Fr | Locale::Es => km.replace('.', ","),
+    }
+}
+
+/// The nearest of the eight compass points, abbreviated as the locale does.
+pub fn compass_abbreviation(locale: Locale, bearing_deg: f64) -> &'static str {
+    let points = match locale {
+        Locale::En => ["N", "NE", "E", "SE", "S", "SW", "W", "NW"],
+        Locale::De => ["N", "NO", "O", "SO", "S", "SW", "W", "NW"],
+        Locale::Fr | Locale::Es => ["N", "NE", "E", "SE", "S", "SO", "O", "NO"],
+    };
+    points[((bearing_deg.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8]
+}
+
+pub fn summary(locale: Locale, facts: &Facts) -> String {
+    let (kind, id) = facts.key;
+    let distance = format_distance(locale, facts.distance_m);
+    let link = format!("osm.org/{kind}/{id}");
+    let closes = match facts.hours {
+        Hours::Until(minute) => format_hhmm(minute),
+        _ => String::new(),
+    };
+    let hours = match facts.hours {
+        Hours::Unknown => "unknown",
+        Hours::Always => "always",
+        Hours::Until(_) => "until",
+        Hours::Closed => "closed",
+    };
+    let fee = match facts.paid {
+        Some(false) => "free",
+        Some(true) => "paid",
+        None => "unknown",
+    };
+    i18n::format(locale, i18n::NEAREST_SUMMARY, &[
+        ("amenity", Arg::Str(if facts.public { "toilets" } else { "other" })),
+        ("distance", Arg::Str(&distance)),
+        ("direction", Arg::Str(compass_abbreviation(locale, facts.bearing_deg))),
+        ("fee", Arg::Str(fee)),
+        ("hours", Arg::Str(hours)),
+        ("closes", Arg::Str(&closes)),
+        ("link", Arg::Str(&link)),
+    ])
+}
+
+/// The row closest to the origin by walking distance.
+pub fn nearest(rows: &[ScoredRow]) -> Option<&ResultRow> {
+    rows.iter().map(|r| &r.row).min_by(|a, b| a.distance_m.total_cmp(&b.distance_m))
+}
+
+async fn copy(text: &str) -> Result<(), JsValue> {
+    JsFuture::from(write_text(text)).await.map(|_| ())
+}
+
+/// Copies the nearest of `rows`' summary to the clipboard.
+#[component]
+pub fn CopySummary(cx: Scope, rows: Memo<Vec<ScoredRow>>, origin: Memo<Option<(f64, f64)>>) -> impl IntoView {
+    let i18n = use_i18n(cx);
+    let toasts = use_toasts(cx);
+    let text = move || {
+        let origin = origin.get()?;
+        let now = WeekTime::from_js_millis(js_sys::Date::now());
+        rows.with(|rows| nearest(rows).map(|row| summary(i18n.0.get(), &Facts::of(row, origin, now))))
+    };
+    let on_click = move |_| {
+        let Some(text) = text() else {
+            return;
+        };
+        spawn_local(async move {
+            match copy(&text).await {
+                Ok(()) => toasts.push(format!("Copied: {text}")),
+                Err(e) => {
+                    log::warn!("couldn't copy the summary: {e:?}");
+                    toasts.push("Couldn't copy; your browser blocked the clipboard.");
+                }
+            }
+        });
+    };
+    view! { cx,
+        <button class="copy-summary" prop:disabled=move || rows.with(Vec::is_empty) on:click=on_click>
+            "Copy summary"
+        </button>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts(hours: Hours, paid: Option<bool>) -> Facts {
+        Facts { public: true, distance_m: 180.0, bearing_deg: 40.0, paid, hours, key: (OsmType::Node, 123) }
+    }
+
+    #[test]
+    fn summarizes_the_nearest_toilet() {
+        let facts = facts(Hours::Until(22 * 60), Some(false));
+        assert_eq!(summary(Locale::En, &facts), "Nearest public toilet: 180 m NE, free, open until 22:00 — osm.org/node/123");
+        assert_eq!(
+            summary(Locale::De, &Facts { distance_m: 1240.0, ..facts }),
+            "Nächste öffentliche Toilette: 1,2 km NO, kostenlos, geöffnet bis 22:00 — osm.org/node/123"
+        );
+    }
+
+    #[test]
+    fn leaves_out_what_is_not_known() {
+        let cafe = Facts { public: false, ..facts(Hours::Unknown, None) };
+        assert_eq!(summary(Locale::En, &cafe), "Nearest toilet: 180 m NE — osm.org/node/123");
+        assert_eq!(summary(Locale::Es, &facts(Hours::Always, Some(true))), "Baño público más cercano: 180 m NE, de pago, abierto 24 h — osm.org/node/123");
+    }
+
+    #[test]
+    fn reads_opening_hours() {
+        let monday_noon = WeekTime::new(0, 12, 0);
+        assert_eq!(Hours::from_tag(Some("24/7"), monday_noon), Hours::Always);
+        assert_eq!(Hours::from_tag(Some("Mo-Fr 08:00-22:00"), monday_noon), Hours::Until(22 * 60));
+        assert_eq!(Hours::from_tag(Some("Mo 18:00-02:00"), WeekTime::new(0, 23, 0)), Hours::Until(2 * 60));
+        assert_eq!(Hours::from_tag(Some("Sa 10:00-12:00"), monday_noon), Hours::Closed);
+        assert_eq!(Hours::from_tag(Some("sunrise-sunset"), monday_noon), Hours::Unknown);
+    }
+}
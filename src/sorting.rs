@@ -0,0 +1,176 @@
+//! Sorting the results table by a column. The ranked order is the default;
+//! clicking a column header sorts by it ascending, again descending, and a
+//! third time goes back to the ranking. Places missing the value (unnamed,
+//! fee or wheelchair access not mapped) stay at the bottom either way.
+//! Sorting by visited puts the places the user has been to first.
+
+use crate::annotations;
+use crate::collate::Collator;
+use crate::results::ScoredRow;
+use crate::tags::Tags;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Rank,
+    Name,
+    Distance,
+    Id,
+    Fee,
+    Access,
+    Visited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortState {
+    pub key: SortKey,
+    pub direction: Direction,
+}
+
+impl SortState {
+    /// The state after clicking the header for `key`.
+    pub fn toggle(self, key: SortKey) -> SortState {
+        match (self.key == key, self.direction) {
+            (false, _) => SortState { key, direction: Direction::Ascending },
+            (true, Direction::Ascending) => SortState { key, direction: Direction::Descending },
+            (true, Direction::Descending) => SortState::default(),
+        }
+    }
+
+    /// The arrow for `key`'s header; empty unless the table is sorted by it.
+    pub fn indicator(&self, key: SortKey) -> &'static str {
+        match (self.key == key, self.direction) {
+            (false, _) => "",
+            (true, Direction::Ascending) => " ▲",
+            (true, Direction::Descending) => " ▼",
+        }
+    }
+
+    /// `aria-sort` for `key`'s header.
+    pub fn aria(&self, key: SortKey) -> &'static str {
+        match (self.key == key, self.direction) {
+            (false, _) => "none",
+            (true, Direction::Ascending) => "ascending",
+            (true, Direction::Descending) => "descending",
+        }
+    }
+}
+
+/// Free first, then paid; `None` when `fee` isn't mapped.
+pub fn fee_order(tags: &Tags) -> Option<u8> {
+    match tags.get("fee")? {
+        "no" => Some(0),
+        "donation" => Some(1),
+        _ => Some(2),
+    }
+}
+
+/// Wheelchair accessible first, then limited, then not.
+pub fn access_order(tags: &Tags) -> Option<u8> {
+    match tags.get("wheelchair")? {
+        "yes" | "designated" => Some(0),
+        "limited" => Some(1),
+        "no" => Some(2),
+        _ => None,
+    }
+}
+
+/// `order` in `direction`, with missing values last regardless.
+fn missing_last<T>(a: Option<T>, b: Option<T>, direction: Direction, order: impl FnOnce(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => match direction {
+            Direction::Ascending => order(a, b),
+            Direction::Descending => order(a, b).reverse(),
+        },
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+fn by<T: PartialOrd>(a: Option<T>, b: Option<T>, direction: Direction) -> Ordering {
+    missing_last(a, b, direction, |a, b| a.partial_cmp(&b).unwrap_or(Ordering::Equal))
+}
+
+/// How `a` and `b` order under `state`; names compare with `collator` when
+/// given, and `visited` has the [annotation keys](crate::annotations::element_key)
+/// of places the user has been to.
+pub fn compare(state: SortState, a: &ScoredRow, b: &ScoredRow, collator: Option<&Collator>, visited: &HashSet<String>) -> Ordering {
+    let (ea, eb) = (&a.row.element, &b.row.element);
+    match state.key {
+        SortKey::Rank => by(Some(a.rank), Some(b.rank), state.direction),
+        SortKey::Name => missing_last(ea.tags.get("name"), eb.tags.get("name"), state.direction, |a, b| match collator {
+            Some(collator) => collator.compare(a, b),
+            None => a.cmp(b),
+        }),
+        SortKey::Distance => by(Some(a.row.distance_m), Some(b.row.distance_m), state.direction),
+        SortKey::Id => by(Some(ea.id), Some(eb.id), state.direction),
+        SortKey::Fee => by(fee_order(&ea.tags), fee_order(&eb.tags), state.direction),
+        SortKey::Access => by(access_order(&ea.tags), access_order(&eb.tags), state.direction),
+        SortKey::Visited => {
+            let been = |e| !visited.contains(&annotations::element_key(e));
+            by(Some(been(ea)), Some(been(eb)), state.direction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::ResultRow;
+    use crate::scoring::Confidence;
+    use crate::Element;
+    use std::rc::Rc;
+
+    fn row(id: i64, distance_m: f64, tags: &[(&str, &str)]) -> ScoredRow {
+        let mut element = Element { id, ..Default::default() };
+        for (k, v) in tags {
+            element.tags.insert(k.to_string(), v.to_string());
+        }
+        ScoredRow {
+            row: ResultRow { element: Rc::new(element), distance_m, duration_s: 0.0 },
+            confidence: Confidence::Normal,
+            rank: distance_m,
+        }
+    }
+
+    fn ids(mut rows: Vec<ScoredRow>, state: SortState) -> Vec<i64> {
+        rows.sort_by(|a, b| compare(state, a, b, None, &HashSet::from(["node/2".to_string()])));
+        rows.iter().map(|r| r.row.element.id).collect()
+    }
+
+    #[test]
+    fn headers_cycle_up_down_and_back() {
+        let name = SortState::default().toggle(SortKey::Name);
+        assert_eq!((name.key, name.indicator(SortKey::Name)), (SortKey::Name, " ▲"));
+        let name = name.toggle(SortKey::Name);
+        assert_eq!((name.indicator(SortKey::Name), name.aria(SortKey::Name)), (" ▼", "descending"));
+        assert_eq!(name.toggle(SortKey::Name), SortState::default());
+        assert_eq!(name.toggle(SortKey::Fee), SortState { key: SortKey::Fee, direction: Direction::Ascending });
+        assert_eq!(name.indicator(SortKey::Fee), "");
+    }
+
+    #[test]
+    fn unmapped_values_stay_last() {
+        let rows = vec![
+            row(1, 300.0, &[("fee", "yes"), ("wheelchair", "no")]),
+            row(2, 100.0, &[]),
+            row(3, 200.0, &[("fee", "no"), ("wheelchair", "yes")]),
+        ];
+        let asc = |key| SortState { key, direction: Direction::Ascending };
+        let desc = |key| SortState { key, direction: Direction::Descending };
+        assert_eq!(ids(rows.clone(), asc(SortKey::Distance)), [2, 3, 1]);
+        assert_eq!(ids(rows.clone(), desc(SortKey::Id)), [3, 2, 1]);
+        assert_eq!(ids(rows.clone(), asc(SortKey::Fee)), [3, 1, 2]);
+        assert_eq!(ids(rows.clone(), desc(SortKey::Fee)), [1, 3, 2]);
+        assert_eq!(ids(rows.clone(), desc(SortKey::Access)), [1, 3, 2]);
+        assert_eq!(ids(rows, asc(SortKey::Visited)), [2, 1, 3]);
+    }
+}
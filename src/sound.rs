@@ -0,0 +1,81 @@
+//! Short sound cues, synthesized with the Web Audio API so there's nothing to
+//! download. Play them through [`Notifier::play`](crate::notify::Notifier::play),
+//! which knows about the volume setting and quiet hours.
+
+use std::cell::RefCell;
+use wasm_bindgen::JsValue;
+use web_sys::{AudioContext, OscillatorType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    ResultsReady,
+    Arrival,
+    Error,
+}
+
+/// One tone of a cue: frequency in Hz and length in seconds.
+pub type Note = (f32, f64);
+
+impl Cue {
+    /// Rising for good news, falling for bad.
+    pub fn notes(&self) -> &'static [Note] {
+        match self {
+            Cue::ResultsReady => &[(660.0, 0.08), (880.0, 0.12)],
+            Cue::Arrival => &[(523.25, 0.1), (659.25, 0.1), (783.99, 0.18)],
+            Cue::Error => &[(440.0, 0.12), (330.0, 0.2)],
+        }
+    }
+}
+
+thread_local! {
+    // Browsers cap the number of audio contexts, so there's one for the page.
+    static CONTEXT: RefCell<Option<AudioContext>> = const { RefCell::new(None) };
+}
+
+fn context() -> Result<AudioContext, JsValue> {
+    CONTEXT.with(|c| {
+        if let Some(ctx) = c.borrow().as_ref() {
+            return Ok(ctx.clone());
+        }
+        let ctx = AudioContext::new()?;
+        *c.borrow_mut() = Some(ctx.clone());
+        Ok(ctx)
+    })
+}
+
+/// Play `cue` at `volume` (0 to 1). Contexts start suspended until the page has
+/// seen a user gesture, so the first cue may be silent.
+pub fn play(cue: Cue, volume: f64) -> Result<(), JsValue> {
+    let ctx = context()?;
+    let _ = ctx.resume()?;
+    let mut at = ctx.current_time();
+    for &(frequency, length) in cue.notes() {
+        let oscillator = ctx.create_oscillator()?;
+        oscillator.set_type(OscillatorType::Sine);
+        oscillator.frequency().set_value_at_time(frequency, at)?;
+        let gain = ctx.create_gain()?;
+        // A quick fade in and out, so notes don't click.
+        gain.gain().set_value_at_time(0.0, at)?;
+        gain.gain().linear_ramp_to_value_at_time(volume.clamp(0.0, 1.0) as f32, at + 0.01)?;
+        gain.gain().linear_ramp_to_value_at_time(0.0, at + length)?;
+        oscillator.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&ctx.destination())?;
+        oscillator.start_with_when(at)?;
+        oscillator.stop_with_when(at + length)?;
+        at += length;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cues_are_short() {
+        for cue in [Cue::ResultsReady, Cue::Arrival, Cue::Error] {
+            let length: f64 = cue.notes().iter().map(|(_, l)| l).sum();
+            assert!(length > 0.0 && length < 0.5, "{cue:?}");
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! A uniform lat/lon grid over a set of points, so bounding box and nearest-N
+//! queries only look at nearby cells instead of every point. Doesn't wrap
+//! around the antimeridian, which no search here crosses.
+
+use crate::geo::{haversine_m, BBox};
+use std::collections::HashMap;
+
+const M_PER_DEG_LAT: f64 = 111_320.0;
+
+pub struct GridIndex<T> {
+    points: Vec<((f64, f64), T)>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    cell_deg: f64,
+    bounds: Option<BBox>,
+}
+
+impl<T> GridIndex<T> {
+    /// Index `(lat, lon)` points in square cells `cell_deg` degrees wide. Cells
+    /// a bit larger than a typical query keep the number of cells visited small.
+    pub fn new(items: impl IntoIterator<Item = ((f64, f64), T)>, cell_deg: f64) -> GridIndex<T> {
+        let points: Vec<_> = items.into_iter().collect();
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        let mut bounds: Option<BBox> = None;
+        for (i, &((lat, lon), _)) in points.iter().enumerate() {
+            cells.entry(cell(lat, lon, cell_deg)).or_default().push(i);
+            bounds = Some(match bounds {
+                None => BBox { south: lat, west: lon, north: lat, east: lon },
+                Some(b) => BBox {
+                    south: b.south.min(lat),
+                    west: b.west.min(lon),
+                    north: b.north.max(lat),
+                    east: b.east.max(lon),
+                },
+            });
+        }
+        GridIndex { points, cells, cell_deg, bounds }
+    }
+
+    /// Points inside `bbox`, edges included, in no particular order.
+    pub fn within(&self, bbox: &BBox) -> Vec<((f64, f64), &T)> {
+        let (south, west) = cell(bbox.south, bbox.west, self.cell_deg);
+        let (north, east) = cell(bbox.north, bbox.east, self.cell_deg);
+        let visited = (north - south + 1).saturating_mul(east - west + 1);
+        let candidates: Box<dyn Iterator<Item = usize>> = if visited as usize > self.points.len() {
+            // A query much larger than the data: scanning beats walking empty cells.
+            Box::new(0..self.points.len())
+        } else {
+            Box::new(
+                (south..=north)
+                    .flat_map(|row| (west..=east).map(move |col| (row, col)))
+                    .filter_map(|c| self.cells.get(&c))
+                    .flatten()
+                    .copied(),
+            )
+        };
+        candidates
+            .map(|i| &self.points[i])
+            .filter(|(point, _)| bbox.contains(*point))
+            .map(|(point, item)| (*point, item))
+            .collect()
+    }
+
+    /// Up to `n` points closest to `point`, nearest first, with their distance in meters.
+    pub fn nearest(&self, point: (f64, f64), n: usize) -> Vec<(f64, &T)> {
+        let Some(bounds) = self.bounds else {
+            return Vec::new();
+        };
+        if n == 0 {
+            return Vec::new();
+        }
+        let mut radius_m = self.cell_deg * M_PER_DEG_LAT;
+        loop {
+            let search = around(point, radius_m);
+            let covers_all = search.south <= bounds.south
+                && search.west <= bounds.west
+                && search.north >= bounds.north
+                && search.east >= bounds.east;
+            let mut found: Vec<(f64, &T)> = self
+                .within(&search)
+                .into_iter()
+                .map(|(p, item)| (haversine_m(point, p), item))
+                // Only points inside the circle are certain to beat ones outside the box.
+                .filter(|(d, _)| covers_all || *d <= radius_m)
+                .collect();
+            if found.len() >= n || covers_all {
+                found.sort_by(|a, b| a.0.total_cmp(&b.0));
+                found.truncate(n);
+                return found;
+            }
+            radius_m *= 2.0;
+        }
+    }
+}
+
+fn cell(lat: f64, lon: f64, cell_deg: f64) -> (i64, i64) {
+    ((lat / cell_deg).floor() as i64, (lon / cell_deg).floor() as i64)
+}
+
+/// The box around the circle of `radius_m` around `(lat, lon)`.
+fn around((lat, lon): (f64, f64), radius_m: f64) -> BBox {
+    let d_lat = radius_m / M_PER_DEG_LAT;
+    // Clamped so the box stays finite near the poles.
+    let d_lon = d_lat / lat.to_radians().cos().max(0.01);
+    BBox {
+        south: lat - d_lat,
+        west: lon - d_lon,
+        north: lat + d_lat,
+        east: lon + d_lon,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> GridIndex<usize> {
+        let points = (0..20).flat_map(|i| (0..20).map(move |j| (52.0 + i as f64 * 0.001, 13.0 + j as f64 * 0.001)));
+        GridIndex::new(points.enumerate().map(|(i, p)| (p, i)), 0.002)
+    }
+
+    #[test]
+    fn finds_points_in_a_box() {
+        let bbox = BBox { south: 52.0045, west: 13.0045, north: 52.0075, east: 13.0065 };
+        let mut found: Vec<usize> = grid().within(&bbox).into_iter().map(|(_, &i)| i).collect();
+        found.sort();
+        assert_eq!(found, vec![105, 106, 125, 126, 145, 146]);
+    }
+
+    #[test]
+    fn nearest_matches_a_full_scan() {
+        let grid = grid();
+        for (point, n) in [((52.0101, 13.0052), 5), ((51.9, 12.9), 3), ((52.0, 13.0), 400), ((52.0, 13.0), 1000)] {
+            let mut expected: Vec<f64> = grid.points.iter().map(|(p, _)| haversine_m(point, *p)).collect();
+            expected.sort_by(f64::total_cmp);
+            expected.truncate(n);
+            let found: Vec<f64> = grid.nearest(point, n).into_iter().map(|(d, _)| d).collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn nearest_vertex_of_a_line() {
+        let line = [(0.0, 0.0), (0.0, 1.0), (0.0, 2.0)];
+        let grid = GridIndex::new(line.into_iter().enumerate().map(|(i, p)| (p, i)), 0.01);
+        assert_eq!(grid.nearest((0.1, 1.2), 1)[0].1, &1);
+    }
+
+    #[test]
+    fn empty_index_finds_nothing() {
+        let grid: GridIndex<()> = GridIndex::new([], 0.01);
+        assert!(grid.nearest((52.0, 13.0), 3).is_empty());
+    }
+}
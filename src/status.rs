@@ -0,0 +1,79 @@
+//! A notice while a third-party service is failing, from the provider status
+//! events on the [bus](crate::bus); since those are broadcast, a tab also
+//! hears about trouble another tab ran into. Also a dot for the health of
+//! the Overpass mirror in use, checked every few minutes.
+
+use crate::bus::{use_bus, Event, Provider};
+use crate::idle::use_activity;
+use crate::mirrors::{self, Health};
+use leptos::*;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+const HEALTH_EVERY: Duration = Duration::from_secs(5 * 60);
+
+pub fn update(failing: &mut BTreeSet<Provider>, provider: Provider, ok: bool) {
+    if ok {
+        failing.remove(&provider);
+    } else {
+        failing.insert(provider);
+    }
+}
+
+#[component]
+pub fn ServiceStatus(cx: Scope) -> impl IntoView {
+    let failing = create_rw_signal(cx, BTreeSet::new());
+    use_bus(cx).subscribe(cx, move |event| {
+        if let Event::ProviderStatus { provider, ok } = event {
+            failing.update(|f| update(f, *provider, *ok));
+        }
+    });
+    move || {
+        failing.with(|f| {
+            f.iter()
+                .map(|p| view! { cx, <p class="warning">{format!("{} isn't responding right now.", p.label())}</p> })
+                .collect_view(cx)
+        })
+    }
+}
+
+/// Green, yellow or red for the mirror searches go to first; not checked while the user is away.
+#[component]
+pub fn MirrorHealth(cx: Scope) -> impl IntoView {
+    let activity = use_activity(cx);
+    let health = create_rw_signal(cx, None::<(&'static str, Health)>);
+    let check = move || {
+        let endpoint = mirrors::endpoints()[0];
+        spawn_local(async move {
+            health.set(Some((endpoint, mirrors::probe(endpoint).await)));
+        });
+    };
+    check();
+    let tick = move || {
+        if !activity.is_idle() {
+            check();
+        }
+    };
+    if let Ok(handle) = set_interval_with_handle(tick, HEALTH_EVERY) {
+        on_cleanup(cx, move || handle.clear());
+    }
+    move || {
+        health.get().map(|(endpoint, h)| view! { cx,
+            <span class=format!("health-dot {}", h.class()) title=format!("{endpoint} is {}", h.describe())>"●"</span>
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_per_provider() {
+        let mut failing = BTreeSet::new();
+        update(&mut failing, Provider::Search, false);
+        update(&mut failing, Provider::Geocoder, false);
+        update(&mut failing, Provider::Search, true);
+        assert_eq!(failing.into_iter().collect::<Vec<_>>(), [Provider::Geocoder]);
+    }
+}
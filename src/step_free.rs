@@ -0,0 +1,76 @@
+//! Step-free hints: OSRM's foot profile happily routes over stairs, so for the
+//! selected place the walking route is checked against `highway=steps` ways and
+//! flagged when it may include some.
+
+use crate::api::overpass::fetch_overpass;
+use crate::overpass::{Area, Output, QueryBuilder, Select};
+use crate::prefs::use_preferences;
+use crate::trip::{self, TravelMode};
+use crate::geo;
+use leptos::{error::Result, *};
+
+/// Steps this close to the route line count as on it; routes follow way centerlines.
+const STEPS_NEAR_M: i64 = 5;
+
+/// A yes/no answer shouldn't queue behind big searches.
+const STEPS_TIMEOUT_S: u32 = 10;
+
+/// Like trip corridors, the line is simplified to keep the Overpass query short.
+const MAX_LINE_POINTS: usize = 100;
+
+/// Overpass QL for at most one node of any `highway=steps` way along `line`.
+pub fn steps_query(line: &[(f64, f64)]) -> String {
+    let simplified = geo::simplify_to_at_most(line, MAX_LINE_POINTS, 2.0);
+    QueryBuilder::new()
+        .timeout(STEPS_TIMEOUT_S)
+        .select(Select::ways().tag("highway", "steps").within(Area::around_line(STEPS_NEAR_M, &simplified)))
+        .way_nodes()
+        .output(Output::Skel)
+        .limit(1)
+        .build()
+}
+
+/// Whether the walking route from `origin` to `target` passes any steps.
+pub async fn route_has_steps(origin: (f64, f64), target: (f64, f64)) -> Result<bool> {
+    let route = trip::fetch_route(TravelMode::Foot, origin, target).await?;
+    let line = geo::decode_polyline(&route.geometry, 5).ok_or(trip::TripError::NoRoute)?;
+    Ok(!fetch_overpass(&steps_query(&line)).await?.elements.is_empty())
+}
+
+/// "May include stairs" for the route to `target`, when step-free hints are on.
+#[component]
+pub fn StairsHint(cx: Scope, origin: (f64, f64), target: (f64, f64)) -> impl IntoView {
+    let prefs = use_preferences(cx);
+    let steps = create_local_resource(cx, move || prefs.with(|p| p.step_free_hints), move |on| async move {
+        match on {
+            true => route_has_steps(origin, target).await.map(Some),
+            false => Ok(None),
+        }
+    });
+    move || {
+        let text = match steps.read(cx)? {
+            Ok(Some(true)) => "The walking route may include stairs.",
+            Ok(Some(false)) => "No stairs mapped along the walking route.",
+            Ok(None) => return None,
+            Err(e) => {
+                log::warn!("couldn't check the route for steps: {e}");
+                return None;
+            }
+        };
+        Some(view! { cx, <span class="stairs">{text}</span> })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_steps_along_the_line() {
+        let query = steps_query(&[(52.5, 13.4), (52.501, 13.401)]);
+        assert_eq!(
+            query,
+            "[out:json][timeout:10];way[\"highway\"=\"steps\"](around:5,52.5,13.4,52.501,13.401);node(w);out skel 1;"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! JSON values persisted in `localStorage`.
+//!
+//! Everything here fails soft: no storage (private mode, quota) just means
+//! nothing gets remembered, and a value that can't be read is quarantined (see
+//! [`on_quarantine`]) so the app starts from defaults instead. Values saved by
+//! a newer release are skipped but kept. Outside the browser (tests) an
+//! in-memory map stands in for `localStorage`.
+
+use crate::idb;
+use crate::migrations::{self, MigrationError};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use thiserror::Error;
+#[cfg(target_arch = "wasm32")]
+use web_sys::{window, Storage};
+
+const PREFIX: &str = "free2pee.";
+
+/// Unreadable values are moved under this prefix, keeping a copy for diagnostics.
+pub const QUARANTINE_PREFIX: &str = "quarantine.";
+
+type QuarantineHandler = Box<dyn Fn(&str)>;
+
+thread_local! {
+    /// Keys quarantined before anyone was listening, then the listener.
+    static QUARANTINED: RefCell<(Vec<String>, Option<QuarantineHandler>)> = const { RefCell::new((Vec::new(), None)) };
+}
+
+/// Call `f` with the key of every value that gets quarantined, including ones
+/// quarantined before this was called (at startup, say).
+pub fn on_quarantine(f: impl Fn(&str) + 'static) {
+    let pending = QUARANTINED.with(|q| std::mem::take(&mut q.borrow_mut().0));
+    pending.iter().for_each(|key| f(key));
+    QUARANTINED.with(|q| q.borrow_mut().1 = Some(Box::new(f)));
+}
+
+/// Move the unreadable value at `key` aside so it's neither read nor overwritten again.
+fn quarantine(storage: &Storage, key: &str, raw: &str, reason: &str) {
+    log::warn!("{key} couldn't be read ({reason}); quarantined it");
+    let _ = storage.set_item(&format!("{PREFIX}{QUARANTINE_PREFIX}{key}"), raw);
+    let _ = storage.remove_item(&format!("{PREFIX}{key}"));
+    QUARANTINED.with(|q| {
+        let mut q = q.borrow_mut();
+        match &q.1 {
+            Some(handler) => handler(key),
+            None => q.0.push(key.to_string()),
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<Storage> {
+    window()?.local_storage().ok().flatten()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+use memory::Storage;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn local_storage() -> Option<Storage> {
+    Some(Storage)
+}
+
+/// The parts of the `Storage` API used here, over a map per thread.
+#[cfg(not(target_arch = "wasm32"))]
+mod memory {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    thread_local! {
+        static ITEMS: RefCell<BTreeMap<String, String>> = const { RefCell::new(BTreeMap::new()) };
+    }
+
+    pub struct Storage;
+
+    impl Storage {
+        pub fn get_item(&self, key: &str) -> Result<Option<String>, ()> {
+            Ok(ITEMS.with(|items| items.borrow().get(key).cloned()))
+        }
+
+        pub fn set_item(&self, key: &str, value: &str) -> Result<(), ()> {
+            ITEMS.with(|items| items.borrow_mut().insert(key.to_string(), value.to_string()));
+            Ok(())
+        }
+
+        pub fn remove_item(&self, key: &str) -> Result<(), ()> {
+            ITEMS.with(|items| items.borrow_mut().remove(key));
+            Ok(())
+        }
+
+        pub fn length(&self) -> Result<u32, ()> {
+            Ok(ITEMS.with(|items| items.borrow().len() as u32))
+        }
+
+        pub fn key(&self, index: u32) -> Result<Option<String>, ()> {
+            Ok(ITEMS.with(|items| items.borrow().keys().nth(index as usize).cloned()))
+        }
+    }
+}
+
+/// A `localStorage` key without the app's prefix; `None` for other apps' keys.
+pub fn unprefixed(key: &str) -> Option<&str> {
+    key.strip_prefix(PREFIX)
+}
+
+/// The stored JSON as is, still wrapped in its schema version if it has one.
+pub fn load_raw(key: &str) -> Option<Value> {
+    let raw = local_storage()?.get_item(&format!("{PREFIX}{key}")).ok().flatten()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Loads through [`migrations`], so values saved by older releases come back
+/// upgraded. A value that doesn't parse, can't be migrated or doesn't fit `T`
+/// is quarantined and `None` returned, so callers fall back to their defaults.
+/// One saved by a newer release is valid there, so it's left in place for
+/// when that release is back, and `None` returned here.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let storage = local_storage()?;
+    let raw = storage.get_item(&format!("{PREFIX}{key}")).ok().flatten()?;
+    let data = match serde_json::from_str(&raw).map(|value| migrations::upgrade(key, value)) {
+        Ok(Ok(data)) => data,
+        Ok(Err(e @ MigrationError::TooNew(_))) => {
+            log::warn!("{key}: {e} Skipped it.");
+            return None;
+        }
+        Err(e) => {
+            quarantine(&storage, key, &raw, &e.to_string());
+            return None;
+        }
+    };
+    match serde_json::from_value(data) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            quarantine(&storage, key, &raw, &e.to_string());
+            None
+        }
+    }
+}
+
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum StorageError {
+    #[error("Storage isn't available in this browser.")]
+    Unavailable,
+    #[error("Couldn't serialize {0}.")]
+    Serialize(String),
+    /// Usually the quota.
+    #[error("Couldn't write {0}; storage may be full.")]
+    Write(String),
+}
+
+/// Store `raw` exactly; see [`load_raw`].
+pub fn save_raw(key: &str, raw: &Value) -> Result<(), StorageError> {
+    let storage = local_storage().ok_or(StorageError::Unavailable)?;
+    storage
+        .set_item(&format!("{PREFIX}{key}"), &raw.to_string())
+        .map_err(|_| StorageError::Write(key.to_string()))
+}
+
+/// For callers that need to know whether the value was persisted. Values with
+/// a schema are stored stamped with its current version.
+pub fn try_save<T: Serialize>(key: &str, value: &T) -> Result<(), StorageError> {
+    let data = serde_json::to_value(value).map_err(|_| StorageError::Serialize(key.to_string()))?;
+    match migrations::schema_for(key) {
+        Some(schema) => save_raw(key, &migrations::stamp(schema, data)),
+        None => save_raw(key, &data),
+    }
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    match try_save(key, value) {
+        Ok(()) | Err(StorageError::Unavailable) => {}
+        Err(e) => log::warn!("{e}"),
+    }
+}
+
+pub fn remove(key: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(&format!("{PREFIX}{key}"));
+    }
+}
+
+/// Every key this app has stored (without the prefix) and the size of its value in bytes.
+pub fn entries() -> Vec<(String, usize)> {
+    let Some(storage) = local_storage() else {
+        return Vec::new();
+    };
+    let len = storage.length().unwrap_or(0);
+    let mut entries: Vec<(String, usize)> = (0..len)
+        .filter_map(|i| storage.key(i).ok().flatten())
+        .filter_map(|key| {
+            let size = storage.get_item(&key).ok().flatten()?.len();
+            Some((key.strip_prefix(PREFIX)?.to_string(), size))
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// Remove everything this app has stored here, leaving other sites' and apps' keys alone.
+fn clear() {
+    if let Some(storage) = local_storage() {
+        for (key, _) in entries() {
+            let _ = storage.remove_item(&format!("{PREFIX}{key}"));
+        }
+    }
+}
+
+/// Delete everything the app has stored, here and in IndexedDB. The IndexedDB
+/// stores are cleared in one transaction first, so if that fails nothing is
+/// deleted; `localStorage` is then cleared without yielding in between.
+pub async fn wipe_all() -> Result<(), idb::IdbError> {
+    match idb::clear_all().await {
+        Ok(()) | Err(idb::IdbError::Unavailable) => {}
+        Err(e) => return Err(e),
+    }
+    clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::Annotations;
+    use serde_json::json;
+
+    #[test]
+    fn newer_values_are_kept_and_unreadable_ones_quarantined() {
+        let newer = migrations::schema_for("annotations").unwrap().version() + 1;
+        let saved = json!({ "schema": newer, "data": { "node/1": { "favorite": true } } });
+        save_raw("annotations", &saved).unwrap();
+        assert_eq!(load::<Annotations>("annotations"), None);
+        assert_eq!(load_raw("annotations"), Some(saved));
+        assert!(load_raw(&format!("{QUARANTINE_PREFIX}annotations")).is_none());
+
+        save_raw("session", &json!("not a session")).unwrap();
+        assert_eq!(load::<crate::session::SessionStats>("session"), None);
+        assert_eq!(load_raw("session"), None);
+        assert_eq!(load_raw(&format!("{QUARANTINE_PREFIX}session")), Some(json!("not a session")));
+    }
+}
@@ -0,0 +1,129 @@
+//! Coordination between open tabs, through `localStorage`: the `storage` event
+//! fires in every other tab of the origin when a value changes, and is passed
+//! on as [`Event::Stored`]. Settings, annotations, stats and saved places are
+//! reloaded when another tab saves them, and a short lease elects one leader
+//! tab for what shouldn't run twice: follow-me's position watch and kiosk
+//! refreshes.
+
+use crate::bus::{use_bus, Bus, Event};
+use crate::storage;
+use leptos::*;
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+
+const LEASE_KEY: &str = "tab_leader";
+
+/// A leader that stops renewing (closed, frozen in the background) is replaced after this.
+pub const LEASE_FOR_MS: f64 = 6000.0;
+const RENEW_EVERY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Lease {
+    pub tab: u64,
+    pub until_ms: f64,
+}
+
+/// The lease `tab` holds after trying to claim it at `now`; `None` while
+/// another tab's lease is still good, unless taking over.
+pub fn claim(current: Option<Lease>, tab: u64, now: f64, take_over: bool) -> Option<Lease> {
+    match current {
+        Some(lease) if lease.tab != tab && lease.until_ms > now && !take_over => None,
+        _ => Some(Lease { tab, until_ms: now + LEASE_FOR_MS }),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Tabs {
+    id: u64,
+    leader: RwSignal<bool>,
+    bus: Bus,
+}
+
+impl Tabs {
+    /// Reactive.
+    pub fn is_leader(&self) -> bool {
+        self.leader.get()
+    }
+
+    pub fn is_leader_untracked(&self) -> bool {
+        self.leader.get_untracked()
+    }
+
+    fn renew(&self, take_over: bool) {
+        let current = storage::load::<Lease>(LEASE_KEY);
+        let lease = claim(current, self.id, js_sys::Date::now(), take_over);
+        if let Some(lease) = lease {
+            storage::save(LEASE_KEY, &lease);
+        }
+        if self.leader.get_untracked() != lease.is_some() {
+            self.leader.set(lease.is_some());
+        }
+    }
+
+    /// Become the leader now, e.g. when the user starts following here.
+    pub fn take_over(&self) {
+        self.renew(true);
+    }
+
+    fn resign(&self) {
+        if storage::load::<Lease>(LEASE_KEY).is_some_and(|l| l.tab == self.id) {
+            storage::remove(LEASE_KEY);
+        }
+    }
+
+    /// Run `f` with the key whenever another tab stores a key that `matches`.
+    pub fn on_stored_elsewhere(&self, cx: Scope, matches: impl Fn(&str) -> bool + 'static, f: impl Fn(&str) + 'static) {
+        self.bus.subscribe(cx, move |event| match event {
+            Event::Stored { key } if matches(key) => f(key),
+            _ => {}
+        });
+    }
+}
+
+pub fn provide_tabs(cx: Scope) -> Tabs {
+    let tabs = Tabs {
+        id: (js_sys::Math::random() * 2f64.powi(53)) as u64,
+        leader: create_rw_signal(cx, false),
+        bus: use_bus(cx),
+    };
+    tabs.renew(false);
+    if let Ok(handle) = set_interval_with_handle(move || tabs.renew(false), RENEW_EVERY) {
+        on_cleanup(cx, move || handle.clear());
+    }
+    window_event_listener(ev::storage, move |ev| {
+        let Some(key) = ev.key() else {
+            return;
+        };
+        match storage::unprefixed(&key) {
+            Some(LEASE_KEY) => tabs.renew(false),
+            Some(key) => tabs.bus.publish(Event::Stored { key: key.to_string() }),
+            None => {}
+        }
+    });
+    window_event_listener(ev::pagehide, move |_| tabs.resign());
+    provide_context(cx, tabs);
+    tabs
+}
+
+/// Without a provider this tab leads and never hears from others.
+pub fn use_tabs(cx: Scope) -> Tabs {
+    use_context(cx).unwrap_or_else(|| Tabs {
+        id: 0,
+        leader: create_rw_signal(cx, true),
+        bus: use_bus(cx),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_tab_holds_the_lease_until_it_lapses() {
+        let lease = claim(None, 1, 0.0, false).unwrap();
+        assert_eq!(claim(Some(lease), 2, 1000.0, false), None);
+        assert_eq!(claim(Some(lease), 1, 1000.0, false), Some(Lease { tab: 1, until_ms: 1000.0 + LEASE_FOR_MS }));
+        assert_eq!(claim(Some(lease), 2, LEASE_FOR_MS, false).map(|l| l.tab), Some(2));
+        assert_eq!(claim(Some(lease), 2, 1000.0, true).map(|l| l.tab), Some(2));
+    }
+}
@@ -0,0 +1,215 @@
+//! Compact OSM tags. Elements rarely have more than a dozen tags, so a single
+//! boxed slice sorted by key is both cheaper to build than a `HashMap` (no
+//! buckets, no hashing) and as fast to read through a binary search.
+//!
+//! Keys and short values are [interned](crate::intern), so the `yes`es and
+//! `toilets` of a big result set share one allocation each. Longer values are
+//! mostly names and opening hours, which rarely repeat.
+//!
+//! Serializes as a plain JSON object, like the `HashMap` it replaced, so
+//! cached results keep loading.
+
+use crate::intern::intern;
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::Arc;
+
+/// Values up to this many bytes are interned.
+const INTERNED_VALUE_LEN: usize = 16;
+
+fn key(s: &str) -> Arc<str> {
+    intern(s)
+}
+
+fn value(s: &str) -> Arc<str> {
+    if s.len() <= INTERNED_VALUE_LEN {
+        intern(s)
+    } else {
+        Arc::from(s)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tags(Box<[(Arc<str>, Arc<str>)]>);
+
+impl Tags {
+    fn position(&self, key: &str) -> Result<usize, usize> {
+        self.0.binary_search_by(|(k, _)| (**k).cmp(key))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.position(key).ok().map(|i| &*self.0[i].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_ok()
+    }
+
+    /// Set a tag. Rebuilds the slice, which is fine for the odd edit.
+    pub fn insert(&mut self, k: String, v: String) {
+        let mut tags = std::mem::take(&mut self.0).into_vec();
+        match tags.binary_search_by(|(existing, _)| (**existing).cmp(k.as_str())) {
+            Ok(i) => tags[i].1 = value(&v),
+            Err(i) => tags.insert(i, (key(&k), value(&v))),
+        }
+        self.0 = tags.into_boxed_slice();
+    }
+
+    /// In key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (&**k, &**v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(k, _)| &**k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// On duplicate keys the last value wins, as with `HashMap`.
+    fn from_vec(mut tags: Vec<(Arc<str>, Arc<str>)>) -> Tags {
+        // Stable, so equal keys stay in input order and the last one can be kept.
+        tags.sort_by(|a, b| a.0.cmp(&b.0));
+        tags.reverse();
+        tags.dedup_by(|a, b| a.0 == b.0);
+        tags.reverse();
+        Tags(tags.into_boxed_slice())
+    }
+}
+
+impl FromIterator<(String, String)> for Tags {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Tags {
+        Tags::from_vec(iter.into_iter().map(|(k, v)| (key(&k), value(&v))).collect())
+    }
+}
+
+impl<const N: usize> From<[(String, String); N]> for Tags {
+    fn from(tags: [(String, String); N]) -> Tags {
+        tags.into_iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a Tags {
+    type Item = (&'a str, &'a str);
+    type IntoIter = std::iter::Map<std::slice::Iter<'a, (Arc<str>, Arc<str>)>, fn(&'a (Arc<str>, Arc<str>)) -> (&'a str, &'a str)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter().map(|(k, v)| (&**k, &**v))
+    }
+}
+
+impl Serialize for Tags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in self.iter() {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+/// A key or value read straight from the input into its `Arc<str>`, without
+/// an intermediate `String`.
+struct TagStr<const IS_KEY: bool>(Arc<str>);
+
+impl<'de, const IS_KEY: bool> Deserialize<'de> for TagStr<IS_KEY> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StrVisitor<const IS_KEY: bool>;
+
+        impl<'de, const IS_KEY: bool> Visitor<'de> for StrVisitor<IS_KEY> {
+            type Value = TagStr<IS_KEY>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<TagStr<IS_KEY>, E> {
+                Ok(TagStr(if IS_KEY { key(s) } else { value(s) }))
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+struct TagsVisitor;
+
+impl<'de> Visitor<'de> for TagsVisitor {
+    type Value = Tags;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of tag keys to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Tags, A::Error> {
+        let mut tags = Vec::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some((TagStr::<true>(k), TagStr::<false>(v))) = access.next_entry()? {
+            tags.push((k, v));
+        }
+        Ok(Tags::from_vec(tags))
+    }
+}
+
+impl<'de> Deserialize<'de> for Tags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Tags, D::Error> {
+        deserializer.deserialize_map(TagsVisitor)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Tags from `(key, value)` pairs, for tests here and elsewhere.
+    pub(crate) fn tags(pairs: &[(&str, &str)]) -> Tags {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn looks_up_by_key() {
+        let t = tags(&[("name", "A"), ("amenity", "toilets"), ("fee", "no")]);
+        assert_eq!(t.get("fee"), Some("no"));
+        assert!(t.contains_key("amenity"));
+        assert!(!t.contains_key("wheelchair"));
+        assert_eq!(t.keys().collect::<Vec<_>>(), ["amenity", "fee", "name"]);
+    }
+
+    #[test]
+    fn last_duplicate_wins() {
+        let t = tags(&[("fee", "yes"), ("a", "1"), ("fee", "no")]);
+        assert_eq!(t.len(), 2);
+        assert_eq!(t.get("fee"), Some("no"));
+    }
+
+    #[test]
+    fn insert_keeps_order() {
+        let mut t = tags(&[("b", "2")]);
+        t.insert("a".into(), "1".into());
+        t.insert("b".into(), "3".into());
+        assert_eq!(t, tags(&[("a", "1"), ("b", "3")]));
+    }
+
+    #[test]
+    fn interns_keys_and_short_values() {
+        let a: Tags = serde_json::from_str(r#"{"fee":"no","name":"Toilets at the northern end of the park"}"#).unwrap();
+        let b = tags(&[("fee", "no"), ("name", "Toilets at the northern end of the park")]);
+        assert!(Arc::ptr_eq(&a.0[0].0, &b.0[0].0));
+        assert!(Arc::ptr_eq(&a.0[0].1, &b.0[0].1));
+        assert!(!Arc::ptr_eq(&a.0[1].1, &b.0[1].1));
+    }
+
+    #[test]
+    fn json_is_a_plain_object() {
+        let t: Tags = serde_json::from_str(r#"{"fee":"no","amenity":"toilets"}"#).unwrap();
+        assert_eq!(t, tags(&[("amenity", "toilets"), ("fee", "no")]));
+        assert_eq!(serde_json::to_string(&t).unwrap(), r#"{"amenity":"toilets","fee":"no"}"#);
+    }
+}
@@ -0,0 +1,211 @@
+//! A tiny bounded-concurrency task runner.
+//!
+//! Used wherever we fire a batch of requests (tile queries, OSRM table batches)
+//! and don't want more than a handful in flight at once. Batches to a service
+//! go through [`TaskRunner::for_service`], so they stay within its
+//! [usage policy](crate::policy).
+
+use crate::policy::Service;
+use futures::{Future, StreamExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("The operation was cancelled.")]
+pub struct Cancelled;
+
+/// Shared flag to stop a running batch. Tasks that haven't started yet are
+/// never started, and the runner stops awaiting the ones in flight.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskRunner {
+    max_concurrent: usize,
+    cancel: CancelToken,
+}
+
+impl TaskRunner {
+    pub fn new(max_concurrent: usize) -> Self {
+        TaskRunner {
+            max_concurrent: max_concurrent.max(1),
+            cancel: CancelToken::new(),
+        }
+    }
+
+    /// No limit of its own; requests are limited per service by [`TaskRunner::for_service`].
+    pub fn unbounded() -> Self {
+        TaskRunner::new(usize::MAX)
+    }
+
+    /// Shares this runner's cancel token, with no more in flight than both it
+    /// and `service`'s policy allow.
+    pub fn for_service(&self, service: Service) -> TaskRunner {
+        TaskRunner {
+            max_concurrent: self.max_concurrent.min(service.policy().max_concurrent).max(1),
+            cancel: self.cancel.clone(),
+        }
+    }
+
+    /// Use an existing token, so the caller can cancel from elsewhere (e.g. on unmount).
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Run `tasks` with at most `max_concurrent` in flight, calling `on_progress`
+    /// after each one finishes. Results come back in the order the tasks were given.
+    pub async fn run<T, Fut>(
+        &self,
+        tasks: impl IntoIterator<Item = Fut>,
+        mut on_progress: impl FnMut(Progress),
+    ) -> Result<Vec<T>, Cancelled>
+    where
+        Fut: Future<Output = T>,
+    {
+        let tasks: Vec<Fut> = tasks.into_iter().collect();
+        let total = tasks.len();
+        on_progress(Progress { done: 0, total });
+
+        let cancel = &self.cancel;
+        let mut running = futures::stream::iter(tasks.into_iter().enumerate().map(|(i, task)| async move {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            Some((i, task.await))
+        }))
+        .buffer_unordered(self.max_concurrent);
+
+        let mut results: Vec<Option<T>> = (0..total).map(|_| None).collect();
+        let mut done = 0;
+        while let Some(finished) = running.next().await {
+            let Some((i, result)) = finished else {
+                return Err(Cancelled);
+            };
+            if cancel.is_cancelled() {
+                return Err(Cancelled);
+            }
+            results[i] = Some(result);
+            done += 1;
+            on_progress(Progress { done, total });
+        }
+
+        Ok(results.into_iter().map(|r| r.expect("every task finished")).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::pin::Pin;
+    use std::sync::atomic::AtomicUsize;
+    use std::task::{Context, Poll};
+
+    /// Returns `Pending` a few times before completing, so tasks overlap.
+    struct YieldTimes(usize);
+
+    impl Future for YieldTimes {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 == 0 {
+                return Poll::Ready(());
+            }
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn respects_concurrency_limit_and_keeps_order() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+
+        let tasks = (0..10).map(|i| {
+            let in_flight = in_flight.clone();
+            let max_seen = max_seen.clone();
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_seen.fetch_max(now, Ordering::SeqCst);
+                // Later tasks finish first, to check results are reordered.
+                YieldTimes(10 - i).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                i * 2
+            }
+        });
+
+        let mut progress = vec![];
+        let results = block_on(TaskRunner::new(3).run(tasks, |p| progress.push(p))).unwrap();
+
+        assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(max_seen.load(Ordering::SeqCst), 3);
+        assert_eq!(progress.len(), 11);
+        assert_eq!(progress[0], Progress { done: 0, total: 10 });
+        assert_eq!(progress[10], Progress { done: 10, total: 10 });
+    }
+
+    #[test]
+    fn cancel_stops_remaining_tasks() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let cancel = CancelToken::new();
+        let runner = TaskRunner::new(1).with_cancel(cancel.clone());
+
+        let tasks = (0..5).map(|_| {
+            let started = started.clone();
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                YieldTimes(1).await;
+            }
+        });
+
+        let result = block_on(runner.run(tasks, |p| {
+            if p.done == 2 {
+                cancel.cancel();
+            }
+        }));
+
+        assert_eq!(result, Err(Cancelled));
+        assert!(started.load(Ordering::SeqCst) < 5);
+    }
+
+    #[test]
+    fn services_cap_the_runner() {
+        let cancel = CancelToken::new();
+        let runner = TaskRunner::unbounded().with_cancel(cancel.clone());
+        assert_eq!(runner.for_service(Service::Osrm).max_concurrent, 1);
+        assert_eq!(runner.for_service(Service::Overpass).max_concurrent, Service::Overpass.policy().max_concurrent);
+        assert_eq!(TaskRunner::new(1).for_service(Service::Overpass).max_concurrent, 1);
+        cancel.cancel();
+        assert!(runner.for_service(Service::Osrm).cancel.is_cancelled());
+    }
+
+    #[test]
+    fn empty_batch() {
+        let tasks: Vec<futures::future::Ready<()>> = vec![];
+        let results = block_on(TaskRunner::new(4).run(tasks, |_| {})).unwrap();
+        assert!(results.is_empty());
+    }
+}
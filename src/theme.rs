@@ -0,0 +1,140 @@
+//! Theme colors and hideable UI sections, overridable from the URL for embedders
+//! and power users: `?theme_bg=%23102030&theme_accent=teal&hide=places,settings`.
+//!
+//! Colors end up inside a `style` attribute, so only hex colors and plain
+//! keyword names get through; anything else is dropped.
+
+use leptos::*;
+use std::collections::HashSet;
+
+/// `(URL parameter, CSS custom property)` for each themable color.
+pub const COLOR_PARAMS: [(&str, &str); 4] = [
+    ("theme_bg", "--f2p-bg"),
+    ("theme_fg", "--f2p-fg"),
+    ("theme_accent", "--f2p-accent"),
+    ("theme_muted", "--f2p-muted"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Section {
+    Nav,
+    Settings,
+    Places,
+    Pack,
+    Selected,
+    Secondary,
+    Credit,
+}
+
+impl Section {
+    pub fn from_param(s: &str) -> Option<Section> {
+        Some(match s.trim() {
+            "nav" => Section::Nav,
+            "settings" => Section::Settings,
+            "places" => Section::Places,
+            "pack" => Section::Pack,
+            "selected" => Section::Selected,
+            "secondary" => Section::Secondary,
+            "credit" => Section::Credit,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theme {
+    /// Validated `(custom property, value)` pairs.
+    pub colors: Vec<(&'static str, String)>,
+    pub hidden: HashSet<Section>,
+}
+
+/// `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, or a keyword like `teal`.
+pub fn sanitize_color(value: &str) -> Option<String> {
+    let value = value.trim();
+    let valid = match value.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => (3..=20).contains(&value.len()) && value.chars().all(|c| c.is_ascii_alphabetic()),
+    };
+    valid.then(|| value.to_ascii_lowercase())
+}
+
+impl Theme {
+    /// `get` looks up a URL parameter.
+    pub fn from_params(get: impl Fn(&str) -> Option<String>) -> Theme {
+        let colors = COLOR_PARAMS
+            .into_iter()
+            .filter_map(|(param, property)| {
+                let raw = get(param)?;
+                let color = sanitize_color(&raw);
+                if color.is_none() {
+                    log::warn!("ignoring invalid {param}");
+                }
+                Some((property, color?))
+            })
+            .collect();
+        let hidden = get("hide")
+            .map(|hide| hide.split(',').filter_map(Section::from_param).collect())
+            .unwrap_or_default();
+        Theme { colors, hidden }
+    }
+
+    pub fn style(&self) -> String {
+        self.colors.iter().map(|(property, value)| format!("{property}:{value};")).collect()
+    }
+
+    pub fn shows(&self, section: Section) -> bool {
+        !self.hidden.contains(&section)
+    }
+}
+
+pub fn provide_theme(cx: Scope) -> Theme {
+    let params = crate::search_params();
+    let theme = Theme::from_params(|name| params.get(name));
+    provide_context(cx, theme.clone());
+    theme
+}
+
+pub fn use_theme(cx: Scope) -> Theme {
+    use_context(cx).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn theme(params: &[(&str, &str)]) -> Theme {
+        let params: HashMap<String, String> = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Theme::from_params(|name| params.get(name).cloned())
+    }
+
+    #[test]
+    fn accepts_hex_and_keywords() {
+        assert_eq!(sanitize_color("#FFF"), Some("#fff".to_string()));
+        assert_eq!(sanitize_color("#10203040"), Some("#10203040".to_string()));
+        assert_eq!(sanitize_color("Teal"), Some("teal".to_string()));
+    }
+
+    #[test]
+    fn rejects_css_injection() {
+        assert_eq!(sanitize_color("red;background:url(https://evil)"), None);
+        assert_eq!(sanitize_color("#fff}body{display:none"), None);
+        assert_eq!(sanitize_color("expression(alert(1))"), None);
+        assert_eq!(sanitize_color("#ggg"), None);
+        assert_eq!(sanitize_color("#12345"), None);
+    }
+
+    #[test]
+    fn builds_style_from_valid_params_only() {
+        let theme = theme(&[("theme_bg", "#102030"), ("theme_accent", "red;x:y"), ("theme_fg", "white")]);
+        assert_eq!(theme.style(), "--f2p-bg:#102030;--f2p-fg:white;");
+    }
+
+    #[test]
+    fn hides_known_sections() {
+        let theme = theme(&[("hide", "places, settings,bogus")]);
+        assert!(!theme.shows(Section::Places));
+        assert!(!theme.shows(Section::Settings));
+        assert!(theme.shows(Section::Nav));
+    }
+}
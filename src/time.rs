@@ -0,0 +1,89 @@
+//! Readable times: when results were fetched, as a clock time in the
+//! reader's locale, and how long ago that was ("2 minutes ago"). In the
+//! browser both go through `Intl`; elsewhere (tests) English stands in. OSM's
+//! `timestamp_osm_base` is parsed here too, to tell how current the map data is.
+
+use crate::i18n::Locale;
+
+const MS_PER_MIN: f64 = 60.0 * 1000.0;
+
+/// Milliseconds since the epoch of an OSM timestamp like `2024-03-02T10:15:22Z`.
+pub fn parse_osm(timestamp: &str) -> Option<f64> {
+    let (date, time) = timestamp.trim().strip_suffix('Z')?.split_once('T')?;
+    let date: Vec<i64> = date.split('-').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let time: Vec<i64> = time.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let (&[year, month, day], &[hour, minute, second]) = (&date[..], &time[..]) else {
+        return None;
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    // Days from civil, after Howard Hinnant.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(((days * 86400 + hour * 3600 + minute * 60 + second) * 1000) as f64)
+}
+
+/// The largest whole unit `age_ms` makes, counted back from now; `None` under a minute.
+pub fn ago_in_units(age_ms: f64) -> Option<(f64, &'static str)> {
+    let minutes = (age_ms.max(0.0) / MS_PER_MIN).floor();
+    match minutes {
+        m if m < 1.0 => None,
+        m if m < 120.0 => Some((m, "minute")),
+        m if m < 48.0 * 60.0 => Some(((m / 60.0).floor(), "hour")),
+        m => Some(((m / (24.0 * 60.0)).floor(), "day")),
+    }
+}
+
+/// `"2 minutes ago"`, `"vor 2 Minuten"`, `"now"`...
+#[cfg(target_arch = "wasm32")]
+pub fn ago(locale: Locale, age_ms: f64) -> String {
+    use wasm_bindgen::JsValue;
+    let locales = js_sys::Array::of1(&JsValue::from_str(locale.code()));
+    let options = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&options, &"numeric".into(), &"auto".into());
+    let format = js_sys::Intl::RelativeTimeFormat::new(&locales, &options);
+    let (value, unit) = ago_in_units(age_ms).unwrap_or((0.0, "second"));
+    format.format(-value, unit).into()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn ago(_locale: Locale, age_ms: f64) -> String {
+    match ago_in_units(age_ms) {
+        None => "now".to_string(),
+        Some((1.0, unit)) => format!("1 {unit} ago"),
+        Some((value, unit)) => format!("{value} {unit}s ago"),
+    }
+}
+
+/// The time of day at `at_ms`, as the locale writes it.
+pub fn clock(locale: Locale, at_ms: f64) -> String {
+    js_sys::Date::new(&at_ms.into()).to_locale_time_string(locale.code()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_osm_timestamps() {
+        assert_eq!(parse_osm("1970-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(parse_osm("2024-03-02T10:15:22Z"), Some(1_709_374_522_000.0));
+        assert_eq!(parse_osm("2024-02-29T00:00:00Z"), Some(1_709_164_800_000.0));
+        assert_eq!(parse_osm("2024-03-02 10:15:22"), None);
+        assert_eq!(parse_osm("2024-13-02T10:15:22Z"), None);
+    }
+
+    #[test]
+    fn counts_back_in_the_largest_unit() {
+        assert_eq!(ago(Locale::En, 30_000.0), "now");
+        assert_eq!(ago(Locale::En, MS_PER_MIN), "1 minute ago");
+        assert_eq!(ago(Locale::En, 45.0 * MS_PER_MIN), "45 minutes ago");
+        assert_eq!(ago(Locale::En, 150.0 * MS_PER_MIN), "2 hours ago");
+        assert_eq!(ago_in_units(3.0 * 24.0 * 60.0 * MS_PER_MIN), Some((3.0, "day")));
+    }
+}
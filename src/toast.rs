@@ -0,0 +1,67 @@
+//! Short-lived messages at the bottom of the page, for things that happened in
+//! the background (a save that failed, say).
+
+use crate::bus::{use_bus, Event};
+use leptos::*;
+use std::time::Duration;
+
+const SHOW_FOR: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    id: u64,
+    pub message: String,
+}
+
+#[derive(Clone, Copy)]
+pub struct Toasts {
+    toasts: RwSignal<Vec<Toast>>,
+    next_id: StoredValue<u64>,
+}
+
+impl Toasts {
+    pub fn push(&self, message: impl Into<String>) {
+        let id = self.next_id.get_value();
+        self.next_id.set_value(id + 1);
+        self.toasts.update(|t| t.push(Toast { id, message: message.into() }));
+        let toasts = self.toasts;
+        set_timeout(move || toasts.update(|t| t.retain(|toast| toast.id != id)), SHOW_FOR);
+    }
+}
+
+/// Also shows every [`Event::Toast`] published on the bus.
+pub fn provide_toasts(cx: Scope) -> Toasts {
+    let toasts = Toasts {
+        toasts: create_rw_signal(cx, Vec::new()),
+        next_id: store_value(cx, 0),
+    };
+    use_bus(cx).subscribe(cx, move |event| {
+        if let Event::Toast { message } = event {
+            toasts.push(message.clone());
+        }
+    });
+    provide_context(cx, toasts);
+    toasts
+}
+
+/// Without a provider nothing is shown, but pushing still works.
+pub fn use_toasts(cx: Scope) -> Toasts {
+    use_context(cx).unwrap_or_else(|| Toasts {
+        toasts: create_rw_signal(cx, Vec::new()),
+        next_id: store_value(cx, 0),
+    })
+}
+
+#[component]
+pub fn ToastHost(cx: Scope) -> impl IntoView {
+    let toasts = use_toasts(cx).toasts;
+    view! { cx,
+        <div class="toasts" role="status" aria-live="polite">
+            <For
+                each=move || toasts.get()
+                key=|t| t.id
+                view=move |cx, t| view! { cx, <p class="toast">{t.message}</p> }
+            />
+        </div>
+    }
+}
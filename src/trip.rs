@@ -0,0 +1,474 @@
+//! "Plan a trip": bathrooms along a route instead of around a point, with the
+//! chosen stops exportable as GPX.
+
+use crate::api::overpass::fetch_overpass;
+use crate::opening_hours::{self, OpenStatus, WeekTime};
+use crate::links::{self, DirectionsProvider};
+use crate::overpass::Area;
+use crate::packs::AmenityPack;
+use crate::policy::{self, Service};
+use crate::prefs::use_preferences;
+use crate::ranking::RankingStrategy;
+use crate::results::ResultRow;
+use crate::scoring::Confidence;
+use crate::spatial::GridIndex;
+use crate::{current_position, export, geo, import, Element, Route, RouteRoot};
+use leptos::{error::Result, *};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::rc::Rc;
+use thiserror::Error;
+
+/// Imported tracks are usually recorded every few meters; this default is plenty for a corridor.
+const DEFAULT_SIMPLIFY_M: f64 = 20.0;
+
+/// Grid cell size for finding each candidate's nearest route vertex; about a kilometer.
+const ROUTE_CELL_DEG: f64 = 0.01;
+
+/// Overpass chokes on very long `around` polylines, so the route is simplified to this many points.
+const MAX_CORRIDOR_POINTS: usize = 100;
+
+#[derive(Error, Clone, Debug)]
+pub enum TripError {
+    #[error("Enter the destination as \"lat,lon\".")]
+    InvalidDestination,
+    #[error("No route found to the destination.")]
+    NoRoute,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TravelMode {
+    #[default]
+    Foot,
+    Bike,
+    Car,
+}
+
+impl TravelMode {
+    pub const ALL: [TravelMode; 3] = [TravelMode::Foot, TravelMode::Bike, TravelMode::Car];
+
+    fn osrm_profile(&self) -> &'static str {
+        match self {
+            TravelMode::Foot => "routed-foot",
+            TravelMode::Bike => "routed-bike",
+            TravelMode::Car => "routed-car",
+        }
+    }
+
+    /// How far off the route (in meters) a stop may be.
+    pub fn corridor_m(&self) -> i64 {
+        match self {
+            TravelMode::Foot => 150,
+            TravelMode::Bike => 300,
+            TravelMode::Car => 1000,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TravelMode::Foot => "Walking",
+            TravelMode::Bike => "Cycling",
+            TravelMode::Car => "Driving",
+        }
+    }
+
+    /// Rough average speed in m/s, for imported tracks that come without a duration and for measuring.
+    pub fn speed_m_s(&self) -> f64 {
+        match self {
+            TravelMode::Foot => 1.4,
+            TravelMode::Bike => 4.2,
+            TravelMode::Car => 16.7,
+        }
+    }
+
+    fn from_label(label: &str) -> TravelMode {
+        TravelMode::ALL
+            .into_iter()
+            .find(|m| m.label() == label)
+            .unwrap_or(TravelMode::Foot)
+    }
+}
+
+/// What to search along: a route to a typed destination, or an imported track.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TripRequest {
+    To(String),
+    /// An imported track and the tolerance in meters to simplify it with.
+    Track(Vec<(f64, f64)>, f64),
+}
+
+/// A bathroom near the route, `along_m` meters from the start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripStop {
+    pub element: Element,
+    pub along_m: f64,
+    /// Estimated arrival, as a JS timestamp in milliseconds.
+    pub eta_ms: f64,
+    /// Whether it will be open at `eta_ms`.
+    pub status_on_arrival: OpenStatus,
+}
+
+impl TripStop {
+    pub fn eta_label(&self) -> String {
+        let eta = js_sys::Date::new(&self.eta_ms.into());
+        eta.to_locale_time_string("en-US").into()
+    }
+
+    pub fn status_label(&self) -> &'static str {
+        match self.status_on_arrival {
+            OpenStatus::Open => "Open",
+            OpenStatus::Closed => "Will be closed when you arrive",
+            OpenStatus::Unknown => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripPlan {
+    pub origin: (f64, f64),
+    pub destination: (f64, f64),
+    pub line: Vec<(f64, f64)>,
+    pub distance_m: f64,
+    pub duration_s: f64,
+    pub candidates: Vec<TripStop>,
+}
+
+pub async fn fetch_route(mode: TravelMode, from: (f64, f64), to: (f64, f64)) -> Result<Route> {
+    let url = format!(
+        "https://routing.openstreetmap.de/{}/route/v1/driving/{},{};{},{}?overview=full",
+        mode.osrm_profile(),
+        from.1,
+        from.0,
+        to.1,
+        to.0
+    );
+    let json: RouteRoot = policy::get(Service::Fossgis, &url).await.send().await?.json().await?;
+    Ok(json.routes.into_iter().next().ok_or(TripError::NoRoute)?)
+}
+
+pub fn corridor_query(line: &[(f64, f64)], width_m: i64, pack: AmenityPack) -> String {
+    let simplified = geo::simplify_to_at_most(line, MAX_CORRIDOR_POINTS, 10.0);
+    let origin = line.first().copied().unwrap_or_default();
+    pack.query(origin, &Area::around_line(width_m, &simplified))
+}
+
+/// Seconds into the trip when `along_m` is reached, assuming constant speed along the route.
+pub fn arrival_offset_s(along_m: f64, route_length_m: f64, duration_s: f64) -> f64 {
+    if route_length_m <= 0.0 {
+        return 0.0;
+    }
+    (along_m / route_length_m).clamp(0.0, 1.0) * duration_s
+}
+
+/// Where places fall along a route: as far from the start as the route's
+/// vertex nearest to them.
+pub struct AlongRoute {
+    vertices: GridIndex<f64>,
+    length_m: f64,
+}
+
+impl AlongRoute {
+    pub fn new(line: &[(f64, f64)]) -> AlongRoute {
+        let cumulative = geo::cumulative_distances(line);
+        let length_m = cumulative.last().copied().unwrap_or_default();
+        AlongRoute {
+            vertices: GridIndex::new(line.iter().copied().zip(cumulative), ROUTE_CELL_DEG),
+            length_m,
+        }
+    }
+
+    /// Meters from the start; `None` for an empty route.
+    pub fn along_m(&self, point: (f64, f64)) -> Option<f64> {
+        self.vertices.nearest(point, 1).pop().map(|(_, &along_m)| along_m)
+    }
+}
+
+/// Road trip results in the order they come along the planned route.
+impl RankingStrategy for AlongRoute {
+    fn rank(&self, row: &ResultRow, _: Confidence) -> f64 {
+        self.along_m((row.element.lat, row.element.lon)).unwrap_or(f64::INFINITY)
+    }
+}
+
+/// The route of the last trip plan, kept for the finder to order road trip results by.
+pub fn provide_loaded_route(cx: Scope) -> RwSignal<Option<Rc<AlongRoute>>> {
+    let route = create_rw_signal(cx, None);
+    provide_context(cx, route);
+    route
+}
+
+pub fn use_loaded_route(cx: Scope) -> RwSignal<Option<Rc<AlongRoute>>> {
+    use_context(cx).unwrap_or_else(|| create_rw_signal(cx, None))
+}
+
+/// Place each element at the distance along `line` of its nearest vertex, in route order,
+/// and evaluate its opening hours at the estimated arrival for a trip starting at `departure_ms`.
+pub fn candidates_along(line: &[(f64, f64)], elements: Vec<Element>, departure_ms: f64, duration_s: f64) -> Vec<TripStop> {
+    let route = AlongRoute::new(line);
+    let mut stops: Vec<TripStop> = elements
+        .into_iter()
+        .filter_map(|element| {
+            let along_m = route.along_m((element.lat, element.lon))?;
+            let eta_ms = departure_ms + arrival_offset_s(along_m, route.length_m, duration_s) * 1000.0;
+            let status_on_arrival = opening_hours::status_for_tag(
+                element.tags.get("opening_hours"),
+                WeekTime::from_js_millis(eta_ms),
+            );
+            Some(TripStop {
+                element,
+                along_m,
+                eta_ms,
+                status_on_arrival,
+            })
+        })
+        .collect();
+    stops.sort_by(|a, b| a.along_m.total_cmp(&b.along_m));
+    stops
+}
+
+pub async fn plan_trip(request: TripRequest, mode: TravelMode, pack: AmenityPack) -> Result<TripPlan> {
+    let (origin, destination, line, distance_m, duration_s) = match request {
+        TripRequest::To(destination) => {
+            let destination = geo::parse_lat_lon(&destination).ok_or(TripError::InvalidDestination)?;
+            let origin = current_position().await?;
+            let route = fetch_route(mode, origin, destination).await?;
+            let line = geo::decode_polyline(&route.geometry, 5).ok_or(TripError::NoRoute)?;
+            (origin, destination, line, route.distance, route.duration)
+        }
+        TripRequest::Track(track, tolerance_m) => {
+            let line = geo::simplify(&track, tolerance_m);
+            let (Some(origin), Some(destination)) = (line.first().copied(), line.last().copied()) else {
+                return Err(import::ImportError::NoTrack.into());
+            };
+            let distance = geo::cumulative_distances(&line).last().copied().unwrap_or_default();
+            (origin, destination, line, distance, distance / mode.speed_m_s())
+        }
+    };
+
+    let res = fetch_overpass(&corridor_query(&line, mode.corridor_m(), pack)).await?;
+    let candidates = candidates_along(&line, res.elements, js_sys::Date::now(), duration_s);
+
+    Ok(TripPlan {
+        origin,
+        destination,
+        line,
+        distance_m,
+        duration_s,
+        candidates,
+    })
+}
+
+#[component]
+pub fn TripPlanner(cx: Scope) -> impl IntoView {
+    let (destination, set_destination) = create_signal(cx, String::new());
+    let prefs = use_preferences(cx);
+    let (mode, set_mode) = create_signal(cx, prefs.with_untracked(|p| p.travel_mode));
+    let chosen = create_rw_signal(cx, HashSet::<i64>::new());
+    let plan = create_action(cx, move |(request, mode): &(TripRequest, TravelMode)| {
+        plan_trip(request.clone(), *mode, prefs.with_untracked(|p| p.amenity_pack))
+    });
+    let loaded_route = use_loaded_route(cx);
+    create_effect(cx, move |_| {
+        if let Some(Ok(plan)) = plan.value().get() {
+            loaded_route.set(Some(Rc::new(AlongRoute::new(&plan.line))));
+        }
+    });
+
+    let (import_error, set_import_error) = create_signal(cx, None::<import::ImportError>);
+    let (tolerance, set_tolerance) = create_signal(cx, DEFAULT_SIMPLIFY_M);
+    let import_file = move |file: Option<web_sys::File>| {
+        let Some(file) = file else {
+            return;
+        };
+        spawn_local(async move {
+            match import::read_track(file).await {
+                Ok(track) => {
+                    set_import_error.set(None);
+                    chosen.set(HashSet::new());
+                    plan.dispatch((TripRequest::Track(track, tolerance.get_untracked()), mode.get_untracked()));
+                }
+                Err(e) => set_import_error.set(Some(e)),
+            }
+        });
+    };
+
+    let provider = create_memo(cx, move |_| prefs.with(|p| p.directions_provider));
+    let directions_url = move || {
+        let plan = plan.value().get()?.ok()?;
+        let stops: Vec<(f64, f64)> = chosen.with(|chosen| {
+            plan.candidates
+                .iter()
+                .filter(|stop| chosen.contains(&stop.element.id))
+                .map(|stop| (stop.element.lat, stop.element.lon))
+                .collect()
+        });
+        Some(links::multi_stop_directions(provider.get(), mode.get(), plan.origin, &stops, plan.destination))
+    };
+
+    let export_gpx = move |_| {
+        let Some(Ok(plan)) = plan.value().get_untracked() else {
+            return;
+        };
+        let chosen = chosen.get_untracked();
+        let waypoints: Vec<export::Waypoint> = plan
+            .candidates
+            .iter()
+            .filter(|stop| chosen.contains(&stop.element.id))
+            .map(|stop| {
+                let name = stop.element.tags.get("name").map(str::to_string);
+                let name = name.unwrap_or_else(|| format!("Toilet (OSM:{})", stop.element.id));
+                (stop.element.lat, stop.element.lon, name)
+            })
+            .collect();
+        let gpx = export::gpx("Free2Pee trip", &waypoints, &plan.line);
+        if let Err(e) = export::download("free2pee-trip.gpx", "application/gpx+xml", &gpx) {
+            log::error!("GPX export failed: {e:?}");
+        }
+    };
+
+    let plan_view = move || {
+        plan.value().get().map(|res| match res {
+            Err(e) => view! { cx, <p class="error">{e.to_string()}</p> }.into_view(cx),
+            Ok(plan) => {
+                let summary = format!(
+                    "{:.1} km, about {:.0} min, {} bathrooms along the way",
+                    plan.distance_m / 1000.0,
+                    plan.duration_s / 60.0,
+                    plan.candidates.len()
+                );
+                let rows = plan.candidates.into_iter().map(|stop| {
+                    let (kind, id) = (stop.element.type_field, stop.element.id);
+                    let closed = stop.status_on_arrival == OpenStatus::Closed;
+                    view! { cx,
+                        <tr>
+                            <td>
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || chosen.with(|c| c.contains(&id))
+                                    on:change=move |ev| {
+                                        let on = event_target_checked(&ev);
+                                        chosen.update(|c| {
+                                            if on { c.insert(id); } else { c.remove(&id); }
+                                        });
+                                    }
+                                />
+                            </td>
+                            <td>
+                                <a href={format!("https://www.openstreetmap.org/{kind}/{id}")} target="_blank">OSM:{id}</a>
+                            </td>
+                            <td>{format!("{:.1}", stop.along_m / 1000.0)}</td>
+                            <td>{stop.eta_label()}</td>
+                            <td class:closed=closed>{stop.status_label()}</td>
+                        </tr>
+                    }
+                }).collect_view(cx);
+                view! { cx,
+                    <p>{summary}</p>
+                    <table>
+                        <thead>
+                            <tr>
+                                <th>"Stop"</th>
+                                <th>"OSM Node"</th>
+                                <th>"Along route [km]"</th>
+                                <th>"Arrival"</th>
+                                <th>"Open on arrival"</th>
+                            </tr>
+                        </thead>
+                        <tbody>{rows}</tbody>
+                    </table>
+                    <button on:click=export_gpx disabled=move || chosen.with(|c| c.is_empty())>"Export GPX"</button>
+                    <select on:change=move |ev| {
+                        let p = DirectionsProvider::from_label(&event_target_value(&ev));
+                        prefs.update(|prefs| prefs.directions_provider = p);
+                    }>
+                        {DirectionsProvider::ALL.into_iter().map(|p| view! { cx,
+                            <option value=p.label() selected=move || provider.get() == p>{p.label()}</option>
+                        }).collect_view(cx)}
+                    </select>
+                    <a href=directions_url target="_blank">"Open directions"</a>
+                }
+                .into_view(cx)
+            }
+        })
+    };
+
+    view! { cx,
+        <div class="trip">
+            <h2>"Plan a trip"</h2>
+            <input
+                placeholder="Destination lat,lon"
+                prop:value=move || destination.get()
+                on:input=move |ev| set_destination.set(event_target_value(&ev))
+            />
+            <select on:change=move |ev| set_mode.set(TravelMode::from_label(&event_target_value(&ev)))>
+                {TravelMode::ALL.into_iter().map(|m| view! { cx,
+                    <option value=m.label() selected=move || mode.get() == m>{m.label()}</option>
+                }).collect_view(cx)}
+            </select>
+            <button
+                on:click=move |_| {
+                    chosen.set(HashSet::new());
+                    plan.dispatch((TripRequest::To(destination.get_untracked()), mode.get_untracked()));
+                }
+                disabled=move || plan.pending().get()
+            >
+                "Plan"
+            </button>
+            <div
+                class="dropzone"
+                on:dragover=|ev| ev.prevent_default()
+                on:drop=move |ev| {
+                    ev.prevent_default();
+                    import_file(ev.data_transfer().and_then(|d| d.files()).and_then(|f| f.get(0)));
+                }
+            >
+                "Or search along a track: drop a GPX/GeoJSON file here or "
+                <input
+                    type="file"
+                    accept=".gpx,.geojson,.json"
+                    on:change=move |ev| {
+                        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+                        import_file(input.files().and_then(|f| f.get(0)));
+                    }
+                />
+                <label>
+                    "Simplify to within "
+                    <input
+                        type="number"
+                        min="1"
+                        max="500"
+                        prop:value=move || tolerance.get().to_string()
+                        on:change=move |ev| {
+                            if let Ok(t) = event_target_value(&ev).parse::<f64>() {
+                                set_tolerance.set(t.clamp(1.0, 500.0));
+                            }
+                        }
+                    />
+                    " m"
+                </label>
+            </div>
+            {move || import_error.get().map(|e| view! { cx, <p class="error">{e.to_string()}</p> })}
+            <Show when=move || plan.pending().get() fallback=|_| ()>
+                <p>"Planning..."</p>
+            </Show>
+            {plan_view}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_by_distance_along_the_route() {
+        let route = AlongRoute::new(&[(52.0, 13.0), (52.0, 13.1), (52.0, 13.2)]);
+        let row = |lat, lon| ResultRow { element: Rc::new(Element { lat, lon, ..Default::default() }), distance_m: 0.0, duration_s: 0.0 };
+        let start = route.rank(&row(52.001, 13.0), Confidence::Normal);
+        let middle = route.rank(&row(51.999, 13.1), Confidence::Normal);
+        let end = route.rank(&row(52.0, 13.21), Confidence::Normal);
+        assert_eq!(start, 0.0);
+        assert!(start < middle && middle < end);
+        assert_eq!(AlongRoute::new(&[]).rank(&row(52.0, 13.0), Confidence::Normal), f64::INFINITY);
+    }
+}
@@ -0,0 +1,117 @@
+//! Regional tagging conventions. Mappers in some countries tag toilets in ways
+//! the plain `amenity=toilets` query misses, so searches there include extra patterns.
+
+use crate::geo::BBox;
+
+pub struct RegionalVocabulary {
+    /// ISO 3166-1 alpha-2 code.
+    pub country: &'static str,
+    /// Rough bounding box. Border areas may pick up a neighbour's patterns,
+    /// which only widens the search.
+    pub bbox: BBox,
+    /// Extra `(key, value)` filters that also mean "there are toilets here".
+    pub toilet_patterns: &'static [(&'static str, &'static str)],
+}
+
+pub const BUILDING_TOILETS: (&str, &str) = ("building", "toilets");
+pub const TOILETS_YES: (&str, &str) = ("toilets", "yes");
+
+pub const REGIONS: &[RegionalVocabulary] = &[
+    RegionalVocabulary {
+        country: "GB",
+        bbox: BBox { south: 49.8, west: -8.7, north: 60.9, east: 1.8 },
+        toilet_patterns: &[BUILDING_TOILETS],
+    },
+    RegionalVocabulary {
+        country: "JP",
+        bbox: BBox { south: 24.0, west: 122.9, north: 45.6, east: 146.0 },
+        toilet_patterns: &[BUILDING_TOILETS, TOILETS_YES],
+    },
+    RegionalVocabulary {
+        country: "IN",
+        bbox: BBox { south: 6.7, west: 68.1, north: 35.7, east: 97.4 },
+        toilet_patterns: &[BUILDING_TOILETS],
+    },
+    RegionalVocabulary {
+        country: "DE",
+        bbox: BBox { south: 47.2, west: 5.8, north: 55.1, east: 15.1 },
+        toilet_patterns: &[TOILETS_YES],
+    },
+    RegionalVocabulary {
+        country: "NL",
+        bbox: BBox { south: 50.7, west: 3.3, north: 53.6, east: 7.3 },
+        toilet_patterns: &[TOILETS_YES],
+    },
+];
+
+/// Which [`REGIONS`] contain `origin`, as a bit set over their indices.
+/// Searches with the same set use the same patterns, so it doubles as a cache key.
+pub fn regions_at(origin: (f64, f64)) -> u32 {
+    REGIONS
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.bbox.contains(origin))
+        .fold(0, |set, (i, _)| set | 1 << i)
+}
+
+/// Extra toilet patterns for a [`regions_at`] set, deduplicated.
+pub fn toilet_patterns_in(regions: u32) -> Vec<(&'static str, &'static str)> {
+    let mut patterns = Vec::new();
+    for (_, region) in REGIONS.iter().enumerate().filter(|(i, _)| regions & 1 << i != 0) {
+        log::debug!("including {} toilet tagging conventions", region.country);
+        for pattern in region.toilet_patterns {
+            if !patterns.contains(pattern) {
+                patterns.push(*pattern);
+            }
+        }
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toilet_patterns_at(origin: (f64, f64)) -> Vec<(&'static str, &'static str)> {
+        toilet_patterns_in(regions_at(origin))
+    }
+
+    #[test]
+    fn regions_are_valid_boxes() {
+        for region in REGIONS {
+            assert!(region.bbox.south < region.bbox.north, "{}", region.country);
+            assert!(region.bbox.west < region.bbox.east, "{}", region.country);
+            assert!(!region.toilet_patterns.is_empty(), "{}", region.country);
+        }
+    }
+
+    #[test]
+    fn london_uses_building_toilets() {
+        assert_eq!(toilet_patterns_at((51.507, -0.128)), vec![BUILDING_TOILETS]);
+    }
+
+    #[test]
+    fn tokyo_uses_both() {
+        assert_eq!(toilet_patterns_at((35.68, 139.77)), vec![BUILDING_TOILETS, TOILETS_YES]);
+    }
+
+    #[test]
+    fn border_overlap_is_deduplicated() {
+        // Near the German-Dutch border both boxes match.
+        assert_eq!(toilet_patterns_at((51.8, 6.1)), vec![TOILETS_YES]);
+    }
+
+    #[test]
+    fn elsewhere_adds_nothing() {
+        assert!(toilet_patterns_at((40.71, -74.0)).is_empty());
+        assert_eq!(regions_at((40.71, -74.0)), 0);
+    }
+
+    #[test]
+    fn region_sets_index_the_table() {
+        let border = regions_at((51.8, 6.1));
+        assert_eq!(border.count_ones(), 2);
+        let countries: Vec<_> = (0..REGIONS.len()).filter(|i| border & 1 << i != 0).map(|i| REGIONS[i].country).collect();
+        assert_eq!(countries, ["DE", "NL"]);
+    }
+}
@@ -0,0 +1,46 @@
+//! what3words provider, only built with the `what3words` feature.
+//!
+//! The API key comes from `W3W_API_KEY` at build time, or from the
+//! `what3words_api_key` storage entry, which wins when both are set.
+
+use crate::capabilities::WordAddressProvider;
+use crate::storage;
+use async_trait::async_trait;
+use leptos::error::Result;
+use serde_derive::Deserialize;
+
+const API_KEY_STORAGE: &str = "what3words_api_key";
+
+#[derive(Debug, Deserialize)]
+struct ConvertResponse {
+    words: String,
+}
+
+pub struct What3Words {
+    api_key: String,
+}
+
+impl What3Words {
+    pub fn from_config() -> Option<What3Words> {
+        let api_key = storage::load::<String>(API_KEY_STORAGE)
+            .or_else(|| option_env!("W3W_API_KEY").map(str::to_string))
+            .filter(|key| !key.is_empty())?;
+        Some(What3Words { api_key })
+    }
+}
+
+#[async_trait(?Send)]
+impl WordAddressProvider for What3Words {
+    fn name(&self) -> &'static str {
+        "what3words"
+    }
+
+    async fn word_address(&self, lat: f64, lon: f64) -> Result<String> {
+        let url = format!(
+            "https://api.what3words.com/v3/convert-to-3wa?coordinates={lat},{lon}&key={}",
+            js_sys::encode_uri_component(&self.api_key)
+        );
+        let res: ConvertResponse = reqwasm::http::Request::get(&url).send().await?.json().await?;
+        Ok(format!("///{}", res.words))
+    }
+}
@@ -0,0 +1,43 @@
+// Keeps the app shell (page, wasm, manifest) so Free2Pee opens offline.
+// Network first, so a new deploy is picked up on the next visit online; the
+// cache only answers when the network can't. Search results are kept by the
+// app itself (src/cache.rs), not here.
+const CACHE = "free2pee-shell-v1";
+const SHELL = ["./", "./index.html", "./manifest.webmanifest", "./icon.svg"];
+
+self.addEventListener("install", (event) => {
+    event.waitUntil(caches.open(CACHE).then((cache) => cache.addAll(SHELL)).then(() => self.skipWaiting()));
+});
+
+self.addEventListener("activate", (event) => {
+    event.waitUntil(
+        caches
+            .keys()
+            .then((keys) => Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key))))
+            .then(() => self.clients.claim())
+    );
+});
+
+self.addEventListener("fetch", (event) => {
+    const request = event.request;
+    // Overpass, routing and tiles are other origins; the app handles those.
+    if (request.method !== "GET" || new URL(request.url).origin !== self.location.origin) {
+        return;
+    }
+    const navigation = request.mode === "navigate";
+    event.respondWith(
+        fetch(request)
+            .then((response) => {
+                if (response.ok) {
+                    const copy = response.clone();
+                    event.waitUntil(caches.open(CACHE).then((cache) => cache.put(request, copy)));
+                }
+                return response;
+            })
+            .catch(() =>
+                // `?debug=1` and the like are still the same page.
+                caches.match(request, { ignoreSearch: navigation }).then((cached) => cached || (navigation ? caches.match("./") : undefined))
+            )
+            .then((response) => response || Response.error())
+    );
+});
@@ -0,0 +1,62 @@
+//! Recorded Overpass responses, parsed and merged the way a search does it.
+//! Runs natively: nothing here touches the browser.
+
+use outcall::geo::BBox;
+use outcall::models::{OsmType, OverpassResponse};
+use outcall::overpass::{Area, CountResponse, QueryBuilder, Select};
+
+fn fixture(name: &str) -> OverpassResponse {
+    let path = format!("{}/tests/fixtures/overpass/{name}", env!("CARGO_MANIFEST_DIR"));
+    serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap()
+}
+
+#[test]
+fn places_nodes_and_way_centers() {
+    let response = fixture("toilets.json");
+    assert_eq!(response.osm3s.timestamp_osm_base, "2024-03-02T10:15:22Z");
+    let found: Vec<_> = response.elements.iter().map(|e| (e.type_field, e.id)).collect();
+    // The way without a center can't be placed and is left out.
+    assert_eq!(found, [(OsmType::Node, 1655286470), (OsmType::Way, 24962316), (OsmType::Node, 3081517532)]);
+
+    let cafe = &response.elements[1];
+    assert_eq!((cafe.lat, cafe.lon), (52.5208112, 13.4094753));
+    assert_eq!(cafe.tags.get("name"), Some("Café am Fernsehturm"));
+    assert_eq!(cafe.version, Some(12));
+
+    let untagged = &response.elements[2];
+    assert_eq!(untagged.tags.get("amenity"), None);
+    assert_eq!(untagged.timestamp, None);
+}
+
+#[test]
+fn tiles_merge_without_duplicates() {
+    let mut merged = fixture("tile_west.json");
+    merged.merge(fixture("tile_east.json"));
+    let ids: Vec<i64> = merged.elements.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [101, 102, 103]);
+}
+
+#[test]
+fn timeouts_come_back_empty() {
+    assert!(fixture("timeout.json").elements.is_empty());
+}
+
+#[test]
+fn counts_are_read_from_the_count_element() {
+    let path = format!("{}/tests/fixtures/overpass/count.json", env!("CARGO_MANIFEST_DIR"));
+    let count: CountResponse = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(count.total(), Some(2716));
+}
+
+#[test]
+fn tiles_query_their_own_boxes() {
+    let bbox = BBox::parse("48.85,2.33,48.86,2.37").unwrap();
+    let queries: Vec<String> = bbox
+        .tiles(2)
+        .into_iter()
+        .map(|tile| QueryBuilder::new().select(Select::new(Some(OsmType::Node)).tag("amenity", "toilets").within(Area::BBox(tile))).build())
+        .collect();
+    assert_eq!(queries.len(), 2);
+    assert!(queries.iter().all(|q| q.starts_with("[out:json];node[\"amenity\"=\"toilets\"](48.85,")));
+    assert_ne!(queries[0], queries[1]);
+}